@@ -1,4 +1,4 @@
-#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub enum AtomKind {
     Transform,
     Match,
@@ -82,56 +82,319 @@ pub struct ExecutionStep {
     pub pouch: String,
     pub kind: AtomKind,
     pub input_from: StepInput,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub alternatives: Vec<StepCandidate>,
 }
 
+/// 同一 AtomKind 的备选原子，按规划时的排序依次重试。
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct StepCandidate {
+    pub atom_name: String,
+    pub pouch: String,
+}
+
+/*
+ * 执行计划为 DAG：steps 按拓扑序排列，每步只依赖更早的步骤。
+ *   - UserInput / Fragment：无依赖，同一波次内并行执行（扇出）
+ *   - PreviousStep(i)：串行依赖
+ *   - Merge([i, j, ..])：扇入，依赖的输出以 " | " 拼接（ComposePouch 的输入格式）
+ * 计划输出为汇点（未被其他步骤引用的步骤）的输出，多个汇点按序以换行拼接。
+ */
 #[derive(Debug, Clone, serde::Serialize)]
 pub enum StepInput {
     UserInput,
     PreviousStep(usize),
+    Merge(Vec<usize>),
+    Fragment(String),
+}
+
+pub const MERGE_SEPARATOR: &str = " | ";
+pub const MERGE_ATOM: &str = "compose";
+const MAX_FRAGMENTS: usize = 4;
+const MAX_STEP_ALTERNATIVES: usize = 3;
+
+impl StepInput {
+    pub fn deps(&self) -> Vec<usize> {
+        match self {
+            StepInput::UserInput | StepInput::Fragment(_) => Vec::new(),
+            StepInput::PreviousStep(i) => vec![*i],
+            StepInput::Merge(v) => v.clone(),
+        }
+    }
+}
+
+impl ExecutionPlan {
+    pub fn sinks(&self) -> Vec<usize> {
+        let mut used = vec![false; self.steps.len()];
+        for step in &self.steps {
+            for d in step.input_from.deps() {
+                if let Some(u) = used.get_mut(d) {
+                    *u = true;
+                }
+            }
+        }
+        (0..self.steps.len()).filter(|i| !used[*i]).collect()
+    }
+
+    /// 按依赖分层：同一层内的步骤互不依赖，可并行执行。
+    pub fn waves(&self) -> Vec<Vec<usize>> {
+        let mut level = vec![0usize; self.steps.len()];
+        for (i, step) in self.steps.iter().enumerate() {
+            level[i] = step
+                .input_from
+                .deps()
+                .iter()
+                .filter(|d| **d < i)
+                .map(|d| level[*d] + 1)
+                .max()
+                .unwrap_or(0);
+        }
+        let depth = level.iter().copied().max().map_or(0, |m| m + 1);
+        let mut waves = vec![Vec::new(); depth];
+        for (i, l) in level.iter().enumerate() {
+            waves[*l].push(i);
+        }
+        waves
+    }
+
+    pub fn is_parallel(&self) -> bool {
+        self.waves().iter().any(|w| w.len() > 1)
+    }
+}
+
+/*
+ * 含连接词/顺承词的常用词：其中的 和/与/跟/再 不是分隔符（「和平」「与其」「再见」）。
+ */
+const SEPARATOR_COMPOUNDS: &[&str] = &[
+    "和平", "和谐", "温和", "柔和", "缓和", "平和", "随和", "暖和", "总和", "共和", "和善", "和蔼", "附和", "和解",
+    "和好", "和声", "与其", "参与", "与会", "与否", "给与", "赋与", "跟随", "跟踪", "跟进", "跟前", "脚跟", "再见",
+    "再次", "再生", "再现", "再度", "再三", "一再",
+];
+
+/*
+ * text 中 pos 处的 sep 是否只是某个词的一部分：中文看是否落在 SEPARATOR_COMPOUNDS 里，
+ * 英文（and/vs/then）看是否紧贴字母或数字（如 vscode）。
+ */
+fn inside_word(text: &str, pos: usize, sep: &str) -> bool {
+    if sep.trim().chars().all(|c| c.is_ascii_alphabetic()) {
+        let before = text[..pos + sep.len() - sep.trim_start().len()].chars().next_back();
+        let after = text[pos + sep.trim_end().len()..].chars().next();
+        return before.is_some_and(|c| c.is_ascii_alphanumeric()) || after.is_some_and(|c| c.is_ascii_alphanumeric());
+    }
+    SEPARATOR_COMPOUNDS.iter().any(|word| {
+        word.match_indices(sep)
+            .any(|(k, _)| pos >= k && text.get(pos - k..).is_some_and(|t| t.starts_with(word)))
+    })
+}
+
+fn find_boundary(text: &str, sep: &str) -> Option<usize> {
+    text.match_indices(sep).map(|(i, _)| i).find(|&i| !inside_word(text, i, sep))
+}
+
+fn split_at_boundaries(text: &str, sep: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut last = 0;
+    for (i, _) in text.match_indices(sep) {
+        if !inside_word(text, i, sep) {
+            parts.push(text[last..i].to_string());
+            last = i + sep.len();
+        }
+    }
+    parts.push(text[last..].to_string());
+    parts
+}
+
+/*
+ * 并行主体拆分：「对比材料A和B然后打印」→ ["材料A", "B"]。
+ * 仅在含对比/分别类关键词时生效；在顺承词（然后/再/then/逗号）处截断，
+ * 按 和/与/跟/以及/、/and/vs 切分，得到 2..=4 个主体时返回，否则为空。
+ * 连接词在词内时（「和平」「再见」「vscode」）不截断也不切分。
+ */
+pub fn split_parallel_subjects(input: &str) -> Vec<String> {
+    let lower = input.to_lowercase();
+    const LEADS: [&str; 6] = ["对比", "比较", "分别", "compare", "versus", " vs "];
+    if !LEADS.iter().any(|k| lower.contains(k)) {
+        return Vec::new();
+    }
+    let src = if lower.len() == input.len() { input } else { lower.as_str() };
+    let mut start = 0;
+    for lead in ["对比一下", "比较一下", "对比", "比较", "分别", "compare"] {
+        if let Some(pos) = lower.find(lead) {
+            start = pos + lead.len();
+            break;
+        }
+    }
+    let mut end = src.len();
+    for stop in ["然后", "之后", "再", "并且", "，", ",", "。", " then "] {
+        if let Some(pos) = find_boundary(&lower[start..], stop) {
+            end = end.min(start + pos);
+        }
+    }
+    let mut parts = vec![src[start..end].to_string()];
+    for sep in ["以及", "和", "与", "跟", "、", " and ", " vs ", "vs"] {
+        parts = parts
+            .iter()
+            .flat_map(|p| split_at_boundaries(p, sep))
+            .collect();
+    }
+    let parts: Vec<String> = parts
+        .into_iter()
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty())
+        .collect();
+    if parts.len() >= 2 && parts.len() <= MAX_FRAGMENTS {
+        parts
+    } else {
+        Vec::new()
+    }
 }
 
 impl CapabilityRegistry {
+    /*
+     * 候选排序：分数 ≥ 阈值者按分数、置信度上限降序在前；
+     * 低于阈值者按置信度上限排在其后（无评分器时全部按置信度）。
+     * 置信度相同时保持 max_by 语义（后注册者优先）。compose 仅在唯一时参与。
+     */
+    fn rank_atoms(
+        &self,
+        kind: AtomKind,
+        pouch_score: Option<&dyn Fn(&str) -> f64>,
+        threshold: f64,
+    ) -> Vec<AtomDeclaration> {
+        let mut candidates = self.find_by_kind(kind);
+        if candidates.iter().any(|a| a.name != MERGE_ATOM) {
+            candidates.retain(|a| a.name != MERGE_ATOM);
+        }
+        let by_confidence = |list: &mut Vec<&AtomDeclaration>| {
+            list.sort_by(|a, b| {
+                a.confidence_range.1.partial_cmp(&b.confidence_range.1).unwrap_or(std::cmp::Ordering::Equal)
+            });
+            list.reverse();
+        };
+        let ranked: Vec<&AtomDeclaration> = if let Some(score_fn) = pouch_score {
+            let (mut above, mut below): (Vec<_>, Vec<_>) =
+                candidates.into_iter().partition(|a| score_fn(&a.pouch) >= threshold);
+            above.sort_by(|a, b| {
+                let sa = score_fn(&a.pouch);
+                let sb = score_fn(&b.pouch);
+                sb.partial_cmp(&sa).unwrap_or(std::cmp::Ordering::Equal)
+                    .then_with(|| b.confidence_range.1.partial_cmp(&a.confidence_range.1).unwrap_or(std::cmp::Ordering::Equal))
+            });
+            by_confidence(&mut below);
+            above.extend(below);
+            above
+        } else {
+            by_confidence(&mut candidates);
+            candidates
+        };
+        ranked.into_iter().cloned().collect()
+    }
+
+    fn step_for(
+        &self,
+        kind: AtomKind,
+        input_from: StepInput,
+        pouch_score: Option<&dyn Fn(&str) -> f64>,
+        threshold: f64,
+    ) -> Option<ExecutionStep> {
+        let ranked = self.rank_atoms(kind, pouch_score, threshold);
+        let best = ranked.first()?;
+        let mut alternatives: Vec<StepCandidate> = Vec::new();
+        for a in ranked.iter().skip(1) {
+            if a.pouch == best.pouch || alternatives.iter().any(|c| c.pouch == a.pouch) {
+                continue;
+            }
+            alternatives.push(StepCandidate { atom_name: a.name.clone(), pouch: a.pouch.clone() });
+            if alternatives.len() >= MAX_STEP_ALTERNATIVES {
+                break;
+            }
+        }
+        Some(ExecutionStep {
+            atom_name: best.name.clone(),
+            pouch: best.pouch.clone(),
+            kind,
+            input_from,
+            alternatives,
+        })
+    }
+
     pub fn plan_for_kinds(
         &self,
         kinds: &[AtomKind],
         pouch_score: Option<&dyn Fn(&str) -> f64>,
         low_score_threshold: Option<f64>,
+    ) -> Option<ExecutionPlan> {
+        let stages: Vec<Vec<AtomKind>> = kinds.iter().map(|k| vec![*k]).collect();
+        self.plan_for_stages(&stages, &[], pouch_score, low_score_threshold)
+    }
+
+    /*
+     * 分阶段规划：stages[i] 内的 kind 并行执行；fragments 非空（≥2）时
+     * 第一阶段对每个主体各扇出一步。相邻阶段之间若上一阶段有多个输出，
+     * 插入 compose 合并步（未注册 compose 时由下一阶段直接 Merge）。
+     * 最后一阶段多输出且有 compose 时追加合并步，保证单一汇点。
+     */
+    pub fn plan_for_stages(
+        &self,
+        stages: &[Vec<AtomKind>],
+        fragments: &[String],
+        pouch_score: Option<&dyn Fn(&str) -> f64>,
+        low_score_threshold: Option<f64>,
     ) -> Option<ExecutionPlan> {
         let threshold = low_score_threshold.unwrap_or(0.5);
-        let mut steps = Vec::new();
-        for (i, kind) in kinds.iter().enumerate() {
-            let mut candidates = self.find_by_kind(*kind);
-            let best = if let Some(score_fn) = pouch_score {
-                candidates.retain(|a| score_fn(&a.pouch) >= threshold);
-                if candidates.is_empty() {
-                    let fallback = self.find_by_kind(*kind);
-                    (*fallback.iter().max_by(|a, b| {
-                        a.confidence_range.1.partial_cmp(&b.confidence_range.1).unwrap_or(std::cmp::Ordering::Equal)
-                    })?).clone()
+        let merge_atom = self.find_by_name(MERGE_ATOM).cloned();
+        let mut steps: Vec<ExecutionStep> = Vec::new();
+        let mut prev: Vec<usize> = Vec::new();
+        for (stage_idx, stage) in stages.iter().enumerate() {
+            if stage.is_empty() {
+                continue;
+            }
+            let input_from = match prev.len() {
+                0 => StepInput::UserInput,
+                1 => StepInput::PreviousStep(prev[0]),
+                _ => match &merge_atom {
+                    Some(m) => {
+                        steps.push(ExecutionStep {
+                            atom_name: m.name.clone(),
+                            pouch: m.pouch.clone(),
+                            kind: m.kind,
+                            input_from: StepInput::Merge(prev.clone()),
+                            alternatives: Vec::new(),
+                        });
+                        StepInput::PreviousStep(steps.len() - 1)
+                    }
+                    None => StepInput::Merge(prev.clone()),
+                },
+            };
+            let fan_out = stage_idx == 0 && fragments.len() >= 2;
+            let mut current = Vec::new();
+            for kind in stage {
+                if fan_out {
+                    for frag in fragments {
+                        steps.push(self.step_for(*kind, StepInput::Fragment(frag.clone()), pouch_score, threshold)?);
+                        current.push(steps.len() - 1);
+                    }
                 } else {
-                    candidates.sort_by(|a, b| {
-                        let sa = score_fn(&a.pouch);
-                        let sb = score_fn(&b.pouch);
-                        sb.partial_cmp(&sa).unwrap_or(std::cmp::Ordering::Equal)
-                            .then_with(|| b.confidence_range.1.partial_cmp(&a.confidence_range.1).unwrap_or(std::cmp::Ordering::Equal))
-                    });
-                    (*candidates.first()?).clone()
+                    steps.push(self.step_for(*kind, input_from.clone(), pouch_score, threshold)?);
+                    current.push(steps.len() - 1);
                 }
-            } else {
-                (*candidates.iter().max_by(|a, b| {
-                    a.confidence_range.1.partial_cmp(&b.confidence_range.1).unwrap_or(std::cmp::Ordering::Equal)
-                })?).clone()
-            };
-            steps.push(ExecutionStep {
-                atom_name: best.name.clone(),
-                pouch: best.pouch.clone(),
-                kind: *kind,
-                input_from: if i == 0 { StepInput::UserInput } else { StepInput::PreviousStep(i - 1) },
-            });
+            }
+            prev = current;
         }
         if steps.is_empty() {
             return None;
         }
+        if prev.len() > 1 {
+            if let Some(m) = &merge_atom {
+                steps.push(ExecutionStep {
+                    atom_name: m.name.clone(),
+                    pouch: m.pouch.clone(),
+                    kind: m.kind,
+                    input_from: StepInput::Merge(prev),
+                    alternatives: Vec::new(),
+                });
+            }
+        }
         Some(ExecutionPlan { steps })
     }
 
@@ -141,10 +404,19 @@ impl CapabilityRegistry {
             let input = match &step.input_from {
                 StepInput::UserInput => "input".into(),
                 StepInput::PreviousStep(n) => format!("step{}", n),
+                StepInput::Merge(v) => v.iter().map(|n| format!("step{}", n)).collect::<Vec<_>>().join("+"),
+                StepInput::Fragment(f) => format!("\"{}\"", f),
             };
             parts.push(format!("[{}] {}({})←{}", i, step.atom_name, step.pouch, input));
         }
-        parts.join(" → ")
+        if !plan.is_parallel() {
+            return parts.join(" → ");
+        }
+        plan.waves()
+            .iter()
+            .map(|w| w.iter().filter_map(|i| parts.get(*i).cloned()).collect::<Vec<_>>().join(" ‖ "))
+            .collect::<Vec<_>>()
+            .join(" → ")
     }
 }
 
@@ -251,6 +523,26 @@ mod tests {
         assert_eq!(plan.steps[0].pouch, "pouch_low");
     }
 
+    #[test]
+    fn test_plan_step_lists_ranked_alternatives() {
+        let mut reg = CapabilityRegistry::new();
+        for (name, pouch, hi) in [("a", "pouch_a", 0.9), ("a2", "pouch_a", 0.85), ("b", "pouch_b", 0.8), ("c", "pouch_c", 0.7)] {
+            reg.register(AtomDeclaration {
+                name: name.into(),
+                kind: AtomKind::Match,
+                pouch: pouch.into(),
+                confidence_range: (0.5, hi),
+            });
+        }
+        let plan = match reg.plan_for_kinds(&[AtomKind::Match], None, None) {
+            Some(p) => p,
+            None => panic!("plan: None"),
+        };
+        assert_eq!(plan.steps[0].pouch, "pouch_a");
+        let alts: Vec<&str> = plan.steps[0].alternatives.iter().map(|c| c.pouch.as_str()).collect();
+        assert_eq!(alts, vec!["pouch_b", "pouch_c"]);
+    }
+
     #[test]
     fn test_plan_for_kinds_with_low_score_filtered() {
         let mut reg = CapabilityRegistry::new();
@@ -273,7 +565,7 @@ mod tests {
             confidence_range: (0.5, 0.7),
         });
         let scorer = |name: &str| {
-            if name == "pouch_low" { 0.3 } else if name == "pouch_a" { 2.0 } else { 2.0 }
+            if name == "pouch_low" { 0.3 } else { 2.0 }
         };
         let plan = match reg.plan_for_kinds(&[AtomKind::Match], Some(&scorer), None) {
             Some(p) => p,
@@ -299,7 +591,7 @@ mod tests {
             pouch: "pouch_old".into(),
             confidence_range: (0.5, 0.7),
         });
-        let scorer = |name: &str| if name == "pouch_new" { 1.0 } else { 1.0 };
+        let scorer = |_: &str| 1.0;
         let plan = match reg.plan_for_kinds(&[AtomKind::Match], Some(&scorer), None) {
             Some(p) => p,
             None => panic!("plan: None"),
@@ -308,6 +600,90 @@ mod tests {
         assert_eq!(plan.steps[0].pouch, "pouch_new");
     }
 
+    fn dag_registry() -> CapabilityRegistry {
+        let mut reg = CapabilityRegistry::new();
+        reg.register(AtomDeclaration {
+            name: "convert".into(), kind: AtomKind::Transform, pouch: "material".into(), confidence_range: (0.6, 0.9),
+        });
+        reg.register(AtomDeclaration {
+            name: "gcode".into(), kind: AtomKind::Generate, pouch: "printer".into(), confidence_range: (0.6, 0.9),
+        });
+        reg.register(AtomDeclaration {
+            name: "compose".into(), kind: AtomKind::Transform, pouch: "compose".into(), confidence_range: (0.8, 1.0),
+        });
+        reg
+    }
+
+    #[test]
+    fn test_plan_for_stages_fan_out_and_merge() {
+        let fragments = vec!["材料A".to_string(), "B".to_string()];
+        let stages = vec![vec![AtomKind::Transform], vec![AtomKind::Generate]];
+        let plan = match dag_registry().plan_for_stages(&stages, &fragments, None, None) {
+            Some(p) => p,
+            None => panic!("plan: None"),
+        };
+        assert_eq!(plan.steps.len(), 4);
+        assert!(matches!(plan.steps[0].input_from, StepInput::Fragment(ref f) if f == "材料A"));
+        assert!(matches!(plan.steps[1].input_from, StepInput::Fragment(ref f) if f == "B"));
+        assert_eq!(plan.steps[2].pouch, "compose");
+        assert!(matches!(plan.steps[2].input_from, StepInput::Merge(ref v) if v == &vec![0, 1]));
+        assert!(matches!(plan.steps[3].input_from, StepInput::PreviousStep(2)));
+        assert_eq!(plan.waves(), vec![vec![0, 1], vec![2], vec![3]]);
+        assert_eq!(plan.sinks(), vec![3]);
+        assert!(plan.is_parallel());
+
+        let mut reg = dag_registry();
+        reg.unregister_pouch("compose");
+        let direct = match reg.plan_for_stages(&stages, &fragments, None, None) {
+            Some(p) => p,
+            None => panic!("plan: None"),
+        };
+        assert_eq!(direct.steps.len(), 3);
+        assert!(matches!(direct.steps[2].input_from, StepInput::Merge(ref v) if v == &vec![0, 1]));
+    }
+
+    #[test]
+    fn test_plan_for_stages_parallel_kinds_get_final_merge() {
+        let reg = dag_registry();
+        let stages = vec![vec![AtomKind::Generate, AtomKind::Transform]];
+        let plan = match reg.plan_for_stages(&stages, &[], None, None) {
+            Some(p) => p,
+            None => panic!("plan: None"),
+        };
+        assert_eq!(plan.steps.len(), 3);
+        assert!(matches!(plan.steps[0].input_from, StepInput::UserInput));
+        assert!(matches!(plan.steps[1].input_from, StepInput::UserInput));
+        assert_eq!(plan.sinks(), vec![2]);
+        let summary = CapabilityRegistry::plan_summary(&plan);
+        assert!(summary.contains(" ‖ "), "parallel wave must render with ‖: {}", summary);
+        assert!(summary.contains("←step0+step1"), "merge must list inputs: {}", summary);
+    }
+
+    #[test]
+    fn test_plan_for_kinds_stays_linear() {
+        let reg = dag_registry();
+        let plan = match reg.plan_for_kinds(&[AtomKind::Transform, AtomKind::Generate], None, None) {
+            Some(p) => p,
+            None => panic!("plan: None"),
+        };
+        assert!(!plan.is_parallel());
+        assert!(matches!(plan.steps[1].input_from, StepInput::PreviousStep(0)));
+        assert!(!CapabilityRegistry::plan_summary(&plan).contains('‖'));
+    }
+
+    #[test]
+    fn test_split_parallel_subjects() {
+        assert_eq!(split_parallel_subjects("对比材料A和B然后打印更好的"), vec!["材料A", "B"]);
+        assert_eq!(split_parallel_subjects("compare PLA and PETG, then print"), vec!["PLA", "PETG"]);
+        assert!(split_parallel_subjects("打印材料A").is_empty());
+        assert!(split_parallel_subjects("对比一下").is_empty());
+        assert_eq!(split_parallel_subjects("比较和平与战争"), vec!["和平", "战争"]);
+        assert_eq!(split_parallel_subjects("分别说再见和你好"), vec!["说再见", "你好"]);
+        assert!(split_parallel_subjects("对比与其说的温和").is_empty());
+        assert_eq!(split_parallel_subjects("compare vscode vs vim"), vec!["vscode", "vim"]);
+        assert_eq!(split_parallel_subjects("compare vscode and vim"), vec!["vscode", "vim"]);
+    }
+
     #[test]
     fn test_plan_for_kinds_configurable_threshold() {
         let mut reg = CapabilityRegistry::new();
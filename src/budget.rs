@@ -0,0 +1,140 @@
+use std::future::Future;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+/*
+ * 取消令牌：CancelHandle 被 drop（HTTP 客户端断开导致处理 future 被丢弃）后，
+ * 所有 CancelToken 进入已取消状态。
+ */
+pub struct CancelHandle {
+    _tx: watch::Sender<()>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CancelToken(watch::Receiver<()>);
+
+pub fn cancel_pair() -> (CancelHandle, CancelToken) {
+    let (tx, rx) = watch::channel(());
+    (CancelHandle { _tx: tx }, CancelToken(rx))
+}
+
+impl CancelToken {
+    pub fn is_cancelled(&self) -> bool {
+        self.0.has_changed().is_err()
+    }
+
+    pub async fn cancelled(&self) {
+        let mut rx = self.0.clone();
+        while rx.changed().await.is_ok() {}
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CallCut {
+    Timeout(u64),
+    Cancelled,
+}
+
+/*
+ * 单次请求的预算：总时限 deadline、尿袋调用次数上限 calls_left、可选取消令牌。
+ * 时限从请求到达（started）算起，等锁的时间也计入。
+ * 只在 begin_request_budget 与 end_request_budget 之间生效；学习周期等后台调用
+ * 只受单尿袋超时约束。
+ */
+#[derive(Debug, Clone)]
+pub struct RequestBudget {
+    pub deadline: Instant,
+    pub calls_left: usize,
+    pub cancel: Option<CancelToken>,
+}
+
+impl RequestBudget {
+    pub fn new(timeout_ms: u64, max_calls: usize, cancel: Option<CancelToken>, started: Instant) -> Self {
+        Self {
+            deadline: started + Duration::from_millis(timeout_ms),
+            calls_left: max_calls,
+            cancel,
+        }
+    }
+
+    pub fn remaining(&self) -> Duration {
+        self.deadline.saturating_duration_since(Instant::now())
+    }
+
+    pub fn exhausted(&self) -> Option<String> {
+        if self.cancel.as_ref().is_some_and(|c| c.is_cancelled()) {
+            return Some("请求已取消".into());
+        }
+        if self.remaining().is_zero() {
+            return Some("请求超时".into());
+        }
+        if self.calls_left == 0 {
+            return Some("尿袋调用预算耗尽".into());
+        }
+        None
+    }
+}
+
+/*
+ * 在 limit 内等待 fut；超时或取消时丢弃 fut（reqwest 请求随之中止）。
+ */
+pub async fn bounded<F, T>(fut: F, limit: Duration, cancel: Option<CancelToken>) -> Result<T, CallCut>
+where
+    F: Future<Output = T>,
+{
+    let cancelled = async {
+        match cancel {
+            Some(c) => c.cancelled().await,
+            None => std::future::pending::<()>().await,
+        }
+    };
+    tokio::select! {
+        r = tokio::time::timeout(limit, fut) => r.map_err(|_| CallCut::Timeout(limit.as_millis() as u64)),
+        _ = cancelled => Err(CallCut::Cancelled),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_bounded_times_out_slow_future() {
+        let slow = tokio::time::sleep(Duration::from_secs(5));
+        let r = bounded(slow, Duration::from_millis(20), None).await;
+        assert_eq!(r, Err(CallCut::Timeout(20)));
+    }
+
+    #[tokio::test]
+    async fn test_dropping_handle_cancels() {
+        let (handle, token) = cancel_pair();
+        assert!(!token.is_cancelled());
+        let waiter = tokio::spawn(bounded(
+            tokio::time::sleep(Duration::from_secs(5)),
+            Duration::from_secs(10),
+            Some(token.clone()),
+        ));
+        drop(handle);
+        let r = match waiter.await {
+            Ok(r) => r,
+            Err(e) => panic!("join: {}", e),
+        };
+        assert_eq!(r, Err(CallCut::Cancelled));
+        assert!(token.is_cancelled());
+    }
+
+    #[test]
+    fn test_budget_exhausted_by_calls() {
+        let mut b = RequestBudget::new(60_000, 1, None, Instant::now());
+        assert!(b.exhausted().is_none());
+        b.calls_left = 0;
+        assert_eq!(b.exhausted().as_deref(), Some("尿袋调用预算耗尽"));
+    }
+
+    #[test]
+    fn test_budget_counts_time_before_begin() {
+        let arrived = Instant::now() - Duration::from_millis(50);
+        let b = RequestBudget::new(20, 8, None, arrived);
+        assert_eq!(b.exhausted().as_deref(), Some("请求超时"));
+    }
+}
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 
@@ -51,6 +52,126 @@ impl Default for RoutingScoreConfig {
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChainConfig {
+    #[serde(default = "ChainConfig::default_chain_spec_max_depth")]
+    pub chain_spec_max_depth: usize,
+    #[serde(default = "ChainConfig::default_a2_chain_hops")]
+    pub a2_chain_hops: usize,
+}
+
+impl ChainConfig {
+    fn default_chain_spec_max_depth() -> usize { 6 }
+    fn default_a2_chain_hops() -> usize { 3 }
+}
+
+impl Default for ChainConfig {
+    fn default() -> Self {
+        Self {
+            chain_spec_max_depth: 6,
+            a2_chain_hops: 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionConfig {
+    #[serde(default = "SessionConfig::default_idle_ttl_secs")]
+    pub idle_ttl_secs: u64,
+    #[serde(default = "SessionConfig::default_max_sessions")]
+    pub max_sessions: usize,
+    #[serde(default = "SessionConfig::default_persist")]
+    pub persist: bool,
+    /* 两次写 sessions.json 的最短间隔；期间的改动在下次到期、学习周期或退出时写入 */
+    #[serde(default = "SessionConfig::default_save_interval_secs")]
+    pub save_interval_secs: u64,
+}
+
+impl SessionConfig {
+    fn default_idle_ttl_secs() -> u64 { 1800 }
+    fn default_max_sessions() -> usize { 256 }
+    fn default_persist() -> bool { true }
+    fn default_save_interval_secs() -> u64 { 30 }
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            idle_ttl_secs: 1800,
+            max_sessions: 256,
+            persist: true,
+            save_interval_secs: 30,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BudgetConfig {
+    #[serde(default = "BudgetConfig::default_pouch_timeout_ms")]
+    pub pouch_timeout_ms: u64,
+    #[serde(default = "BudgetConfig::default_request_timeout_ms")]
+    pub request_timeout_ms: u64,
+    #[serde(default = "BudgetConfig::default_max_pouch_calls")]
+    pub max_pouch_calls: usize,
+    #[serde(default)]
+    pub pouch_timeout_overrides: HashMap<String, u64>,
+}
+
+impl BudgetConfig {
+    fn default_pouch_timeout_ms() -> u64 { 12_000 }
+    fn default_request_timeout_ms() -> u64 { 30_000 }
+    fn default_max_pouch_calls() -> usize { 64 }
+
+    pub fn pouch_timeout_ms(&self, name: &str) -> u64 {
+        self.pouch_timeout_overrides.get(name).copied().unwrap_or(self.pouch_timeout_ms)
+    }
+}
+
+impl Default for BudgetConfig {
+    fn default() -> Self {
+        Self {
+            pouch_timeout_ms: 12_000,
+            request_timeout_ms: 30_000,
+            max_pouch_calls: 64,
+            pouch_timeout_overrides: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntentConfig {
+    #[serde(default = "IntentConfig::default_min_confidence")]
+    pub min_confidence: f64,
+}
+
+impl IntentConfig {
+    fn default_min_confidence() -> f64 { 0.5 }
+}
+
+impl Default for IntentConfig {
+    fn default() -> Self {
+        Self { min_confidence: 0.5 }
+    }
+}
+
+/*
+ * 语言尿袋分词器。namespace_tokenizers 按命名空间覆盖，未列出的沿用 tokenizer。
+ * 切换后启动时自动把对应命名空间的已存模式重新分词。
+ */
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LanguageConfig {
+    #[serde(default)]
+    pub tokenizer: crate::tokenizer::TokenizerKind,
+    #[serde(default)]
+    pub namespace_tokenizers: std::collections::BTreeMap<String, crate::tokenizer::TokenizerKind>,
+}
+
+impl LanguageConfig {
+    pub fn tokenizer_for(&self, namespace: &str) -> crate::tokenizer::TokenizerKind {
+        self.namespace_tokenizers.get(namespace).copied().unwrap_or(self.tokenizer)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SystemConfig {
     pub pouches: Vec<PouchConfig>,
@@ -58,6 +179,16 @@ pub struct SystemConfig {
     pub resource_limits: ResourceLimits,
     #[serde(default)]
     pub routing_score: RoutingScoreConfig,
+    #[serde(default)]
+    pub chain: ChainConfig,
+    #[serde(default)]
+    pub session: SessionConfig,
+    #[serde(default)]
+    pub budget: BudgetConfig,
+    #[serde(default)]
+    pub intent: IntentConfig,
+    #[serde(default)]
+    pub language: LanguageConfig,
     pub version: String,
 }
 
@@ -76,6 +207,11 @@ impl Default for SystemConfig {
                 memory_mb: 512,
             },
             routing_score: RoutingScoreConfig::default(),
+            chain: ChainConfig::default(),
+            session: SessionConfig::default(),
+            budget: BudgetConfig::default(),
+            intent: IntentConfig::default(),
+            language: LanguageConfig::default(),
             version: "1.0".to_string(),
         }
     }
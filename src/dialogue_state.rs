@@ -0,0 +1,345 @@
+use crate::language_pouch::MAX_CONTEXT_TURNS;
+use crate::tokenizer::Lang;
+use serde::{Deserialize, Serialize};
+
+/*
+ * 对话状态：记录最近 MAX_CONTEXT_TURNS 轮用户输入里提到的实体和属性，用于改写指代和省略。
+ *   - 实体：中文按功能词（是/的/什么/呢…）切块，「的」之前的块是实体、之后的块是属性，
+ *     句首的单字也算实体（如「铁」）；英文取非停用词的连续片段，最后一段是话题
+ *   - 指代：短问句里单独成词的 它/它们/他/她 → 当前话题（其他、吉他、他们… 等合成词不算）；
+ *     英文只改指代位置：its/their，以及 about/of/explain… 之后或 what is it 句尾的 it/them，
+ *     不改 Is it raining 这类形式主语，也不改泛指的 they
+ *   - 省略：「(那)X呢」——X 像属性（见过的属性或以 点/度/率… 结尾）→「话题的X呢」，
+ *     否则视为换了实体，把上一轮里的话题替换成 X；英文「what/how about X」同理
+ * 改写只依赖用户输入本身，不读取回复；状态随会话保存（Session.dialogue）。
+ */
+const ENTITY_MAX_CHARS: usize = 8;
+const ZH_PRONOUNS: &[&str] = &["它们", "它", "他", "她"];
+/* 含代词字的常见词，出现时该字不是指代 */
+const ZH_PRONOUN_COMPOUNDS: &[&str] = &[
+    "其他", "其它", "吉他", "他们", "她们", "他人", "他乡", "他妈", "利他", "排他", "他山", "无他", "他日",
+];
+const ZH_SHORT_QUESTION_MAX_CHARS: usize = 16;
+const ZH_QUESTION_MARKERS: &[&str] = &["呢", "吗", "什么", "多少", "怎么", "几", "哪", "如何", "是不是", "有没有", "谁"];
+const EN_POSSESSIVES: &[&str] = &["its", "their"];
+const EN_OBJECT_PRONOUNS: &[&str] = &["it", "them"];
+const EN_OBJECT_CUES: &[&str] = &["about", "of", "explain", "describe", "define"];
+const EN_COPULAS: &[&str] = &["is", "are", "was", "were"];
+const ZH_FUNCTION_WORDS: &[&str] = &[
+    "我想知道", "介绍一下", "是什么", "什么是", "怎么样", "为什么", "告诉我", "有没有", "请问", "怎么", "什么",
+    "多少", "哪些", "哪里", "哪个", "如何", "介绍", "一下", "知道", "可以", "一个", "它们", "他们", "她们", "那么",
+    "是", "的", "呢", "吗", "吧", "啊", "呀", "和", "与", "跟", "还", "也", "都", "那", "这", "我", "你", "它",
+    "他", "她", "有", "能", "会", "在", "了", "么", "给", "请", "说", "讲", "要", "想",
+];
+const EN_STOPWORDS: &[&str] = &[
+    "a", "an", "the", "is", "are", "was", "were", "be", "what", "who", "how", "why", "when", "where", "which",
+    "do", "does", "did", "can", "could", "would", "should", "tell", "me", "about", "of", "in", "on", "for", "to",
+    "and", "or", "it", "its", "they", "them", "their", "this", "that", "you", "your", "i", "my", "please", "much",
+    "many", "with", "from", "at", "by", "there",
+];
+const ATTRIBUTE_SUFFIX: &str = "点度量率性价格积重长高色味期间数名龄址码值温压";
+const QUESTION_END: &[char] = &['？', '?', '。', '.', '！', '!'];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Turn {
+    input: String,
+    entities: Vec<String>,
+    attributes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DialogueState {
+    turns: Vec<Turn>,
+}
+
+/*
+ * 随提案传给尿袋的只读视图：entities 按最近提及排序、去重，第一个是当前话题。
+ */
+#[derive(Debug, Clone, Default)]
+pub struct DialogueSnapshot {
+    pub entities: Vec<String>,
+}
+
+fn is_en_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '-' || c == '\''
+}
+
+fn extract_zh(input: &str) -> (Vec<String>, Vec<String>) {
+    let mut entities = Vec::new();
+    let mut attributes = Vec::new();
+    let mut chunk = String::new();
+    let mut after_de = false;
+    let mut first = true;
+    let mut flush = |chunk: &mut String, next_is_de: bool, after_de: bool, first: &mut bool| {
+        let len = chunk.chars().count();
+        if len > 0 && len <= ENTITY_MAX_CHARS && (len >= 2 || *first || next_is_de) {
+            if after_de {
+                attributes.push(chunk.clone());
+            } else {
+                entities.push(chunk.clone());
+            }
+        }
+        if len > 0 {
+            *first = false;
+        }
+        chunk.clear();
+    };
+    let mut rest = input;
+    while let Some(c) = rest.chars().next() {
+        if let Some(w) = ZH_FUNCTION_WORDS.iter().find(|w| rest.starts_with(**w)) {
+            flush(&mut chunk, *w == "的", after_de, &mut first);
+            after_de = *w == "的";
+            rest = &rest[w.len()..];
+            continue;
+        }
+        if c.is_whitespace() || c.is_ascii_punctuation() || (!c.is_alphanumeric() && !c.is_ascii()) {
+            flush(&mut chunk, false, after_de, &mut first);
+            after_de = false;
+        } else {
+            chunk.push(c);
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+    flush(&mut chunk, false, after_de, &mut first);
+    (entities, attributes)
+}
+
+fn extract_en(input: &str) -> Vec<String> {
+    let mut runs: Vec<Vec<&str>> = vec![Vec::new()];
+    for word in input.split(|c: char| !is_en_word_char(c)).filter(|w| !w.is_empty()) {
+        let lower = word.to_ascii_lowercase();
+        let base = lower.trim_end_matches("'s");
+        if EN_STOPWORDS.contains(&base) || base.len() < 2 {
+            if runs.last().is_some_and(|r| !r.is_empty()) {
+                runs.push(Vec::new());
+            }
+        } else if let Some(run) = runs.last_mut() {
+            run.push(word.trim_end_matches("'s"));
+        }
+    }
+    /* 英文话题通常在句尾（what is X / tell me about X），最后一段排在最前 */
+    runs.into_iter().rev().filter(|r| !r.is_empty()).map(|r| r.join(" ")).collect()
+}
+
+fn replace_en_pronouns(input: &str, topic: &str) -> Option<String> {
+    let words: Vec<(usize, &str)> = input
+        .char_indices()
+        .filter(|&(i, c)| c.is_ascii_alphabetic() && !input[..i].ends_with(|p: char| p.is_ascii_alphabetic()))
+        .map(|(i, _)| {
+            let len = input[i..].find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(input.len() - i);
+            (i, &input[i..i + len])
+        })
+        .collect();
+    let mut out = String::with_capacity(input.len() + topic.len());
+    let mut copied = 0;
+    for (n, &(start, word)) in words.iter().enumerate() {
+        let lower = word.to_ascii_lowercase();
+        let prev = n.checked_sub(1).map(|p| words[p].1.to_ascii_lowercase()).unwrap_or_default();
+        let last = n + 1 == words.len();
+        let replacement = if EN_POSSESSIVES.contains(&lower.as_str()) {
+            format!("{}'s", topic)
+        } else if EN_OBJECT_PRONOUNS.contains(&lower.as_str())
+            && (EN_OBJECT_CUES.contains(&prev.as_str()) || (last && EN_COPULAS.contains(&prev.as_str()) && n >= 2))
+        {
+            topic.to_string()
+        } else {
+            continue;
+        };
+        out.push_str(&input[copied..start]);
+        out.push_str(&replacement);
+        copied = start + word.len();
+    }
+    if copied == 0 {
+        return None;
+    }
+    out.push_str(&input[copied..]);
+    Some(out)
+}
+
+/*
+ * 单独成词的中文代词位置（字节区间），跳过落在合成词里的。
+ */
+fn zh_pronoun_spans(input: &str) -> Vec<(usize, usize)> {
+    let blocked: Vec<(usize, usize)> = ZH_PRONOUN_COMPOUNDS
+        .iter()
+        .flat_map(|c| input.match_indices(c).map(|(i, m)| (i, i + m.len())))
+        .collect();
+    let mut spans = Vec::new();
+    let mut i = 0;
+    while i < input.len() {
+        let hit = ZH_PRONOUNS.iter().find(|p| input[i..].starts_with(**p));
+        match hit {
+            Some(p) if !blocked.iter().any(|&(a, b)| i >= a && i < b) => {
+                spans.push((i, i + p.len()));
+                i += p.len();
+            }
+            _ => i += input[i..].chars().next().map_or(1, char::len_utf8),
+        }
+    }
+    spans
+}
+
+fn is_short_zh_question(input: &str) -> bool {
+    input.chars().count() <= ZH_SHORT_QUESTION_MAX_CHARS
+        && (input.ends_with(['？', '?']) || ZH_QUESTION_MARKERS.iter().any(|m| input.contains(m)))
+}
+
+impl DialogueState {
+    pub fn observe(&mut self, input: &str) {
+        let input = input.trim();
+        if input.is_empty() {
+            return;
+        }
+        let (entities, attributes) = match Lang::detect(input) {
+            Lang::Zh => extract_zh(input),
+            Lang::En => (extract_en(input), Vec::new()),
+        };
+        if self.turns.len() >= MAX_CONTEXT_TURNS {
+            self.turns.remove(0);
+        }
+        self.turns.push(Turn { input: input.to_string(), entities, attributes });
+    }
+
+    pub fn clear(&mut self) {
+        self.turns.clear();
+    }
+
+    pub fn topic(&self) -> Option<&str> {
+        self.turns.iter().rev().find_map(|t| t.entities.first()).map(|s| s.as_str())
+    }
+
+    pub fn snapshot(&self) -> DialogueSnapshot {
+        let mut entities: Vec<String> = Vec::new();
+        for e in self.turns.iter().rev().flat_map(|t| t.entities.iter()) {
+            if !entities.contains(e) {
+                entities.push(e.clone());
+            }
+        }
+        DialogueSnapshot { entities }
+    }
+
+    fn is_attribute(&self, word: &str) -> bool {
+        self.turns.iter().any(|t| t.attributes.iter().any(|a| a == word))
+            || word.chars().last().is_some_and(|c| ATTRIBUTE_SUFFIX.contains(c))
+    }
+
+    fn last_input_with(&self, topic: &str) -> Option<&str> {
+        self.turns.iter().rev().find(|t| t.input.contains(topic)).map(|t| t.input.as_str())
+    }
+
+    /*
+     * 把指代/省略改写成完整问句；无需改写或没有可用话题时返回 None。
+     */
+    pub fn resolve(&self, input: &str) -> Option<String> {
+        let topic = self.topic()?;
+        let input = input.trim();
+        match Lang::detect(input) {
+            Lang::Zh => self.resolve_zh(input, topic),
+            Lang::En => self.resolve_en(input, topic),
+        }
+    }
+
+    fn resolve_zh(&self, input: &str, topic: &str) -> Option<String> {
+        let spans = zh_pronoun_spans(input);
+        if !spans.is_empty() {
+            let mut out = String::with_capacity(input.len() + topic.len());
+            let mut remainder = String::new();
+            let mut copied = 0;
+            for &(a, b) in &spans {
+                remainder.push_str(&input[copied..a]);
+                out.push_str(&input[copied..a]);
+                out.push_str(topic);
+                copied = b;
+            }
+            remainder.push_str(&input[copied..]);
+            out.push_str(&input[copied..]);
+            let remainder = remainder.chars().filter(|c| c.is_alphanumeric()).count();
+            if remainder < 2 || !is_short_zh_question(input) {
+                return None;
+            }
+            return Some(out);
+        }
+        let core = input.trim_end_matches(QUESTION_END);
+        let core = core.strip_prefix("那么").or_else(|| core.strip_prefix("那")).unwrap_or(core);
+        let x = core.strip_suffix("呢")?.trim();
+        let len = x.chars().count();
+        if len == 0 || len > ENTITY_MAX_CHARS || x.contains(topic) || x.contains('的') {
+            return None;
+        }
+        if self.is_attribute(x) {
+            return Some(format!("{}的{}呢？", topic, x));
+        }
+        self.last_input_with(topic).map(|prev| prev.replacen(topic, x, 1))
+    }
+
+    fn resolve_en(&self, input: &str, topic: &str) -> Option<String> {
+        if let Some(out) = replace_en_pronouns(input, topic) {
+            return Some(out);
+        }
+        let lower = input.to_ascii_lowercase();
+        let lower = lower.trim_end_matches(QUESTION_END);
+        let x = ["what about ", "how about ", "and "]
+            .iter()
+            .find_map(|p| lower.strip_prefix(p))
+            .map(|rest| input[lower.len() - rest.len()..].trim_end_matches(QUESTION_END).trim())?;
+        if x.is_empty() || x.eq_ignore_ascii_case(topic) {
+            return None;
+        }
+        self.last_input_with(topic).map(|prev| prev.replacen(topic, x, 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(turns: &[&str]) -> DialogueState {
+        let mut s = DialogueState::default();
+        for t in turns {
+            s.observe(t);
+        }
+        s
+    }
+
+    #[test]
+    fn test_extract_entities_and_attributes() {
+        let s = state(&["铁是什么金属", "北京的天气怎么样"]);
+        assert_eq!(s.turns[0].entities, vec!["铁".to_string(), "金属".to_string()]);
+        assert_eq!(s.turns[1].entities, vec!["北京".to_string()]);
+        assert_eq!(s.turns[1].attributes, vec!["天气".to_string()]);
+        assert_eq!(s.topic(), Some("北京"));
+        assert_eq!(s.snapshot().entities, vec!["北京".to_string(), "铁".to_string(), "金属".to_string()]);
+        let en = state(&["How tall is the Eiffel Tower?"]);
+        assert_eq!(en.topic(), Some("Eiffel Tower"));
+    }
+
+    #[test]
+    fn test_resolve_pronoun_and_ellipsis() {
+        let s = state(&["铁是什么金属"]);
+        assert_eq!(s.resolve("那它的熔点呢？").as_deref(), Some("那铁的熔点呢？"));
+        assert_eq!(s.resolve("它"), None);
+        assert_eq!(s.resolve("今天星期几"), None);
+
+        let s = state(&["铁是什么金属", "那铁的熔点呢？"]);
+        assert_eq!(s.resolve("密度呢").as_deref(), Some("铁的密度呢？"));
+        assert_eq!(s.resolve("铜呢？").as_deref(), Some("那铜的熔点呢？"));
+
+        let s = state(&["北京的天气怎么样"]);
+        assert_eq!(s.resolve("那上海呢？").as_deref(), Some("上海的天气怎么样"));
+
+        let en = state(&["What is iron?"]);
+        assert_eq!(en.resolve("What is its melting point?").as_deref(), Some("What is iron's melting point?"));
+        assert_eq!(en.resolve("what about copper?").as_deref(), Some("What is copper?"));
+        assert_eq!(en.resolve("Tell me more about it.").as_deref(), Some("Tell me more about iron."));
+        assert_eq!(en.resolve("Is it raining?"), None);
+        assert_eq!(en.resolve("They say it never rains here"), None);
+
+        let s = state(&["李白是谁"]);
+        assert_eq!(s.resolve("他写过什么诗？").as_deref(), Some("李白写过什么诗？"));
+        assert_eq!(s.resolve("还有其他问题吗"), None);
+        assert_eq!(s.resolve("吉他怎么弹"), None);
+        assert_eq!(s.resolve("他们的老师是谁"), None);
+        assert_eq!(s.resolve("我昨天和他在公园里散步聊了很久最近的工作和生活"), None);
+        assert_eq!(DialogueState::default().resolve("它的熔点呢"), None);
+    }
+}
@@ -0,0 +1,505 @@
+// ============================================================================
+// LOGOS 命令注册表 (Logic Layer) - 系统命令的别名、参数结构与用法，静态表，无熵
+// ============================================================================
+
+use super::logic::SystemCmd;
+
+/*
+ * 参数类别：
+ *   - Name：单个词（可用引号包含空格），转小写，用于尿袋名、配置键等标识
+ *   - Until(sep)：到分隔符为止的文本（不含分隔符），保留大小写
+ *   - Rest：剩余全部文本，整体被引号包裹时去掉引号
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArgKind {
+    Name,
+    Until(&'static str),
+    Rest,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ArgSpec {
+    pub name: &'static str,
+    pub kind: ArgKind,
+}
+
+pub struct CommandSpec {
+    pub aliases: &'static [&'static str],
+    pub args: &'static [ArgSpec],
+    pub usage: &'static str,
+    pub summary: &'static str,
+    pub usage_en: &'static str,
+    pub summary_en: &'static str,
+    build: fn(Vec<String>) -> SystemCmd,
+}
+
+impl CommandSpec {
+    pub fn usage_line(&self) -> String {
+        let head = self.aliases.first().copied().unwrap_or_default();
+        if self.usage.is_empty() {
+            head.to_string()
+        } else {
+            format!("{} {}", head, self.usage)
+        }
+    }
+
+    /* 英文用法：以第一个英文别名开头 */
+    pub fn usage_line_en(&self) -> String {
+        let head = self.english_aliases().next().unwrap_or_default();
+        if self.usage_en.is_empty() {
+            head.to_string()
+        } else {
+            format!("{} {}", head, self.usage_en)
+        }
+    }
+
+    fn english_aliases(&self) -> impl Iterator<Item = &'static str> {
+        self.aliases.iter().copied().filter(|a| a.is_ascii())
+    }
+}
+
+/*
+ * 参数错误；按命中的别名是中文还是英文给出对应语言的说明。
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ArgError {
+    Unclosed,
+    MissingSep(&'static str),
+    Missing(&'static str),
+    Extra(String),
+}
+
+impl ArgError {
+    fn message(&self, en: bool) -> String {
+        match (self, en) {
+            (ArgError::Unclosed, false) => "引号未闭合".into(),
+            (ArgError::Unclosed, true) => "unclosed quote".into(),
+            (ArgError::MissingSep(sep), false) => format!("缺少分隔符 {}", sep),
+            (ArgError::MissingSep(sep), true) => format!("missing separator {}", sep),
+            (ArgError::Missing(name), false) => format!("缺少参数 <{}>", name),
+            (ArgError::Missing(_), true) => "missing argument".into(),
+            (ArgError::Extra(extra), false) => format!("多余参数: {}", extra),
+            (ArgError::Extra(extra), true) => format!("unexpected argument: {}", extra),
+        }
+    }
+}
+
+const NAME: ArgSpec = ArgSpec { name: "名", kind: ArgKind::Name };
+
+fn first(mut v: Vec<String>) -> String {
+    if v.is_empty() { String::new() } else { v.swap_remove(0) }
+}
+
+fn pair(v: Vec<String>) -> (String, String) {
+    let mut it = v.into_iter();
+    (it.next().unwrap_or_default(), it.next().unwrap_or_default())
+}
+
+pub static COMMANDS: &[CommandSpec] = &[
+    CommandSpec { aliases: &["帮助", "help", "?"], args: &[], usage: "", summary: "命令列表", usage_en: "", summary_en: "command list", build: |_| SystemCmd::Help },
+    CommandSpec { aliases: &["状态", "status"], args: &[], usage: "", summary: "系统状态", usage_en: "", summary_en: "system status", build: |_| SystemCmd::Status },
+    CommandSpec { aliases: &["尿袋列表", "list", "pouches"], args: &[], usage: "", summary: "已安装尿袋", usage_en: "", summary_en: "installed pouches", build: |_| SystemCmd::ListPouches },
+    CommandSpec { aliases: &["安装尿袋", "install"], args: &[NAME], usage: "<名>", summary: "安装", usage_en: "<name>", summary_en: "install", build: |v| SystemCmd::InstallPouch(first(v)) },
+    CommandSpec { aliases: &["卸载尿袋", "uninstall"], args: &[NAME], usage: "<名>", summary: "卸载", usage_en: "<name>", summary_en: "uninstall", build: |v| SystemCmd::UninstallPouch(first(v)) },
+    CommandSpec { aliases: &["休眠", "sleep"], args: &[NAME], usage: "<名>", summary: "休眠尿袋", usage_en: "<name>", summary_en: "put a pouch to sleep", build: |v| SystemCmd::SleepPouch(first(v)) },
+    CommandSpec { aliases: &["唤醒", "wake"], args: &[NAME], usage: "<名>", summary: "唤醒尿袋", usage_en: "<name>", summary_en: "wake a pouch", build: |v| SystemCmd::WakePouch(first(v)) },
+    CommandSpec {
+        aliases: &["教你", "learn"],
+        args: &[ArgSpec { name: "触发", kind: ArgKind::Until("->") }, ArgSpec { name: "回复", kind: ArgKind::Rest }],
+        usage: "<触发> -> <回复>",
+        summary: "教我新模式",
+        usage_en: "<trigger> -> <response>",
+        summary_en: "teach me a new pattern",
+        build: |v| {
+            let (trigger, response) = pair(v);
+            SystemCmd::Teach(trigger, response)
+        },
+    },
+    CommandSpec {
+        aliases: &["补充回复", "add variant"],
+        args: &[ArgSpec { name: "触发", kind: ArgKind::Until("->") }, ArgSpec { name: "回复", kind: ArgKind::Rest }],
+        usage: "<触发> -> <回复> [[when after=…; tag=…; hours=9-18]]",
+        summary: "为已有模式追加回复变体",
+        usage_en: "<trigger> -> <response> [[when after=…; tag=…; hours=9-18]]",
+        summary_en: "add a response variant to an existing pattern",
+        build: |v| {
+            let (trigger, response) = pair(v);
+            SystemCmd::TeachVariant(trigger, response)
+        },
+    },
+    CommandSpec {
+        aliases: &["流水线", "pipeline"],
+        args: &[ArgSpec { name: "阶段", kind: ArgKind::Until(":") }, ArgSpec { name: "数据", kind: ArgKind::Rest }],
+        usage: "<A,B,C>: <数据>",
+        summary: "Pipeline执行",
+        usage_en: "<A,B,C>: <data>",
+        summary_en: "run a pipeline",
+        build: |v| {
+            let (stages, data) = pair(v);
+            let stages = stages.split(',').map(|s| s.trim().to_lowercase()).filter(|s| !s.is_empty()).collect();
+            SystemCmd::RunPipeline(stages, data)
+        },
+    },
+    CommandSpec { aliases: &["配置", "config", "show config"], args: &[], usage: "", summary: "查看配置", usage_en: "", summary_en: "show config", build: |_| SystemCmd::ConfigShow },
+    CommandSpec {
+        aliases: &["配置设置", "config set"],
+        args: &[ArgSpec { name: "键", kind: ArgKind::Name }, ArgSpec { name: "值", kind: ArgKind::Rest }],
+        usage: "<键> <值>",
+        summary: "修改配置",
+        usage_en: "<key> <value>",
+        summary_en: "change config",
+        build: |v| {
+            let (key, value) = pair(v);
+            SystemCmd::ConfigSet(key, value)
+        },
+    },
+    CommandSpec { aliases: &["自检", "selftest"], args: &[], usage: "", summary: "系统自检", usage_en: "", summary_en: "self test", build: |_| SystemCmd::SelfTest },
+    CommandSpec { aliases: &["解释", "explain"], args: &[NAME], usage: "<名>", summary: "查看尿袋说明", usage_en: "<name>", summary_en: "explain a pouch", build: |v| SystemCmd::Explain(first(v)) },
+    CommandSpec { aliases: &["导出模式", "export patterns"], args: &[], usage: "", summary: "导出语言模式", usage_en: "", summary_en: "export language patterns", build: |_| SystemCmd::ExportPatterns },
+    CommandSpec {
+        aliases: &["导出数据集", "export dataset"],
+        args: &[ArgSpec { name: "格式", kind: ArgKind::Rest }],
+        usage: "<jsonl|sharegpt|csv> [路径] [weight>0.5] [origin:teach] [since:2026-01-01] [until:2026-12-31]",
+        summary: "导出模式全文为数据集文件",
+        usage_en: "<jsonl|sharegpt|csv> [path] [weight>0.5] [origin:teach] [since:2026-01-01] [until:2026-12-31]",
+        summary_en: "export patterns as a dataset file",
+        build: |v| SystemCmd::ExportDataset(first(v)),
+    },
+    CommandSpec {
+        aliases: &["导入模式", "import patterns"],
+        args: &[ArgSpec { name: "路径", kind: ArgKind::Rest }],
+        usage: "<路径> [merge|append|replace] [keep-old|keep-new|keep-both] [dry-run]",
+        summary: "导入语言模式（默认合并去重）",
+        usage_en: "<path> [merge|append|replace] [keep-old|keep-new|keep-both] [dry-run]",
+        summary_en: "import language patterns (merge and dedupe by default)",
+        build: |v| SystemCmd::ImportPatterns(first(v)),
+    },
+    CommandSpec { aliases: &["回滚", "rollback"], args: &[], usage: "", summary: "回滚语言模式", usage_en: "", summary_en: "roll back language patterns", build: |_| SystemCmd::Rollback },
+    CommandSpec { aliases: &["训练", "train"], args: &[], usage: "", summary: "触发云训练", usage_en: "", summary_en: "start cloud training", build: |_| SystemCmd::Train },
+    CommandSpec { aliases: &["演化", "演化状态", "evolution"], args: &[], usage: "", summary: "被动演化状态", usage_en: "", summary_en: "passive evolution status", build: |_| SystemCmd::EvolutionStatus },
+    CommandSpec { aliases: &["能力", "原子能力", "capabilities"], args: &[], usage: "", summary: "原子能力注册表", usage_en: "", summary_en: "atomic capability registry", build: |_| SystemCmd::Capabilities },
+    CommandSpec {
+        aliases: &["模式溯源", "provenance"],
+        args: &[ArgSpec { name: "编号", kind: ArgKind::Name }],
+        usage: "<编号>",
+        summary: "查看模式来源",
+        usage_en: "<id>",
+        summary_en: "show where a pattern came from",
+        build: |v| SystemCmd::PatternInfo(first(v)),
+    },
+    CommandSpec { aliases: &["模式列表", "list patterns"], args: &[], usage: "", summary: "按权重列出模式（第一页）", usage_en: "", summary_en: "list patterns by weight (first page)", build: |_| SystemCmd::ListPatterns },
+    CommandSpec {
+        aliases: &["查找模式", "search patterns"],
+        args: &[ArgSpec { name: "条件", kind: ArgKind::Rest }],
+        usage: "<文字 token:词 origin:来源 weight<0.5 sort:recent page:2>",
+        summary: "查找/分页浏览模式",
+        usage_en: "<text token:word origin:source weight<0.5 sort:recent page:2>",
+        summary_en: "search and page through patterns",
+        build: |v| SystemCmd::SearchPatterns(first(v)),
+    },
+    CommandSpec {
+        aliases: &["修改模式", "edit pattern"],
+        args: &[ArgSpec { name: "编号", kind: ArgKind::Name }, ArgSpec { name: "修改", kind: ArgKind::Rest }],
+        usage: "<编号> 回复=<新回复>|权重=<数值>",
+        summary: "修改模式回复或权重",
+        usage_en: "<id> response=<text>|weight=<number>",
+        summary_en: "change a pattern's response or weight",
+        build: |v| {
+            let (id, spec) = pair(v);
+            SystemCmd::EditPattern(id, spec)
+        },
+    },
+    CommandSpec {
+        aliases: &["删除模式", "delete pattern"],
+        args: &[ArgSpec { name: "编号", kind: ArgKind::Name }],
+        usage: "<编号>",
+        summary: "删除单条模式",
+        usage_en: "<id>",
+        summary_en: "delete one pattern",
+        build: |v| SystemCmd::DeletePattern(first(v)),
+    },
+    CommandSpec {
+        aliases: &["批量删除模式", "delete patterns"],
+        args: &[ArgSpec { name: "条件", kind: ArgKind::Rest }],
+        usage: "<条件>",
+        summary: "按条件批量删除模式",
+        usage_en: "<filter>",
+        summary_en: "delete patterns matching a filter",
+        build: |v| SystemCmd::DeletePatterns(first(v)),
+    },
+    CommandSpec { aliases: &["命名空间", "namespaces"], args: &[], usage: "", summary: "列出语言模式命名空间", usage_en: "", summary_en: "list pattern namespaces", build: |_| SystemCmd::ListNamespaces },
+    CommandSpec { aliases: &["新建命名空间", "namespace create"], args: &[NAME], usage: "<名>", summary: "新建命名空间", usage_en: "<name>", summary_en: "create a namespace", build: |v| SystemCmd::CreateNamespace(first(v)) },
+    CommandSpec {
+        aliases: &["复制命名空间", "namespace copy"],
+        args: &[ArgSpec { name: "源", kind: ArgKind::Name }, ArgSpec { name: "目标", kind: ArgKind::Name }],
+        usage: "<源> <目标>",
+        summary: "复制命名空间",
+        usage_en: "<from> <to>",
+        summary_en: "copy a namespace",
+        build: |v| {
+            let (from, to) = pair(v);
+            SystemCmd::CopyNamespace(from, to)
+        },
+    },
+    CommandSpec { aliases: &["删除命名空间", "namespace drop"], args: &[NAME], usage: "<名>", summary: "删除命名空间", usage_en: "<name>", summary_en: "drop a namespace", build: |v| SystemCmd::DropNamespace(first(v)) },
+    CommandSpec { aliases: &["使用命名空间", "namespace use"], args: &[NAME], usage: "<名>", summary: "当前会话切换命名空间", usage_en: "<name>", summary_en: "switch this session's namespace", build: |v| SystemCmd::UseNamespace(first(v)) },
+    CommandSpec {
+        aliases: &["屏蔽回复", "block response"],
+        args: &[ArgSpec { name: "规则", kind: ArgKind::Rest }],
+        usage: "<文字|/正则/>",
+        summary: "拦截含该文字或匹配正则的回复",
+        usage_en: "<text|/regex/>",
+        summary_en: "block responses containing the text or matching the regex",
+        build: |v| SystemCmd::BlockResponse(first(v)),
+    },
+    CommandSpec {
+        aliases: &["禁止回答", "never answer"],
+        args: &[ArgSpec { name: "触发", kind: ArgKind::Until("->") }, ArgSpec { name: "回复", kind: ArgKind::Rest }],
+        usage: "<触发> -> <回复>",
+        summary: "不要用该回复回答此类输入",
+        usage_en: "<trigger> -> <response>",
+        summary_en: "never answer this kind of input with that response",
+        build: |v| {
+            let (trigger, response) = pair(v);
+            SystemCmd::ForbidAnswer(trigger, response)
+        },
+    },
+    CommandSpec { aliases: &["屏蔽规则", "guard rules"], args: &[], usage: "", summary: "列出屏蔽规则和否定模式", usage_en: "", summary_en: "list block rules and negative patterns", build: |_| SystemCmd::ListGuardRules },
+    CommandSpec { aliases: &["删除屏蔽", "unblock"], args: &[ArgSpec { name: "编号", kind: ArgKind::Name }], usage: "<编号>", summary: "删除屏蔽规则", usage_en: "<id>", summary_en: "remove a block rule", build: |v| SystemCmd::RemoveGuardRule(first(v)) },
+    CommandSpec { aliases: &["隔离区", "quarantine"], args: &[], usage: "", summary: "被拦截待审的候选", usage_en: "", summary_en: "candidates held for review", build: |_| SystemCmd::ListQuarantine },
+    CommandSpec { aliases: &["放行", "release"], args: &[ArgSpec { name: "编号", kind: ArgKind::Name }], usage: "<编号>", summary: "学习隔离区条目", usage_en: "<id>", summary_en: "learn a quarantined entry", build: |v| SystemCmd::ReleaseQuarantine(first(v)) },
+    CommandSpec { aliases: &["丢弃隔离", "discard"], args: &[ArgSpec { name: "编号", kind: ArgKind::Name }], usage: "<编号>", summary: "丢弃隔离区条目", usage_en: "<id>", summary_en: "discard a quarantined entry", build: |v| SystemCmd::DiscardQuarantine(first(v)) },
+    CommandSpec { aliases: &["清空上下文", "重置对话", "clear context"], args: &[], usage: "", summary: "清空当前会话上下文", usage_en: "", summary_en: "clear this session's context", build: |_| SystemCmd::ClearContext },
+];
+
+/* 别名后可直接跟的分隔符（会被去掉），引号保留给参数 */
+const ALIAS_SEPARATORS: &[char] = &[':', '：', ',', '，'];
+
+/*
+ * 中文别名后可直接跟参数（「安装尿袋材料」），以下续接是日常说法而不是命令。
+ */
+const CJK_COMPOUNDS: &[(&str, &[&str])] = &[
+    ("教你", &["一", "怎么", "如何"]),
+    ("解释", &["一下", "为什么", "什么"]),
+    ("唤醒", &["我"]),
+];
+
+/*
+ * 去掉前缀别名（不区分大小写），返回剩余参数文本。
+ *   - 以 ASCII 字母数字结尾的别名后面必须是空白、结尾、分隔符或引号，避免 "learning" 命中 "learn"
+ *   - 中文别名后可直接跟参数，CJK_COMPOUNDS 中的续接除外（「教你一个道理」不是命令）
+ */
+fn strip_alias<'a>(input: &'a str, alias: &str) -> Option<&'a str> {
+    let n = alias.chars().count();
+    if input.chars().count() < n {
+        return None;
+    }
+    let end = input.char_indices().nth(n).map_or(input.len(), |(i, _)| i);
+    if input[..end].to_lowercase() != alias {
+        return None;
+    }
+    let rest = &input[end..];
+    if let Some(after) = rest.strip_prefix(ALIAS_SEPARATORS) {
+        return Some(after);
+    }
+    if alias.chars().last().is_some_and(|c| c.is_ascii_alphanumeric()) {
+        let bounded = rest.is_empty() || rest.starts_with(|c: char| c.is_whitespace() || "\"'“".contains(c));
+        return bounded.then_some(rest);
+    }
+    let compound = CJK_COMPOUNDS
+        .iter()
+        .any(|(head, tails)| *head == alias && tails.iter().any(|t| rest.starts_with(t)));
+    (!compound).then_some(rest)
+}
+
+fn unquote(s: &str) -> &str {
+    for q in ['"', '\'', '“'] {
+        let close = if q == '“' { '”' } else { q };
+        if s.len() >= 2 && s.starts_with(q) && s.ends_with(close) {
+            return &s[q.len_utf8()..s.len() - close.len_utf8()];
+        }
+    }
+    s
+}
+
+/*
+ * 取一个词：引号开头时取到对应的闭合引号，否则取到空白为止。
+ */
+fn next_word(s: &str) -> Result<(String, &str), ArgError> {
+    let mut chars = s.char_indices();
+    match chars.next() {
+        None => Ok((String::new(), s)),
+        Some((_, q)) if q == '"' || q == '\'' || q == '“' => {
+            let close = if q == '“' { '”' } else { q };
+            let body = &s[q.len_utf8()..];
+            match body.find(close) {
+                Some(i) => Ok((body[..i].to_string(), &body[i + close.len_utf8()..])),
+                None => Err(ArgError::Unclosed),
+            }
+        }
+        Some(_) => {
+            let end = s.find(char::is_whitespace).unwrap_or(s.len());
+            Ok((s[..end].to_string(), &s[end..]))
+        }
+    }
+}
+
+fn parse_args(spec: &CommandSpec, raw: &str) -> Result<Vec<String>, ArgError> {
+    let mut cursor = raw;
+    let mut values = Vec::with_capacity(spec.args.len());
+    for arg in spec.args {
+        cursor = cursor.trim_start();
+        let value = match arg.kind {
+            ArgKind::Name => {
+                let (w, next) = next_word(cursor)?;
+                cursor = next;
+                w.to_lowercase()
+            }
+            ArgKind::Until(sep) => match cursor.split_once(sep) {
+                Some((head, tail)) => {
+                    cursor = tail;
+                    unquote(head.trim()).to_string()
+                }
+                None => return Err(ArgError::MissingSep(sep)),
+            },
+            ArgKind::Rest => {
+                let v = unquote(cursor.trim()).to_string();
+                cursor = "";
+                v
+            }
+        };
+        if value.trim().is_empty() {
+            return Err(ArgError::Missing(arg.name));
+        }
+        values.push(value);
+    }
+    let extra = cursor.trim();
+    if !extra.is_empty() {
+        return Err(ArgError::Extra(extra.to_string()));
+    }
+    Ok(values)
+}
+
+/*
+ * 解析系统命令：取最长匹配的别名。
+ *   - None：不是命令（无参数命令后面跟了其他文字，按普通对话处理）
+ *   - Some(Err)：是命令但参数不合法，返回带用法的错误（用英文别名时为英文）
+ */
+pub fn parse(input: &str) -> Option<Result<SystemCmd, String>> {
+    let input = input.trim();
+    let mut best: Option<(&CommandSpec, &str, &str)> = None;
+    for spec in COMMANDS {
+        for alias in spec.aliases {
+            if let Some(rest) = strip_alias(input, alias) {
+                if best.is_none_or(|(_, _, hit)| alias.len() > hit.len()) {
+                    best = Some((spec, rest, alias));
+                }
+            }
+        }
+    }
+    let (spec, rest, alias) = best?;
+    if spec.args.is_empty() && !rest.trim().is_empty() {
+        return None;
+    }
+    Some(parse_args(spec, rest).map(spec.build).map_err(|why| {
+        if alias.is_ascii() {
+            format!("Invalid arguments: {}\nUsage: {}", why.message(true), spec.usage_line_en())
+        } else {
+            format!("参数错误: {}\n用法: {}", why.message(false), spec.usage_line())
+        }
+    }))
+}
+
+pub fn help_text() -> String {
+    let mut lines = vec!["LOGOS命令:".to_string(), "直接对话 - 语言尿袋交流".to_string()];
+    for spec in COMMANDS {
+        let others: Vec<&str> = spec.aliases.iter().skip(1).copied().collect();
+        let alias_note = if others.is_empty() { String::new() } else { format!(" (别名: {})", others.join(" / ")) };
+        lines.push(format!("{} - {}{}", spec.usage_line(), spec.summary, alias_note));
+    }
+    lines.join("\n")
+}
+
+pub fn help_text_en() -> String {
+    let mut lines = vec!["LOGOS commands:".to_string(), "just talk - chat with the language pouch".to_string()];
+    for spec in COMMANDS {
+        let others: Vec<&str> = spec.english_aliases().skip(1).collect();
+        let alias_note = if others.is_empty() { String::new() } else { format!(" (aliases: {})", others.join(" / ")) };
+        lines.push(format!("{} - {}{}", spec.usage_line_en(), spec.summary_en, alias_note));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_quoted_and_multiple_args() {
+        let cmd = parse("config set \"idle_threshold\" 600");
+        assert_eq!(cmd, Some(Ok(SystemCmd::ConfigSet("idle_threshold".into(), "600".into()))));
+        let cmd = parse("教你 \"Hello World\" -> 你好世界");
+        assert_eq!(cmd, Some(Ok(SystemCmd::Teach("Hello World".into(), "你好世界".into()))));
+        assert_eq!(
+            parse("补充回复 你好 -> 嗨 [[when tag=vip]]"),
+            Some(Ok(SystemCmd::TeachVariant("你好".into(), "嗨 [[when tag=vip]]".into())))
+        );
+        let cmd = parse("pipeline Pilot, compose: 数据");
+        assert_eq!(cmd, Some(Ok(SystemCmd::RunPipeline(vec!["pilot".into(), "compose".into()], "数据".into()))));
+    }
+
+    #[test]
+    fn test_parse_reports_usage_errors() {
+        match parse("安装尿袋") {
+            Some(Err(e)) => assert!(e.contains("用法: 安装尿袋 <名>"), "{}", e),
+            other => panic!("expected usage error, got {:?}", other),
+        }
+        match parse("install") {
+            Some(Err(e)) => assert!(e.contains("Usage: install <name>"), "{}", e),
+            other => panic!("expected usage error, got {:?}", other),
+        }
+        match parse("config set idle_threshold") {
+            Some(Err(e)) => assert!(e.contains("<value>"), "{}", e),
+            other => panic!("expected usage error, got {:?}", other),
+        }
+        match parse("安装尿袋 \"pilot") {
+            Some(Err(e)) => assert!(e.contains("引号未闭合"), "{}", e),
+            other => panic!("expected usage error, got {:?}", other),
+        }
+        assert!(matches!(parse("install pilot extra"), Some(Err(_))));
+    }
+
+    #[test]
+    fn test_help_text_per_language() {
+        assert!(help_text().contains("安装尿袋 <名> - 安装"));
+        let en = help_text_en();
+        assert!(en.contains("install <name> - install"), "{}", en);
+        assert!(en.contains("list - installed pouches (aliases: pouches)"), "{}", en);
+        assert!(!en.chars().any(|c| ('\u{4E00}'..='\u{9FFF}').contains(&c)), "{}", en);
+    }
+
+    #[test]
+    fn test_parse_alias_boundaries() {
+        assert_eq!(parse("状态怎么样"), None);
+        assert_eq!(parse("learning is fun"), None);
+        assert_eq!(parse("教你一个道理"), None);
+        assert_eq!(parse("帮助我看看这段代码"), None);
+        assert_eq!(parse("安装尿袋：pilot"), Some(Ok(SystemCmd::InstallPouch("pilot".into()))));
+        assert_eq!(parse("休眠材料"), Some(Ok(SystemCmd::SleepPouch("材料".into()))));
+        assert_eq!(parse("教你天气->晴天"), Some(Ok(SystemCmd::Teach("天气".into(), "晴天".into()))));
+        assert_eq!(parse("解释一下这段代码"), None);
+        assert_eq!(parse("Install Pilot"), Some(Ok(SystemCmd::InstallPouch("pilot".into()))));
+        assert_eq!(parse("演化状态"), Some(Ok(SystemCmd::EvolutionStatus)));
+        assert_eq!(parse("模式溯源 12"), Some(Ok(SystemCmd::PatternInfo("12".into()))));
+        assert_eq!(parse("namespaces"), Some(Ok(SystemCmd::ListNamespaces)));
+        assert_eq!(parse("namespace copy base Team-A"), Some(Ok(SystemCmd::CopyNamespace("base".into(), "team-a".into()))));
+    }
+
+    #[test]
+    fn test_help_lists_every_command() {
+        let help = help_text();
+        for spec in COMMANDS {
+            assert!(help.contains(&spec.usage_line()));
+        }
+    }
+}
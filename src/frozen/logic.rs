@@ -9,6 +9,8 @@ pub enum Layer { Bedrock, Logic, Orchestrator, Pouch }
 pub enum RouteDecision {
     ToPouch(String),
     SystemCommand(SystemCmd),
+    Usage(String),
+    Ambiguous(Vec<String>),
     Reject(&'static str),
 }
 
@@ -24,114 +26,147 @@ pub enum SystemCmd {
     Status,
     Help,
     Teach(String, String),
+    TeachVariant(String, String),
     RunPipeline(Vec<String>, String),
     SelfTest,
     ImportPatterns(String),
     ExportPatterns,
+    ExportDataset(String),
     Rollback,
     Explain(String),
     Train,
     EvolutionStatus,
     Capabilities,
     ClearContext,
+    PatternInfo(String),
+    ListPatterns,
+    SearchPatterns(String),
+    EditPattern(String, String),
+    DeletePattern(String),
+    DeletePatterns(String),
+    ListNamespaces,
+    CreateNamespace(String),
+    CopyNamespace(String, String),
+    DropNamespace(String),
+    UseNamespace(String),
+    BlockResponse(String),
+    ForbidAnswer(String, String),
+    ListGuardRules,
+    RemoveGuardRule(String),
+    ListQuarantine,
+    ReleaseQuarantine(String),
+    DiscardQuarantine(String),
 }
 
-pub fn route(input: &str, installed: &[&str]) -> RouteDecision {
-    let lower = input.to_lowercase();
-    let trimmed = lower.trim();
+/*
+ * 尿袋声明的路由触发：
+ *   - phrases：触发短语/别名（含中文名），ASCII 短语按整词匹配，其余按子串匹配
+ *   - negative：出现任一否定词时该尿袋不参与匹配
+ *   - priority：越大越优先；同一最高优先级下有多个尿袋命中时返回 Ambiguous
+ * 尿袋名本身只在显式点名时命中（输入以名字开头，或包含 @名字），优先于所有短语。
+ */
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PouchTrigger {
+    pub phrases: Vec<String>,
+    pub negative: Vec<String>,
+    pub priority: i32,
+}
 
-    if trimmed.starts_with("安装尿袋") || trimmed.starts_with("install ") {
-        let name = trimmed.replace("安装尿袋", "").replace("install ", "").trim().to_string();
-        return RouteDecision::SystemCommand(SystemCmd::InstallPouch(name));
-    }
-    if trimmed.starts_with("卸载尿袋") || trimmed.starts_with("uninstall ") {
-        let name = trimmed.replace("卸载尿袋", "").replace("uninstall ", "").trim().to_string();
-        return RouteDecision::SystemCommand(SystemCmd::UninstallPouch(name));
+#[derive(Debug, Clone)]
+pub struct RouteTarget {
+    pub name: String,
+    pub trigger: PouchTrigger,
+}
+
+impl RouteTarget {
+    pub fn bare(name: &str) -> Self {
+        Self { name: name.to_string(), trigger: PouchTrigger::default() }
     }
-    if trimmed.starts_with("休眠") || trimmed.starts_with("sleep ") {
-        let name = trimmed.replace("休眠", "").replace("sleep ", "").trim().to_string();
-        return RouteDecision::SystemCommand(SystemCmd::SleepPouch(name));
+}
+
+fn phrase_hit(text: &str, phrase: &str) -> bool {
+    let phrase = phrase.trim().to_lowercase();
+    if phrase.is_empty() {
+        return false;
     }
-    if trimmed.starts_with("唤醒") || trimmed.starts_with("wake ") {
-        let name = trimmed.replace("唤醒", "").replace("wake ", "").trim().to_string();
-        return RouteDecision::SystemCommand(SystemCmd::WakePouch(name));
+    if !phrase.is_ascii() {
+        return text.contains(&phrase);
     }
-    if trimmed == "配置" || trimmed == "config" || trimmed == "show config" {
-        return RouteDecision::SystemCommand(SystemCmd::ConfigShow);
+    let is_word = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    text.match_indices(&phrase).any(|(i, m)| {
+        let before = text[..i].chars().next_back();
+        let after = text[i + m.len()..].chars().next();
+        !before.is_some_and(is_word) && !after.is_some_and(is_word)
+    })
+}
+
+fn explicit_address(text: &str, name: &str) -> bool {
+    let name = name.to_lowercase();
+    if text.contains(&format!("@{}", name)) {
+        return true;
     }
-    if trimmed.starts_with("配置设置") || trimmed.starts_with("config set ") {
-        let content = trimmed.replace("配置设置", "").replace("config set ", "").trim().to_string();
-        if let Some((key, value)) = content.split_once(' ') {
-            return RouteDecision::SystemCommand(SystemCmd::ConfigSet(key.to_string(), value.to_string()));
+    match text.strip_prefix(name.as_str()) {
+        Some(rest) => {
+            rest.is_empty()
+                || !name.is_ascii()
+                || rest.starts_with(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
         }
+        None => false,
     }
-    if trimmed == "尿袋列表" || trimmed == "list" || trimmed == "pouches" {
-        return RouteDecision::SystemCommand(SystemCmd::ListPouches);
-    }
-    if trimmed == "状态" || trimmed == "status" {
-        return RouteDecision::SystemCommand(SystemCmd::Status);
-    }
-    if trimmed == "自检" || trimmed == "selftest" {
-        return RouteDecision::SystemCommand(SystemCmd::SelfTest);
-    }
-    if trimmed == "帮助" || trimmed == "help" || trimmed == "?" {
-        return RouteDecision::SystemCommand(SystemCmd::Help);
-    }
-    if trimmed.contains("->") && (trimmed.starts_with("教你") || trimmed.starts_with("learn")) {
-        let content = trimmed.replace("教你", "").replace("learn", "").trim().to_string();
-        if let Some((trigger, response)) = content.split_once("->") {
-            return RouteDecision::SystemCommand(SystemCmd::Teach(
-                trigger.trim().to_string(),
-                response.trim().to_string(),
-            ));
+}
+
+/*
+ * 返回命中的 (尿袋, 优先级, 命中短语长度)；显式点名的优先级记为 i32::MAX。
+ */
+fn match_targets(text: &str, targets: &[RouteTarget]) -> Vec<(String, i32, usize)> {
+    let mut hits = Vec::new();
+    for t in targets {
+        if t.trigger.negative.iter().any(|n| phrase_hit(text, n)) {
+            continue;
         }
-    }
-    if trimmed.starts_with("流水线") || trimmed.starts_with("pipeline") {
-        let content = trimmed.replace("流水线", "").replace("pipeline", "").trim().to_string();
-        if let Some((stages_str, data)) = content.split_once(':') {
-            let stages: Vec<String> = stages_str.split(',').map(|s| s.trim().to_string()).collect();
-            if !stages.is_empty() {
-                return RouteDecision::SystemCommand(SystemCmd::RunPipeline(stages, data.trim().to_string()));
-            }
+        if explicit_address(text, &t.name) {
+            hits.push((t.name.clone(), i32::MAX, t.name.chars().count()));
+            continue;
+        }
+        let best = t
+            .trigger
+            .phrases
+            .iter()
+            .filter(|p| phrase_hit(text, p))
+            .map(|p| p.chars().count())
+            .max();
+        if let Some(len) = best {
+            hits.push((t.name.clone(), t.trigger.priority, len));
         }
     }
-    if trimmed == "导出模式" || trimmed == "export patterns" {
-        return RouteDecision::SystemCommand(SystemCmd::ExportPatterns);
-    }
-    if trimmed.starts_with("导入模式") || trimmed.starts_with("import patterns") {
-        let path = trimmed.replace("导入模式", "").replace("import patterns", "").trim().to_string();
-        return RouteDecision::SystemCommand(SystemCmd::ImportPatterns(path));
-    }
-    if trimmed == "回滚" || trimmed == "rollback" {
-        return RouteDecision::SystemCommand(SystemCmd::Rollback);
-    }
-    if trimmed == "训练" || trimmed == "train" {
-        return RouteDecision::SystemCommand(SystemCmd::Train);
-    }
-    if trimmed == "演化" || trimmed == "evolution" || trimmed == "演化状态" {
-        return RouteDecision::SystemCommand(SystemCmd::EvolutionStatus);
-    }
-    if trimmed == "能力" || trimmed == "capabilities" || trimmed == "原子能力" {
-        return RouteDecision::SystemCommand(SystemCmd::Capabilities);
-    }
-    if trimmed == "清空上下文" || trimmed == "clear context" || trimmed == "重置对话" {
-        return RouteDecision::SystemCommand(SystemCmd::ClearContext);
-    }
-    if trimmed.starts_with("解释") || trimmed.starts_with("explain ") {
-        let name = trimmed.replace("解释", "").replace("explain ", "").trim().to_string();
-        return RouteDecision::SystemCommand(SystemCmd::Explain(name));
+    hits
+}
+
+pub fn route(input: &str, targets: &[RouteTarget]) -> RouteDecision {
+    let lower = input.to_lowercase();
+    let trimmed = lower.trim();
+
+    match super::commands::parse(input) {
+        Some(Ok(cmd)) => return RouteDecision::SystemCommand(cmd),
+        Some(Err(usage)) => return RouteDecision::Usage(usage),
+        None => {}
     }
 
     if let Some(target) = check_promoted_route(trimmed) {
-        if installed.contains(&target.as_str()) {
+        if targets.iter().any(|t| t.name == target) {
             return RouteDecision::ToPouch(target);
         }
     }
 
-    for pouch in installed {
-        if trimmed.contains(&pouch.to_lowercase()) {
-            return RouteDecision::ToPouch(pouch.to_string());
+    let mut hits = match_targets(trimmed, targets);
+    if let Some(top) = hits.iter().map(|h| h.1).max() {
+        hits.retain(|h| h.1 == top);
+        hits.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.0.cmp(&b.0)));
+        let mut names: Vec<String> = hits.into_iter().map(|h| h.0).collect();
+        if names.len() == 1 {
+            return RouteDecision::ToPouch(names.remove(0));
         }
+        return RouteDecision::Ambiguous(names);
     }
 
     RouteDecision::Reject("Unknown command")
@@ -279,12 +314,56 @@ mod tests {
         assert_eq!(result, RouteDecision::SystemCommand(SystemCmd::Teach("天气".into(), "晴天".into())));
     }
 
+    #[test]
+    fn test_route_teach_compound_is_chat() {
+        let result = route("教你一个道理", &[RouteTarget::bare("language")]);
+        assert_eq!(result, RouteDecision::Reject("Unknown command"));
+    }
+
+    #[test]
+    fn test_route_usage_error_does_not_fall_through() {
+        let result = route("install", &[RouteTarget::bare("language")]);
+        assert!(matches!(result, RouteDecision::Usage(ref u) if u.contains("install <name>")));
+    }
+
     #[test]
     fn test_route_to_pouch() {
-        let result = route("你好 language", &["language"]);
+        let result = route("language 你好", &[RouteTarget::bare("language")]);
+        assert_eq!(result, RouteDecision::ToPouch("language".into()));
+        let result = route("你好 @language", &[RouteTarget::bare("language")]);
         assert_eq!(result, RouteDecision::ToPouch("language".into()));
     }
 
+    #[test]
+    fn test_route_name_substring_not_hijacked() {
+        let targets = [RouteTarget::bare("memory"), RouteTarget::bare("pilot")];
+        assert_eq!(route("my memory is bad", &targets), RouteDecision::Reject("Unknown command"));
+        assert_eq!(route("autopilot 怎么用", &targets), RouteDecision::Reject("Unknown command"));
+    }
+
+    #[test]
+    fn test_route_triggers_priority_negative_and_ambiguity() {
+        let memory = RouteTarget {
+            name: "memory".into(),
+            trigger: PouchTrigger { phrases: vec!["记忆".into()], negative: vec!["内存".into()], priority: 1 },
+        };
+        let recall = RouteTarget {
+            name: "knowledge".into(),
+            trigger: PouchTrigger { phrases: vec!["回忆".into()], negative: vec![], priority: 1 },
+        };
+        let low = RouteTarget {
+            name: "pilot".into(),
+            trigger: PouchTrigger { phrases: vec!["记忆".into()], negative: vec![], priority: 0 },
+        };
+        let targets = [memory, recall, low];
+        assert_eq!(route("帮我记忆这段话", &targets), RouteDecision::ToPouch("memory".into()));
+        assert_eq!(route("记忆内存不足", &targets), RouteDecision::ToPouch("pilot".into()));
+        assert_eq!(
+            route("回忆一下记忆", &targets),
+            RouteDecision::Ambiguous(vec!["knowledge".into(), "memory".into()])
+        );
+    }
+
     #[test]
     fn test_decompose_material_print() {
         let kinds = decompose_intent("用材料打印一个零件");
@@ -1,2 +1,3 @@
 pub mod bedrock;
+pub mod commands;
 pub mod logic;
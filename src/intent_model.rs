@@ -0,0 +1,272 @@
+use crate::atom::AtomKind;
+use crate::tokenizer::TokenizerState;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+/*
+ * 本地意图模型：把输入 token 映射到 AtomKind 序列（每个不同序列是一个类）。
+ *   - 训练样本：成功的 EvolutionEntry（单步，按尿袋的主原子类别）、已学路由、成功执行的计划（完整序列）
+ *   - 打分：多项式朴素贝叶斯（Laplace 平滑）给出类后验，再乘以输入 token 在该类词表中的覆盖率，
+ *     避免与任何样本都不相关的输入（如「你好」）得到高置信度
+ *   - 关键词规则（frozen::logic::decompose_intent）作为先验：命中的序列按 noisy-OR 合并 RULE_PRIOR，
+ *     没有训练数据时与原关键词行为一致
+ * 结果确定：同分时规则序列优先，其次按类的插入顺序。
+ * token 一律用 base 命名空间的分词器切分，tokenizer 记录其状态；同时保留最近 MAX_SAMPLES 条
+ * 原始文本样本，分词器变化后按新分词器从原文重新训练（retokenize）。
+ */
+pub const RULE_PRIOR: f64 = 0.6;
+const MAX_CLASS_TOKENS: usize = 4096;
+const MAX_CLASSES: usize = 64;
+const MAX_SAMPLES: usize = 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IntentClass {
+    kinds: Vec<AtomKind>,
+    docs: u32,
+    token_counts: HashMap<String, u32>,
+    token_total: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IntentSample {
+    text: String,
+    kinds: Vec<AtomKind>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IntentModel {
+    classes: Vec<IntentClass>,
+    docs: u32,
+    #[serde(default)]
+    samples: VecDeque<IntentSample>,
+    #[serde(default)]
+    tokenizer: Option<TokenizerState>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IntentGuess {
+    pub kinds: Vec<AtomKind>,
+    pub confidence: f64,
+    pub source: &'static str,
+}
+
+impl IntentGuess {
+    pub fn summary(&self) -> String {
+        let kinds: Vec<String> = self.kinds.iter().map(|k| format!("{:?}", k)).collect();
+        format!("{} {:.2} {}", kinds.join(">"), self.confidence, self.source)
+    }
+}
+
+impl IntentModel {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_tokenizer(tokenizer: TokenizerState) -> Self {
+        Self { tokenizer: Some(tokenizer), ..Self::default() }
+    }
+
+    /* token 是否由给定分词器切分：否则已存词表与新输入对不上 */
+    pub fn matches_tokenizer(&self, tokenizer: &TokenizerState) -> bool {
+        self.tokenizer.as_ref() == Some(tokenizer)
+    }
+
+    /*
+     * 换用新分词器：清空词表，按保存的原始文本重新切分训练。返回重新训练的样本数；
+     * 旧文件没有原文样本时结果为空模型，由调用方另行引导。
+     */
+    pub fn retokenize(&mut self, tokenizer: TokenizerState, tokenize: impl Fn(&str) -> Vec<String>) -> usize {
+        let samples = std::mem::take(&mut self.samples);
+        *self = Self::with_tokenizer(tokenizer);
+        for sample in &samples {
+            self.observe(&tokenize(&sample.text), &sample.kinds);
+        }
+        self.samples = samples;
+        self.samples.len()
+    }
+
+    /* 带原始文本的训练样本：除计入词表外保留原文，供分词器变化后重新训练 */
+    pub fn observe_text(&mut self, text: &str, tokens: &[String], kinds: &[AtomKind]) {
+        if tokens.is_empty() || kinds.is_empty() {
+            return;
+        }
+        if self.samples.len() >= MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(IntentSample { text: text.to_string(), kinds: kinds.to_vec() });
+        self.observe(tokens, kinds);
+    }
+
+    pub fn class_count(&self) -> usize {
+        self.classes.len()
+    }
+
+    pub fn observe(&mut self, tokens: &[String], kinds: &[AtomKind]) {
+        if tokens.is_empty() || kinds.is_empty() {
+            return;
+        }
+        let idx = match self.classes.iter().position(|c| c.kinds == kinds) {
+            Some(i) => i,
+            None => {
+                if self.classes.len() >= MAX_CLASSES {
+                    return;
+                }
+                self.classes.push(IntentClass {
+                    kinds: kinds.to_vec(),
+                    docs: 0,
+                    token_counts: HashMap::new(),
+                    token_total: 0,
+                });
+                self.classes.len() - 1
+            }
+        };
+        let class = &mut self.classes[idx];
+        class.docs += 1;
+        for t in tokens {
+            if !class.token_counts.contains_key(t) && class.token_counts.len() >= MAX_CLASS_TOKENS {
+                continue;
+            }
+            *class.token_counts.entry(t.clone()).or_insert(0) += 1;
+            class.token_total += 1;
+        }
+        self.docs += 1;
+    }
+
+    fn vocab_size(&self) -> usize {
+        let mut seen: std::collections::HashSet<&str> = std::collections::HashSet::new();
+        for c in &self.classes {
+            seen.extend(c.token_counts.keys().map(|s| s.as_str()));
+        }
+        seen.len().max(1)
+    }
+
+    /*
+     * 各类的模型置信度 = 朴素贝叶斯后验 × 覆盖率。
+     */
+    fn model_scores(&self, tokens: &[String]) -> Vec<f64> {
+        if self.classes.is_empty() || tokens.is_empty() {
+            return vec![0.0; self.classes.len()];
+        }
+        let vocab = self.vocab_size() as f64;
+        let n_classes = self.classes.len() as f64;
+        let logs: Vec<f64> = self
+            .classes
+            .iter()
+            .map(|c| {
+                let prior = (c.docs as f64 + 1.0) / (self.docs as f64 + n_classes);
+                let denom = c.token_total as f64 + vocab;
+                tokens.iter().fold(prior.ln(), |acc, t| {
+                    let n = c.token_counts.get(t).copied().unwrap_or(0) as f64;
+                    acc + ((n + 1.0) / denom).ln()
+                })
+            })
+            .collect();
+        let max = logs.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let exps: Vec<f64> = logs.iter().map(|l| (l - max).exp()).collect();
+        let sum: f64 = exps.iter().sum();
+        self.classes
+            .iter()
+            .zip(exps)
+            .map(|(c, e)| {
+                let covered = tokens.iter().filter(|t| c.token_counts.contains_key(*t)).count();
+                let coverage = covered as f64 / tokens.len() as f64;
+                if sum > 0.0 { e / sum * coverage } else { 0.0 }
+            })
+            .collect()
+    }
+
+    pub fn predict(&self, tokens: &[String], rule: &[AtomKind]) -> Option<IntentGuess> {
+        let scores = self.model_scores(tokens);
+        let mut best: Option<IntentGuess> = None;
+        let mut rule_seen = false;
+        for (c, m) in self.classes.iter().zip(scores) {
+            let is_rule = !rule.is_empty() && c.kinds == rule;
+            let (confidence, source) = if is_rule {
+                rule_seen = true;
+                (1.0 - (1.0 - m) * (1.0 - RULE_PRIOR), "model+rule")
+            } else {
+                (m, "model")
+            };
+            let better = match &best {
+                None => confidence > 0.0,
+                Some(b) => confidence > b.confidence || (is_rule && confidence >= b.confidence),
+            };
+            if better {
+                best = Some(IntentGuess { kinds: c.kinds.clone(), confidence, source });
+            }
+        }
+        if !rule_seen && !rule.is_empty() && best.as_ref().is_none_or(|b| RULE_PRIOR >= b.confidence) {
+            best = Some(IntentGuess { kinds: rule.to_vec(), confidence: RULE_PRIOR, source: "rule" });
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn toks(s: &[&str]) -> Vec<String> {
+        s.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn test_rule_only_without_training() {
+        let model = IntentModel::new();
+        let guess = match model.predict(&toks(&["对比", "能力"]), &[AtomKind::Match, AtomKind::Score]) {
+            Some(g) => g,
+            None => panic!("rule prior should produce a guess"),
+        };
+        assert_eq!(guess.kinds, vec![AtomKind::Match, AtomKind::Score]);
+        assert_eq!(guess.source, "rule");
+        assert!((guess.confidence - RULE_PRIOR).abs() < 1e-9);
+        assert!(model.predict(&toks(&["你好"]), &[]).is_none());
+    }
+
+    #[test]
+    fn test_trained_sequence_beats_missing_rule() {
+        let mut model = IntentModel::new();
+        for _ in 0..3 {
+            model.observe(&toks(&["代码", "缺陷", "扫描"]), &[AtomKind::Transform, AtomKind::Score]);
+            model.observe(&toks(&["写", "一首", "诗"]), &[AtomKind::Generate]);
+        }
+        let guess = match model.predict(&toks(&["扫描", "代码", "缺陷"]), &[]) {
+            Some(g) => g,
+            None => panic!("trained input should produce a guess"),
+        };
+        assert_eq!(guess.kinds, vec![AtomKind::Transform, AtomKind::Score]);
+        assert!(guess.confidence > 0.5);
+        let unrelated = model.predict(&toks(&["天气", "如何"]), &[]);
+        assert!(unrelated.is_none_or(|g| g.confidence < 0.1));
+    }
+
+    #[test]
+    fn test_retokenize_retrains_from_text() {
+        let mut model = IntentModel::new();
+        let chars = |s: &str| s.chars().map(|c| c.to_string()).collect::<Vec<_>>();
+        model.observe_text("扫描代码", &toks(&["扫描代码"]), &[AtomKind::Score]);
+        model.observe(&toks(&["路由", "样本"]), &[AtomKind::Route]);
+        assert!(model.predict(&chars("扫描"), &[]).is_none());
+        let state = TokenizerState::default();
+        assert_eq!(model.retokenize(state, chars), 1);
+        assert!(model.matches_tokenizer(&state));
+        assert_eq!(model.class_count(), 1);
+        let guess = match model.predict(&chars("扫描"), &[]) {
+            Some(g) => g,
+            None => panic!("retrained tokens should match"),
+        };
+        assert_eq!(guess.kinds, vec![AtomKind::Score]);
+    }
+
+    #[test]
+    fn test_rule_and_model_agree_raise_confidence() {
+        let mut model = IntentModel::new();
+        model.observe(&toks(&["对比", "两者"]), &[AtomKind::Match, AtomKind::Score]);
+        let guess = match model.predict(&toks(&["对比", "两者"]), &[AtomKind::Match, AtomKind::Score]) {
+            Some(g) => g,
+            None => panic!("guess"),
+        };
+        assert_eq!(guess.source, "model+rule");
+        assert!(guess.confidence > RULE_PRIOR);
+    }
+}
@@ -0,0 +1,391 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/*
+ * 语言评估：指标、按标签分组汇总、与上一次结果逐条对比。
+ *   - 默认只读：用 LanguagePouch::explain 推演回复，不改权重/频次/tick，不记录未命中；
+ *     live 时沿用 process（评估过程中会学习，与旧口径一致）
+ *   - 输出：<数据目录>/eval_result.jsonl，指定 name 时为 eval_<name>.jsonl；
+ *     与 compare 对比时回退项另写 <输出>.diff.jsonl
+ *   - 指标：精确匹配、编辑距离相似度、字符 n-gram F 值（chrF，n=1..6，β=2）、
+ *     字符级 BLEU-4（n>1 加一平滑，含长度惩罚）；空白不计入 n-gram
+ *   - 标签：输入行的 category / source / tag 字段（依次取第一个），用于分组；
+ *     split 指定时只评估该字段值相同的行（留出集）
+ *   - 回退：同一输入，上次精确匹配而这次没有，或 chrF 下降超过 REGRESSION_DELTA
+ */
+pub const DEFAULT_EVAL_NAME: &str = "result";
+const REGRESSION_DELTA: f64 = 0.05;
+const REGRESSIONS_SHOWN: usize = 10;
+const CHRF_ORDER: usize = 6;
+const CHRF_BETA: f64 = 2.0;
+const BLEU_ORDER: usize = 4;
+const UNTAGGED: &str = "未标注";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EvalOptions {
+    pub live: bool,
+    pub name: Option<String>,
+    pub compare: Option<String>,
+    pub split: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EvalRow {
+    pub input: String,
+    pub reference: String,
+    pub logos_output: String,
+    pub hit: bool,
+    pub pattern_id: Option<u32>,
+    #[serde(default)]
+    pub tag: String,
+    #[serde(default)]
+    pub exact: bool,
+    #[serde(default)]
+    pub similarity: f64,
+    #[serde(default)]
+    pub chrf: f64,
+    #[serde(default)]
+    pub bleu: f64,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Tally {
+    total: usize,
+    exact: usize,
+    hits: usize,
+    hit_sim: f64,
+    similarity: f64,
+    chrf: f64,
+    bleu: f64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct EvalReport {
+    overall: Tally,
+    by_tag: BTreeMap<String, Tally>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Regression {
+    pub input: String,
+    pub tag: String,
+    pub reference: String,
+    pub before: String,
+    pub after: String,
+    pub chrf_before: f64,
+    pub chrf_after: f64,
+    pub exact_before: bool,
+    pub exact_after: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Comparison {
+    pub matched: usize,
+    pub improved: usize,
+    pub regressions: Vec<Regression>,
+}
+
+impl EvalOptions {
+    pub fn output_path(&self, data_dir: &str) -> Result<String, String> {
+        let name = self.name.as_deref().map(str::trim).filter(|n| !n.is_empty()).unwrap_or(DEFAULT_EVAL_NAME);
+        if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return Err(format!("评估名只能包含字母、数字、- 和 _: {}", name));
+        }
+        Ok(format!("{}/eval_{}.jsonl", data_dir, name))
+    }
+
+    /*
+     * 终端写法：语言评估 <路径> [live] [name:名] [compare:名或路径] [split:test]，
+     * 末尾的选项词依次识别（路径本身可含空格）。
+     */
+    pub fn split_command(arg: &str) -> (String, Self) {
+        let mut opts = EvalOptions::default();
+        let mut words: Vec<&str> = arg.split_whitespace().collect();
+        while words.len() > 1 {
+            let last = words[words.len() - 1];
+            match last.split_once(':') {
+                Some(("name" | "名", v)) => opts.name = Some(v.to_string()),
+                Some(("compare" | "对比", v)) => opts.compare = Some(v.to_string()),
+                Some(("split" | "划分", v)) => opts.split = Some(v.to_string()),
+                None if last == "live" || last == "实时" => opts.live = true,
+                _ => break,
+            }
+            words.pop();
+        }
+        (words.join(" "), opts)
+    }
+
+    /* 对比对象：存在的文件路径直接用，否则按评估名找 eval_<名>.jsonl */
+    pub fn compare_path(&self, data_dir: &str) -> Option<String> {
+        let target = self.compare.as_deref().map(str::trim).filter(|c| !c.is_empty())?;
+        if std::path::Path::new(target).is_file() {
+            Some(target.to_string())
+        } else {
+            Some(format!("{}/eval_{}.jsonl", data_dir, target))
+        }
+    }
+
+    pub fn accepts(&self, line: &serde_json::Value) -> bool {
+        match self.split.as_deref() {
+            Some(split) => line.get("split").and_then(|s| s.as_str()) == Some(split),
+            None => true,
+        }
+    }
+}
+
+pub fn line_tag(line: &serde_json::Value) -> String {
+    ["category", "source", "tag"]
+        .iter()
+        .find_map(|k| line.get(*k).and_then(|v| v.as_str()).map(str::trim).filter(|v| !v.is_empty()))
+        .unwrap_or(UNTAGGED)
+        .to_string()
+}
+
+fn char_ngrams(chars: &[char], n: usize) -> HashMap<&[char], usize> {
+    let mut grams = HashMap::new();
+    for g in chars.windows(n) {
+        *grams.entry(g).or_insert(0) += 1;
+    }
+    grams
+}
+
+/* (重合数, 候选总数, 参考总数) */
+fn ngram_overlap(hyp: &[char], reference: &[char], n: usize) -> (usize, usize, usize) {
+    let h = char_ngrams(hyp, n);
+    let r = char_ngrams(reference, n);
+    let overlap = h.iter().map(|(g, c)| (*c).min(r.get(g).copied().unwrap_or(0))).sum();
+    (overlap, hyp.len().saturating_sub(n - 1), reference.len().saturating_sub(n - 1))
+}
+
+fn visible_chars(s: &str) -> Vec<char> {
+    s.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+pub fn chrf(hyp: &str, reference: &str) -> f64 {
+    let (h, r) = (visible_chars(hyp), visible_chars(reference));
+    if h.is_empty() || r.is_empty() {
+        return if h.is_empty() && r.is_empty() { 1.0 } else { 0.0 };
+    }
+    let (mut p_sum, mut r_sum, mut orders) = (0.0, 0.0, 0usize);
+    for n in 1..=CHRF_ORDER {
+        let (overlap, h_total, r_total) = ngram_overlap(&h, &r, n);
+        if h_total == 0 || r_total == 0 {
+            break;
+        }
+        p_sum += overlap as f64 / h_total as f64;
+        r_sum += overlap as f64 / r_total as f64;
+        orders += 1;
+    }
+    let (p, rc) = (p_sum / orders as f64, r_sum / orders as f64);
+    let b2 = CHRF_BETA * CHRF_BETA;
+    if p + rc == 0.0 {
+        0.0
+    } else {
+        (1.0 + b2) * p * rc / (b2 * p + rc)
+    }
+}
+
+pub fn bleu(hyp: &str, reference: &str) -> f64 {
+    let (h, r) = (visible_chars(hyp), visible_chars(reference));
+    if h.is_empty() || r.is_empty() {
+        return if h.is_empty() && r.is_empty() { 1.0 } else { 0.0 };
+    }
+    let mut log_sum = 0.0;
+    for n in 1..=BLEU_ORDER {
+        let (overlap, h_total, _) = ngram_overlap(&h, &r, n);
+        let smooth = if n > 1 { 1.0 } else { 0.0 };
+        let p = (overlap as f64 + smooth) / (h_total as f64 + smooth).max(1.0);
+        if p == 0.0 {
+            return 0.0;
+        }
+        log_sum += p.ln();
+    }
+    let bp = if h.len() >= r.len() { 1.0 } else { (1.0 - r.len() as f64 / h.len() as f64).exp() };
+    bp * (log_sum / BLEU_ORDER as f64).exp()
+}
+
+impl EvalRow {
+    /*
+     * 由输出与参考算出各项指标；similarity 由调用方给出（LanguagePouch 的编辑距离相似度）。
+     */
+    pub fn scored(mut self, similarity: f64) -> Self {
+        self.similarity = similarity;
+        self.exact = self.logos_output.trim() == self.reference.trim() || similarity >= 0.99;
+        self.chrf = chrf(&self.logos_output, &self.reference);
+        self.bleu = bleu(&self.logos_output, &self.reference);
+        self
+    }
+}
+
+impl Tally {
+    fn add(&mut self, row: &EvalRow) {
+        self.total += 1;
+        self.exact += row.exact as usize;
+        if row.hit {
+            self.hits += 1;
+            self.hit_sim += row.similarity;
+        }
+        self.similarity += row.similarity;
+        self.chrf += row.chrf;
+        self.bleu += row.bleu;
+    }
+
+    fn avg(&self, sum: f64) -> f64 {
+        if self.total == 0 { 0.0 } else { sum / self.total as f64 }
+    }
+
+    fn pct(&self, n: usize) -> f64 {
+        if self.total == 0 { 0.0 } else { n as f64 * 100.0 / self.total as f64 }
+    }
+}
+
+impl EvalReport {
+    pub fn add(&mut self, row: &EvalRow) {
+        self.overall.add(row);
+        self.by_tag.entry(row.tag.clone()).or_default().add(row);
+    }
+
+    pub fn render(&self, out_path: &str, live: bool) -> String {
+        let t = &self.overall;
+        let misses = t.total - t.hits;
+        let hit_avg = if t.hits > 0 { t.hit_sim / t.hits as f64 } else { 0.0 };
+        let miss_avg = if misses > 0 { (t.similarity - t.hit_sim) / misses as f64 } else { 0.0 };
+        let mut out = format!(
+            "语言评估{}: {} 条, 精确匹配 {:.1}%, 平均相似度 {:.3}, chrF {:.3}, BLEU {:.3}. 命中 {:.1}% (命中均相似 {:.3}, 未命中均相似 {:.3}). 详情 {}",
+            if live { "" } else { "（只读）" },
+            t.total,
+            t.pct(t.exact),
+            t.avg(t.similarity),
+            t.avg(t.chrf),
+            t.avg(t.bleu),
+            t.pct(t.hits),
+            hit_avg,
+            miss_avg,
+            out_path
+        );
+        if self.by_tag.len() > 1 || !self.by_tag.contains_key(UNTAGGED) {
+            for (tag, s) in &self.by_tag {
+                out.push_str(&format!(
+                    "\n  [{}] {} 条, 精确 {:.1}%, 命中 {:.1}%, chrF {:.3}, BLEU {:.3}",
+                    tag,
+                    s.total,
+                    s.pct(s.exact),
+                    s.pct(s.hits),
+                    s.avg(s.chrf),
+                    s.avg(s.bleu)
+                ));
+            }
+        }
+        out
+    }
+}
+
+/*
+ * 按输入文本逐条对比（同一输入重复出现时取第一条）。
+ */
+pub fn compare(previous: &[EvalRow], current: &[EvalRow]) -> Comparison {
+    let mut before: HashMap<&str, &EvalRow> = HashMap::new();
+    for row in previous {
+        before.entry(row.input.as_str()).or_insert(row);
+    }
+    let mut out = Comparison::default();
+    for row in current {
+        let Some(prev) = before.remove(row.input.as_str()) else {
+            continue;
+        };
+        out.matched += 1;
+        if (prev.exact && !row.exact) || row.chrf < prev.chrf - REGRESSION_DELTA {
+            out.regressions.push(Regression {
+                input: row.input.clone(),
+                tag: row.tag.clone(),
+                reference: row.reference.clone(),
+                before: prev.logos_output.clone(),
+                after: row.logos_output.clone(),
+                chrf_before: prev.chrf,
+                chrf_after: row.chrf,
+                exact_before: prev.exact,
+                exact_after: row.exact,
+            });
+        } else if (!prev.exact && row.exact) || row.chrf > prev.chrf + REGRESSION_DELTA {
+            out.improved += 1;
+        }
+    }
+    out
+}
+
+impl Comparison {
+    pub fn render(&self, against: &str, diff_path: &str) -> String {
+        let mut out = format!(
+            "对比 {}: 共同 {} 条, 提升 {}, 回退 {}",
+            against,
+            self.matched,
+            self.improved,
+            self.regressions.len()
+        );
+        if self.regressions.is_empty() {
+            return out;
+        }
+        out.push_str(&format!("（逐条见 {}）", diff_path));
+        let preview = |s: &str| -> String { s.chars().take(30).collect() };
+        for r in self.regressions.iter().take(REGRESSIONS_SHOWN) {
+            out.push_str(&format!(
+                "\n  - [{}] {} | chrF {:.3}→{:.3} | 之前: {} | 现在: {}",
+                r.tag,
+                preview(&r.input),
+                r.chrf_before,
+                r.chrf_after,
+                preview(&r.before),
+                preview(&r.after)
+            ));
+        }
+        if self.regressions.len() > REGRESSIONS_SHOWN {
+            out.push_str(&format!("\n  …另有 {} 条", self.regressions.len() - REGRESSIONS_SHOWN));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(input: &str, output: &str, reference: &str) -> EvalRow {
+        EvalRow {
+            input: input.into(),
+            reference: reference.into(),
+            logos_output: output.into(),
+            hit: true,
+            pattern_id: None,
+            tag: UNTAGGED.into(),
+            exact: false,
+            similarity: 0.0,
+            chrf: 0.0,
+            bleu: 0.0,
+        }
+        .scored(if output == reference { 1.0 } else { 0.0 })
+    }
+
+    #[test]
+    fn test_metrics_and_regressions() {
+        assert!((chrf("今天天气晴朗", "今天天气晴朗") - 1.0).abs() < 1e-9);
+        assert!((bleu("今天天气晴朗", "今天天气晴朗") - 1.0).abs() < 1e-9);
+        assert_eq!(chrf("", "参考"), 0.0);
+        let partial = chrf("今天天气不错", "今天天气晴朗");
+        assert!(partial > 0.2 && partial < 1.0);
+        assert!(bleu("今天", "今天天气晴朗") < bleu("今天天气晴", "今天天气晴朗"));
+
+        let previous = vec![row("a", "晴朗", "晴朗"), row("b", "无关", "下雨"), row("c", "多云", "多云")];
+        let current = vec![row("a", "不知道", "晴朗"), row("b", "下雨", "下雨"), row("c", "多云", "多云")];
+        let cmp = compare(&previous, &current);
+        assert_eq!((cmp.matched, cmp.improved, cmp.regressions.len()), (3, 1, 1));
+        assert_eq!(cmp.regressions[0].input, "a");
+
+        let opts = EvalOptions { name: Some("../x".into()), ..Default::default() };
+        assert!(opts.output_path("data").is_err());
+        assert_eq!(EvalOptions::default().output_path("data"), Ok("data/eval_result.jsonl".to_string()));
+        let (path, opts) = EvalOptions::split_command("data/test.jsonl live name:v2 compare:v1");
+        assert_eq!(path, "data/test.jsonl");
+        assert!(opts.live);
+        assert_eq!((opts.name.as_deref(), opts.compare.as_deref()), (Some("v2"), Some("v1")));
+    }
+}
@@ -1,91 +1,252 @@
 use crate::frozen::bedrock;
+use crate::pattern_index::{InvertedIndex, PatternId};
+use crate::match_explain::{self, CandidateScore, FilterReason, MatchExplanation, MatchStage, TemplateHit};
+use crate::language_eval::{self, EvalOptions, EvalReport, EvalRow};
+use crate::pattern_export::{ExportOptions, ExportRow};
+use crate::response_guard::{GuardRule, ResponseGuard};
+use crate::pattern_import::{ConflictPolicy, ImportMode, ImportOptions, ImportSummary};
+use crate::pattern_admin::{PatternEdit, PatternPage, PatternQuery, PatternSort, PatternSummary};
+use crate::provenance::{Origin, Provenance};
+use crate::response_variant::{self, ResponseVariant, VariantCondition, VariantContext, MAX_VARIANTS};
+use crate::slot_template::{self, Captures, SlotTemplate};
+use crate::tokenizer::{self, CharBigramTokenizer, Lang, Tokenizer, TokenizerState};
 use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::io::Write;
+use chrono::Timelike;
 use serde::{Deserialize, Serialize};
 
+/*
+ * id 为稳定 id：分配后不变、不复用，淘汰/删除/压缩都不影响其它模式的 id，
+ * 反馈记录、评估结果等可以据此引用模式。patterns Vec 中的位置（slot）只在内部使用。
+ * variants 至少有一条；weight 是模式级权重（参与匹配排序），变体权重只决定命中后回哪一句。
+ * template 不为空的是槽位模板：不进倒排索引，tokens 只含固定文字，匹配走 match_template。
+ * provenance 记录来源、创建/修改时间和正负反馈次数，见 provenance。
+ */
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Pattern {
+    pub id: PatternId,
     pub tokens: Vec<String>,
-    pub response: String,
+    pub variants: Vec<ResponseVariant>,
     pub weight: f64,
     pub frequency: u32,
     pub last_used: u64,
+    pub lang: Lang,
+    pub template: Option<SlotTemplate>,
+    pub provenance: Provenance,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
-pub struct FractalNode {
-    pub children: std::collections::HashMap<String, FractalNode>,
-    pub pattern_ids: Vec<usize>,
-}
+impl Pattern {
+    /*
+     * 主回复：权重最高的无条件变体，没有无条件变体时取第一条。
+     */
+    pub fn primary(&self) -> &str {
+        self.variants
+            .iter()
+            .filter(|v| v.condition.is_unconditional())
+            .max_by(|a, b| a.weight.partial_cmp(&b.weight).unwrap_or(Ordering::Equal))
+            .or(self.variants.first())
+            .map(|v| v.text.as_str())
+            .unwrap_or("")
+    }
 
-impl FractalNode {
-    pub fn insert(&mut self, tokens: &[String], id: usize, depth: usize) {
-        if depth >= bedrock::RECURSIVE_MAX_DEPTH || tokens.is_empty() {
-            if !self.pattern_ids.contains(&id) {
-                self.pattern_ids.push(id);
-            }
-            return;
+    fn primary_index(&self) -> Option<usize> {
+        self.variants
+            .iter()
+            .enumerate()
+            .filter(|(_, v)| v.condition.is_unconditional())
+            .max_by(|a, b| a.1.weight.partial_cmp(&b.1.weight).unwrap_or(Ordering::Equal))
+            .map(|(i, _)| i)
+    }
+
+    fn primary_mut(&mut self) -> Option<&mut ResponseVariant> {
+        let idx = self.primary_index().unwrap_or(0);
+        self.variants.get_mut(idx)
+    }
+
+    /*
+     * 取代主回复：原主回复删掉（文本相同时只提权），带条件的和显式追加的其他变体保留。
+     */
+    fn replace_primary(&mut self, text: &str, weight: f64) -> usize {
+        if let Some(i) = self.primary_index().filter(|&i| self.variants[i].text != text) {
+            self.variants.remove(i);
         }
-        self.children
-            .entry(tokens[0].clone())
-            .or_default()
-            .insert(&tokens[1..], id, depth + 1);
+        self.upsert_variant(text, VariantCondition::default(), weight)
     }
 
-    pub fn search(&self, tokens: &[String], depth: usize) -> Vec<(usize, f64)> {
-        let sim = 1.0 - (depth as f64 * 0.02).min(0.5);
-        let mut results: Vec<(usize, f64)> = self.pattern_ids.iter().map(|&id| (id, sim)).collect();
-        if tokens.is_empty() {
-            return results;
+    /* 按教学方式写入回复：带条件的回复总是作为变体 */
+    fn teach_response(&mut self, text: &str, condition: VariantCondition, weight: f64, mode: TeachMode) -> usize {
+        match mode {
+            TeachMode::Replace if condition.is_unconditional() => self.replace_primary(text, weight),
+            _ => self.upsert_variant(text, condition, weight),
         }
-        if let Some(child) = self.children.get(&tokens[0]) {
-            results.extend(child.search(&tokens[1..], depth + 1));
+    }
+
+    /*
+     * 加入或强化一条变体：文本和条件都相同则提权，否则新增；超出上限时丢弃权重最低的一条。
+     */
+    fn upsert_variant(&mut self, text: &str, condition: VariantCondition, weight: f64) -> usize {
+        if let Some(i) = self.variants.iter().position(|v| v.text == text && v.condition == condition) {
+            self.variants[i].weight = self.variants[i].weight.max(weight);
+            return i;
         }
-        for (key, child) in &self.children {
-            if key != &tokens[0] && Self::similar(key, &tokens[0]) {
-                for (id, sim) in child.search(&tokens[1..], depth + 2) {
-                    results.push((id, sim * 0.8));
-                }
+        if self.variants.len() >= MAX_VARIANTS {
+            if let Some(weakest) = self
+                .variants
+                .iter()
+                .enumerate()
+                .min_by(|a, b| a.1.weight.partial_cmp(&b.1.weight).unwrap_or(Ordering::Equal))
+                .map(|(i, _)| i)
+            {
+                self.variants.remove(weakest);
             }
         }
-        results
+        self.variants.push(ResponseVariant { text: text.to_string(), weight, condition });
+        self.variants.len() - 1
     }
+}
 
-    fn similar(a: &str, b: &str) -> bool {
-        if a == b {
-            return true;
-        }
-        if a.contains(b) || b.contains(a) {
-            return true;
+/*
+ * 再教同一触发词时：Replace 取代原主回复（纠正答案），Variant 追加为变体（「补充回复」）。
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TeachMode {
+    Replace,
+    Variant,
+}
+
+fn single_variant(text: String) -> Vec<ResponseVariant> {
+    vec![ResponseVariant { text, weight: 1.0, condition: VariantCondition::default() }]
+}
+
+/*
+ * language.bin 格式：LANGUAGE_MAGIC + bincode(PatternFile)。
+ * 没有魔数的是最初的裸 Vec<PatternV0>：按顺序分配 id，按词元判断语言，来源记为 Legacy。
+ */
+const LANGUAGE_MAGIC: &[u8; 8] = b"LOGOSLP1";
+
+#[derive(Serialize, Deserialize)]
+struct PatternFile {
+    next_id: PatternId,
+    patterns: Vec<Pattern>,
+}
+
+#[derive(Deserialize)]
+struct PatternV0 {
+    tokens: Vec<String>,
+    response: String,
+    weight: f64,
+    frequency: u32,
+    last_used: u64,
+}
+
+impl PatternV0 {
+    fn migrate(self, id: PatternId) -> Pattern {
+        Pattern {
+            id,
+            lang: tokens_lang(&self.tokens),
+            tokens: self.tokens,
+            variants: single_variant(self.response),
+            weight: self.weight,
+            frequency: self.frequency,
+            provenance: Provenance::legacy(self.last_used),
+            last_used: self.last_used,
+            template: None,
         }
-        Self::edit_dist(a, b) <= 2
     }
+}
 
-    fn edit_dist(a: &str, b: &str) -> usize {
-        let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
-        let (m, n) = (a.len(), b.len());
-        if m == 0 {
-            return n;
-        }
-        if n == 0 {
-            return m;
-        }
-        let mut dp = vec![vec![0usize; n + 1]; m + 1];
-        for (i, row) in dp.iter_mut().enumerate().take(m + 1) {
-            row[0] = i;
-        }
-        for (j, val) in dp[0].iter_mut().enumerate().take(n + 1) {
-            *val = j;
-        }
-        for i in 1..=m {
-            for j in 1..=n {
-                let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
-                dp[i][j] = (dp[i - 1][j] + 1)
-                    .min(dp[i][j - 1] + 1)
-                    .min(dp[i - 1][j - 1] + cost);
-            }
-        }
-        dp[m][n]
+fn decode_patterns(data: &[u8]) -> Result<PatternFile, String> {
+    if let Some(body) = data.strip_prefix(LANGUAGE_MAGIC.as_slice()) {
+        return bincode::deserialize(body).map_err(|e| format!("反序列化失败: {}", e));
+    }
+    let old: Vec<PatternV0> = bincode::deserialize(data).map_err(|e| format!("反序列化失败: {}", e))?;
+    let patterns: Vec<Pattern> = old
+        .into_iter()
+        .enumerate()
+        .map(|(i, p)| p.migrate(i as PatternId))
+        .collect();
+    Ok(PatternFile { next_id: patterns.len() as PatternId, patterns })
+}
+
+/*
+ * 合并导入计划中的一个触发词：已有模式的 slot（Ok）或待新增项下标（Err），
+ * 以及计划执行后它会有的回复和主回复。
+ */
+struct ImportTarget {
+    slot: Result<usize, usize>,
+    texts: Vec<String>,
+    primary: String,
+}
+
+/* 合并导入时的去重键：去重、排序后的 token */
+fn token_set(tokens: &[String]) -> Vec<String> {
+    let mut set = tokens.to_vec();
+    set.sort();
+    set.dedup();
+    set
+}
+
+/*
+ * honest_fallback_en 的回复模板，按插值处切成片段。英文兜底必须整句对上模板，
+ * 只是包含 "not sure what" 之类短语的正常回答不算兜底。
+ */
+const EN_FALLBACK_TEMPLATES: &[&[&str]] = &[
+    &["I can't answer that yet. Teach me with \"learn ", " -> (your answer)\" and I'll know it next time. Patterns learned: ", "."],
+    &["I can't do that yet. Type \"list\" to see installed pouches, or teach me a line or two first."],
+    &["Could you be more specific?"],
+    &["I haven't learned \"", "\" yet. Teach me with \"learn phrase -> answer\"."],
+    &["I'm not sure what \"", "\" means. Could you teach me?"],
+    &["I can't match that yet. Install a pouch or teach me a line, then try again."],
+    &["No match yet. ", " patterns learned; teaching me more helps a lot."],
+];
+
+/* 首段开头、末段结尾、中间各段依次出现；只有一段时要求完全相同 */
+fn matches_template(text: &str, pieces: &[&str]) -> bool {
+    let (first, last) = match pieces {
+        [] => return false,
+        [only] => return text == *only,
+        [first, .., last] => (first, last),
+    };
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    for piece in &pieces[1..pieces.len() - 1] {
+        match rest.find(piece) {
+            Some(i) => rest = &rest[i + piece.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+fn tokens_lang(tokens: &[String]) -> Lang {
+    Lang::detect(&tokens.concat())
+}
+
+fn truncate_input(input: &str) -> &str {
+    if input.len() <= bedrock::MAX_INPUT_LEN {
+        return input;
+    }
+    let mut end = bedrock::MAX_INPUT_LEN;
+    while !input.is_char_boundary(end) && end > 0 {
+        end -= 1;
+    }
+    &input[..end]
+}
+
+/*
+ * 短输入（中文不超过 4 字、英文不超过 2 词）允许命中单 token 模式，否则模式至少 2 个 token。
+ */
+fn min_pattern_tokens(input: &str, tokens: &[String], lang: Lang) -> usize {
+    let short = match lang {
+        Lang::Zh => input.chars().filter(|c| !c.is_whitespace()).count() <= 4,
+        Lang::En => tokens.len() <= 2,
+    };
+    if short {
+        1
+    } else {
+        2
     }
 }
 
@@ -101,6 +262,8 @@ const STALE_TICK_THRESHOLD: u64 = 500;
 const STALE_DECAY_FACTOR: f64 = 0.95;
 const ABSORB_WEIGHT: f64 = 1.2;
 const ABSORB_MERGE_THRESHOLD: f64 = 0.85;
+const SEARCH_CANDIDATES: usize = 64;
+const MIN_COVERAGE: f64 = 0.3;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FeedbackRecord {
@@ -110,6 +273,8 @@ pub struct FeedbackRecord {
     pub correction: String,
     pub timestamp: u64,
     pub source: String,
+    #[serde(default)]
+    pub pattern_id: Option<PatternId>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -119,18 +284,53 @@ struct FeedbackState {
     absorbed: usize,
 }
 
+/*
+ * 一轮对话留在 LanguagePouch 上的状态：会话上下文、本轮标签、学习暂停和最近一次命中。
+ * 请求等待远程尿袋、放开编排器锁期间整体换出，其他请求看不到也改不到。
+ */
+#[derive(Default)]
+pub struct LanguageTurn {
+    context: Vec<(String, String)>,
+    last_was_pattern_hit: bool,
+    last_match_weight: f64,
+    last_pattern_id: Option<PatternId>,
+    last_served: Option<(PatternId, usize)>,
+    session_tags: Vec<String>,
+    learning_paused: bool,
+}
+
+/*
+ * 只读阶段（持读锁）预先算好的本库模式匹配，由 process_with_lookup 采用。记下当时的上一句
+ * 输入和会话标签：应用时它们变了、命中的模式已被删除、变体越界或已被拦截，就重新匹配。
+ */
+#[derive(Debug, Clone)]
+pub struct PatternLookup {
+    prev_input: Option<String>,
+    session_tags: Vec<String>,
+    hit: Option<(PatternId, usize, Captures)>,
+}
+
 pub struct LanguagePouch {
     patterns: Vec<Pattern>,
-    root: FractalNode,
+    slots: HashMap<PatternId, usize>,
+    next_id: PatternId,
+    index: InvertedIndex,
+    templates: Vec<PatternId>,
     tick: u64,
     route_patterns: Vec<(Vec<String>, String)>,
     sync_buffer: Vec<(Vec<String>, String)>,
     context: Vec<(String, String)>,
     last_was_pattern_hit: bool,
     last_match_weight: f64,
+    last_pattern_id: Option<PatternId>,
+    last_served: Option<(PatternId, usize)>,
+    session_tags: Vec<String>,
+    learning_paused: bool,
     miss_buffer: Vec<(Vec<String>, String)>,
     feedback_log: Vec<FeedbackRecord>,
     absorbed_count: usize,
+    guard: ResponseGuard,
+    tokenizer: Box<dyn Tokenizer>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -143,18 +343,34 @@ pub struct RequirementIntent {
 
 impl LanguagePouch {
     pub fn new() -> Self {
+        Self::with_tokenizer(Box::new(CharBigramTokenizer))
+    }
+
+    /*
+     * 指定分词器构造：种子模式和之后学到的模式都用它分词，各实例（命名空间）互不影响。
+     */
+    pub fn with_tokenizer(tokenizer: Box<dyn Tokenizer>) -> Self {
         let mut p = Self {
             patterns: Vec::new(),
-            root: FractalNode::default(),
+            slots: HashMap::new(),
+            next_id: 0,
+            index: InvertedIndex::new(),
+            templates: Vec::new(),
             tick: 0,
             route_patterns: Vec::new(),
             sync_buffer: Vec::new(),
             context: Vec::new(),
             last_was_pattern_hit: false,
             last_match_weight: 0.0,
+            last_pattern_id: None,
+            last_served: None,
+            session_tags: Vec::new(),
+            learning_paused: false,
             miss_buffer: Vec::new(),
             feedback_log: Vec::new(),
             absorbed_count: 0,
+            guard: ResponseGuard::default(),
+            tokenizer,
         };
         p.seed();
         p
@@ -196,25 +412,49 @@ impl LanguagePouch {
             (&["天气", "今天天气"], "我无法访问天气数据。我是纯本地系统，没有互联网访问能力（除非通过云端尿袋）。"),
             (&["你好笨"], "同意。当前模式少，所以重复。多对话、多教我，会改善。"),
             (&["继续"], "请告诉我继续做什么。"),
+            (&["hello", "hi"], "Hello. What do you need?"),
+            (&["who are you"], "LOGOS, a local fractal learning system. No cloud LLM; I learn patterns from conversation."),
+            (&["what can you do"], "I remember patterns from conversation, route requests to pouches (pluggable skills), and evolve routing rules from frequent use. Type \"help\" for commands."),
+            (&["thanks", "thank you"], "You're welcome."),
+            (&["bye", "goodbye"], "Goodbye."),
+            (&["how do you learn"], "Each turn is tokenized and stored in a fractal tree; similar inputs hit it next time and gain weight. Teach me directly with \"learn X -> Y\"."),
         ];
         for (triggers, resp) in seeds {
             for trigger in *triggers {
                 let tokens = self.tokenize(trigger);
                 if !tokens.is_empty() {
-                    self.add_pattern(tokens, resp.to_string(), 1.0);
+                    self.add_pattern(tokens, resp.to_string(), 1.0, Origin::Seed);
                 }
             }
         }
     }
 
     pub fn save(&self) -> Result<Vec<u8>, String> {
-        bincode::serialize(&self.patterns).map_err(|e| format!("序列化失败: {}", e))
+        #[derive(Serialize)]
+        struct PatternFileRef<'a> {
+            next_id: PatternId,
+            patterns: &'a [Pattern],
+        }
+        let body = bincode::serialize(&PatternFileRef { next_id: self.next_id, patterns: &self.patterns })
+            .map_err(|e| format!("序列化失败: {}", e))?;
+        let mut data = LANGUAGE_MAGIC.to_vec();
+        data.extend(body);
+        Ok(data)
     }
 
     pub fn load(&mut self, data: &[u8]) -> Result<(), String> {
-        let patterns: Vec<Pattern> =
-            bincode::deserialize(data).map_err(|e| format!("反序列化失败: {}", e))?;
-        self.patterns = patterns;
+        let file = decode_patterns(data)?;
+        let max_id = file.patterns.iter().map(|p| p.id + 1).max().unwrap_or(0);
+        self.next_id = file.next_id.max(max_id);
+        /* tick 不落盘：续上已记录的最大 tick，保证新旧模式的 tick 可比、年龄不下溢 */
+        let max_tick = file
+            .patterns
+            .iter()
+            .map(|p| p.last_used.max(p.provenance.modified_tick))
+            .max()
+            .unwrap_or(0);
+        self.tick = self.tick.max(max_tick);
+        self.patterns = file.patterns;
         self.rebuild_index();
         Ok(())
     }
@@ -237,75 +477,369 @@ impl LanguagePouch {
         Ok(())
     }
 
-    fn add_pattern(&mut self, tokens: Vec<String>, response: String, weight: f64) {
+    pub fn save_guard(&self) -> Result<Vec<u8>, String> {
+        serde_json::to_vec(&self.guard).map_err(|e| format!("序列化失败: {}", e))
+    }
+
+    pub fn load_guard(&mut self, data: &[u8]) -> Result<(), String> {
+        self.guard = serde_json::from_slice(data).map_err(|e| format!("反序列化失败: {}", e))?;
+        self.compile_guard();
+        Ok(())
+    }
+
+    /* 否定模式只存原文，按当前分词器重新切分 */
+    fn compile_guard(&mut self) {
+        let tokenizer = self.tokenizer.as_ref();
+        self.guard.compile(|text| tokenizer::tokenize_mixed(tokenizer, text));
+    }
+
+    /*
+     * 磁盘上的 language.bin 是否为当前格式；不是则加载后需要重写（迁移）。
+     */
+    pub fn is_current_format(data: &[u8]) -> bool {
+        data.starts_with(LANGUAGE_MAGIC.as_slice())
+    }
+
+    fn add_pattern(&mut self, tokens: Vec<String>, response: String, weight: f64, origin: Origin) {
         if tokens.is_empty() {
             return;
         }
         self.evict_if_needed();
-        let id = self.patterns.len();
+        self.push_pattern(tokens, response, weight, origin);
+    }
+
+    fn push_pattern(&mut self, tokens: Vec<String>, response: String, weight: f64, origin: Origin) -> PatternId {
+        self.push_pattern_with(tokens, single_variant(response), weight, None, origin)
+    }
+
+    fn push_pattern_with(
+        &mut self,
+        tokens: Vec<String>,
+        variants: Vec<ResponseVariant>,
+        weight: f64,
+        template: Option<SlotTemplate>,
+        origin: Origin,
+    ) -> PatternId {
+        let id = self.next_id;
+        self.next_id += 1;
+        if template.is_some() {
+            self.templates.push(id);
+        } else {
+            self.index.insert(id, &tokens);
+        }
+        self.slots.insert(id, self.patterns.len());
         self.patterns.push(Pattern {
-            tokens: tokens.clone(),
-            response,
+            id,
+            lang: tokens_lang(&tokens),
+            tokens,
+            variants,
             weight,
             frequency: 1,
             last_used: self.tick,
+            template,
+            provenance: Provenance::new(origin, self.tick),
         });
-        self.root.insert(&tokens, id, 0);
+        id
+    }
+
+    /*
+     * 按稳定 id 删除：Vec 中 swap_remove（只有被换位的那条更新 slot），索引里打墓碑，
+     * 墓碑累积到一定比例再压缩倒排表。
+     */
+    fn remove_pattern(&mut self, id: PatternId) -> Option<Pattern> {
+        let slot = self.slots.remove(&id)?;
+        let removed = self.patterns.swap_remove(slot);
+        if let Some(moved) = self.patterns.get(slot) {
+            self.slots.insert(moved.id, slot);
+        }
+        if removed.template.is_some() {
+            self.templates.retain(|&t| t != id);
+        } else {
+            self.index.remove(id, &removed.tokens);
+        }
+        Some(removed)
+    }
+
+    fn slot_of(&self, id: PatternId) -> Option<usize> {
+        self.slots.get(&id).copied()
     }
 
     fn evict_if_needed(&mut self) {
         if self.patterns.len() < bedrock::MAX_PATTERNS {
             return;
         }
-        let mut scored: Vec<(usize, f64)> = self
+        let mut scored: Vec<(PatternId, f64)> = self
             .patterns
             .iter()
-            .enumerate()
-            .map(|(i, p)| {
+            .map(|p| {
                 let age = (self.tick - p.last_used + 1) as f64;
                 let score = p.weight * (p.frequency as f64).ln().max(1.0) / age;
-                (i, score)
+                (p.id, score)
             })
             .collect();
-        scored.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal));
-        let to_remove: std::collections::HashSet<usize> = scored
-            .iter()
-            .take(bedrock::LRU_EVICT_COUNT)
-            .map(|(i, _)| *i)
-            .collect();
-        self.patterns = self
-            .patterns
-            .iter()
-            .enumerate()
-            .filter(|(i, _)| !to_remove.contains(i))
-            .map(|(_, p)| p.clone())
-            .collect();
-        self.rebuild_index();
+        scored.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal).then(a.0.cmp(&b.0)));
+        for (id, _) in scored.into_iter().take(bedrock::LRU_EVICT_COUNT) {
+            self.remove_pattern(id);
+        }
+        self.index.maybe_compact();
     }
 
+    /*
+     * 全量重建 slot 表和索引：只在加载、导入、重新分词时使用。
+     */
     fn rebuild_index(&mut self) {
-        self.root = FractalNode::default();
-        for (id, p) in self.patterns.iter().enumerate() {
-            self.root.insert(&p.tokens, id, 0);
-        }
+        self.slots = self.patterns.iter().enumerate().map(|(slot, p)| (p.id, slot)).collect();
+        self.templates = self.patterns.iter().filter(|p| p.template.is_some()).map(|p| p.id).collect();
+        self.index = InvertedIndex::build(
+            self.patterns
+                .iter()
+                .filter(|p| p.template.is_none())
+                .map(|p| (p.id, p.tokens.as_slice())),
+        );
     }
 
     pub fn memory_count(&self) -> usize {
         self.patterns.len()
     }
 
-    pub fn import_patterns(&mut self, patterns: Vec<(Vec<String>, String, f64)>) {
-        self.patterns.clear();
+    pub fn index_tombstones(&self) -> usize {
+        self.index.tombstones()
+    }
+
+    pub fn pattern(&self, id: PatternId) -> Option<&Pattern> {
+        self.slot_of(id).map(|slot| &self.patterns[slot])
+    }
+
+    /*
+     * 触发词原文：模板取源文本，英文按词拼接，中文用当前分词器还原（还原失败时直接拼接 token）。
+     */
+    pub fn trigger_text(&self, p: &Pattern) -> String {
+        if let Some(t) = &p.template {
+            return t.source().to_string();
+        }
+        self.tokens_text(&p.tokens, p.lang)
+    }
+
+    fn tokens_text(&self, tokens: &[String], lang: Lang) -> String {
+        match lang {
+            Lang::En => tokens.join(" "),
+            Lang::Zh => self.tokenizer.reconstruct(tokens).unwrap_or_else(|| tokens.concat()),
+        }
+    }
+
+    pub fn list_patterns(&self, query: &PatternQuery) -> PatternPage {
+        let mut hits: Vec<(&Pattern, String)> = self
+            .patterns
+            .iter()
+            .map(|p| (p, self.trigger_text(p)))
+            .filter(|(p, trigger)| query.matches(p, trigger))
+            .collect();
+        hits.sort_by(|(a, _), (b, _)| {
+            let order = match query.sort {
+                PatternSort::Weight => b.weight.partial_cmp(&a.weight).unwrap_or(Ordering::Equal),
+                PatternSort::Recent => b.last_used.cmp(&a.last_used),
+            };
+            order.then(a.id.cmp(&b.id))
+        });
+        let (page, page_size) = query.paging();
+        PatternPage {
+            total: hits.len(),
+            page,
+            page_size,
+            items: hits
+                .into_iter()
+                .skip((page - 1).saturating_mul(page_size))
+                .take(page_size)
+                .map(|(p, trigger)| PatternSummary::of(p, trigger))
+                .collect(),
+        }
+    }
+
+    /*
+     * 修改主回复和/或模式权重；回复带 [[when ...]] 时只改文字、不改条件。
+     */
+    pub fn edit_pattern(&mut self, id: PatternId, edit: &PatternEdit) -> Result<(), String> {
+        let slot = self.slot_of(id).ok_or_else(|| format!("没有编号为 {} 的模式", id))?;
+        if let Some(w) = edit.weight {
+            if !(0.1..=10.0).contains(&w) {
+                return Err("权重应在 0.1 到 10 之间".into());
+            }
+        }
+        let stripped = match edit.response.as_deref() {
+            Some(r) => Some(VariantCondition::split_suffix(r)?.0),
+            None => None,
+        };
+        let response = match stripped.as_deref().map(str::trim) {
+            Some("") => return Err("回复不能为空".into()),
+            Some(r) => {
+                if let Some(t) = &self.patterns[slot].template {
+                    t.check_response(r)?;
+                }
+                Some(r.to_string())
+            }
+            None => None,
+        };
+        if response.is_none() && edit.weight.is_none() {
+            return Err("没有要修改的内容".into());
+        }
+        let tick = self.tick;
+        let p = &mut self.patterns[slot];
+        if let (Some(r), Some(v)) = (response, p.primary_mut()) {
+            v.text = r;
+        }
+        if let Some(w) = edit.weight {
+            p.weight = w;
+        }
+        p.provenance.touch(tick);
+        Ok(())
+    }
+
+    pub fn delete_pattern(&mut self, id: PatternId) -> bool {
+        let removed = self.remove_pattern(id).is_some();
+        if removed {
+            self.index.maybe_compact();
+        }
+        removed
+    }
+
+    /*
+     * 批量删除符合条件的模式，返回删除条数；没有筛选条件时拒绝（避免清空全部）。
+     */
+    pub fn delete_matching(&mut self, query: &PatternQuery) -> Result<usize, String> {
+        if !query.has_filter() {
+            return Err("批量删除需要至少一个筛选条件".into());
+        }
+        let ids: Vec<PatternId> = self
+            .patterns
+            .iter()
+            .filter(|p| query.matches(p, &self.trigger_text(p)))
+            .map(|p| p.id)
+            .collect();
+        for &id in &ids {
+            self.remove_pattern(id);
+        }
+        self.index.maybe_compact();
+        Ok(ids.len())
+    }
+
+    /*
+     * 单条模式的来源与审计信息（模式溯源命令用）。
+     */
+    pub fn describe_pattern(&self, id: PatternId) -> Option<String> {
+        let p = self.pattern(id)?;
+        let pv = &p.provenance;
+        let when = |tick: u64, at: u64| {
+            let time = match chrono::DateTime::from_timestamp(at as i64, 0) {
+                Some(t) if at > 0 => t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S").to_string(),
+                _ => "未知".to_string(),
+            };
+            format!("tick {} / {}", tick, time)
+        };
+        let mut out = format!("模式 #{}\n触发: {}\n", p.id, self.trigger_text(p));
+        for v in &p.variants {
+            out.push_str(&format!("回复: {} (权重 {:.2})\n", v.text, v.weight));
+        }
+        out.push_str(&format!("来源: {}\n", pv.origin.label()));
+        out.push_str(&format!("创建: {}\n", when(pv.created_tick, pv.created_at)));
+        out.push_str(&format!("修改: {}\n", when(pv.modified_tick, pv.modified_at)));
+        out.push_str(&format!(
+            "权重: {:.2}  命中: {}  反馈: +{} / -{}",
+            p.weight, p.frequency, pv.positive, pv.negative
+        ));
+        Some(out)
+    }
+
+    /*
+     * 按 ImportOptions 导入（见 pattern_import）：先只读地生成计划并统计，dry-run 到此为止，
+     * 否则再执行计划。合并时同一文件内的重复触发词也按同样规则处理。
+     */
+    pub fn import_patterns(
+        &mut self,
+        patterns: Vec<(Vec<String>, String, f64)>,
+        source: &str,
+        opts: &ImportOptions,
+    ) -> ImportSummary {
+        let mut summary = ImportSummary::new(opts);
+        summary.total = patterns.len();
+        let mut adds: Vec<(Vec<String>, Vec<String>, f64)> = Vec::new();
+        let mut edits: Vec<(usize, String, ConflictPolicy)> = Vec::new();
+        /* token 集合 → 该触发词的去向与已知回复 */
+        let mut seen: HashMap<Vec<String>, ImportTarget> = HashMap::new();
+        if opts.mode == ImportMode::Merge {
+            for (slot, p) in self.patterns.iter().enumerate().filter(|(_, p)| p.template.is_none()) {
+                seen.entry(token_set(&p.tokens)).or_insert_with(|| ImportTarget {
+                    slot: Ok(slot),
+                    texts: p.variants.iter().map(|v| v.text.clone()).collect(),
+                    primary: p.primary().to_string(),
+                });
+            }
+        }
         for (tokens, response, weight) in patterns {
-            self.patterns.push(Pattern {
-                tokens,
-                response,
-                weight,
-                frequency: 1,
-                last_used: self.tick,
-            });
+            let response = response.trim().to_string();
+            if tokens.is_empty() || response.is_empty() {
+                summary.invalid += 1;
+                continue;
+            }
+            if opts.mode != ImportMode::Merge {
+                adds.push((tokens, vec![response], weight));
+                continue;
+            }
+            let key = token_set(&tokens);
+            let Some(target) = seen.get_mut(&key) else {
+                let texts = vec![response.clone()];
+                seen.insert(key, ImportTarget { slot: Err(adds.len()), texts: texts.clone(), primary: response });
+                adds.push((tokens, texts, weight));
+                continue;
+            };
+            if target.texts.contains(&response) || opts.conflict == ConflictPolicy::Existing {
+                summary.skipped += 1;
+                continue;
+            }
+            summary.updated += 1;
+            if opts.conflict == ConflictPolicy::Incoming {
+                let old = std::mem::replace(&mut target.primary, response.clone());
+                target.texts.retain(|t| *t != old);
+            }
+            target.texts.push(response.clone());
+            match target.slot {
+                Ok(slot) => edits.push((slot, response, opts.conflict)),
+                Err(i) => adds[i].1.clone_from(&target.texts),
+            }
         }
-        self.rebuild_index();
+        summary.added = adds.len();
+        if opts.mode == ImportMode::Replace {
+            summary.removed = self.patterns.len();
+        }
+        if opts.dry_run {
+            return summary;
+        }
+
+        if opts.mode == ImportMode::Replace {
+            self.patterns.clear();
+            self.slots.clear();
+            self.templates.clear();
+            self.index = InvertedIndex::new();
+        }
+        let tick = self.tick;
+        for (slot, response, policy) in edits {
+            let p = &mut self.patterns[slot];
+            let top = p.variants.iter().map(|v| v.weight).fold(1.0, f64::max);
+            match (policy, p.primary_mut()) {
+                (ConflictPolicy::Incoming, Some(v)) => v.text = response,
+                _ => {
+                    p.upsert_variant(&response, VariantCondition::default(), top);
+                }
+            }
+            p.provenance.touch(tick);
+        }
+        for (tokens, texts, weight) in adds {
+            let variants = texts
+                .into_iter()
+                .map(|text| ResponseVariant { text, weight: 1.0, condition: VariantCondition::default() })
+                .collect();
+            self.push_pattern_with(tokens, variants, weight, None, Origin::Import(source.to_string()));
+        }
+        summary
     }
 
     pub fn is_fallback_response(&self, text: &str) -> bool {
@@ -321,6 +855,82 @@ impl LanguagePouch {
             || text.contains("没匹配到")
             || text.contains("暂时做不了")
             || text.contains("能再说具体一点")
+            || EN_FALLBACK_TEMPLATES.iter().any(|t| matches_template(text.trim(), t))
+    }
+
+    /*
+     * 拦截检查：命中规则时放入隔离区并返回原因。
+     */
+    fn intercept(&mut self, input: &str, response: &str, origin: Origin) -> Option<String> {
+        if self.guard.is_empty() {
+            return None;
+        }
+        let tokens = self.tokenize(input);
+        let reason = self.guard.check(&tokens, response)?;
+        self.guard.quarantine(input, response, origin, reason.clone());
+        Some(reason)
+    }
+
+    pub fn guard(&self) -> &ResponseGuard {
+        &self.guard
+    }
+
+    pub fn add_block_rule(&mut self, spec: &str) -> Result<u32, String> {
+        self.guard.add(GuardRule::parse_block(spec)?)
+    }
+
+    /*
+     * 否定模式：以后不要用含 response 的回复回答与 trigger 相近的输入。
+     */
+    pub fn add_negative_pattern(&mut self, trigger: &str, response: &str) -> Result<u32, String> {
+        let (trigger, response) = (trigger.trim(), response.trim());
+        let tokens = self.tokenize(trigger);
+        if tokens.is_empty() || response.is_empty() {
+            return Err("触发词或回复为空".into());
+        }
+        self.guard.add(GuardRule::Negative { trigger: trigger.to_string(), tokens, response: response.to_string() })
+    }
+
+    pub fn remove_guard_rule(&mut self, id: u32) -> bool {
+        self.guard.remove(id)
+    }
+
+    /*
+     * 放行：按原来源学习，不再经过拦截；学习失败时条目留在隔离区。
+     */
+    pub fn release_quarantined(&mut self, id: u32) -> Result<PatternId, String> {
+        let entry = self.guard.quarantined_entry(id).cloned().ok_or_else(|| format!("隔离区没有编号 {}", id))?;
+        let (input, response) = (entry.input.as_str(), entry.response.as_str());
+        let pattern = self.learn(input, response, VariantCondition::default(), None, TeachMode::Replace, entry.origin)?;
+        self.guard.take_quarantined(id);
+        Ok(pattern)
+    }
+
+    pub fn discard_quarantined(&mut self, id: u32) -> bool {
+        self.guard.take_quarantined(id).is_some()
+    }
+
+    /*
+     * 按筛选条件导出问答行（见 pattern_export），按权重降序、编号升序。
+     */
+    pub fn export_rows(&self, opts: &ExportOptions) -> Result<Vec<ExportRow>, String> {
+        let range = opts.date_range()?;
+        let mut hits: Vec<&Pattern> = self.patterns.iter().filter(|p| opts.matches(p, range)).collect();
+        hits.sort_by(|a, b| b.weight.partial_cmp(&a.weight).unwrap_or(Ordering::Equal).then(a.id.cmp(&b.id)));
+        let mut rows = Vec::new();
+        for p in hits {
+            let human = self.trigger_text(p);
+            for v in &p.variants {
+                rows.push(ExportRow {
+                    human: human.clone(),
+                    gpt: v.text.clone(),
+                    weight: p.weight,
+                    origin: p.provenance.origin.kind().to_string(),
+                    created_at: p.provenance.created_at,
+                });
+            }
+        }
+        Ok(rows)
     }
 
     pub fn export_summary(&self) -> String {
@@ -379,7 +989,7 @@ impl LanguagePouch {
             .to_string()
     }
 
-    pub fn batch_teach_from_content(&mut self, content: &str) -> usize {
+    pub fn batch_teach_from_content(&mut self, content: &str, source: &str) -> usize {
         let mut count = 0usize;
         for line in content.lines() {
             let line = line.trim();
@@ -398,13 +1008,20 @@ impl LanguagePouch {
             if trigger.is_empty() || response.is_empty() { continue; }
             let response = Self::strip_html(&response);
             if response.is_empty() { continue; }
-            self.teach(&trigger, &response);
-            count += 1;
+            if self.teach_as(&trigger, &response, Origin::Import(source.to_string())).is_ok() {
+                count += 1;
+            }
         }
         count
     }
 
-    pub fn import_from_content(&mut self, content: &str, is_jsonl: bool) -> Result<usize, String> {
+    pub fn import_from_content(
+        &mut self,
+        content: &str,
+        is_jsonl: bool,
+        source: &str,
+        opts: &ImportOptions,
+    ) -> Result<ImportSummary, String> {
         let mut patterns: Vec<(Vec<String>, String, f64)> = Vec::new();
         if is_jsonl {
             for line in content.lines() {
@@ -434,22 +1051,21 @@ impl LanguagePouch {
                 serde_json::from_str(content).map_err(|e| format!("解析失败: {}", e))?;
             patterns = parsed;
         }
-        let count = patterns.len();
-        self.import_patterns(patterns);
-        Ok(count)
+        Ok(self.import_patterns(patterns, source, opts))
     }
 
-    pub async fn eval_from_path(&mut self, path: &str, data_dir: &str) -> Result<String, String> {
+    /*
+     * 逐行评估 JSONL（human/gpt），见 language_eval：默认只读推演，opts.live 时走 process。
+     */
+    pub async fn eval_from_path(&mut self, path: &str, data_dir: &str, opts: &EvalOptions) -> Result<String, String> {
         let content = std::fs::read_to_string(path).map_err(|e| format!("读取失败: {}", e))?;
-        let mut exact = 0usize;
-        let mut total = 0usize;
-        let mut sim_sum = 0.0f64;
-        let mut hit_count = 0usize;
-        let mut hit_sim_sum = 0.0f64;
-        let mut miss_count = 0usize;
-        let mut miss_sim_sum = 0.0f64;
-        let out_path = format!("{}/eval_result.jsonl", data_dir);
-        let mut out_file = std::fs::File::create(&out_path).map_err(|e| format!("创建结果文件失败: {}", e))?;
+        let out_path = opts.output_path(data_dir)?;
+        /* 先读上次结果：对比对象可能正是本次要覆盖的文件 */
+        let previous = match opts.compare_path(data_dir) {
+            Some(p) => Some((Self::load_eval_rows(&p)?, p)),
+            None => None,
+        };
+        let mut rows = Vec::new();
         for line in content.lines() {
             let line = line.trim();
             if line.is_empty() {
@@ -459,42 +1075,69 @@ impl LanguagePouch {
                 serde_json::from_str(line).map_err(|e| format!("行解析失败: {}", e))?;
             let human = v.get("human").and_then(|h| h.as_str()).unwrap_or("").trim();
             let reference = v.get("gpt").and_then(|g| g.as_str()).unwrap_or("").trim();
-            if human.is_empty() {
+            if human.is_empty() || !opts.accepts(&v) {
                 continue;
             }
-            let logos_output = self.process(human).await;
-            let sim = Self::norm_similarity(&logos_output, reference);
-            if self.last_was_pattern_hit() {
-                hit_count += 1;
-                hit_sim_sum += sim;
+            let (logos_output, hit, pattern_id) = if opts.live {
+                let out = self.process(human).await;
+                (out, self.last_was_pattern_hit(), self.last_pattern_id)
             } else {
-                miss_count += 1;
-                miss_sim_sum += sim;
-            }
-            if (logos_output.trim() == reference.trim()) || (sim >= 0.99) {
-                exact += 1;
-            }
-            total += 1;
-            sim_sum += sim;
-            let row = serde_json::json!({
-                "input": human,
-                "reference": reference,
-                "logos_output": logos_output,
-                "hit": self.last_was_pattern_hit()
-            });
-            writeln!(out_file, "{}", serde_json::to_string(&row).unwrap_or_default())
+                let e = self.explain(human, 0, None);
+                let hit = matches!(e.stage, MatchStage::Template | MatchStage::Index | MatchStage::SyncBuffer);
+                (e.response, hit, e.selected)
+            };
+            let sim = Self::norm_similarity(&logos_output, reference);
+            let row = EvalRow {
+                input: human.to_string(),
+                reference: reference.to_string(),
+                logos_output,
+                hit,
+                pattern_id,
+                tag: language_eval::line_tag(&v),
+                exact: false,
+                similarity: 0.0,
+                chrf: 0.0,
+                bleu: 0.0,
+            };
+            rows.push(row.scored(sim));
+        }
+        let mut out_file = std::fs::File::create(&out_path).map_err(|e| format!("创建结果文件失败: {}", e))?;
+        let mut report = EvalReport::default();
+        for row in &rows {
+            report.add(row);
+            writeln!(out_file, "{}", serde_json::to_string(row).unwrap_or_default())
                 .map_err(|e| format!("写入失败: {}", e))?;
         }
         out_file.flush().map_err(|e| format!("flush失败: {}", e))?;
-        let exact_pct = if total > 0 { (exact as f64 / total as f64) * 100.0 } else { 0.0 };
-        let avg_sim = if total > 0 { sim_sum / total as f64 } else { 0.0 };
-        let hit_pct = if total > 0 { (hit_count as f64 / total as f64) * 100.0 } else { 0.0 };
-        let hit_avg = if hit_count > 0 { hit_sim_sum / hit_count as f64 } else { 0.0 };
-        let miss_avg = if miss_count > 0 { miss_sim_sum / miss_count as f64 } else { 0.0 };
-        Ok(format!(
-            "语言评估: {} 条, 精确匹配 {:.1}%, 平均相似度 {:.3}. 命中 {:.1}% (命中均相似 {:.3}, 未命中均相似 {:.3}). 详情 {}",
-            total, exact_pct, avg_sim, hit_pct, hit_avg, miss_avg, out_path
-        ))
+        let mut summary = report.render(&out_path, opts.live);
+        if let Some((previous, against)) = previous {
+            let cmp = language_eval::compare(&previous, &rows);
+            let diff_path = format!("{}.diff.jsonl", out_path.trim_end_matches(".jsonl"));
+            let diff: String = cmp
+                .regressions
+                .iter()
+                .filter_map(|r| serde_json::to_string(r).ok())
+                .map(|l| l + "\n")
+                .collect();
+            std::fs::write(&diff_path, diff).map_err(|e| format!("写入对比失败: {}", e))?;
+            summary.push('\n');
+            summary.push_str(&cmp.render(&against, &diff_path));
+        }
+        Ok(summary)
+    }
+
+    /*
+     * 读取评估结果文件；指标按输出与参考重新计算，旧格式（无指标字段）也能对比。
+     */
+    fn load_eval_rows(path: &str) -> Result<Vec<EvalRow>, String> {
+        let content = std::fs::read_to_string(path).map_err(|e| format!("读取对比文件失败: {}", e))?;
+        let mut rows = Vec::new();
+        for line in content.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            let row: EvalRow = serde_json::from_str(line).map_err(|e| format!("对比文件行解析失败: {}", e))?;
+            let sim = Self::norm_similarity(&row.logos_output, &row.reference);
+            rows.push(row.scored(sim));
+        }
+        Ok(rows)
     }
 
     pub fn rollback_from(&mut self, backup_path: &str) -> Result<usize, String> {
@@ -503,9 +1146,13 @@ impl LanguagePouch {
         Ok(self.patterns.len())
     }
 
+    pub fn route_patterns(&self) -> &[(Vec<String>, String)] {
+        &self.route_patterns
+    }
+
     pub fn learn_routing(&mut self, input: &str, pouch_name: &str) {
         let tokens = self.tokenize(input);
-        if tokens.is_empty() {
+        if self.learning_paused || tokens.is_empty() {
             return;
         }
         for (t, p) in &self.route_patterns {
@@ -552,34 +1199,81 @@ impl LanguagePouch {
     }
 
     pub fn tokenize(&self, input: &str) -> Vec<String> {
-        let chars: Vec<char> = input.chars().filter(|c| !c.is_whitespace()).collect();
-        if chars.len() < 4 {
-            return chars.iter().map(|c| c.to_string()).collect();
+        tokenizer::tokenize_mixed(self.tokenizer.as_ref(), input)
+    }
+
+    pub fn tokenizer_state(&self) -> TokenizerState {
+        TokenizerState::of(self.tokenizer.as_ref())
+    }
+
+    /*
+     * 只替换分词器，不改已存 token：用于声明已加载模式当初使用的分词器。
+     */
+    pub fn set_tokenizer(&mut self, tokenizer: Box<dyn Tokenizer>) {
+        self.tokenizer = tokenizer;
+        self.compile_guard();
+    }
+
+    /*
+     * 迁移到新分词器：用旧分词器还原文本后重新分词（模式、路由、同步缓冲、未命中缓冲），
+     * 再重建分形索引。无法还原的条目保留原 token；英文模式按词存储，不受中文分词器影响。
+     * 返回重新分词的模式数。
+     */
+    pub fn retokenize(&mut self, tokenizer: Box<dyn Tokenizer>) -> usize {
+        let old = std::mem::replace(&mut self.tokenizer, tokenizer);
+        let new = self.tokenizer.as_ref();
+        let convert = |tokens: &mut Vec<String>| -> bool {
+            match old.reconstruct(tokens).map(|text| tokenizer::tokenize_mixed(new, &text)) {
+                Some(t) if !t.is_empty() => {
+                    *tokens = t;
+                    true
+                }
+                _ => false,
+            }
+        };
+        let mut migrated = 0;
+        for p in self.patterns.iter_mut().filter(|p| p.lang == Lang::Zh) {
+            if convert(&mut p.tokens) {
+                migrated += 1;
+            }
         }
-        let mut tokens: Vec<String> = Vec::with_capacity(chars.len() * 2);
-        for c in &chars {
-            tokens.push(c.to_string());
+        for (tokens, _) in self.route_patterns.iter_mut().chain(self.sync_buffer.iter_mut()) {
+            convert(tokens);
         }
-        for pair in chars.windows(2) {
-            tokens.push(pair.iter().collect());
+        for (tokens, raw) in self.miss_buffer.iter_mut() {
+            let t = tokenizer::tokenize_mixed(new, raw);
+            if !t.is_empty() {
+                *tokens = t;
+            }
         }
-        tokens
+        self.compile_guard();
+        self.rebuild_index();
+        migrated
     }
 
     pub async fn process(&mut self, input: &str) -> String {
+        self.process_with_base(input, None).await
+    }
+
+    pub async fn process_with_base(&mut self, input: &str, base: Option<&LanguagePouch>) -> String {
+        self.process_with_lookup(input, base, None).await
+    }
+
+    /*
+     * base：命名空间的共享基础库；本库模式未命中时先只读地查 base，再走同步缓冲/上下文兜底。
+     * lookup：读锁下预先做的本库匹配（见 lookup），仍然有效时跳过模式匹配阶段。
+     */
+    pub async fn process_with_lookup(
+        &mut self,
+        input: &str,
+        base: Option<&LanguagePouch>,
+        lookup: Option<PatternLookup>,
+    ) -> String {
         self.tick += 1;
         if self.tick.is_multiple_of(100) {
             self.decay_stale();
         }
-        let input = if input.len() > bedrock::MAX_INPUT_LEN {
-            let mut end = bedrock::MAX_INPUT_LEN;
-            while !input.is_char_boundary(end) && end > 0 {
-                end -= 1;
-            }
-            &input[..end]
-        } else {
-            input
-        };
+        let input = truncate_input(input);
 
         let tokens = self.tokenize(input);
         if tokens.is_empty() {
@@ -590,77 +1284,397 @@ impl LanguagePouch {
             return rejection;
         }
 
-        let matches = self.root.search(&tokens, 0);
-        let mut best: Option<(usize, f64)> = None;
-        let char_count = input.chars().filter(|c| !c.is_whitespace()).count();
-        let min_pattern_tokens: usize = if char_count <= 4 { 1 } else { 2 };
-
-        for (id, sim) in matches {
-            if id >= self.patterns.len() {
-                continue;
-            }
-            if sim < bedrock::SIMILARITY_THRESHOLD {
-                continue;
-            }
-            let p = &self.patterns[id];
-            if p.tokens.len() < min_pattern_tokens {
-                continue;
-            }
-            let p_distinct: std::collections::HashSet<&String> = p.tokens.iter().collect();
-            let i_distinct: std::collections::HashSet<&String> = tokens.iter().collect();
-            let overlap = p_distinct.intersection(&i_distinct).count();
-            let p_cov = overlap as f64 / p_distinct.len().max(1) as f64;
-            let i_cov = overlap as f64 / i_distinct.len().max(1) as f64;
-            let h_cov = if p_cov > 0.0 && i_cov > 0.0 { 2.0 * p_cov * i_cov / (p_cov + i_cov) } else { 0.0 };
-            if h_cov < 0.3 {
-                continue;
-            }
-            let freshness = 1.0 / (1.0 + (self.tick.saturating_sub(p.last_used) as f64) * 0.001);
-            let score = sim * p.weight * (p.frequency as f64).ln().max(1.0) * freshness * h_cov;
-            if best.is_none_or(|(_, s)| score > s) {
-                best = Some((id, score));
-            }
+        let lang = Lang::detect(input);
+        let vctx = self.variant_context();
+        let staged = match lookup.and_then(|l| self.resolve_lookup(l, &tokens)) {
+            Some(staged) => staged,
+            None => self.pattern_stage(input, &tokens, lang, &vctx, self.tick),
+        };
+        if let Some((slot, variant, captures)) = staged {
+            return self.serve(slot, variant, input, &captures);
         }
+        let shared = base.and_then(|b| b.shared_match(input, &vctx));
 
-        if let Some((id, _)) = best {
-            self.patterns[id].frequency += 1;
-            self.patterns[id].weight = (self.patterns[id].weight + bedrock::LEARNING_RATE).min(10.0);
-            self.patterns[id].last_used = self.tick;
-            let response = self.patterns[id].response.clone();
-            self.push_context(input.to_string(), response.clone());
+        self.last_was_pattern_hit = false;
+        self.last_match_weight = 0.0;
+        self.last_pattern_id = None;
+        self.last_served = None;
+
+        if let Some((response, weight, id)) = shared {
             self.last_was_pattern_hit = true;
-            self.last_match_weight = self.patterns[id].weight;
+            self.last_match_weight = weight;
+            self.last_pattern_id = Some(id);
+            self.push_context(input.to_string(), response.clone());
             return response;
         }
 
-        self.last_was_pattern_hit = false;
-        self.last_match_weight = 0.0;
-
         if let Some(sync_response) = self.sync_buffer_fallback(&tokens) {
-            self.absorb_internal(input, &sync_response, ABSORB_WEIGHT);
+            if !self.learning_paused {
+                self.absorb_internal(input, &sync_response, ABSORB_WEIGHT, "sync");
+            }
             self.push_context(input.to_string(), sync_response.clone());
             self.last_was_pattern_hit = true;
             return sync_response;
         }
 
-        if let Some(ctx_response) = self.context_fallback(input) {
+        if let Some(ctx_response) = self.context_fallback(input, lang) {
             self.push_context(input.to_string(), ctx_response.clone());
             return ctx_response;
         }
 
         self.record_miss(input, &tokens);
-        let fallback = self.honest_fallback(input);
+        let fallback = match lang {
+            Lang::Zh => self.honest_fallback(input),
+            Lang::En => self.honest_fallback_en(input, tokens.len()),
+        };
         self.push_context(input.to_string(), fallback.clone());
         fallback
     }
 
+    fn variant_context(&self) -> VariantContext<'_> {
+        VariantContext {
+            prev_input: self.context.last().map(|(prev, _)| prev.as_str()),
+            session_tags: &self.session_tags,
+            hour: chrono::Local::now().hour() as u8,
+        }
+    }
+
+    fn is_exact_hit(&self, tokens: &[String]) -> bool {
+        self.index
+            .exact(tokens)
+            .iter()
+            .any(|&id| self.slot_of(id).is_some_and(|slot| self.patterns[slot].tokens == tokens))
+    }
+
+    /*
+     * 模式匹配阶段：无整句精确命中时先试槽位模板，再做索引候选打分。返回 (slot, 变体, 抓取值)。
+     */
+    fn pattern_stage(
+        &self,
+        input: &str,
+        tokens: &[String],
+        lang: Lang,
+        vctx: &VariantContext,
+        tick: u64,
+    ) -> Option<(usize, usize, Captures)> {
+        if !self.is_exact_hit(tokens) {
+            if let Some((slot, captures, Some(variant))) = self.template_hit(input, tokens, vctx) {
+                return Some((slot, variant, captures));
+            }
+        }
+        self.rank_candidates(input, tokens, lang, vctx, tick, None)
+            .map(|(slot, variant)| (slot, variant, Vec::new()))
+    }
+
+    /*
+     * 只读地做本库的模式匹配阶段，不记账；prev_input 与 session_tags 取自发起请求的会话。
+     * 输入分词为空时返回 None（交给 process 处理）。
+     */
+    pub fn lookup(&self, input: &str, prev_input: Option<&str>, session_tags: &[String]) -> Option<PatternLookup> {
+        let input = truncate_input(input);
+        let tokens = self.tokenize(input);
+        if tokens.is_empty() {
+            return None;
+        }
+        let vctx = VariantContext { prev_input, session_tags, hour: chrono::Local::now().hour() as u8 };
+        let hit = self
+            .pattern_stage(input, &tokens, Lang::detect(input), &vctx, self.tick + 1)
+            .map(|(slot, variant, captures)| (self.patterns[slot].id, variant, captures));
+        Some(PatternLookup { prev_input: prev_input.map(str::to_string), session_tags: session_tags.to_vec(), hit })
+    }
+
+    /*
+     * 预先匹配的结果在当前状态下是否仍可用：可用时返回 Some(命中与否)，否则 None。
+     */
+    fn resolve_lookup(&self, lookup: PatternLookup, tokens: &[String]) -> Option<Option<(usize, usize, Captures)>> {
+        if lookup.prev_input.as_deref() != self.last_context_input() || lookup.session_tags != self.session_tags {
+            return None;
+        }
+        let Some((id, variant, captures)) = lookup.hit else {
+            return Some(None);
+        };
+        let slot = self.slot_of(id)?;
+        let text = &self.patterns[slot].variants.get(variant)?.text;
+        if self.guard.check(tokens, text).is_some() {
+            return None;
+        }
+        Some(Some((slot, variant, captures)))
+    }
+
+    /*
+     * 作为其他命名空间的基础库被查询：只做模式匹配，不记账；vctx 来自发起查询的会话。
+     * 返回 (回复, 模式权重, 基础库中的模式编号)。
+     */
+    pub fn shared_match(&self, input: &str, vctx: &VariantContext) -> Option<(String, f64, PatternId)> {
+        let tokens = self.tokenize(input);
+        if tokens.is_empty() {
+            return None;
+        }
+        let (slot, variant, captures) = self.pattern_stage(input, &tokens, Lang::detect(input), vctx, self.tick)?;
+        let p = &self.patterns[slot];
+        Some((slot_template::fill(&p.variants[variant].text, &captures), p.weight, p.id))
+    }
+
+    /*
+     * 模板命中：(slot, 抓取值, 选中的变体)；条件都不满足时变体为 None，继续走索引匹配。
+     */
+    fn template_hit(
+        &self,
+        input: &str,
+        tokens: &[String],
+        vctx: &VariantContext,
+    ) -> Option<(usize, Captures, Option<usize>)> {
+        let (slot, captures) = self.match_template(input)?;
+        let variant = self.select_variant(&self.patterns[slot], input, tokens, vctx);
+        Some((slot, captures, variant))
+    }
+
+    /*
+     * 选变体时跳过被回复拦截（见 response_guard）命中的变体；全部被拦截时返回 None。
+     */
+    fn select_variant(&self, p: &Pattern, input: &str, tokens: &[String], vctx: &VariantContext) -> Option<usize> {
+        let seed = input.trim();
+        if self.guard.is_empty() {
+            return response_variant::select(&p.variants, vctx, seed, p.id as u64);
+        }
+        let allowed: Vec<usize> = (0..p.variants.len())
+            .filter(|&i| self.guard.check(tokens, &p.variants[i].text).is_none())
+            .collect();
+        if allowed.len() == p.variants.len() {
+            return response_variant::select(&p.variants, vctx, seed, p.id as u64);
+        }
+        let variants: Vec<ResponseVariant> = allowed.iter().map(|&i| p.variants[i].clone()).collect();
+        response_variant::select(&variants, vctx, seed, p.id as u64).map(|i| allowed[i])
+    }
+
+    fn blocks_any(&self, p: &Pattern, tokens: &[String]) -> bool {
+        p.variants.iter().any(|v| self.guard.check(tokens, &v.text).is_some())
+    }
+
+    /*
+     * 倒排索引候选打分，返回得分最高的 (slot, 变体)。
+     * trace 不为空时记录每个候选的各项得分或被过滤的原因（匹配解释用）。
+     */
+    fn rank_candidates(
+        &self,
+        input: &str,
+        tokens: &[String],
+        lang: Lang,
+        vctx: &VariantContext,
+        tick: u64,
+        mut trace: Option<&mut Vec<CandidateScore>>,
+    ) -> Option<(usize, usize)> {
+        let min_pattern_tokens = min_pattern_tokens(input, tokens, lang);
+        let i_distinct: std::collections::HashSet<&String> = tokens.iter().collect();
+        let mut best: Option<(usize, usize, f64)> = None;
+        for (id, sim) in self.index.search(tokens, SEARCH_CANDIDATES) {
+            let slot = match self.slot_of(id) {
+                Some(slot) => slot,
+                None => continue,
+            };
+            let p = &self.patterns[slot];
+            let mut entry = trace.as_ref().map(|_| CandidateScore::new(id, self.trigger_text(p), sim, p.weight));
+            let reason = if p.lang != lang {
+                Some(FilterReason::OtherLang)
+            } else if sim < bedrock::SIMILARITY_THRESHOLD {
+                Some(FilterReason::BelowSimilarity)
+            } else if p.tokens.len() < min_pattern_tokens {
+                Some(FilterReason::TooFewTokens)
+            } else {
+                None
+            };
+            if let Some(reason) = reason {
+                if let (Some(t), Some(e)) = (trace.as_mut(), entry) {
+                    t.push(e.filter(reason));
+                }
+                continue;
+            }
+            let p_distinct: std::collections::HashSet<&String> = p.tokens.iter().collect();
+            let overlap = p_distinct.intersection(&i_distinct).count();
+            let p_cov = overlap as f64 / p_distinct.len().max(1) as f64;
+            let i_cov = overlap as f64 / i_distinct.len().max(1) as f64;
+            let h_cov = if p_cov > 0.0 && i_cov > 0.0 { 2.0 * p_cov * i_cov / (p_cov + i_cov) } else { 0.0 };
+            let ln_frequency = (p.frequency as f64).ln().max(1.0);
+            let freshness = 1.0 / (1.0 + (tick.saturating_sub(p.last_used) as f64) * 0.001);
+            let score = sim * p.weight * ln_frequency * freshness * h_cov;
+            let variant = if h_cov < MIN_COVERAGE {
+                None
+            } else {
+                self.select_variant(p, input, tokens, vctx)
+            };
+            if let (Some(t), Some(mut e)) = (trace.as_mut(), entry.take()) {
+                e.ln_frequency = ln_frequency;
+                e.freshness = freshness;
+                e.h_cov = h_cov;
+                e.score = score;
+                e.variant = variant;
+                t.push(match (h_cov < MIN_COVERAGE, variant) {
+                    (true, _) => e.filter(FilterReason::LowCoverage),
+                    (false, None) if self.blocks_any(p, tokens) => e.filter(FilterReason::Blocked),
+                    (false, None) => e.filter(FilterReason::NoVariant),
+                    _ => e,
+                });
+            }
+            let variant = match variant {
+                Some(v) => v,
+                None => continue,
+            };
+            if best.is_none_or(|(_, _, s)| score > s) {
+                best = Some((slot, variant, score));
+            }
+        }
+        best.map(|(slot, variant, _)| (slot, variant))
+    }
+
+    /*
+     * 推演 process 对该输入会怎样匹配（见 match_explain），只读。
+     */
+    pub fn explain(&self, input: &str, top_k: usize, base: Option<&LanguagePouch>) -> MatchExplanation {
+        let input = truncate_input(input);
+        let tokens = self.tokenize(input);
+        let lang = Lang::detect(input);
+        let mut out = MatchExplanation {
+            input: input.to_string(),
+            tokens: tokens.clone(),
+            lang,
+            stage: MatchStage::Empty,
+            response: String::new(),
+            selected: None,
+            exact_hit: false,
+            template: None,
+            min_pattern_tokens: min_pattern_tokens(input, &tokens, lang),
+            similarity_threshold: bedrock::SIMILARITY_THRESHOLD,
+            coverage_threshold: MIN_COVERAGE,
+            candidates: Vec::new(),
+            filtered: Vec::new(),
+        };
+        if tokens.is_empty() {
+            out.response = "请说点什么。".into();
+            return out;
+        }
+        if let Some(rejection) = self.check_language(input) {
+            out.stage = MatchStage::Rejected;
+            out.response = rejection;
+            return out;
+        }
+        let vctx = self.variant_context();
+        out.exact_hit = self.is_exact_hit(&tokens);
+        if !out.exact_hit {
+            if let Some((slot, captures, variant)) = self.template_hit(input, &tokens, &vctx) {
+                let p = &self.patterns[slot];
+                if let Some(v) = variant {
+                    out.stage = MatchStage::Template;
+                    out.selected = Some(p.id);
+                    out.response = slot_template::fill(&p.variants[v].text, &captures);
+                }
+                out.template = Some(TemplateHit { id: p.id, captures, variant });
+                if variant.is_some() {
+                    return out;
+                }
+            }
+        }
+        let mut trace = Vec::new();
+        let best = self.rank_candidates(input, &tokens, lang, &vctx, self.tick + 1, Some(&mut trace));
+        (out.candidates, out.filtered) = match_explain::split_top_k(trace, top_k);
+        if let Some((slot, variant)) = best {
+            let p = &self.patterns[slot];
+            out.stage = MatchStage::Index;
+            out.selected = Some(p.id);
+            out.response = p.variants[variant].text.clone();
+        } else if let Some((shared, _, _)) = base.and_then(|b| b.shared_match(input, &vctx)) {
+            out.stage = MatchStage::Base;
+            out.response = shared;
+        } else if let Some(sync_response) = self.sync_buffer_fallback(&tokens) {
+            out.stage = MatchStage::SyncBuffer;
+            out.response = sync_response;
+        } else if let Some(ctx_response) = self.context_fallback(input, lang) {
+            out.stage = MatchStage::Context;
+            out.response = ctx_response;
+        } else {
+            out.stage = MatchStage::Fallback;
+            out.response = match lang {
+                Lang::Zh => self.honest_fallback(input),
+                Lang::En => self.honest_fallback_en(input, tokens.len()),
+            };
+        }
+        out
+    }
+
+    /*
+     * 命中后的记账与回复：模板命中时把抓取的槽位值代入所选变体。
+     */
+    fn serve(&mut self, slot: usize, variant: usize, input: &str, captures: &[(String, String)]) -> String {
+        let p = &mut self.patterns[slot];
+        p.frequency += 1;
+        p.weight = (p.weight + bedrock::LEARNING_RATE).min(10.0);
+        p.last_used = self.tick;
+        let response = slot_template::fill(&p.variants[variant].text, captures);
+        self.last_was_pattern_hit = true;
+        self.last_match_weight = p.weight;
+        self.last_pattern_id = Some(p.id);
+        self.last_served = Some((p.id, variant));
+        self.push_context(input.to_string(), response.clone());
+        response
+    }
+
+    /*
+     * 整句命中的模板里取固定文字最长的（更具体），同长度取权重高的，再按 id 保证确定性。
+     */
+    fn match_template(&self, input: &str) -> Option<(usize, Captures)> {
+        let mut best: Option<(usize, Captures, (usize, f64))> = None;
+        for &id in &self.templates {
+            let slot = match self.slot_of(id) {
+                Some(slot) => slot,
+                None => continue,
+            };
+            let p = &self.patterns[slot];
+            let captures = match p.template.as_ref().and_then(|t| t.capture(input)) {
+                Some(c) => c,
+                None => continue,
+            };
+            let rank = (p.template.as_ref().map(|t| t.literal_text().chars().count()).unwrap_or(0), p.weight);
+            let better = match &best {
+                None => true,
+                Some((b, _, (len, w))) => {
+                    rank.0 > *len
+                        || (rank.0 == *len && rank.1 > *w)
+                        || (rank.0 == *len && rank.1 == *w && p.id < self.patterns[*b].id)
+                }
+            };
+            if better {
+                best = Some((slot, captures, rank));
+            }
+        }
+        best.map(|(slot, captures, _)| (slot, captures))
+    }
+
+    /*
+     * 反馈作用到的变体：该模式上一次实际回出的变体，否则取主回复。
+     */
+    fn served_variant(&self, slot: usize) -> usize {
+        let p = &self.patterns[slot];
+        match self.last_served {
+            Some((id, v)) if id == p.id && v < p.variants.len() => v,
+            _ => p
+                .variants
+                .iter()
+                .position(|v| v.text == p.primary())
+                .unwrap_or(0),
+        }
+    }
+
     pub fn reinforce(&mut self, input: &str) -> bool {
         if let Some(id) = self.find_best_overlap(input) {
-            self.patterns[id].weight = (self.patterns[id].weight * (1.0 + REINFORCE_RATE)).min(10.0);
-            self.patterns[id].frequency += 1;
-            self.patterns[id].last_used = self.tick;
-            let resp = self.patterns[id].response.clone();
-            self.log_feedback(input, &resp, 1, String::new(), "reinforce");
+            let v = self.served_variant(id);
+            let p = &mut self.patterns[id];
+            p.weight = (p.weight * (1.0 + REINFORCE_RATE)).min(10.0);
+            p.frequency += 1;
+            p.last_used = self.tick;
+            p.variants[v].weight = (p.variants[v].weight * (1.0 + REINFORCE_RATE)).min(10.0);
+            p.provenance.record_signal(true, self.tick);
+            let resp = p.variants[v].text.clone();
+            let pattern_id = Some(p.id);
+            self.log_feedback(input, &resp, 1, String::new(), "reinforce", pattern_id);
             return true;
         }
         false
@@ -668,9 +1682,14 @@ impl LanguagePouch {
 
     pub fn penalize(&mut self, input: &str) -> bool {
         if let Some(id) = self.find_best_overlap(input) {
-            self.patterns[id].weight = (self.patterns[id].weight * (1.0 - DECAY_RATE)).max(0.1);
-            let resp = self.patterns[id].response.clone();
-            self.log_feedback(input, &resp, -1, String::new(), "penalize");
+            let v = self.served_variant(id);
+            let p = &mut self.patterns[id];
+            p.weight = (p.weight * (1.0 - DECAY_RATE)).max(0.1);
+            p.variants[v].weight = (p.variants[v].weight * (1.0 - DECAY_RATE)).max(0.1);
+            p.provenance.record_signal(false, self.tick);
+            let resp = p.variants[v].text.clone();
+            let pattern_id = Some(p.id);
+            self.log_feedback(input, &resp, -1, String::new(), "penalize", pattern_id);
             return true;
         }
         false
@@ -681,9 +1700,17 @@ impl LanguagePouch {
         if tokens.is_empty() {
             return None;
         }
+        if let Some((slot, _)) = self.match_template(input) {
+            return Some(slot);
+        }
+        let lang = Lang::detect(input);
         let mut best_idx: Option<usize> = None;
         let mut best_score: f64 = 0.0;
-        for (i, p) in self.patterns.iter().enumerate() {
+        for (id, _) in self.index.search(&tokens, SEARCH_CANDIDATES) {
+            let (i, p) = match self.slot_of(id) {
+                Some(i) if self.patterns[i].lang == lang => (i, &self.patterns[i]),
+                _ => continue,
+            };
             let common = p.tokens.iter().filter(|t| tokens.contains(t)).count();
             let denom = p.tokens.len().max(tokens.len());
             if denom == 0 {
@@ -702,46 +1729,64 @@ impl LanguagePouch {
         }
     }
 
-    pub fn absorb(&mut self, input: &str, response: &str, source_weight: f64) {
-        if input.is_empty() || response.is_empty() {
+    /*
+     * source：产出该回复的尿袋或环节（plan、cloud、train…），记入新模式的来源。
+     */
+    pub fn absorb(&mut self, input: &str, response: &str, source_weight: f64, source: &str) {
+        if self.learning_paused || input.is_empty() || response.is_empty() {
             return;
         }
         if self.is_fallback_response(response) {
             return;
         }
-        self.absorb_internal(input, response, source_weight);
-        self.resolve_misses_for(input, response);
+        if self.intercept(input, response, Origin::Absorb(source.to_string())).is_some() {
+            return;
+        }
+        self.absorb_internal(input, response, source_weight, source);
+        self.resolve_misses_for(input, response, source);
     }
 
-    fn absorb_internal(&mut self, input: &str, response: &str, weight: f64) {
+    fn absorb_internal(&mut self, input: &str, response: &str, weight: f64, source: &str) {
         let tokens = self.tokenize(input);
         if tokens.is_empty() || response.is_empty() {
             return;
         }
-        for p in &mut self.patterns {
+        let lang = tokens_lang(&tokens);
+        for (id, _) in self.index.search(&tokens, SEARCH_CANDIDATES) {
+            let p = match self.slots.get(&id).map(|&slot| &mut self.patterns[slot]) {
+                Some(p) if p.lang == lang => p,
+                _ => continue,
+            };
             let common = p.tokens.iter().filter(|t| tokens.contains(t)).count();
             let denom = p.tokens.len().max(tokens.len());
             if denom > 0 && (common as f64 / denom as f64) >= ABSORB_MERGE_THRESHOLD {
-                let sim = Self::norm_similarity(&p.response, response);
+                let sim = Self::norm_similarity(p.primary(), response);
                 if sim > 0.8 {
                     p.weight = (p.weight + weight * 0.3).min(10.0);
                     p.frequency += 1;
                     p.last_used = self.tick;
+                    p.provenance.touch(self.tick);
                     return;
                 }
-                if sim < 0.3 && response.len() > p.response.len() {
-                    p.response = response.to_string();
+                if sim < 0.3 && response.len() > p.primary().len() {
+                    if let Some(v) = p.primary_mut() {
+                        v.text = response.to_string();
+                    }
                     p.weight = (p.weight + weight * 0.5).min(10.0);
                     p.last_used = self.tick;
+                    p.provenance.touch(self.tick);
                     return;
                 }
             }
         }
-        self.add_pattern(tokens, response.to_string(), weight);
+        self.add_pattern(tokens, response.to_string(), weight, Origin::Absorb(source.to_string()));
         self.absorbed_count += 1;
     }
 
     fn record_miss(&mut self, input: &str, tokens: &[String]) {
+        if self.learning_paused {
+            return;
+        }
         for (existing_tokens, _) in &self.miss_buffer {
             if existing_tokens == tokens {
                 return;
@@ -753,7 +1798,7 @@ impl LanguagePouch {
         self.miss_buffer.push((tokens.to_vec(), input.to_string()));
     }
 
-    fn resolve_misses_for(&mut self, input: &str, response: &str) {
+    fn resolve_misses_for(&mut self, input: &str, response: &str, source: &str) {
         let input_tokens = self.tokenize(input);
         if input_tokens.is_empty() {
             return;
@@ -768,13 +1813,19 @@ impl LanguagePouch {
         }
         for i in resolved_indices.into_iter().rev() {
             let (miss_tokens, _) = self.miss_buffer.remove(i);
-            let already_exists = self.patterns.iter().any(|p| {
+            let candidates = self.index.search(&miss_tokens, SEARCH_CANDIDATES);
+            let already_exists = candidates.iter().filter_map(|(id, _)| self.slot_of(*id)).map(|slot| &self.patterns[slot]).any(|p| {
                 let c = p.tokens.iter().filter(|t| miss_tokens.contains(t)).count();
                 let d = p.tokens.len().max(miss_tokens.len());
                 d > 0 && (c as f64 / d as f64) >= ABSORB_MERGE_THRESHOLD
             });
             if !already_exists {
-                self.add_pattern(miss_tokens, response.to_string(), ABSORB_WEIGHT * 0.8);
+                self.add_pattern(
+                    miss_tokens,
+                    response.to_string(),
+                    ABSORB_WEIGHT * 0.8,
+                    Origin::Absorb(source.to_string()),
+                );
                 self.absorbed_count += 1;
             }
         }
@@ -797,11 +1848,27 @@ impl LanguagePouch {
             return;
         }
         self.penalize(input);
-        self.teach(input, correct_response);
-        self.log_feedback(input, correct_response, 0, correct_response.to_string(), "correction");
-    }
-
-    fn log_feedback(&mut self, input: &str, response: &str, signal: i8, correction: String, source: &str) {
+        /* 纠正的回复以压过其余变体的权重写入，保证下一次同输入回出纠正后的内容 */
+        let dominant = self
+            .find_best_overlap(input)
+            .map(|slot| self.patterns[slot].variants.iter().map(|v| v.weight).fold(1.0, f64::max) * 2.0)
+            .unwrap_or(1.0)
+            .min(10.0);
+        let pattern_id = self
+            .teach_weighted(input, correct_response, VariantCondition::default(), Some(dominant), TeachMode::Replace, Origin::Correction)
+            .ok();
+        self.log_feedback(input, correct_response, 0, correct_response.to_string(), "correction", pattern_id);
+    }
+
+    fn log_feedback(
+        &mut self,
+        input: &str,
+        response: &str,
+        signal: i8,
+        correction: String,
+        source: &str,
+        pattern_id: Option<PatternId>,
+    ) {
         if self.feedback_log.len() >= FEEDBACK_LOG_MAX {
             self.feedback_log.remove(0);
         }
@@ -812,6 +1879,7 @@ impl LanguagePouch {
             correction,
             timestamp: self.tick,
             source: source.to_string(),
+            pattern_id,
         });
     }
 
@@ -875,7 +1943,7 @@ impl LanguagePouch {
         let mut clusters: Vec<(String, usize, Vec<String>)> = Vec::new();
         let mut used_miss: std::collections::HashSet<usize> = std::collections::HashSet::new();
         let mut sorted: Vec<_> = freq.into_iter().collect();
-        sorted.sort_by(|a, b| b.1.0.cmp(&a.1.0));
+        sorted.sort_by_key(|s| std::cmp::Reverse(s.1.0));
         for (token, (count, miss_indices)) in sorted {
             if count < min_freq { break; }
             let new_indices: Vec<usize> = miss_indices.iter()
@@ -897,40 +1965,154 @@ impl LanguagePouch {
 
     pub fn top_quality_pairs(&self, limit: usize) -> Vec<(String, String)> {
         let mut scored: Vec<(f64, usize)> = self.patterns.iter().enumerate()
-            .filter(|(_, p)| p.response.len() > 10 && p.tokens.len() >= 2)
+            .filter(|(_, p)| p.template.is_none() && p.primary().len() > 10 && p.tokens.len() >= 2)
+            .filter(|(_, p)| self.guard.check(&p.tokens, p.primary()).is_none())
             .map(|(i, p)| (p.weight * (p.frequency as f64).sqrt(), i))
             .collect();
         scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
         scored.iter().take(limit).filter_map(|(_, idx)| {
             let p = &self.patterns[*idx];
-            let human = p.tokens.join("");
+            let human = self.trigger_text(p);
             if human.len() < 4 { return None; }
-            Some((human, p.response.clone()))
+            Some((human, p.primary().to_string()))
         }).collect()
     }
 
-    pub fn teach(&mut self, trigger: &str, response: &str) {
+    /*
+     * 教学：同一触发词再教不同回复时取代原主回复；回复末尾可带 [[when ...]] 条件块
+     * （见 response_variant），带条件的回复作为变体加入，不影响主回复。
+     */
+    pub fn teach(&mut self, trigger: &str, response: &str) -> Result<PatternId, String> {
+        let (text, condition) = VariantCondition::split_suffix(response)?;
+        self.teach_weighted(trigger, &text, condition, None, TeachMode::Replace, Origin::Teach)
+    }
+
+    /*
+     * 补充回复：追加为变体（与现有最高权重持平，参与确定性抽取），同样可带 [[when ...]]。
+     */
+    pub fn teach_variant(&mut self, trigger: &str, response: &str) -> Result<PatternId, String> {
+        let (text, condition) = VariantCondition::split_suffix(response)?;
+        self.teach_weighted(trigger, &text, condition, None, TeachMode::Variant, Origin::Teach)
+    }
+
+    /*
+     * 以指定来源教学：云端拉取、学习周期投喂、批量导入等。回复按原文学习，
+     * 不解析 [[when ...]]（条件只能由用户交互教学时指定）。
+     */
+    pub fn teach_as(&mut self, trigger: &str, response: &str, origin: Origin) -> Result<PatternId, String> {
+        self.teach_weighted(trigger, response, VariantCondition::default(), None, TeachMode::Replace, origin)
+    }
+
+    fn teach_weighted(
+        &mut self,
+        trigger: &str,
+        text: &str,
+        condition: VariantCondition,
+        weight: Option<f64>,
+        mode: TeachMode,
+        origin: Origin,
+    ) -> Result<PatternId, String> {
+        if let Some(reason) = self.intercept(trigger, text, origin.clone()) {
+            return Err(format!("回复被拦截（{}），已放入隔离区", reason));
+        }
+        self.learn(trigger, text, condition, weight, mode, origin)
+    }
+
+    /*
+     * 教学本体（不经回复拦截）：放行隔离区条目时直接调用。
+     */
+    fn learn(
+        &mut self,
+        trigger: &str,
+        text: &str,
+        condition: VariantCondition,
+        weight: Option<f64>,
+        mode: TeachMode,
+        origin: Origin,
+    ) -> Result<PatternId, String> {
+        if slot_template::is_template(trigger) {
+            return self.teach_template(trigger, text, condition, weight, mode, origin);
+        }
         let tokens = self.tokenize(trigger);
-        if tokens.is_empty() || response.is_empty() {
-            return;
+        if tokens.is_empty() || text.trim().is_empty() {
+            return Err("触发词或回复为空".into());
         }
-        for p in &mut self.patterns {
-            if p.tokens == tokens {
-                p.response = response.to_string();
-                p.weight = (p.weight + 0.5).min(10.0);
-                p.last_used = self.tick;
-                return;
+        let existing = self
+            .index
+            .exact(&tokens)
+            .iter()
+            .filter_map(|&id| self.slot_of(id))
+            .find(|&slot| self.patterns[slot].tokens == tokens);
+        if let Some(slot) = existing {
+            let p = &mut self.patterns[slot];
+            let top = p.variants.iter().map(|v| v.weight).fold(1.0, f64::max);
+            p.teach_response(text, condition, weight.unwrap_or(top), mode);
+            p.weight = (p.weight + 0.5).min(10.0);
+            p.last_used = self.tick;
+            p.provenance.touch(self.tick);
+            return Ok(p.id);
+        }
+        let id = self.push_pattern(tokens, text.to_string(), 1.5, origin);
+        if let Some(slot) = self.slot_of(id) {
+            if let Some(v) = self.patterns[slot].variants.first_mut() {
+                v.condition = condition;
+                v.weight = weight.unwrap_or(1.0);
             }
         }
-        let id = self.patterns.len();
-        self.patterns.push(Pattern {
-            tokens: tokens.clone(),
-            response: response.to_string(),
-            weight: 1.5,
-            frequency: 1,
-            last_used: self.tick,
-        });
-        self.root.insert(&tokens, id, 0);
+        Ok(id)
+    }
+
+    /*
+     * 槽位模板教学：同一模板再教时与普通模式相同（取代主回复或追加变体）；
+     * 回复只能引用模板里定义过的槽位。
+     */
+    fn teach_template(
+        &mut self,
+        trigger: &str,
+        text: &str,
+        condition: VariantCondition,
+        weight: Option<f64>,
+        mode: TeachMode,
+        origin: Origin,
+    ) -> Result<PatternId, String> {
+        let template = SlotTemplate::parse(trigger)?;
+        template.check_response(text)?;
+        let existing = self
+            .templates
+            .iter()
+            .filter_map(|&id| self.slot_of(id))
+            .find(|&slot| self.patterns[slot].template.as_ref() == Some(&template));
+        if let Some(slot) = existing {
+            let p = &mut self.patterns[slot];
+            let top = p.variants.iter().map(|v| v.weight).fold(1.0, f64::max);
+            p.teach_response(text, condition, weight.unwrap_or(top), mode);
+            p.weight = (p.weight + 0.5).min(10.0);
+            p.last_used = self.tick;
+            p.provenance.touch(self.tick);
+            return Ok(p.id);
+        }
+        let tokens = self.tokenize(&template.literal_text());
+        if tokens.is_empty() {
+            return Err("模板至少需要一段固定文字".into());
+        }
+        self.evict_if_needed();
+        let variants = vec![ResponseVariant { text: text.to_string(), weight: weight.unwrap_or(1.0), condition }];
+        Ok(self.push_pattern_with(tokens, variants, 1.5, Some(template), origin))
+    }
+
+    pub fn set_session_tags(&mut self, tags: Vec<String>) {
+        self.session_tags = tags;
+    }
+
+    /*
+     * 暂停本轮学习（吸收、路由学习、未命中记录）：输入是改写过的文本时不固化成模式。
+     */
+    pub fn set_learning_paused(&mut self, paused: bool) {
+        self.learning_paused = paused;
+    }
+
+    pub fn learning_paused(&self) -> bool {
+        self.learning_paused
     }
 
     pub fn identify_requirement(&self, input: &str) -> Option<RequirementIntent> {
@@ -953,6 +2135,11 @@ impl LanguagePouch {
             if tokens.is_empty() || content.is_empty() {
                 continue;
             }
+            if let Some(reason) = self.guard.check(tokens, content) {
+                let input = self.tokens_text(tokens, tokens_lang(tokens));
+                self.guard.quarantine(&input, content, Origin::Absorb("sync".into()), reason);
+                continue;
+            }
             self.sync_buffer.push((tokens.clone(), content.clone()));
             if self.sync_buffer.len() > SYNC_BUFFER_MAX {
                 self.sync_buffer.remove(0);
@@ -966,6 +2153,9 @@ impl LanguagePouch {
         }
         let mut best: Option<(f64, String)> = None;
         for (stokens, scontent) in self.sync_buffer.iter().rev() {
+            if self.guard.check(input_tokens, scontent).is_some() {
+                continue;
+            }
             let common = input_tokens.iter().filter(|t| stokens.contains(t)).count();
             let denom = (input_tokens.len().max(stokens.len())) as f64;
             if denom <= 0.0 {
@@ -979,11 +2169,23 @@ impl LanguagePouch {
         best.map(|(_, s)| s)
     }
 
-    fn context_fallback(&self, input: &str) -> Option<String> {
+    fn context_fallback(&self, input: &str, lang: Lang) -> Option<String> {
         if self.context.is_empty() {
             return None;
         }
         let lower = input.to_lowercase();
+        if lang == Lang::En {
+            let lower = lower.trim_end_matches(['.', '?', '!']).trim();
+            if ["continue", "go on", "what did you say", "earlier"].iter().any(|w| lower.contains(w)) {
+                if let Some((prev_input, prev_response)) = self.context.last() {
+                    return Some(format!("Last time: \"{}\" -> \"{}\"", prev_input, prev_response));
+                }
+            }
+            if lower == "it" || lower == "that" || lower == "this" {
+                return self.context.last().map(|(_, prev_response)| prev_response.clone());
+            }
+            return None;
+        }
         if lower.contains("刚才") || lower.contains("上面") || lower.contains("之前")
             || lower.contains("继续") || lower.contains("接着")
         {
@@ -1003,6 +2205,20 @@ impl LanguagePouch {
         self.context.clear();
     }
 
+    pub fn swap_context(&mut self, context: Vec<(String, String)>) -> Vec<(String, String)> {
+        std::mem::replace(&mut self.context, context)
+    }
+
+    pub fn swap_turn(&mut self, turn: &mut LanguageTurn) {
+        std::mem::swap(&mut self.context, &mut turn.context);
+        std::mem::swap(&mut self.last_was_pattern_hit, &mut turn.last_was_pattern_hit);
+        std::mem::swap(&mut self.last_match_weight, &mut turn.last_match_weight);
+        std::mem::swap(&mut self.last_pattern_id, &mut turn.last_pattern_id);
+        std::mem::swap(&mut self.last_served, &mut turn.last_served);
+        std::mem::swap(&mut self.session_tags, &mut turn.session_tags);
+        std::mem::swap(&mut self.learning_paused, &mut turn.learning_paused);
+    }
+
     pub fn last_was_pattern_hit(&self) -> bool {
         self.last_was_pattern_hit
     }
@@ -1011,6 +2227,10 @@ impl LanguagePouch {
         self.last_match_weight
     }
 
+    pub fn last_pattern_id(&self) -> Option<PatternId> {
+        self.last_pattern_id
+    }
+
     pub fn context_len(&self) -> usize {
         self.context.len()
     }
@@ -1047,6 +2267,35 @@ impl LanguagePouch {
         }
     }
 
+    fn honest_fallback_en(&self, input: &str, word_count: usize) -> String {
+        let lower = input.to_lowercase();
+        let first = lower.split_whitespace().next().unwrap_or("");
+        let has_question = input.contains('?')
+            || ["what", "how", "why", "who", "where", "when", "which", "can", "is", "are", "do", "does"].contains(&first);
+        let has_command = ["please", "help me", "give me", "make", "do "].iter().any(|w| lower.contains(w));
+        let head = input.chars().take(24).collect::<String>();
+
+        if has_question && word_count > 2 {
+            format!(
+                "I can't answer that yet. Teach me with \"learn {} -> (your answer)\" and I'll know it next time. Patterns learned: {}.",
+                head,
+                self.patterns.len()
+            )
+        } else if has_command {
+            "I can't do that yet. Type \"list\" to see installed pouches, or teach me a line or two first.".into()
+        } else if word_count <= 1 && input.trim().chars().count() <= 3 {
+            "Could you be more specific?".into()
+        } else {
+            let idx = (input.as_bytes().iter().map(|b| *b as usize).sum::<usize>()) % 4;
+            match idx {
+                0 => format!("I haven't learned \"{}\" yet. Teach me with \"learn phrase -> answer\".", head),
+                1 => format!("I'm not sure what \"{}\" means. Could you teach me?", head),
+                2 => "I can't match that yet. Install a pouch or teach me a line, then try again.".into(),
+                _ => format!("No match yet. {} patterns learned; teaching me more helps a lot.", self.patterns.len()),
+            }
+        }
+    }
+
     fn check_language(&self, input: &str) -> Option<String> {
         let mut cjk_count = 0;
         let mut latin_count = 0;
@@ -1071,18 +2320,78 @@ impl LanguagePouch {
         if other_count > 0 {
             let ratio = other_count as f64 / (significant as f64 + other_count as f64);
             if ratio > 1.0 / 1.5 {
-                return Some("语言不支持".into());
+                return Some(match cjk_count {
+                    0 => "Language not supported.".into(),
+                    _ => "语言不支持".into(),
+                });
             }
         }
 
-        if cjk_count == 0 && latin_count > 0 {
-            return Some("仅支持中文".into());
-        }
-
         None
     }
 }
 
+/*
+ * 检索基准（logos --bench-index [规模,...]）：用常用字随机拼出 n 条短句模式，
+ * 整批导入后测量建索引耗时和 process 单次延迟。一半查询取自已有模式（应命中），一半随机。
+ */
+pub async fn bench_retrieval(sizes: &[usize]) -> String {
+    const CHARSET: &str = "的一是在不了有和人这中大为上个国我以要他时来用们生到作地于出就分对成会可主发年动同工也能下过子说产种面而方后多定行学法所民得经十三之进着等部度家电力里如水化高自二理起小物现实加量都两体制机当使点从业本去把性好应开它合还因由其些然前外天政四日那社义事平形相全表间样与关各重新线内数正心反你明看原又么利比或但质气第向道命此变条只没结解问意建月公无系军很情者最立代想已通并提直题党程展五果料象员革位入常文总次品式活设及管特件长求老头基资边流路级少图山统接知较将组见计别她手角期根论运农指几九区强放决西被干做必战先回则任取据处理";
+    let chars: Vec<char> = CHARSET.chars().collect();
+    let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut next = move |m: usize| -> usize {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        (seed % m as u64) as usize
+    };
+    let mut report = String::from("检索基准（每规模 200 次 process）:\n");
+    for &n in sizes {
+        let mut lp = LanguagePouch::new();
+        let mut samples: Vec<String> = Vec::new();
+        let mut patterns: Vec<(Vec<String>, String, f64)> = Vec::with_capacity(n);
+        for i in 0..n {
+            let len = 5 + next(4);
+            let text: String = (0..len).map(|_| chars[next(chars.len())]).collect();
+            if i % (n / 100).max(1) == 0 {
+                samples.push(text.clone());
+            }
+            patterns.push((lp.tokenize(&text), format!("r{}", i), 1.0));
+        }
+        let started = std::time::Instant::now();
+        let _ = lp.import_patterns(patterns, "bench", &ImportOptions { mode: ImportMode::Replace, ..Default::default() });
+        let build_ms = started.elapsed().as_millis();
+        let mut latencies: Vec<u128> = Vec::with_capacity(200);
+        let mut hits = 0usize;
+        for q in 0..200 {
+            let query = if q % 2 == 0 {
+                samples[next(samples.len())].clone()
+            } else {
+                (0..6).map(|_| chars[next(chars.len())]).collect()
+            };
+            let started = std::time::Instant::now();
+            let _ = lp.process(&query).await;
+            latencies.push(started.elapsed().as_micros());
+            if q % 2 == 0 && lp.last_was_pattern_hit() {
+                hits += 1;
+            }
+        }
+        latencies.sort_unstable();
+        let avg = latencies.iter().sum::<u128>() / latencies.len() as u128;
+        report.push_str(&format!(
+            "  {} 条模式（索引 {} 条）: 建索引 {} ms, 延迟 avg {} µs, p50 {} µs, p99 {} µs, 已知输入命中 {}/100\n",
+            n,
+            lp.index.len(),
+            build_ms,
+            avg,
+            latencies[latencies.len() / 2],
+            latencies[latencies.len() * 99 / 100],
+            hits
+        ));
+    }
+    report
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1096,17 +2405,35 @@ mod tests {
     }
 
     #[test]
-    fn test_edit_distance() {
-        assert_eq!(FractalNode::edit_dist("hello", "hallo"), 1);
-        assert_eq!(FractalNode::edit_dist("abc", "abc"), 0);
-        assert_eq!(FractalNode::edit_dist("", "abc"), 3);
+    fn test_each_pouch_uses_its_own_tokenizer() {
+        let dict = crate::tokenizer::DictSegmenter::new(crate::tokenizer::Lexicon::parse(crate::tokenizer::DEFAULT_LEXICON));
+        let mut segmented = LanguagePouch::with_tokenizer(Box::new(dict));
+        let plain = LanguagePouch::new();
+        assert!(segmented.tokenize("机器学习是什么").contains(&"机器学习".to_string()));
+        assert!(!plain.tokenize("机器学习是什么").contains(&"机器学习".to_string()));
+        assert_eq!(segmented.tokenizer_state().kind, crate::tokenizer::TokenizerKind::Dictionary);
+        assert!(segmented.teach("机器学习是什么", "从数据中学习").is_ok());
+        assert!(segmented.patterns.iter().any(|p| p.tokens.contains(&"机器学习".to_string())));
+    }
+
+    #[test]
+    fn test_retokenize_to_dictionary_keeps_matches() {
+        let mut lp = LanguagePouch::new();
+        assert!(lp.teach("机器学习是什么", "一种让程序从数据中学习的方法").is_ok());
+        assert!(lp.add_negative_pattern("机器学习是什么", "魔法").is_ok());
+        let dict = crate::tokenizer::DictSegmenter::new(crate::tokenizer::Lexicon::parse(crate::tokenizer::DEFAULT_LEXICON));
+        let migrated = lp.retokenize(Box::new(dict));
+        assert!(migrated > 0);
+        assert!(lp.patterns.iter().any(|p| p.tokens.contains(&"机器学习".to_string())));
+        assert!(!lp.index.search(&lp.tokenize("机器学习是什么"), 10).is_empty());
+        assert!(lp.guard().check(&lp.tokenize("机器学习是什么"), "一种魔法").is_some());
     }
 
     #[test]
     fn test_teach_and_match() {
         let mut lp = LanguagePouch::new();
-        lp.teach("测试输入", "测试响应");
-        assert!(!lp.root.search(&lp.tokenize("测试输入"), 0).is_empty());
+        assert!(lp.teach("测试输入", "测试响应").is_ok());
+        assert!(!lp.index.search(&lp.tokenize("测试输入"), 10).is_empty());
     }
 
     #[test]
@@ -1118,10 +2445,219 @@ mod tests {
     #[test]
     fn test_language_check_english() {
         let lp = LanguagePouch::new();
-        assert_eq!(
-            lp.check_language("Hello world this is english"),
-            Some("仅支持中文".into())
-        );
+        assert_eq!(lp.check_language("Hello world this is english"), None);
+        assert_eq!(lp.check_language("Привет мир, hi"), Some("Language not supported.".into()));
+    }
+
+    #[tokio::test]
+    async fn test_english_patterns_do_not_cross_match() {
+        let mut lp = LanguagePouch::new();
+        assert_eq!(lp.process("Hello!").await, "Hello. What do you need?");
+        assert!(lp.teach("learning rust", "Start with the book.").is_ok());
+        assert_eq!(lp.process("learned Rust").await, "Start with the book.");
+        assert!(lp.teach("rust怎么学", "先看官方教程。").is_ok());
+        assert_eq!(lp.process("rust怎么学").await, "先看官方教程。");
+        assert_eq!(lp.process("learned Rust").await, "Start with the book.");
+        let miss = lp.process("Where is the quantum flux capacitor?").await;
+        assert!(lp.is_fallback_response(&miss), "{}", miss);
+    }
+
+    #[tokio::test]
+    async fn test_base_fallback_reports_weight_and_id() {
+        let mut base = LanguagePouch::new();
+        let id = match base.teach("基础库年假天数", "十天") {
+            Ok(id) => id,
+            Err(e) => panic!("{}", e),
+        };
+        let mut team = LanguagePouch::new();
+        assert_eq!(team.process_with_base("基础库年假天数", Some(&base)).await, "十天");
+        assert!(team.last_was_pattern_hit());
+        assert_eq!(team.last_pattern_id(), Some(id));
+        assert!(team.last_match_weight() >= ABSORB_WEIGHT);
+    }
+
+    #[tokio::test]
+    async fn test_prepared_lookup_is_revalidated() {
+        let mut lp = LanguagePouch::new();
+        let id = match lp.teach("预先匹配报销流程", "先填单再审批") {
+            Ok(id) => id,
+            Err(e) => panic!("{}", e),
+        };
+        let lookup = lp.lookup("预先匹配报销流程", None, &[]);
+        assert!(matches!(lookup, Some(PatternLookup { hit: Some((hit, 0, _)), .. }) if hit == id));
+        assert_eq!(lp.process_with_lookup("预先匹配报销流程", None, lookup).await, "先填单再审批");
+        assert_eq!(lp.last_pattern_id(), Some(id));
+
+        let stale = lp.lookup("预先匹配报销流程", lp.last_context_input(), &[]);
+        assert!(lp.delete_pattern(id));
+        let out = lp.process_with_lookup("预先匹配报销流程", None, stale).await;
+        assert_ne!(out, "先填单再审批");
+        assert_ne!(lp.last_pattern_id(), Some(id));
+
+        let other_session = lp.lookup("你好", Some("别的会话上一句"), &[]);
+        assert!(other_session.is_some_and(|l| lp.resolve_lookup(l, &lp.tokenize("你好")).is_none()));
+    }
+
+    #[test]
+    fn test_pattern_ids_stable_across_eviction_and_reload() {
+        let mut lp = LanguagePouch::new();
+        let keep = match lp.teach("稳定编号测试", "保留") {
+            Ok(id) => id,
+            Err(e) => panic!("teach should return id: {}", e),
+        };
+        lp.patterns[lp.slots[&keep]].weight = 10.0;
+        lp.patterns[lp.slots[&keep]].frequency = 1000;
+        for i in 0..bedrock::MAX_PATTERNS {
+            lp.add_pattern(vec![format!("合成{}", i), "词".into()], format!("r{}", i), 0.5, Origin::Seed);
+        }
+        assert!(lp.memory_count() < bedrock::MAX_PATTERNS);
+        let slot = lp.slot_of(keep).unwrap_or(usize::MAX);
+        assert_eq!(lp.patterns.get(slot).map(|p| p.primary()), Some("保留"));
+        assert!(lp.patterns.iter().enumerate().all(|(i, p)| lp.slots[&p.id] == i));
+        let data = match lp.save() {
+            Ok(d) => d,
+            Err(e) => panic!("save: {}", e),
+        };
+        let mut restored = LanguagePouch::new();
+        assert!(restored.load(&data).is_ok());
+        assert_eq!(restored.teach("稳定编号测试", "保留2"), Ok(keep));
+        assert_eq!(restored.next_id, lp.next_id);
+    }
+
+    #[test]
+    fn test_load_legacy_patterns_detects_language() {
+        #[derive(Serialize)]
+        struct Old(Vec<String>, String, f64, u32, u64);
+        let data = match bincode::serialize(&vec![
+            Old(vec!["旧".into(), "词".into()], "旧响应".into(), 1.0, 1, 0),
+            Old(vec!["old".into(), "word".into()], "old reply".into(), 1.0, 1, 0),
+        ]) {
+            Ok(d) => d,
+            Err(e) => panic!("serialize: {}", e),
+        };
+        let mut lp = LanguagePouch::new();
+        assert!(lp.load(&data).is_ok());
+        assert_eq!(lp.patterns.len(), 2);
+        assert_eq!(lp.patterns[0].lang, Lang::Zh);
+        assert_eq!(lp.patterns[1].lang, Lang::En);
+        assert_eq!(lp.patterns[0].id, 0);
+        assert_eq!(lp.teach("新", "新响应"), Ok(2));
+    }
+
+    #[test]
+    fn test_provenance_origin_signals_and_migration() {
+        let mut lp = LanguagePouch::new();
+        let id = match lp.teach("来源测试触发语", "来源回复") {
+            Ok(id) => id,
+            Err(e) => panic!("{}", e),
+        };
+        lp.reinforce("来源测试触发语");
+        lp.reinforce("来源测试触发语");
+        lp.penalize("来源测试触发语");
+        lp.absorb("量子隧穿是什么", "粒子穿过经典禁区的现象", 1.0, "cloud");
+        let pv = match lp.pattern(id) {
+            Some(p) => p.provenance.clone(),
+            None => panic!("pattern {} missing", id),
+        };
+        assert_eq!(pv.origin, Origin::Teach);
+        assert_eq!((pv.positive, pv.negative), (2, 1));
+        assert!(pv.created_at > 0 && pv.modified_tick >= pv.created_tick);
+        assert!(lp.patterns.iter().any(|p| p.provenance.origin == Origin::Absorb("cloud".into())));
+        let info = lp.describe_pattern(id).unwrap_or_default();
+        assert!(info.contains("来源: 教学") && info.contains("反馈: +2 / -1"), "{}", info);
+        assert!(lp.describe_pattern(u32::MAX).is_none());
+
+        #[derive(Serialize)]
+        struct Old(Vec<String>, String, f64, u32, u64);
+        let old = match bincode::serialize(&vec![Old(vec!["旧".into(), "词".into()], "旧响应".into(), 1.0, 1, 7)]) {
+            Ok(d) => d,
+            Err(e) => panic!("serialize: {}", e),
+        };
+        assert!(!LanguagePouch::is_current_format(&old));
+        let mut migrated = LanguagePouch::new();
+        assert!(migrated.load(&old).is_ok());
+        assert_eq!(migrated.patterns[0].provenance.origin, Origin::Legacy);
+        assert_eq!(migrated.tick, 7);
+        let data = match migrated.save() {
+            Ok(d) => d,
+            Err(e) => panic!("save: {}", e),
+        };
+        assert!(LanguagePouch::is_current_format(&data));
+        let mut restored = LanguagePouch::new();
+        assert!(restored.load(&data).is_ok());
+        assert_eq!(restored.patterns[0].provenance.origin, Origin::Legacy);
+    }
+
+    #[tokio::test]
+    async fn test_pattern_admin_list_edit_delete() {
+        let mut lp = LanguagePouch::new();
+        let keep = match lp.teach("管理测试苹果价格", "苹果五元") {
+            Ok(id) => id,
+            Err(e) => panic!("{}", e),
+        };
+        let drop = match lp.teach("管理测试香蕉价格", "香蕉三元") {
+            Ok(id) => id,
+            Err(e) => panic!("{}", e),
+        };
+        let query = |cond: &str| match PatternQuery::parse(cond) {
+            Ok(q) => q,
+            Err(e) => panic!("{}", e),
+        };
+        let page = lp.list_patterns(&query("管理测试 origin:teach"));
+        assert_eq!(page.total, 2);
+        assert_eq!(lp.list_patterns(&query("香蕉三元")).items.first().map(|s| s.id), Some(drop));
+        assert_eq!(lp.list_patterns(&query("size:1 page:2")).items.len(), 1);
+        assert!(lp.list_patterns(&PatternQuery { page: usize::MAX, ..Default::default() }).items.is_empty());
+
+        let edit = PatternEdit { response: Some("苹果六元".into()), weight: Some(3.0) };
+        assert!(lp.edit_pattern(keep, &edit).is_ok());
+        assert!(lp.edit_pattern(keep, &PatternEdit { response: None, weight: Some(99.0) }).is_err());
+        assert_eq!(lp.process("管理测试苹果价格").await, "苹果六元");
+        let tagged = PatternEdit { response: Some("苹果七元 [[when tag=vip]]".into()), weight: None };
+        assert!(lp.edit_pattern(keep, &tagged).is_ok());
+        assert_eq!(lp.process("管理测试苹果价格").await, "苹果七元");
+        assert_eq!(lp.list_patterns(&PatternQuery::default()).items.first().map(|s| s.id), Some(keep));
+
+        assert!(lp.delete_pattern(drop));
+        assert!(!lp.delete_pattern(drop));
+        assert_ne!(lp.process("管理测试香蕉价格").await, "香蕉三元");
+        assert!(lp.delete_matching(&PatternQuery::default()).is_err());
+        assert_eq!(lp.delete_matching(&query("苹果")), Ok(1));
+        assert!(lp.pattern(keep).is_none());
+        assert!(lp.patterns.iter().enumerate().all(|(i, p)| lp.slots[&p.id] == i));
+    }
+
+    #[tokio::test]
+    async fn test_explain_matches_process_without_mutation() {
+        let mut lp = LanguagePouch::new();
+        let id = match lp.teach("解释测试铁的熔点", "约1538℃") {
+            Ok(id) => id,
+            Err(e) => panic!("{}", e),
+        };
+        assert!(lp.teach("解释测试铜的熔点", "约1085℃").is_ok());
+        let (tick, freq) = (lp.tick, lp.pattern(id).map(|p| p.frequency));
+        let ex = lp.explain("解释测试铁的熔点", 5, None);
+        assert_eq!((lp.tick, lp.pattern(id).map(|p| p.frequency)), (tick, freq));
+        assert_eq!(ex.stage, MatchStage::Index);
+        assert_eq!(ex.selected, Some(id));
+        assert!(ex.exact_hit);
+        let top = match ex.candidates.first() {
+            Some(c) => c,
+            None => panic!("no candidates"),
+        };
+        assert_eq!(top.id, id);
+        let product = top.similarity * top.weight * top.ln_frequency * top.freshness * top.h_cov;
+        assert!((top.score - product).abs() < 1e-9);
+        assert!(ex.candidates.len() <= 5 && ex.filtered.len() <= 5);
+        assert_eq!(lp.process("解释测试铁的熔点").await, ex.response);
+
+        assert!(lp.teach("我叫{name}", "你好，{name}").is_ok());
+        let ex = lp.explain("我叫张三", 5, None);
+        assert_eq!(ex.stage, MatchStage::Template);
+        assert_eq!(ex.response, "你好，张三");
+        let ex = lp.explain("完全无关的句子内容测试", 5, None);
+        assert_eq!(ex.stage, MatchStage::Fallback);
+        assert_eq!(ex.response, lp.process("完全无关的句子内容测试").await);
     }
 
     #[test]
@@ -1143,32 +2679,115 @@ mod tests {
     fn test_absorb_creates_pattern() {
         let mut lp = LanguagePouch::new();
         let before = lp.memory_count();
-        lp.absorb("量子纠缠是什么现象", "量子纠缠是两个粒子之间的非局域关联", 1.2);
+        lp.absorb("量子纠缠是什么现象", "量子纠缠是两个粒子之间的非局域关联", 1.2, "test");
         assert!(lp.memory_count() > before);
     }
 
     #[test]
     fn test_absorb_merge_duplicate() {
         let mut lp = LanguagePouch::new();
-        lp.absorb("量子纠缠原理", "量子纠缠是非局域关联", 1.2);
+        lp.absorb("量子纠缠原理", "量子纠缠是非局域关联", 1.2, "test");
         let count_after_first = lp.memory_count();
-        lp.absorb("量子纠缠原理", "量子纠缠是非局域关联", 1.2);
+        lp.absorb("量子纠缠原理", "量子纠缠是非局域关联", 1.2, "test");
         assert_eq!(lp.memory_count(), count_after_first);
     }
 
     #[test]
     fn test_reinforce_and_penalize() {
         let mut lp = LanguagePouch::new();
-        lp.teach("特殊测试输入语句", "特殊回复");
-        let before = lp.patterns.iter().find(|p| p.response == "特殊回复").map(|p| p.weight).unwrap_or(0.0);
+        let _ = lp.teach("特殊测试输入语句", "特殊回复");
+        let before = lp.patterns.iter().find(|p| p.primary() == "特殊回复").map(|p| p.weight).unwrap_or(0.0);
         lp.reinforce("特殊测试输入语句");
-        let after = lp.patterns.iter().find(|p| p.response == "特殊回复").map(|p| p.weight).unwrap_or(0.0);
+        let after = lp.patterns.iter().find(|p| p.primary() == "特殊回复").map(|p| p.weight).unwrap_or(0.0);
         assert!(after > before);
         lp.penalize("特殊测试输入语句");
-        let after_penalize = lp.patterns.iter().find(|p| p.response == "特殊回复").map(|p| p.weight).unwrap_or(0.0);
+        let after_penalize = lp.patterns.iter().find(|p| p.primary() == "特殊回复").map(|p| p.weight).unwrap_or(0.0);
         assert!(after_penalize < after);
     }
 
+    #[tokio::test]
+    async fn test_response_variants_conditions_and_feedback() {
+        let mut lp = LanguagePouch::new();
+        assert!(lp.teach("变体测试问候语", "回复甲").is_ok());
+        assert!(lp.teach_variant("变体测试问候语", "回复乙").is_ok());
+        assert!(lp.teach("变体测试问候语", "欢迎回来 [[when after=变体测试开场]]").is_ok());
+        assert!(lp.teach("变体测试问候语", "x [[when mood=happy]]").is_err());
+        let first = lp.process("变体测试问候语").await;
+        assert!(first == "回复甲" || first == "回复乙", "{}", first);
+        for _ in 0..3 {
+            assert_eq!(lp.process("变体测试问候语").await, first);
+        }
+
+        lp.clear_context();
+        lp.push_context("变体测试开场".into(), "好的".into());
+        assert_eq!(lp.process("变体测试问候语").await, "欢迎回来");
+
+        lp.clear_context();
+        let served = lp.process("变体测试问候语").await;
+        let weight_of = |lp: &LanguagePouch, text: &str| {
+            lp.patterns
+                .iter()
+                .flat_map(|p| p.variants.iter())
+                .find(|v| v.text == text)
+                .map(|v| v.weight)
+                .unwrap_or(0.0)
+        };
+        let before = weight_of(&lp, &served);
+        assert!(lp.reinforce("变体测试问候语"));
+        assert!(weight_of(&lp, &served) > before);
+
+        let other = if served == "回复甲" { "回复乙" } else { "回复甲" };
+        let _ = lp.process("变体测试问候语").await;
+        lp.feedback_correction("变体测试问候语", other);
+        lp.clear_context();
+        assert_eq!(lp.process("变体测试问候语").await, other);
+
+        assert!(lp.teach("变体测试改答案", "旧答案").is_ok());
+        assert!(lp.teach("变体测试改答案", "新答案").is_ok());
+        assert_eq!(lp.process("变体测试改答案").await, "新答案");
+        assert!(lp.patterns.iter().flat_map(|p| p.variants.iter()).all(|v| v.text != "旧答案"));
+
+        let id = match lp.teach_as("变体测试云端", "原文 [[when tag=vip]]", Origin::CloudPull) {
+            Ok(id) => id,
+            Err(e) => panic!("{}", e),
+        };
+        assert_eq!(lp.pattern(id).map(|p| p.primary()), Some("原文 [[when tag=vip]]"));
+    }
+
+    #[tokio::test]
+    async fn test_slot_template_teach_match_and_reload() {
+        let mut lp = LanguagePouch::new();
+        let id = match lp.teach("我叫{name}", "你好，{name}") {
+            Ok(id) => id,
+            Err(e) => panic!("{}", e),
+        };
+        assert!(lp.teach("{city:entity}天气怎么样", "{{weather:{city}}}").is_ok());
+        assert!(lp.teach("我叫{name}", "你好，{nick}").is_err());
+        assert_eq!(lp.process("我叫张三").await, "你好，张三");
+        assert_eq!(lp.last_pattern_id, Some(id));
+        assert_eq!(lp.process("北京天气怎么样？").await, "{{weather:北京}}");
+
+        assert!(lp.teach("我叫李四", "李四你好").is_ok());
+        assert_eq!(lp.process("我叫李四").await, "李四你好");
+
+        let data = match lp.save() {
+            Ok(d) => d,
+            Err(e) => panic!("save: {}", e),
+        };
+        let mut restored = LanguagePouch::new();
+        assert!(restored.load(&data).is_ok());
+        assert_eq!(restored.process("我叫王五").await, "你好，王五");
+        assert_eq!(restored.teach("我叫{name}", "嗨，{name}"), Ok(id));
+
+        let code = match restored.teach_as("rust 里 fn main() { println!(\"{}\", x); } 怎么写", "先声明 x", Origin::CloudPull) {
+            Ok(id) => id,
+            Err(e) => panic!("brace trigger must be learned as plain text: {}", e),
+        };
+        assert!(restored.pattern(code).is_some_and(|p| p.template.is_none()));
+        assert!(restored.teach("解析 {\"name\": \"logos\"} 这段 JSON", "是一个对象").is_ok());
+        assert_eq!(restored.process("解析 {\"name\": \"logos\"} 这段 JSON").await, "是一个对象");
+    }
+
     #[tokio::test]
     async fn test_miss_buffer_records() {
         let mut lp = LanguagePouch::new();
@@ -1189,7 +2808,7 @@ mod tests {
     #[test]
     fn test_feedback_stats() {
         let mut lp = LanguagePouch::new();
-        lp.teach("反馈测试输入", "反馈测试回复");
+        assert!(lp.teach("反馈测试输入", "反馈测试回复").is_ok());
         lp.reinforce("反馈测试输入");
         let (_, log_count, _, _) = lp.feedback_stats();
         assert!(log_count > 0);
@@ -1198,7 +2817,7 @@ mod tests {
     #[test]
     fn test_export_feedback_jsonl() {
         let mut lp = LanguagePouch::new();
-        lp.teach("导出测试输入", "导出测试回复");
+        assert!(lp.teach("导出测试输入", "导出测试回复").is_ok());
         lp.reinforce("导出测试输入");
         let jsonl = lp.export_feedback_jsonl();
         assert!(!jsonl.is_empty());
@@ -1207,7 +2826,7 @@ mod tests {
     #[test]
     fn test_feedback_save_load_roundtrip() {
         let mut lp = LanguagePouch::new();
-        lp.teach("持久化测试输入", "持久化测试回复");
+        assert!(lp.teach("持久化测试输入", "持久化测试回复").is_ok());
         lp.reinforce("持久化测试输入");
         lp.miss_buffer.push((vec!["test".into()], "test_input".into()));
         let data = lp.save_feedback().expect("save");
@@ -1224,5 +2843,109 @@ mod tests {
         assert!(lp.is_fallback_response("还没学过"));
         assert!(lp.is_fallback_response("没匹配到"));
         assert!(!lp.is_fallback_response("量子纠缠是一种物理现象"));
+        assert!(lp.is_fallback_response("I'm not sure what \"flux\" means. Could you teach me?"));
+        assert!(lp.is_fallback_response("No match yet. 12 patterns learned; teaching me more helps a lot."));
+        assert!(!lp.is_fallback_response("I'm not sure what the weather will be, so bring an umbrella."));
+        assert!(!lp.is_fallback_response("Could you be more specific about the deadline? Friday works."));
+        assert!(!lp.is_fallback_response("You haven't learned Rust until you fight the borrow checker."));
+    }
+
+    #[test]
+    fn test_import_merge_conflicts_and_dry_run() {
+        let mut lp = LanguagePouch::new();
+        assert!(lp.teach("导入测试天气", "晴").is_ok());
+        let before = lp.memory_count();
+        let tokens = lp.tokenize("导入测试天气");
+        let batch = vec![
+            (tokens.clone(), "晴".to_string(), 1.0),
+            (tokens.clone(), "多云".to_string(), 1.0),
+            (lp.tokenize("导入测试新问题"), "新回复".to_string(), 1.0),
+            (Vec::new(), "无效".to_string(), 1.0),
+        ];
+        let find = |lp: &LanguagePouch| lp.patterns.iter().find(|p| token_set(&p.tokens) == token_set(&tokens)).cloned();
+        let texts = |lp: &LanguagePouch| find(lp).map(|p| p.variants.iter().map(|v| v.text.clone()).collect::<Vec<_>>());
+        let opts = |conflict, dry_run| ImportOptions { mode: ImportMode::Merge, conflict, dry_run };
+
+        let s = lp.import_patterns(batch.clone(), "t", &opts(ConflictPolicy::Both, true));
+        assert_eq!((s.total, s.added, s.updated, s.skipped, s.invalid), (4, 1, 1, 1, 1));
+        assert_eq!(lp.memory_count(), before);
+
+        let s = lp.import_patterns(batch.clone(), "t", &opts(ConflictPolicy::Existing, false));
+        assert_eq!((s.added, s.updated, s.skipped), (1, 0, 2));
+        assert_eq!(lp.memory_count(), before + 1);
+        assert_eq!(texts(&lp), Some(vec!["晴".to_string()]));
+
+        let s = lp.import_patterns(batch.clone(), "t", &opts(ConflictPolicy::Both, false));
+        assert_eq!((s.added, s.updated, s.skipped), (0, 1, 2));
+        assert_eq!(texts(&lp), Some(vec!["晴".to_string(), "多云".to_string()]));
+
+        let s = lp.import_patterns(vec![(tokens.clone(), "阴".to_string(), 1.0)], "t", &opts(ConflictPolicy::Incoming, false));
+        assert_eq!((s.added, s.updated), (0, 1));
+        assert_eq!(find(&lp).map(|p| p.primary().to_string()), Some("阴".to_string()));
+        assert_eq!(texts(&lp).map(|t| t.len()), Some(2));
+
+        let replace = ImportOptions { mode: ImportMode::Replace, ..Default::default() };
+        let s = lp.import_patterns(batch, "t", &replace);
+        assert_eq!((s.added, s.removed, s.invalid), (3, before + 1, 1));
+        assert_eq!(lp.memory_count(), 3);
+    }
+
+    #[test]
+    fn test_export_rows_readable_and_filtered() {
+        let mut lp = LanguagePouch::new();
+        assert!(lp.teach("导出测试今天天气怎么样", "晴").is_ok());
+        assert!(lp.teach_variant("导出测试今天天气怎么样", "多云").is_ok());
+        assert!(lp.teach_as("导出测试你叫什么", "逻各斯", Origin::CloudPull).is_ok());
+        let rows = match lp.export_rows(&ExportOptions { origin: Some("teach".into()), ..Default::default() }) {
+            Ok(r) => r,
+            Err(e) => panic!("{}", e),
+        };
+        assert_eq!(rows.len(), 2);
+        assert!(rows.iter().all(|r| r.human == "导出测试今天天气怎么样"));
+        let pulled = lp.export_rows(&ExportOptions { origin: Some("cloud_pull".into()), ..Default::default() });
+        assert_eq!(pulled.map(|r| r.len()), Ok(1));
+        let heavy = lp.export_rows(&ExportOptions { min_weight: Some(100.0), ..Default::default() });
+        assert_eq!(heavy.map(|r| r.len()), Ok(0));
+        let future = lp.export_rows(&ExportOptions { since: Some("2999-01-01".into()), ..Default::default() });
+        assert_eq!(future.map(|r| r.len()), Ok(0));
+    }
+
+    #[test]
+    fn test_guard_blocks_learning_and_matching() {
+        let mut lp = LanguagePouch::new();
+        assert!(lp.teach("拦截测试你是哪个模型", "我是文心一言").is_ok());
+        assert_eq!(lp.explain("拦截测试你是哪个模型", 5, None).stage, MatchStage::Index);
+
+        assert!(lp.add_negative_pattern("拦截测试你是哪个模型", "文心一言").is_ok());
+        let e = lp.explain("拦截测试你是哪个模型", 5, None);
+        assert_ne!(e.stage, MatchStage::Index);
+        assert!(e.filtered.iter().any(|c| c.filtered == Some(FilterReason::Blocked)));
+
+        assert!(lp.add_block_rule("/广告.*链接/").is_ok());
+        let before = lp.memory_count();
+        assert!(lp.teach("拦截测试推荐一下", "点这个广告购买链接").is_err());
+        lp.absorb("拦截测试另一个问题", "看广告领链接", 1.0, "web");
+        let tokens = lp.tokenize("拦截测试同步");
+        lp.receive_sync_patterns(&[(tokens, "广告与链接".to_string(), 1.0)]);
+        assert_eq!(lp.memory_count(), before);
+        assert_eq!(lp.guard().quarantined().len(), 3);
+
+        let saved = match lp.save_guard() {
+            Ok(d) => d,
+            Err(e) => panic!("{}", e),
+        };
+        let mut copy = LanguagePouch::new();
+        assert!(copy.load_guard(&saved).is_ok());
+        assert!(copy.teach("拦截测试推荐一下", "广告在此链接").is_err());
+
+        let id = lp.guard().quarantined()[0].id;
+        assert!(lp.release_quarantined(id).is_ok());
+        assert_eq!(lp.memory_count(), before + 1);
+        assert!(lp.release_quarantined(id).is_err());
+
+        lp.guard.quarantine("   ", "广告链接", Origin::Teach, "test".into());
+        let bad = lp.guard().quarantined().iter().find(|q| q.input == "   ").map(|q| q.id).unwrap_or_default();
+        assert!(lp.release_quarantined(bad).is_err());
+        assert!(lp.guard().quarantined_entry(bad).is_some());
     }
 }
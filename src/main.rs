@@ -1,13 +1,17 @@
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::{header, StatusCode},
-    response::{Html, IntoResponse, Response},
-    routing::{get, post},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        Html, IntoResponse, Response,
+    },
+    routing::{delete, get, post},
     Json, Router,
 };
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::sync::{mpsc, Mutex, RwLock};
+use tokio_stream::{wrappers::UnboundedReceiverStream, Stream, StreamExt};
 
 mod atom;
 mod frozen;
@@ -16,6 +20,23 @@ mod language_pouch;
 mod manager_math;
 mod pouch_trait;
 mod resource_monitor;
+mod session;
+mod budget;
+mod intent_model;
+mod tokenizer;
+mod pattern_index;
+mod response_variant;
+mod slot_template;
+mod provenance;
+mod pattern_admin;
+mod match_explain;
+mod namespace;
+mod pattern_import;
+mod pattern_export;
+mod language_eval;
+mod response_guard;
+mod dialogue_state;
+mod trace;
 mod remote_pouch;
 mod config;
 mod pouch_programming;
@@ -40,17 +61,46 @@ mod pouch_realtime;
 mod pouch_sanitize;
 mod test_terminal;
 
-use orchestrator::Orchestrator;
+use orchestrator::{Orchestrator, Turn};
 use resource_monitor::Monitor;
 
 struct App {
-    orch: Mutex<Orchestrator>,
+    orch: RwLock<Orchestrator>,
     monitor: Mutex<Monitor>,
 }
 
 #[derive(Deserialize)]
 struct Req {
     message: String,
+    #[serde(default)]
+    session_id: Option<String>,
+    #[serde(default)]
+    trace: bool,
+    #[serde(default)]
+    tags: Option<Vec<String>>,
+    #[serde(default)]
+    namespace: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct StreamReq {
+    message: String,
+    #[serde(default)]
+    session_id: Option<String>,
+    #[serde(default)]
+    trace: bool,
+    #[serde(default)]
+    tags: Option<String>,
+    #[serde(default)]
+    namespace: Option<String>,
+}
+
+/*
+ * 随请求设置的会话标签（写入会话）和本次使用的命名空间（只作用于本次）。
+ */
+struct ChatScope {
+    tags: Option<Vec<String>>,
+    namespace: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -58,6 +108,9 @@ struct Res {
     response: String,
     status: String,
     pouch: String,
+    session_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    trace: Option<trace::ExecutionTrace>,
 }
 
 #[derive(Serialize)]
@@ -126,6 +179,7 @@ struct DataSizeRes {
     evolution_chain_json_bytes: u64,
     promoted_rules_json_bytes: u64,
     pouches_json_bytes: u64,
+    sessions_json_bytes: u64,
     total_bytes: u64,
     memory_count: usize,
 }
@@ -141,13 +195,29 @@ async fn main() {
     if import_path.is_some() || eval_path.is_some() {
         let mut orch = Orchestrator::new(&data_dir);
         if let Some(p) = import_path {
-            match orch.import_patterns_from_file(p).await {
+            let flag = |name: &str| args.iter().position(|a| a == name).and_then(|i| args.get(i + 1)).map(|s| s.as_str());
+            let mut words: Vec<&str> = [flag("--import-mode"), flag("--conflict")].into_iter().flatten().collect();
+            if args.iter().any(|a| a == "--dry-run") {
+                words.push("dry-run");
+            }
+            let opts = match pattern_import::ImportOptions::parse(&words) {
+                Ok(o) => o,
+                Err(e) => { eprintln!("import error: {}", e); std::process::exit(1); }
+            };
+            match Turn::direct(&mut orch).import_patterns_from_file(p, opts).await {
                 Ok(msg) => println!("{}", msg),
                 Err(e) => { eprintln!("import error: {}", e); std::process::exit(1); }
             }
         }
         if let Some(p) = eval_path {
-            match orch.eval_language(p).await {
+            let flag = |name: &str| args.iter().position(|a| a == name).and_then(|i| args.get(i + 1)).cloned();
+            let opts = language_eval::EvalOptions {
+                live: args.iter().any(|a| a == "--eval-live"),
+                name: flag("--eval-name"),
+                compare: flag("--eval-compare"),
+                split: flag("--eval-split"),
+            };
+            match orch.eval_language(p, &opts).await {
                 Ok(msg) => println!("{}", msg),
                 Err(e) => { eprintln!("eval error: {}", e); std::process::exit(1); }
             }
@@ -155,6 +225,16 @@ async fn main() {
         return;
     }
 
+    if let Some(i) = args.iter().position(|a| a == "--bench-index") {
+        let sizes: Vec<usize> = args
+            .get(i + 1)
+            .map(|s| s.split(',').filter_map(|n| n.trim().parse().ok()).collect())
+            .filter(|v: &Vec<usize>| !v.is_empty())
+            .unwrap_or_else(|| vec![10_000, 100_000, 1_000_000]);
+        println!("{}", language_pouch::bench_retrieval(&sizes).await);
+        return;
+    }
+
     if args.iter().any(|a| a == "--terminal") {
         test_terminal::run().await;
         return;
@@ -163,7 +243,7 @@ async fn main() {
     log::info!("LOGOS v{} 启动", frozen::bedrock::VERSION);
 
     let app = Arc::new(App {
-        orch: Mutex::new(Orchestrator::new(&data_dir)),
+        orch: RwLock::new(Orchestrator::new(&data_dir)),
         monitor: Mutex::new(Monitor::new()),
     });
 
@@ -184,7 +264,7 @@ async fn main() {
                 }
                 resource_monitor::Status::Critical(metrics, msg) => {
                     log::error!("OVERLOAD {} CPU:{:.1}% MEM:{}MB", msg, metrics.cpu, metrics.mem_mb);
-                    let mut orch = app_c.orch.lock().await;
+                    let mut orch = app_c.orch.write().await;
                     orch.log_event_pub(format!("OVERLOAD CPU:{:.1}% MEM:{}MB", metrics.cpu, metrics.mem_mb));
                 }
             }
@@ -197,9 +277,25 @@ async fn main() {
         let mut interval = tokio::time::interval(std::time::Duration::from_secs(90));
         loop {
             interval.tick().await;
-            let mut orch = app_learn.orch.lock().await;
-            if orch.is_ready() {
-                orch.autonomous_learning_cycle().await;
+            let mut cycle = {
+                let mut orch = app_learn.orch.write().await;
+                orch.flush_sessions();
+                if !orch.is_ready() {
+                    continue;
+                }
+                orch.begin_learning_cycle()
+            };
+            loop {
+                let more = Turn::shared(&app_learn.orch).await.learning_step(&mut cycle).await;
+                if !more {
+                    break;
+                }
+                tokio::task::yield_now().await;
+            }
+            if cycle.cloud_sync_due() {
+                let req = app_learn.orch.read().await.cloud_sync_request();
+                let res = Orchestrator::fetch_cloud_sync(req).await;
+                app_learn.orch.write().await.apply_cloud_sync(res);
             }
         }
     });
@@ -208,6 +304,7 @@ async fn main() {
         .route("/", get(ui))
         .route("/docs/:name", get(serve_doc))
         .route("/api/chat", post(chat))
+        .route("/api/chat/stream", get(chat_stream))
         .route("/api/status", get(status))
         .route("/api/pouches", get(pouches_list))
         .route("/api/events", get(events))
@@ -217,11 +314,22 @@ async fn main() {
         .route("/api/feedback", post(feedback))
         .route("/api/feedback_status", get(feedback_status_handler))
         .route("/api/language_debug", post(language_debug))
+        .route("/api/language_explain", post(language_explain))
         .route("/api/batch_teach", post(batch_teach))
         .route("/api/seed_routes", post(seed_routes))
         .route("/api/dashboard", get(dashboard))
         .route("/api/learning_state", get(learning_state))
-        .with_state(app);
+        .route("/api/patterns", get(patterns_list))
+        .route("/api/patterns/delete", post(patterns_delete))
+        .route("/api/patterns/export", get(patterns_export))
+        .route("/api/pattern/:id", get(pattern_info).put(pattern_edit).delete(pattern_delete))
+        .route("/api/guard", get(guard_list))
+        .route("/api/guard/block", post(guard_block))
+        .route("/api/guard/negative", post(guard_negative))
+        .route("/api/guard/rule/:id", delete(guard_rule_delete))
+        .route("/api/guard/quarantine/:id", delete(quarantine_discard))
+        .route("/api/guard/quarantine/:id/release", post(quarantine_release))
+        .with_state(app.clone());
 
     let addr = "127.0.0.1:3000";
     log::info!("http://{}", addr);
@@ -238,6 +346,7 @@ async fn main() {
         })
         .await
         .ok();
+    app.orch.write().await.flush_sessions();
 }
 
 async fn ui() -> Html<&'static str> {
@@ -260,46 +369,141 @@ async fn serve_doc(Path(name): Path<String>) -> Response {
     }
 }
 
+/*
+ * 执行放在独立任务中，处理 future 只持有 CancelHandle：客户端断开时 axum 丢弃该 future，
+ * 令牌随之取消，正在进行的尿袋调用被中止，本轮仍会正常收尾（写回会话、释放锁）。
+ */
 async fn chat(State(app): State<Arc<App>>, Json(req): Json<Req>) -> (StatusCode, Json<Res>) {
+    let session_id = session::SessionStore::normalize_id(req.session_id.as_deref());
+    let (_cancel, token) = budget::cancel_pair();
+    let task = tokio::spawn(async move {
+        let scope = ChatScope { tags: req.tags, namespace: req.namespace };
+        run_chat(&app, &req.message, session_id, req.trace, scope, None, Some(token)).await
+    });
+    match task.await {
+        Ok((code, res)) => (code, Json(res)),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(Res {
+                response: format!("执行任务异常: {}", e),
+                status: "error".into(),
+                pouch: "system".into(),
+                session_id: session::DEFAULT_SESSION.into(),
+                trace: None,
+            }),
+        ),
+    }
+}
+
+/*
+ * 流式对话：GET /api/chat/stream?message=...&session_id=...&trace=true&tags=vip,beta&namespace=team-a
+ *   - event: progress  data: {"kind","detail"}，路由决策、计划步骤、云端步骤、吸收等逐条推送
+ *   - event: done      data: 与 /api/chat 相同的 Res（trace=true 时附带执行轨迹）
+ * 执行在独立任务中进行；客户端断开时事件流被丢弃，CancelHandle 随之释放，
+ * 未完成的尿袋调用被取消，本轮照常收尾并写入会话。
+ */
+async fn chat_stream(
+    State(app): State<Arc<App>>,
+    Query(req): Query<StreamReq>,
+) -> Sse<impl Stream<Item = Result<Event, axum::Error>>> {
+    let session_id = session::SessionStore::normalize_id(req.session_id.as_deref());
+    let tags = req.tags.as_deref().map(|t| {
+        t.split(',').map(str::trim).filter(|s| !s.is_empty()).map(String::from).collect()
+    });
+    let (tx, rx) = mpsc::unbounded_channel::<Result<Event, axum::Error>>();
+    let (cancel, token) = budget::cancel_pair();
+    tokio::spawn(async move {
+        let (ptx, mut prx) = mpsc::unbounded_channel::<orchestrator::ProgressEvent>();
+        let forward_tx = tx.clone();
+        let forward = tokio::spawn(async move {
+            while let Some(p) = prx.recv().await {
+                let _ = forward_tx.send(Event::default().event("progress").json_data(&p));
+            }
+        });
+        let scope = ChatScope { tags, namespace: req.namespace };
+        let (_, res) = run_chat(&app, &req.message, session_id, req.trace, scope, Some(ptx), Some(token)).await;
+        let _ = forward.await;
+        let _ = tx.send(Event::default().event("done").json_data(&res));
+    });
+    let stream = UnboundedReceiverStream::new(rx).map(move |ev| {
+        let _held = &cancel;
+        ev
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+async fn run_chat(
+    app: &App,
+    message: &str,
+    session_id: String,
+    want_trace: bool,
+    scope: ChatScope,
+    tap: Option<mpsc::UnboundedSender<orchestrator::ProgressEvent>>,
+    cancel: Option<budget::CancelToken>,
+) -> (StatusCode, Res) {
+    let started = std::time::Instant::now();
     {
         let mut m = app.monitor.lock().await;
         if let resource_monitor::Status::Critical(_, msg) = m.check() {
             return (
                 StatusCode::SERVICE_UNAVAILABLE,
-                Json(Res {
+                Res {
                     response: format!("系统过载: {}", msg),
                     status: "overload".into(),
                     pouch: "system".into(),
-                }),
+                    session_id,
+                    trace: None,
+                },
             );
         }
     }
-    let mut orch = app.orch.lock().await;
-    let input = if req.message.len() > frozen::bedrock::MAX_INPUT_LEN {
+    let input = if message.len() > frozen::bedrock::MAX_INPUT_LEN {
         let mut end = frozen::bedrock::MAX_INPUT_LEN;
-        while !req.message.is_char_boundary(end) && end > 0 {
+        while !message.is_char_boundary(end) && end > 0 {
             end -= 1;
         }
-        &req.message[..end]
+        &message[..end]
     } else {
-        &req.message
+        message
     };
-    match orch.execute_with_pouch(input).await {
+    let prepared = app.orch.read().await.prepare(&session_id, input, scope.namespace.as_deref());
+    let mut turn = Turn::shared(&app.orch).await;
+    if let Some(tags) = scope.tags {
+        turn.set_session_tags(&session_id, tags);
+    }
+    turn.set_request_namespace(scope.namespace);
+    turn.set_prepared(Some(prepared));
+    turn.set_event_tap(tap);
+    turn.begin_request_budget(cancel, started);
+    if want_trace {
+        turn.begin_trace(input);
+    }
+    let result = turn.execute_in_session(&session_id, input).await;
+    let trace = turn.take_trace();
+    turn.set_request_namespace(None);
+    turn.end_request_budget();
+    turn.set_event_tap(None);
+    drop(turn);
+    match result {
         Ok((r, pouch)) => (
             StatusCode::OK,
-            Json(Res {
+            Res {
                 response: r,
                 status: "ok".into(),
                 pouch,
-            }),
+                session_id,
+                trace,
+            },
         ),
         Err((e, pouch)) => (
             StatusCode::INTERNAL_SERVER_ERROR,
-            Json(Res {
+            Res {
                 response: e,
                 status: "error".into(),
                 pouch,
-            }),
+                session_id,
+                trace,
+            },
         ),
     }
 }
@@ -311,7 +515,8 @@ async fn status(State(app): State<Arc<App>>) -> Json<StatusRes> {
         | resource_monitor::Status::Warn(m, _)
         | resource_monitor::Status::Critical(m, _) => m,
     };
-    let orch = app.orch.lock().await;
+    drop(m);
+    let orch = app.orch.read().await;
     Json(StatusRes {
         version: frozen::bedrock::VERSION.into(),
         cpu: metrics.cpu,
@@ -324,7 +529,7 @@ async fn status(State(app): State<Arc<App>>) -> Json<StatusRes> {
 }
 
 async fn pouches_list(State(app): State<Arc<App>>) -> Json<Vec<PouchInfo>> {
-    let orch = app.orch.lock().await;
+    let orch = app.orch.read().await;
     let info: Vec<PouchInfo> = orch.pouches_info().into_iter().map(|(name, role, memory, awake)| {
         PouchInfo { name, role, memory, awake }
     }).collect();
@@ -332,17 +537,17 @@ async fn pouches_list(State(app): State<Arc<App>>) -> Json<Vec<PouchInfo>> {
 }
 
 async fn events(State(app): State<Arc<App>>) -> Json<EventsRes> {
-    let orch = app.orch.lock().await;
+    let orch = app.orch.read().await;
     Json(EventsRes { events: orch.recent_events() })
 }
 
 async fn capabilities(State(app): State<Arc<App>>) -> Json<Vec<atom::AtomDeclaration>> {
-    let orch = app.orch.lock().await;
+    let orch = app.orch.read().await;
     Json(orch.capabilities_info())
 }
 
 async fn architecture(State(app): State<Arc<App>>) -> Json<ArchitectureRes> {
-    let orch = app.orch.lock().await;
+    let orch = app.orch.read().await;
     let layers = vec![
         LayerInfo { name: "Bedrock".into(), description: "公式层：定义系统常量与不变量".into(), status: "active".into() },
         LayerInfo { name: "Logic".into(), description: "逻辑层：路由决策与层守卫".into(), status: "active".into() },
@@ -384,7 +589,7 @@ struct FeedbackRes {
 }
 
 async fn feedback(State(app): State<Arc<App>>, Json(req): Json<FeedbackReq>) -> Json<FeedbackRes> {
-    let mut orch = app.orch.lock().await;
+    let mut orch = app.orch.write().await;
     orch.apply_feedback(&req.input, req.signal, req.correction.as_deref());
     Json(FeedbackRes {
         status: "ok".into(),
@@ -393,7 +598,7 @@ async fn feedback(State(app): State<Arc<App>>, Json(req): Json<FeedbackReq>) ->
 }
 
 async fn feedback_status_handler(State(app): State<Arc<App>>) -> Json<serde_json::Value> {
-    let orch = app.orch.lock().await;
+    let orch = app.orch.read().await;
     let (misses, log_count, absorbed, net_positive) = orch.language_feedback_stats();
     serde_json::json!({
         "misses": misses,
@@ -415,10 +620,11 @@ struct LanguageDebugRes {
     status: String,
     is_fallback: bool,
     last_match_weight: f64,
+    pattern_id: Option<u32>,
 }
 
 async fn language_debug(State(app): State<Arc<App>>, Json(req): Json<LanguageDebugReq>) -> Json<LanguageDebugRes> {
-    let mut orch = app.orch.lock().await;
+    let mut orch = app.orch.write().await;
     let input = if req.input.len() > frozen::bedrock::MAX_INPUT_LEN {
         let mut end = frozen::bedrock::MAX_INPUT_LEN;
         while !req.input.is_char_boundary(end) && end > 0 {
@@ -428,17 +634,18 @@ async fn language_debug(State(app): State<Arc<App>>, Json(req): Json<LanguageDeb
     } else {
         &req.input
     };
-    let (response, is_fallback, last_match_weight) = orch.language_debug(input).await;
+    let (response, is_fallback, last_match_weight, pattern_id) = orch.language_debug(input).await;
     Json(LanguageDebugRes {
         response,
         status: "ok".into(),
         is_fallback,
         last_match_weight,
+        pattern_id,
     })
 }
 
 async fn seed_routes(State(app): State<Arc<App>>, body: String) -> Json<serde_json::Value> {
-    let mut orch = app.orch.lock().await;
+    let mut orch = app.orch.write().await;
     let mut count = 0usize;
     for line in body.lines() {
         let line = line.trim();
@@ -455,8 +662,23 @@ async fn seed_routes(State(app): State<Arc<App>>, body: String) -> Json<serde_js
     Json(serde_json::json!({ "status": "ok", "seeded": count }))
 }
 
+#[derive(Deserialize)]
+struct LanguageExplainReq {
+    input: String,
+    top_k: Option<usize>,
+}
+
+async fn language_explain(
+    State(app): State<Arc<App>>,
+    Json(req): Json<LanguageExplainReq>,
+) -> Json<match_explain::MatchExplanation> {
+    let orch = app.orch.read().await;
+    let top_k = req.top_k.unwrap_or(10).clamp(1, 64);
+    Json(orch.explain_match(&req.input, top_k))
+}
+
 async fn batch_teach(State(app): State<Arc<App>>, body: String) -> Json<serde_json::Value> {
-    let mut orch = app.orch.lock().await;
+    let mut orch = app.orch.write().await;
     let before = orch.total_memory_count();
     let taught = orch.batch_teach_content(&body);
     let after = orch.total_memory_count();
@@ -474,7 +696,7 @@ fn file_size(path: &std::path::Path) -> u64 {
 
 async fn data_size(State(app): State<Arc<App>>) -> Json<DataSizeRes> {
     let (dir, memory_count) = {
-        let orch = app.orch.lock().await;
+        let orch = app.orch.read().await;
         (orch.data_dir().to_string(), orch.total_memory_count())
     };
     let base = std::path::Path::new(&dir);
@@ -484,12 +706,14 @@ async fn data_size(State(app): State<Arc<App>>) -> Json<DataSizeRes> {
     let evolution_chain_json_bytes = file_size(&base.join("evolution_chain.json"));
     let promoted_rules_json_bytes = file_size(&base.join("promoted_rules.json"));
     let pouches_json_bytes = file_size(&base.join("pouches.json"));
+    let sessions_json_bytes = file_size(&base.join("sessions.json"));
     let total_bytes = language_bin_bytes
         + routes_bin_bytes
         + evolution_json_bytes
         + evolution_chain_json_bytes
         + promoted_rules_json_bytes
-        + pouches_json_bytes;
+        + pouches_json_bytes
+        + sessions_json_bytes;
     Json(DataSizeRes {
         data_dir: dir,
         language_bin_bytes,
@@ -498,6 +722,7 @@ async fn data_size(State(app): State<Arc<App>>) -> Json<DataSizeRes> {
         evolution_chain_json_bytes,
         promoted_rules_json_bytes,
         pouches_json_bytes,
+        sessions_json_bytes,
         total_bytes,
         memory_count,
     })
@@ -511,7 +736,7 @@ async fn dashboard(State(app): State<Arc<App>>) -> Json<serde_json::Value> {
         | resource_monitor::Status::Critical(m, _) => m,
     };
     drop(m);
-    let orch = app.orch.lock().await;
+    let orch = app.orch.read().await;
     let pouches_data: Vec<serde_json::Value> = orch.pouches_detail()
         .into_iter()
         .map(|(name, role, memory, awake, _, atoms)| {
@@ -546,6 +771,7 @@ async fn dashboard(State(app): State<Arc<App>>) -> Json<serde_json::Value> {
         "events": events,
         "fb": {"misses": fb.0, "log": fb.1, "absorbed": fb.2, "net": fb.3},
         "memory": orch.total_memory_count(),
+        "sessions": orch.session_count(),
         "atoms": {"total": atoms.len(), "kinds": kinds},
         "cfg": {"baseline": cfg_b, "low": cfg_l, "promote": cfg_p},
         "learn": {
@@ -564,8 +790,155 @@ async fn dashboard(State(app): State<Arc<App>>) -> Json<serde_json::Value> {
     }))
 }
 
+/*
+ * 模式管理接口的 ?namespace=：省略时为 base。
+ */
+#[derive(Deserialize)]
+struct NamespaceParam {
+    #[serde(default)]
+    namespace: Option<String>,
+}
+
+fn namespace_error(e: String) -> Response {
+    (StatusCode::NOT_FOUND, Json(serde_json::json!({ "status": "error", "error": e }))).into_response()
+}
+
+async fn pattern_info(
+    State(app): State<Arc<App>>,
+    Path(id): Path<u32>,
+    Query(ns): Query<NamespaceParam>,
+) -> Response {
+    let orch = app.orch.read().await;
+    match orch.pattern_json(ns.namespace.as_deref(), id) {
+        Ok(Some(v)) => Json(v).into_response(),
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => namespace_error(e),
+    }
+}
+
+async fn patterns_list(
+    State(app): State<Arc<App>>,
+    Query(ns): Query<NamespaceParam>,
+    Query(q): Query<pattern_admin::PatternQuery>,
+) -> Response {
+    let orch = app.orch.read().await;
+    match orch.list_patterns(ns.namespace.as_deref(), &q) {
+        Ok(page) => Json(page).into_response(),
+        Err(e) => namespace_error(e),
+    }
+}
+
+async fn patterns_export(
+    State(app): State<Arc<App>>,
+    Query(opts): Query<pattern_export::ExportOptions>,
+) -> Response {
+    let orch = app.orch.read().await;
+    match orch.export_dataset(&opts) {
+        Ok((body, _)) => ([(header::CONTENT_TYPE, opts.format.content_type())], body).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "status": "error", "error": e }))).into_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct GuardBlockReq {
+    pattern: String,
+}
+
+#[derive(Deserialize)]
+struct GuardNegativeReq {
+    trigger: String,
+    response: String,
+}
+
+fn guard_result(result: Result<u32, String>, key: &str) -> Response {
+    match result {
+        Ok(id) => Json(serde_json::json!({ "status": "ok", key: id })).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "status": "error", "error": e }))).into_response(),
+    }
+}
+
+async fn guard_list(State(app): State<Arc<App>>) -> Json<serde_json::Value> {
+    let orch = app.orch.read().await;
+    Json(orch.guard_json())
+}
+
+async fn guard_block(State(app): State<Arc<App>>, Json(req): Json<GuardBlockReq>) -> Response {
+    let mut orch = app.orch.write().await;
+    guard_result(orch.add_block_rule(&req.pattern), "id")
+}
+
+async fn guard_negative(State(app): State<Arc<App>>, Json(req): Json<GuardNegativeReq>) -> Response {
+    let mut orch = app.orch.write().await;
+    guard_result(orch.add_negative_pattern(&req.trigger, &req.response), "id")
+}
+
+async fn guard_rule_delete(State(app): State<Arc<App>>, Path(id): Path<u32>) -> Response {
+    let mut orch = app.orch.write().await;
+    if orch.remove_guard_rule(id) {
+        Json(serde_json::json!({ "status": "ok", "deleted": 1 })).into_response()
+    } else {
+        StatusCode::NOT_FOUND.into_response()
+    }
+}
+
+async fn quarantine_release(State(app): State<Arc<App>>, Path(id): Path<u32>) -> Response {
+    let mut orch = app.orch.write().await;
+    guard_result(orch.release_quarantined(id), "pattern_id")
+}
+
+async fn quarantine_discard(State(app): State<Arc<App>>, Path(id): Path<u32>) -> Response {
+    let mut orch = app.orch.write().await;
+    if orch.discard_quarantined(id) {
+        Json(serde_json::json!({ "status": "ok", "deleted": 1 })).into_response()
+    } else {
+        StatusCode::NOT_FOUND.into_response()
+    }
+}
+
+async fn pattern_edit(
+    State(app): State<Arc<App>>,
+    Path(id): Path<u32>,
+    Query(ns): Query<NamespaceParam>,
+    Json(edit): Json<pattern_admin::PatternEdit>,
+) -> Response {
+    let ns = ns.namespace.as_deref();
+    let mut orch = app.orch.write().await;
+    match orch.edit_pattern(ns, id, &edit) {
+        Ok(()) => match orch.pattern_json(ns, id) {
+            Ok(Some(v)) => Json(v).into_response(),
+            _ => StatusCode::NOT_FOUND.into_response(),
+        },
+        Err(e) => (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "status": "error", "error": e }))).into_response(),
+    }
+}
+
+async fn pattern_delete(
+    State(app): State<Arc<App>>,
+    Path(id): Path<u32>,
+    Query(ns): Query<NamespaceParam>,
+) -> Response {
+    let mut orch = app.orch.write().await;
+    match orch.delete_pattern(ns.namespace.as_deref(), id) {
+        Ok(true) => Json(serde_json::json!({ "status": "ok", "deleted": 1 })).into_response(),
+        Ok(false) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => namespace_error(e),
+    }
+}
+
+async fn patterns_delete(
+    State(app): State<Arc<App>>,
+    Query(ns): Query<NamespaceParam>,
+    Json(q): Json<pattern_admin::PatternQuery>,
+) -> Response {
+    let mut orch = app.orch.write().await;
+    match orch.delete_patterns(ns.namespace.as_deref(), &q) {
+        Ok(n) => Json(serde_json::json!({ "status": "ok", "deleted": n })).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "status": "error", "error": e }))).into_response(),
+    }
+}
+
 async fn learning_state(State(app): State<Arc<App>>) -> Json<serde_json::Value> {
-    let orch = app.orch.lock().await;
+    let orch = app.orch.read().await;
     let ls = orch.learning_snapshot();
     let (pattern_count, pouch_count, avg_maturity) = orch.learning_metrics_extra();
     Json(serde_json::json!({
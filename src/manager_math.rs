@@ -42,6 +42,17 @@ pub fn score_from_evolution_stats(
     raw.max(baseline)
 }
 
+/*
+ * 失败惩罚：按平滑成功率 (成功+1)/(成功+失败+1) 缩放分数。
+ * 无失败记录时分数不变；只有失败时 1 次减半、2 次剩 1/3，逐步低于 low_score_threshold。
+ */
+pub fn penalize_failures(score: f64, successes: usize, failures: usize) -> f64 {
+    if failures == 0 {
+        return score;
+    }
+    score * (successes as f64 + 1.0) / ((successes + failures) as f64 + 1.0)
+}
+
 pub fn promote_eligible(score: f64, min_chain: f64) -> bool {
     min_chain <= 0.0 || score >= min_chain
 }
@@ -0,0 +1,109 @@
+use crate::pattern_index::PatternId;
+use crate::slot_template::Captures;
+use crate::tokenizer::Lang;
+use serde::Serialize;
+
+/*
+ * 匹配解释：按 LanguagePouch::process 的同一流程逐步推演，但不改动频次/权重/tick/上下文。
+ *   - 阶段：空输入 → 语言检查 → 槽位模板（无整句精确命中时）→ 倒排索引候选打分
+ *     → 命名空间基础库（base）→ 同步缓冲兜底 → 上下文兜底 → 诚实兜底
+ *   - 候选得分 = 相似度 × 权重 × max(ln(频次), 1) × 新鲜度 × 覆盖率(h_cov)
+ *   - 被过滤的候选单独列出并注明原因（blocked：可选变体全被回复拦截规则挡下）
+ * tick 取 process 下一次会用的值（当前 +1），每 100 次的陈旧衰减不在推演范围内。
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchStage {
+    Empty,
+    Rejected,
+    Template,
+    Index,
+    Base,
+    SyncBuffer,
+    Context,
+    Fallback,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterReason {
+    OtherLang,
+    BelowSimilarity,
+    TooFewTokens,
+    LowCoverage,
+    NoVariant,
+    Blocked,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CandidateScore {
+    pub id: PatternId,
+    pub trigger: String,
+    pub similarity: f64,
+    pub weight: f64,
+    pub ln_frequency: f64,
+    pub freshness: f64,
+    pub h_cov: f64,
+    pub score: f64,
+    pub variant: Option<usize>,
+    pub filtered: Option<FilterReason>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TemplateHit {
+    pub id: PatternId,
+    pub captures: Captures,
+    pub variant: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MatchExplanation {
+    pub input: String,
+    pub tokens: Vec<String>,
+    pub lang: Lang,
+    pub stage: MatchStage,
+    pub response: String,
+    pub selected: Option<PatternId>,
+    pub exact_hit: bool,
+    pub template: Option<TemplateHit>,
+    pub min_pattern_tokens: usize,
+    pub similarity_threshold: f64,
+    pub coverage_threshold: f64,
+    pub candidates: Vec<CandidateScore>,
+    pub filtered: Vec<CandidateScore>,
+}
+
+impl CandidateScore {
+    pub fn new(id: PatternId, trigger: String, similarity: f64, weight: f64) -> Self {
+        CandidateScore {
+            id,
+            trigger,
+            similarity,
+            weight,
+            ln_frequency: 0.0,
+            freshness: 0.0,
+            h_cov: 0.0,
+            score: 0.0,
+            variant: None,
+            filtered: None,
+        }
+    }
+
+    pub fn filter(mut self, reason: FilterReason) -> Self {
+        self.filtered = Some(reason);
+        self
+    }
+}
+
+/*
+ * 参与打分的候选按得分降序，被过滤的按相似度降序，各取前 top_k 条。
+ */
+pub fn split_top_k(all: Vec<CandidateScore>, top_k: usize) -> (Vec<CandidateScore>, Vec<CandidateScore>) {
+    let (mut scored, mut filtered): (Vec<_>, Vec<_>) = all.into_iter().partition(|c| c.filtered.is_none());
+    let desc = |a: f64, b: f64| b.partial_cmp(&a).unwrap_or(std::cmp::Ordering::Equal);
+    scored.sort_by(|a, b| desc(a.score, b.score).then(a.id.cmp(&b.id)));
+    filtered.sort_by(|a, b| desc(a.similarity, b.similarity).then(a.id.cmp(&b.id)));
+    scored.truncate(top_k);
+    filtered.truncate(top_k);
+    (scored, filtered)
+}
@@ -0,0 +1,215 @@
+use crate::language_pouch::LanguagePouch;
+use crate::tokenizer::{Tokenizer, TokenizerState};
+use std::collections::{BTreeMap, BTreeSet};
+
+/*
+ * 语言模式命名空间：每个命名空间是一个独立的 LanguagePouch（模式、路由、反馈状态各自保存）。
+ *   - base：共享基础库，沿用 data_dir 下的 language.bin / routes.bin / feedback.json / guard.json
+ *   - 其他：data_dir/namespaces/<名>/ 下的同名文件
+ *   - 请求期间当前命名空间被换入 Orchestrator.language，本轮结束换回 base（等待远程袋期间也暂时换回）；
+ *     后台学习、云同步和不带会话的接口始终作用于 base
+ *   - 非 base 命名空间的模式未命中时，只读地查 base（不改 base 的频次/权重）
+ *   - 各命名空间有自己的分词器，记录在目录下的 tokenizer.json
+ *   - 保存时只写当前命名空间和上次保存后被换入过（可能改动过）的停放命名空间
+ */
+pub const BASE_NAMESPACE: &str = "base";
+const NAMESPACE_NAME_MAX_LEN: usize = 32;
+
+pub fn validate_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.len() > NAMESPACE_NAME_MAX_LEN {
+        return Err(format!("命名空间名长度应为 1-{}", NAMESPACE_NAME_MAX_LEN));
+    }
+    if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(format!("命名空间名只能包含字母、数字、- 和 _: {}", name));
+    }
+    Ok(())
+}
+
+pub fn dir(data_dir: &str, name: &str) -> String {
+    if name == BASE_NAMESPACE {
+        data_dir.to_string()
+    } else {
+        format!("{}/namespaces/{}", data_dir, name)
+    }
+}
+
+/*
+ * 磁盘上已有的非 base 命名空间（按名排序）。
+ */
+pub fn stored_names(data_dir: &str) -> Vec<String> {
+    let mut names: Vec<String> = std::fs::read_dir(format!("{}/namespaces", data_dir))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|e| e.path().join("language.bin").exists())
+        .filter_map(|e| e.file_name().into_string().ok())
+        .filter(|n| n != BASE_NAMESPACE && validate_name(n).is_ok())
+        .collect();
+    names.sort();
+    names
+}
+
+pub fn save_pouch(dir: &str, language: &LanguagePouch) -> Result<(), String> {
+    std::fs::create_dir_all(dir).map_err(|e| format!("创建目录失败: {}", e))?;
+    let write = |file: &str, data: Vec<u8>| {
+        std::fs::write(format!("{}/{}", dir, file), data).map_err(|e| format!("写入 {} 失败: {}", file, e))
+    };
+    write("language.bin", language.save()?)?;
+    write("routes.bin", language.save_routes()?)?;
+    write("feedback.json", language.save_feedback()?)?;
+    write("guard.json", language.save_guard()?)?;
+    let state = serde_json::to_vec(&language.tokenizer_state()).map_err(|e| format!("序列化失败: {}", e))?;
+    write("tokenizer.json", state)
+}
+
+/* 目录下 tokenizer.json 记录的分词器（已存 token 由它产生）；文件缺失或损坏时为 None */
+pub fn stored_tokenizer(dir: &str) -> Option<TokenizerState> {
+    let json = std::fs::read_to_string(format!("{}/tokenizer.json", dir)).ok()?;
+    serde_json::from_str(&json).ok()
+}
+
+/*
+ * 读取一个命名空间目录；tokenizer 为已存 token 所用的分词器。
+ * 路由、反馈和拦截规则文件缺失时保持为空。
+ */
+pub fn load_pouch(dir: &str, tokenizer: Box<dyn Tokenizer>) -> Result<LanguagePouch, String> {
+    let data = std::fs::read(format!("{}/language.bin", dir)).map_err(|e| format!("读取失败: {}", e))?;
+    let mut language = LanguagePouch::with_tokenizer(tokenizer);
+    language.load(&data)?;
+    if let Ok(data) = std::fs::read(format!("{}/routes.bin", dir)) {
+        language.load_routes(&data).ok();
+    }
+    if let Ok(data) = std::fs::read(format!("{}/feedback.json", dir)) {
+        language.load_feedback(&data).ok();
+    }
+    if let Ok(data) = std::fs::read(format!("{}/guard.json", dir)) {
+        language.load_guard(&data).ok();
+    }
+    Ok(language)
+}
+
+/*
+ * 复制：按持久化格式往返一次，得到独立的一份（不含会话上下文）。
+ * tokenizer 应与 language 所用的分词器同类。
+ */
+pub fn duplicate(language: &LanguagePouch, tokenizer: Box<dyn Tokenizer>) -> Result<LanguagePouch, String> {
+    let mut copy = LanguagePouch::with_tokenizer(tokenizer);
+    copy.load(&language.save()?)?;
+    copy.load_routes(&language.save_routes()?)?;
+    copy.load_feedback(&language.save_feedback()?)?;
+    copy.load_guard(&language.save_guard()?)?;
+    Ok(copy)
+}
+
+/*
+ * 当前换入 Orchestrator.language 的命名空间名，以及其余（停放中的）命名空间；
+ * dirty 为上次保存后换入过或被批量修改过的停放命名空间。
+ */
+pub struct Namespaces {
+    active: String,
+    parked: BTreeMap<String, LanguagePouch>,
+    dirty: BTreeSet<String>,
+}
+
+impl Namespaces {
+    pub fn new() -> Self {
+        Self { active: BASE_NAMESPACE.to_string(), parked: BTreeMap::new(), dirty: BTreeSet::new() }
+    }
+
+    pub fn active(&self) -> &str {
+        &self.active
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.active == name || self.parked.contains_key(name)
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.parked.keys().cloned().collect();
+        names.push(self.active.clone());
+        names.sort();
+        names
+    }
+
+    /*
+     * 供回退查询的 base：当前就是 base 时返回 None（不需要回退）。
+     */
+    pub fn base(&self) -> Option<&LanguagePouch> {
+        self.parked.get(BASE_NAMESPACE)
+    }
+
+    pub fn parked(&self, name: &str) -> Option<&LanguagePouch> {
+        self.parked.get(name)
+    }
+
+    /* 可改动的停放命名空间，取出即视为已改动 */
+    pub fn parked_mut(&mut self, name: &str) -> Option<&mut LanguagePouch> {
+        let language = self.parked.get_mut(name)?;
+        self.dirty.insert(name.to_string());
+        Some(language)
+    }
+
+    /*
+     * 取出需要保存的停放命名空间并清空标记。
+     */
+    pub fn take_dirty(&mut self) -> Vec<(&String, &LanguagePouch)> {
+        let dirty = std::mem::take(&mut self.dirty);
+        self.parked.iter().filter(|(name, _)| dirty.contains(*name)).collect()
+    }
+
+    pub fn park(&mut self, name: &str, language: LanguagePouch) {
+        self.parked.insert(name.to_string(), language);
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<LanguagePouch> {
+        self.dirty.remove(name);
+        self.parked.remove(name)
+    }
+
+    /*
+     * 把 name 换入 language，原来的按当前名停放。
+     */
+    pub fn switch(&mut self, language: &mut LanguagePouch, name: &str) -> Result<(), String> {
+        if self.active == name {
+            return Ok(());
+        }
+        let next = self.parked.remove(name).ok_or_else(|| format!("命名空间不存在: {}", name))?;
+        let prev = std::mem::replace(language, next);
+        let prev_name = std::mem::replace(&mut self.active, name.to_string());
+        self.dirty.insert(prev_name.clone());
+        self.parked.insert(prev_name, prev);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_switch_parks_previous_and_validates_names() {
+        let mut ns = Namespaces::new();
+        let mut language = LanguagePouch::new();
+        assert!(language.teach("基础库专有问题", "基础回复").is_ok());
+        let base_count = language.memory_count();
+        ns.park("team-a", LanguagePouch::new());
+        assert!(ns.switch(&mut language, "missing").is_err());
+        assert!(ns.take_dirty().is_empty());
+        assert!(ns.switch(&mut language, "team-a").is_ok());
+        assert_eq!(ns.active(), "team-a");
+        let dirty: Vec<&String> = ns.take_dirty().into_iter().map(|(name, _)| name).collect();
+        assert_eq!(dirty, vec![BASE_NAMESPACE]);
+        assert!(ns.take_dirty().is_empty());
+        assert_eq!(ns.base().map(|b| b.memory_count()), Some(base_count));
+        assert_eq!(ns.names(), vec!["base".to_string(), "team-a".to_string()]);
+        assert!(ns.switch(&mut language, BASE_NAMESPACE).is_ok());
+        assert_eq!(language.memory_count(), base_count);
+        assert!(ns.base().is_none());
+
+        assert!(validate_name("team_b-2").is_ok());
+        assert!(validate_name("../etc").is_err());
+        assert!(validate_name("").is_err());
+        assert_eq!(dir("data", BASE_NAMESPACE), "data");
+        assert_eq!(dir("data", "team-a"), "data/namespaces/team-a");
+    }
+}
@@ -1,12 +1,27 @@
 use crate::frozen::logic::{self, Layer, RouteDecision, SystemCmd};
-use crate::language_pouch::LanguagePouch;
-use crate::pouch_trait::{Pouch, PouchMeta, PouchRole, create_proposal};
-use crate::atom::{AtomDeclaration, CapabilityRegistry};
+use crate::language_pouch::{LanguagePouch, LanguageTurn, PatternLookup};
+use crate::pouch_trait::{Pouch, PouchMeta, PouchOutput, PouchRole, ProposalMessage, ValidatedProposal, create_proposal};
+use crate::atom::{AtomDeclaration, CapabilityRegistry, StepCandidate};
 use crate::frozen::bedrock;
 use crate::config::SystemConfig;
+use crate::dialogue_state::DialogueState;
+use crate::provenance::Origin;
+use crate::match_explain::MatchExplanation;
+use crate::namespace::{self, Namespaces, BASE_NAMESPACE};
+use crate::pattern_admin::{PatternEdit, PatternPage, PatternQuery};
+use crate::language_eval::EvalOptions;
+use crate::pattern_export::{self, ExportOptions};
+use crate::pattern_import::ImportOptions;
+use crate::session::SessionStore;
+use crate::budget::{self, CallCut, CancelToken, RequestBudget};
+use crate::intent_model::IntentModel;
+use crate::tokenizer::{self, Lang, TokenizerState};
+use crate::trace::{self, ExecutionTrace, PlanTrace, StepTrace};
 use crate::manager_math;
 use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+use tokio::sync::{Mutex, RwLock, RwLockWriteGuard};
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct EvolutionRecord {
@@ -44,6 +59,7 @@ const SATURATION_THRESHOLD: f64 = 0.65;
 const CROSS_LEARN_BATCH: usize = 8;
 const MATURITY_HUNGRY: f64 = 0.3;
 const A2_NEXT_INTENT_MAX: usize = 500;
+const LOW_CONFIDENCE_NOTE: &str = " [低置信度]";
 
 fn hash_str(s: &str) -> u64 {
     let mut hasher = std::collections::hash_map::DefaultHasher::new();
@@ -51,6 +67,14 @@ fn hash_str(s: &str) -> u64 {
     hasher.finish()
 }
 
+fn parse_pattern_id(s: &str) -> Result<u32, String> {
+    s.trim().trim_start_matches('#').parse().map_err(|_| "模式编号应为数字".to_string())
+}
+
+fn parse_guard_id(s: &str) -> Result<u32, String> {
+    s.trim().trim_start_matches('#').parse().map_err(|_| "编号应为数字".to_string())
+}
+
 pub const VERSION: &str = "5.0.0";
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -155,7 +179,10 @@ fn fetch_remote_spec_from_cloud(name: &str) -> Option<crate::remote_pouch::Remot
     None
 }
 
-async fn analyze_cloud(input: &str) -> Result<CloudPlan, String> {
+/* 云端分析在请求预算和单尿袋超时覆盖表里使用的名字 */
+const CLOUD_ANALYZE: &str = "cloud_analyze";
+
+async fn analyze_cloud(input: String) -> Result<CloudPlan, String> {
     let client = reqwest::Client::builder()
         .timeout(std::time::Duration::from_secs(10))
         .build()
@@ -173,8 +200,122 @@ async fn analyze_cloud(input: &str) -> Result<CloudPlan, String> {
         .map_err(|_| "解析分析结果失败".into())
 }
 
+async fn fetch_import(url: String) -> Result<String, String> {
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(30))
+        .build()
+        .map_err(|e| format!("http client: {}", e))?;
+    let resp = client.get(&url).send().await.map_err(|e| format!("fetch: {}", e))?;
+    if !resp.status().is_success() {
+        return Err(format!("http {} {}", resp.status(), url));
+    }
+    resp.text().await.map_err(|e| format!("body: {}", e))
+}
+
+/*
+ * 流式进度事件：execute_with_pouch 运行期间，log_event 写入的每条事件
+ * 以及计划/云端步骤的输出会同时推送到 event_tap（若已设置）。
+ *   - kind 取事件首个字段（ROUTE / PLAN / PLAN_STEP / CLOUD_STEP / ABSORB / STEP_OUTPUT …）
+ *   - 未设置 event_tap 时不产生任何额外开销；接收端断开不影响执行
+ */
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ProgressEvent {
+    pub kind: String,
+    pub detail: String,
+}
+
+#[derive(Debug, Default)]
+pub struct CloudSyncRequest {
+    cursor: i64,
+    push_pairs: Vec<(String, String)>,
+}
+
+#[derive(Debug, Default)]
+pub struct CloudSyncResult {
+    pairs: Vec<(String, String)>,
+    max_id: Option<i64>,
+    inserted: u64,
+}
+
+/*
+ * 歧义消解后的路由结果：与 RouteDecision 相同但没有 Ambiguous，由 resolve_ambiguous 产生。
+ */
+#[derive(Debug)]
+enum Routed {
+    ToPouch(String),
+    SystemCommand(SystemCmd),
+    Usage(String),
+    Reject,
+}
+
+/*
+ * 持读锁完成的路由和本库模式匹配（Orchestrator::prepare），请求随后持写锁执行时只做记账等改动。
+ * 输入经净化、指代消解后变了，或命名空间、已装尿袋与准备时不同，就照常重新计算。
+ */
+#[derive(Debug)]
+pub struct Prepared {
+    input: String,
+    namespace: String,
+    installed: Vec<String>,
+    route: Option<RouteDecision>,
+    lookup: Option<PatternLookup>,
+}
+
+/*
+ * 自主学习分片：一个学习周期由 begin_learning_cycle 规划为任务队列，
+ * learning_step 每次只执行一个任务（一个意图 / 一次广播 / 一次落盘），
+ * 调用方在步骤之间释放 Orchestrator 锁，步骤内等待远程袋时也经 Turn::unlocked 释放，
+ * 使 /api/chat 不必等待整个周期。
+ *   - 周期内的中间结果（lang_outputs、teacher_outputs、A2 链式意图）保存在 LearningCycle 中，不占锁
+ *   - 云同步拆为 cloud_sync_request（持读锁）→ fetch_cloud_sync（无锁网络 I/O）→ apply_cloud_sync（持写锁）
+ *   - 步骤之间用户请求可能改变模式库，学习结果以步骤执行时的状态为准
+ */
+#[derive(Debug)]
+enum LearningTask {
+    FeedMiss(String),
+    FeedSeed(String, String),
+    FeedHungry(String, String),
+    DiscoverGaps,
+    FeedSummary,
+    SynapseLang(String),
+    SynapseBroadcast,
+    SynapseHop(String),
+    SynapseHopEnd,
+    SynapseTeach,
+    SelfOptimize,
+    Evolve,
+    Persist,
+}
+
+#[derive(Debug)]
+pub struct LearningCycle {
+    cycle: u64,
+    miss_count: usize,
+    hungry: Vec<String>,
+    teachers: Vec<String>,
+    students: Vec<String>,
+    before_mem: usize,
+    before_evo: usize,
+    fed_count: u64,
+    lang_outputs: Vec<(Vec<String>, String)>,
+    hop: usize,
+    a2_hops: usize,
+    hop_outputs: Vec<(String, String, String)>,
+    teacher_outputs: Vec<(String, String, String)>,
+    queue: std::collections::VecDeque<LearningTask>,
+}
+
+impl LearningCycle {
+    pub fn cloud_sync_due(&self) -> bool {
+        self.cycle > 0 && self.cycle.is_multiple_of(7)
+    }
+}
+
 pub struct Orchestrator {
     language: LanguagePouch,
+    namespaces: Namespaces,
+    session_namespace: Option<String>,
+    request_namespace: Option<String>,
     pouches: HashMap<String, Box<dyn Pouch>>,
     meta: HashMap<String, PouchMeta>,
     call_stack: Vec<Layer>,
@@ -189,15 +330,112 @@ pub struct Orchestrator {
     promoted_cache: HashMap<u64, String>,
     chain_result_cache: std::collections::VecDeque<(String, String)>,
     registry: CapabilityRegistry,
+    sessions: SessionStore,
+    sessions_dirty: bool,
+    sessions_saved_at: u64,
+    session_gates: HashMap<String, Arc<Mutex<()>>>,
+    dialogue: DialogueState,
+    event_tap: Option<tokio::sync::mpsc::UnboundedSender<ProgressEvent>>,
+    trace: Option<ExecutionTrace>,
+    budget: Option<RequestBudget>,
+    intent: IntentModel,
+    prepared: Option<Prepared>,
     pub learning: LearningState,
 }
 
+/*
+ * 对编排器的一次持有：请求和后台学习经由它执行，等待远程尿袋、云端分析等网络 I/O 时不持有写锁。
+ *   - direct：调用方已独占编排器（命令行、测试），网络 I/O 原地等待
+ *   - shared：经 App 的读写锁持有；放锁前把本轮的请求状态（命名空间、会话上下文、对话状态、
+ *     调用栈、轨迹、预算、进度推送）换出，编排器回到空闲状态供其他请求使用，重新持锁后换回
+ *   - 放锁期间其他请求可能改动模式库或卸载尿袋，恢复后以当时的状态为准
+ *   - /api/chat 先持读锁完成路由和本库模式匹配（prepare），再持写锁执行，写锁阶段只做记账、
+ *     上下文等改动和本地尿袋执行（Pouch::process_proposal 需要 &mut）
+ *   - 同一会话的轮次按会话门（session_gates）串行：上一轮放锁等待时，下一轮在门外等待而不是
+ *     取走同一份会话上下文，两轮的上下文都会保留
+ */
+pub struct Turn<'a> {
+    hold: Hold<'a>,
+    parked: TurnState,
+}
+
+enum Hold<'a> {
+    Direct(&'a mut Orchestrator),
+    Shared(&'a RwLock<Orchestrator>, Option<RwLockWriteGuard<'a, Orchestrator>>),
+}
+
+#[derive(Default)]
+struct TurnState {
+    namespace: String,
+    session_namespace: Option<String>,
+    request_namespace: Option<String>,
+    call_stack: Vec<Layer>,
+    dialogue: DialogueState,
+    event_tap: Option<tokio::sync::mpsc::UnboundedSender<ProgressEvent>>,
+    trace: Option<ExecutionTrace>,
+    budget: Option<RequestBudget>,
+    prepared: Option<Prepared>,
+    language: LanguageTurn,
+}
+
+impl<'a> Turn<'a> {
+    pub fn direct(orch: &'a mut Orchestrator) -> Self {
+        Self { hold: Hold::Direct(orch), parked: TurnState::default() }
+    }
+
+    pub async fn shared(lock: &'a RwLock<Orchestrator>) -> Self {
+        let guard = lock.write().await;
+        Self { hold: Hold::Shared(lock, Some(guard)), parked: TurnState::default() }
+    }
+
+    /* 放开写锁等待 fut（direct 时原地等待），重新持锁后换回本轮状态 */
+    async fn unlocked<F: std::future::Future>(&mut self, fut: F) -> F::Output {
+        let Hold::Shared(lock, guard) = &mut self.hold else {
+            return fut.await;
+        };
+        let lock: &'a RwLock<Orchestrator> = lock;
+        if let Some(mut g) = guard.take() {
+            g.park_turn(&mut self.parked);
+        }
+        let out = fut.await;
+        let mut g = lock.write().await;
+        g.resume_turn(&mut self.parked);
+        *guard = Some(g);
+        out
+    }
+}
+
+impl std::ops::Deref for Turn<'_> {
+    type Target = Orchestrator;
+
+    fn deref(&self) -> &Orchestrator {
+        match &self.hold {
+            Hold::Direct(o) => o,
+            Hold::Shared(_, Some(g)) => g,
+            Hold::Shared(_, None) => unreachable!("写锁只在 unlocked 等待期间放开"),
+        }
+    }
+}
+
+impl std::ops::DerefMut for Turn<'_> {
+    fn deref_mut(&mut self) -> &mut Orchestrator {
+        match &mut self.hold {
+            Hold::Direct(o) => o,
+            Hold::Shared(_, Some(g)) => g,
+            Hold::Shared(_, None) => unreachable!("写锁只在 unlocked 等待期间放开"),
+        }
+    }
+}
+
 impl Orchestrator {
     pub fn new(data_dir: &str) -> Self {
         let config_path = format!("{}/pouch_config.json", data_dir);
         let config = SystemConfig::load(&config_path).unwrap_or_default();
         let mut o = Self {
             language: LanguagePouch::new(),
+            namespaces: Namespaces::new(),
+            session_namespace: None,
+            request_namespace: None,
             pouches: HashMap::new(),
             meta: HashMap::new(),
             call_stack: Vec::new(),
@@ -212,10 +450,22 @@ impl Orchestrator {
             promoted_cache: HashMap::new(),
             chain_result_cache: std::collections::VecDeque::new(),
             registry: CapabilityRegistry::new(),
+            sessions: SessionStore::new(),
+            sessions_dirty: false,
+            sessions_saved_at: 0,
+            session_gates: HashMap::new(),
+            dialogue: DialogueState::default(),
+            event_tap: None,
+            trace: None,
+            budget: None,
+            intent: IntentModel::new(),
+            prepared: None,
             learning: LearningState::default(),
         };
         o.meta.insert("language".into(), PouchMeta { role: PouchRole::E0 });
+        let language_restored = std::path::Path::new(&format!("{}/language.bin", data_dir)).exists();
         o.load_state();
+        o.init_tokenizer(language_restored);
         o.registry.register(AtomDeclaration {
             name: "route_intent".into(),
             kind: crate::atom::AtomKind::Route,
@@ -256,537 +506,583 @@ impl Orchestrator {
         list
     }
 
+    #[cfg(test)]
+    pub async fn execute_in_session(
+        &mut self,
+        session_id: &str,
+        input: &str,
+    ) -> Result<(String, String), (String, String)> {
+        Turn::direct(self).execute_in_session(session_id, input).await
+    }
+
     pub async fn execute_with_pouch(
         &mut self,
         input: &str,
     ) -> Result<(String, String), (String, String)> {
-        if !self.call_stack.is_empty() {
-            self.call_stack.clear();
+        Turn::direct(self).execute_with_pouch(input).await
+    }
+
+    #[cfg(test)]
+    pub async fn call_pouch(&mut self, name: &str, input: &str) -> Result<String, String> {
+        Turn::direct(self).call_pouch(name, input).await
+    }
+
+    /*
+     * 换入会话：命名空间、上下文、对话状态和标签；返回换出的外层上下文和对话状态，
+     * 由 leave_session 换回。
+     */
+    fn enter_session(&mut self, session_id: &str, now: u64) -> Result<(Vec<(String, String)>, DialogueState), String> {
+        let expired = self.sessions.expire_idle(now, self.config.session.idle_ttl_secs);
+        if expired > 0 {
+            self.log_event(format!("SESSION_EXPIRE {}", expired));
         }
-        let input = if input.len() > bedrock::MAX_INPUT_LEN {
-            let mut end = bedrock::MAX_INPUT_LEN;
-            while !input.is_char_boundary(end) && end > 0 {
-                end -= 1;
+        if let Some(ns) = self.request_namespace.as_deref().filter(|ns| !self.namespaces.contains(ns)) {
+            return Err(format!("命名空间不存在: {}", ns));
+        }
+        let session_ns = self.sessions.namespace(session_id).filter(|ns| self.namespaces.contains(ns));
+        let ns = self.request_namespace.clone().or_else(|| session_ns.clone());
+        self.enter_namespace(ns.as_deref().unwrap_or(BASE_NAMESPACE));
+        self.session_namespace = session_ns;
+        let ctx = self.sessions.take_context(session_id, now, self.config.session.max_sessions);
+        let outer = self.language.swap_context(ctx);
+        let outer_dialogue = std::mem::replace(&mut self.dialogue, self.sessions.take_dialogue(session_id));
+        self.language.set_session_tags(self.sessions.tags(session_id));
+        Ok((outer, outer_dialogue))
+    }
+
+    /* 会话门：同一会话同时只有一轮在 enter_session 与 leave_session 之间 */
+    fn session_gate(&mut self, session_id: &str) -> Arc<Mutex<()>> {
+        self.session_gates.entry(session_id.to_string()).or_default().clone()
+    }
+
+    /* 没有其他轮次在门外等待时移除会话门（此时只剩表里和 gate 两个引用） */
+    fn release_session_gate(&mut self, session_id: &str, gate: Arc<Mutex<()>>) {
+        if Arc::strong_count(&gate) <= 2 {
+            self.session_gates.remove(session_id);
+        }
+    }
+
+    fn leave_session(&mut self, session_id: &str, outer: (Vec<(String, String)>, DialogueState), now: u64) {
+        let (outer, outer_dialogue) = outer;
+        self.language.set_session_tags(Vec::new());
+        let dialogue = std::mem::replace(&mut self.dialogue, outer_dialogue);
+        self.sessions.put_dialogue(session_id, dialogue);
+        let ctx = self.language.swap_context(outer);
+        self.sessions.put_context(session_id, ctx, now);
+        let chosen = self.session_namespace.take();
+        self.sessions.set_namespace(session_id, chosen);
+        self.enter_namespace(BASE_NAMESPACE);
+        if self.config.session.persist {
+            self.sessions_dirty = true;
+            if now.saturating_sub(self.sessions_saved_at) >= self.config.session.save_interval_secs {
+                self.flush_sessions();
             }
-            &input[..end]
+        }
+    }
+
+    fn swap_request_state(&mut self, t: &mut TurnState) {
+        std::mem::swap(&mut self.session_namespace, &mut t.session_namespace);
+        std::mem::swap(&mut self.request_namespace, &mut t.request_namespace);
+        std::mem::swap(&mut self.call_stack, &mut t.call_stack);
+        std::mem::swap(&mut self.dialogue, &mut t.dialogue);
+        std::mem::swap(&mut self.event_tap, &mut t.event_tap);
+        std::mem::swap(&mut self.trace, &mut t.trace);
+        std::mem::swap(&mut self.budget, &mut t.budget);
+        std::mem::swap(&mut self.prepared, &mut t.prepared);
+    }
+
+    /* 放锁前换出本轮状态，编排器回到 base 命名空间和空闲的请求状态 */
+    fn park_turn(&mut self, t: &mut TurnState) {
+        self.language.swap_turn(&mut t.language);
+        t.namespace = self.namespaces.active().to_string();
+        self.enter_namespace(BASE_NAMESPACE);
+        self.swap_request_state(t);
+    }
+
+    fn resume_turn(&mut self, t: &mut TurnState) {
+        self.swap_request_state(t);
+        let ns = std::mem::take(&mut t.namespace);
+        self.enter_namespace(&ns);
+        self.language.swap_turn(&mut t.language);
+    }
+
+    /*
+     * 本次请求使用的命名空间（优先于会话设置），请求结束后应清空。
+     */
+    pub fn set_request_namespace(&mut self, namespace: Option<String>) {
+        self.request_namespace = namespace;
+    }
+
+    fn enter_namespace(&mut self, name: &str) {
+        if let Err(e) = self.namespaces.switch(&mut self.language, name) {
+            self.log_event(format!("NAMESPACE_SWITCH 失败 {}", e));
+        }
+    }
+
+    pub fn set_session_tags(&mut self, session_id: &str, tags: Vec<String>) {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        self.sessions.set_tags(session_id, tags, now);
+    }
+
+    pub fn session_count(&self) -> usize {
+        self.sessions.len()
+    }
+
+    /*
+     * 有未写入的会话改动时写 sessions.json；对话中按 save_interval_secs 节流，
+     * 学习周期开始和服务退出时也会调用。
+     */
+    pub fn flush_sessions(&mut self) {
+        if !self.sessions_dirty {
+            return;
+        }
+        let _ = std::fs::create_dir_all(&self.data_dir);
+        let Ok(data) = self.sessions.save() else {
+            return;
+        };
+        if std::fs::write(format!("{}/sessions.json", self.data_dir), data).is_err() {
+            return;
+        }
+        self.sessions_dirty = false;
+        self.sessions_saved_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+    }
+
+    /*
+     * 只读阶段：按请求将使用的命名空间和会话上下文预先路由、匹配本库模式。
+     * 调用方持读锁，结果经 set_prepared 交给随后的写锁阶段。
+     */
+    pub fn prepare(&self, session_id: &str, input: &str, namespace: Option<&str>) -> Prepared {
+        let input = input.trim();
+        let namespace = namespace
+            .map(str::to_string)
+            .or_else(|| self.sessions.namespace(session_id).filter(|ns| self.namespaces.contains(ns)))
+            .unwrap_or_else(|| BASE_NAMESPACE.to_string());
+        let route = logic::route(input, &self.route_targets());
+        let language = if namespace == self.namespaces.active() {
+            Some(&self.language)
         } else {
-            input
+            self.namespaces.parked(&namespace)
+        };
+        let lookup = match (&route, language) {
+            (RouteDecision::Reject(_), Some(language)) => {
+                language.lookup(input, self.sessions.last_input(session_id), &self.sessions.tags(session_id))
+            }
+            _ => None,
         };
+        Prepared { input: input.to_string(), namespace, installed: self.installed_names(), route: Some(route), lookup }
+    }
 
-        if let Some(domain) = Self::classify_domain(input) {
-            self.auto_ensure_pouch(domain);
+    pub fn set_prepared(&mut self, prepared: Option<Prepared>) {
+        self.prepared = prepared;
+    }
+
+    fn installed_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.installed().into_iter().map(str::to_string).collect();
+        names.sort();
+        names
+    }
+
+    /* 与当前输入、命名空间和已装尿袋相符的准备结果 */
+    fn prepared_for(&mut self, input: &str) -> Option<&mut Prepared> {
+        let installed = self.installed_names();
+        let namespace = self.namespaces.active().to_string();
+        self.prepared
+            .as_mut()
+            .filter(|p| p.input == input && p.namespace == namespace && p.installed == installed)
+    }
+
+    pub fn begin_trace(&mut self, input: &str) {
+        self.trace = Some(ExecutionTrace::new(input));
+    }
+
+    pub fn take_trace(&mut self) -> Option<ExecutionTrace> {
+        self.trace.take()
+    }
+
+    /* started 为请求到达时刻：等待编排器锁的时间也计入请求时限 */
+    pub fn begin_request_budget(&mut self, cancel: Option<CancelToken>, started: std::time::Instant) {
+        let b = &self.config.budget;
+        self.budget = Some(RequestBudget::new(b.request_timeout_ms, b.max_pouch_calls, cancel, started));
+    }
+
+    pub fn end_request_budget(&mut self) {
+        self.budget = None;
+    }
+
+    fn budget_exhausted(&self) -> Option<String> {
+        self.budget.as_ref().and_then(|b| b.exhausted())
+    }
+
+    /*
+     * 调用前扣减请求预算。language 为本地兜底，不计入次数也不受请求时限约束，
+     * 保证预算耗尽后仍能给出回答。
+     */
+    fn take_call_budget(&mut self, name: &str) -> Option<String> {
+        if name == "language" {
+            return None;
+        }
+        let b = self.budget.as_mut()?;
+        if let Some(why) = b.exhausted() {
+            return Some(why);
         }
+        b.calls_left -= 1;
+        None
+    }
 
-        if let Err(e) = self.guard(Layer::Orchestrator) {
-            return Err((e, "system".into()));
+    fn call_bounds(&self, name: &str) -> (std::time::Duration, Option<CancelToken>) {
+        let limit = std::time::Duration::from_millis(self.config.budget.pouch_timeout_ms(name));
+        match self.budget.as_ref() {
+            Some(b) if name != "language" => (limit.min(b.remaining()), b.cancel.clone()),
+            Some(b) => (limit, b.cancel.clone()),
+            None => (limit, None),
         }
+    }
 
-        let trimmed = input.trim();
-        let sanitized_store;
-        let input = if let Some((rejected, out)) = self.sanitize_input(trimmed).await {
-            if rejected {
-                self.unguard();
-                return Ok((out, "sanitize".into()));
+    /*
+     * 超时：记为失败的 EvolutionEntry，参与 score_pouch_from_entries 降权；
+     * 取消：客户端已断开，不归咎于尿袋，只记事件。
+     */
+    fn cut_call(&mut self, name: &str, input: &str, cut: CallCut) -> Result<String, String> {
+        match cut {
+            CallCut::Timeout(ms) => {
+                self.log_event(format!("TIMEOUT {} {}ms", name, ms));
+                let err = format!("{}超时({}ms)", name, ms);
+                self.after_pouch_call(name, input, &Err(err.clone()));
+                Err(err)
             }
-            sanitized_store = out;
-            &sanitized_store
-        } else {
-            trimmed
-        };
-        if input.starts_with("语言评估") || input.to_lowercase().starts_with("eval language") {
-            let path = input
-                .replace("语言评估", "")
-                .replace("eval language", "")
-                .replace("Eval Language", "")
-                .trim()
-                .to_string();
-            if !path.is_empty() {
-                match self.eval_language(&path).await {
-                    Ok(msg) => return Ok((msg, "system".into())),
-                    Err(e) => return Err((e, "system".into())),
-                }
+            CallCut::Cancelled => {
+                self.log_event(format!("CANCEL {}", name));
+                Err("请求已取消".into())
             }
         }
+    }
 
-        let lower = trimmed.to_lowercase();
-        if let Some(fb_result) = self.detect_feedback(&lower) {
-            self.unguard();
-            return Ok((fb_result, "feedback".into()));
+    fn trace_fallback(&mut self, stage: &str, accepted: bool, reason: &str) {
+        if let Some(t) = self.trace.as_mut() {
+            t.fallback(stage, accepted, reason);
         }
-        if lower == "反哺状态" || lower == "feedback status" {
-            let result = self.feedback_status();
-            self.unguard();
-            return Ok((result, "system".into()));
+    }
+
+    fn trace_absorb(&mut self, what: &str) {
+        if let Some(t) = self.trace.as_mut() {
+            t.absorbed.push(what.to_string());
         }
-        if lower == "反哺导出" || lower == "feedback export" {
-            let result = self.language.export_feedback_jsonl();
-            self.unguard();
-            if result.is_empty() {
-                return Ok(("无反馈数据".into(), "system".into()));
-            }
-            return Ok((result, "system".into()));
+    }
+
+    fn unguard_then<T>(&mut self, v: T) -> T {
+        self.unguard();
+        v
+    }
+
+    fn role_priority(role: PouchRole) -> u8 {
+        match role {
+            PouchRole::E0 => 0,
+            PouchRole::E1 => 1,
+            PouchRole::E2 => 2,
         }
-        if lower == "自我优化" || lower == "自我进化" || lower == "优化自己"
-            || lower == "self optimize" || lower == "self-optimize"
-        {
-            match self.self_optimize().await {
-                Ok(msg) => return Ok((msg, "system".into())),
-                Err(e) => return Err((e, "system".into())),
+    }
+
+    /*
+     * 指代/省略改写（见 dialogue_state），并把改写后的输入记入对话状态；命令既不改写也不记录。
+     * 改写结果记入事件和执行轨迹；改写过的一轮只用于匹配和路由，不参与学习（改写可能出错）。
+     */
+    fn track_dialogue(&mut self, input: &str) -> Option<String> {
+        if crate::frozen::commands::parse(input).is_some() {
+            return None;
+        }
+        let rewritten = match self.dialogue.resolve(input) {
+            Some(r) => r,
+            None => {
+                self.dialogue.observe(input);
+                return None;
             }
+        };
+        self.language.set_learning_paused(true);
+        self.dialogue.observe(&rewritten);
+        self.log_event(format!(
+            "DIALOGUE_REWRITE {} → {}",
+            input.chars().take(20).collect::<String>(),
+            rewritten.chars().take(20).collect::<String>()
+        ));
+        if let Some(t) = self.trace.as_mut() {
+            t.dialogue = Some(format!("{} → {}", trace::clip(input), trace::clip(&rewritten)));
         }
-        if lower.starts_with("对标") || lower.starts_with("进化能力") || lower.starts_with("升级能力")
-            || lower == "evolve"
-            || (lower.contains("对标") && (lower.contains("进化") || lower.contains("升级") || lower.contains("补齐")))
-        {
-            match self.evolve().await {
-                Ok(msg) => return Ok((msg, "system".into())),
-                Err(e) => return Err((e, "system".into())),
+        Some(rewritten)
+    }
+
+    fn finish_call(&mut self, name: &str, input: &str, outcome: Result<Result<String, String>, CallCut>) -> Result<String, String> {
+        match outcome {
+            Ok(r) => {
+                self.after_pouch_call(name, input, &r);
+                r
             }
+            Err(cut) => self.cut_call(name, input, cut),
         }
-        if lower == "自主学习" || lower == "autonomous learn" || lower == "auto learn" {
-            self.autonomous_learning_cycle().await;
-            let ls = &self.learning;
-            let summary = format!(
-                "周期:{} 阶段:{} 外部:{}/{} 突触:{}/{} 饱和:{:.0}%",
-                ls.cycle_count, ls.phase,
-                ls.external_absorbed, ls.external_fed,
-                ls.cross_absorbed, ls.cross_fed,
-                ls.saturation * 100.0
-            );
-            return Ok((summary, "system".into()));
+    }
+
+    /* 远程调用结果交回发起的尿袋；等待期间尿袋被卸载时原样返回 */
+    fn finish_remote_call(&mut self, name: &str, result: Result<PouchOutput, String>) -> Result<PouchOutput, String> {
+        match self.pouches.get_mut(name) {
+            Some(p) => p.finish_remote(result),
+            None => result,
         }
+    }
 
-        let installed = self.installed();
-        let mut decision = logic::route(input, &installed);
-
-        if matches!(decision, RouteDecision::Reject(_)) {
-            if let Some(intent) = self.language.identify_requirement(input) {
-                if intent.confidence > 0.6 {
-                    let cap = &intent.capability_needed;
-                    if !self.installed().contains(&cap.as_str()) {
-                        self.log_event(format!("AUTO_INSTALL {}", cap));
-                        let _ = self.install(cap);
-                    }
-                    if self.installed().contains(&cap.as_str()) {
-                        decision = RouteDecision::ToPouch(cap.clone());
-                    }
-                }
-            }
-        }
-        if matches!(decision, RouteDecision::Reject(_)) {
-            let lang_check = self.language.process(input).await;
-            if self.language.last_was_pattern_hit()
-                && self.language.last_match_weight() >= ABSORB_WEIGHT
-                && !self.language.is_fallback_response(&lang_check)
-            {
-                self.log_event("LANG_PRIORITY hit".into());
-                if let Some(out) = self.execute_chain_spec(input, &lang_check).await {
-                    self.unguard();
-                    return Ok((out, "chain".into()));
-                }
-                if let Some(expanded) = self.expand_template(input, &lang_check).await {
-                    self.unguard();
-                    return Ok((expanded, "template".into()));
-                }
-                self.unguard();
-                return Ok((lang_check, "language".into()));
+    fn proposal_result(output: Result<PouchOutput, String>) -> Result<String, String> {
+        match output {
+            Ok(output) => {
+                let conf_note = if output.confidence < 0.5 { LOW_CONFIDENCE_NOTE } else { "" };
+                Ok(format!("{}{}", output.data, conf_note))
             }
+            Err(e) => Err(format!("处理失败: {}", e)),
         }
+    }
 
-        let route_event = match &decision {
-            RouteDecision::ToPouch(n) => format!("ROUTE → {}", n),
-            RouteDecision::SystemCommand(_) => "ROUTE → system".into(),
-            RouteDecision::Reject(_) => "ROUTE → language".into(),
-        };
-        self.log_event(route_event);
+    fn validated_for(&self, name: &str, proposal: &ProposalMessage) -> Option<ValidatedProposal> {
+        self.pouches.get(name)?.validator().validate(proposal).ok()
+    }
 
-        let result = match decision {
-            RouteDecision::ToPouch(name) => {
-                let r = self.call_pouch(&name, input).await;
-                let ev = match &r {
-                    Ok(_) => format!("EXEC {} → ok", name),
-                    Err(e) => format!("EXEC {} → {}", name, e),
-                };
-                self.log_event(ev);
-                match r {
-                    Ok(r) => Ok((r, name)),
-                    Err(e) => Err((e, name)),
-                }
-            }
-            RouteDecision::SystemCommand(cmd) => {
-                let cmd_name = format!("{:?}", cmd).chars().take(40).collect::<String>();
-                let r = self.handle_cmd(cmd).await;
-                self.log_event(format!("CMD {} → {}", cmd_name, if r.is_ok() { "ok" } else { "err" }));
-                match r {
-                    Ok(r) => Ok((r, "system".into())),
-                    Err(e) => Err((e, "system".into())),
+    fn pouch_precheck(&mut self, name: &str, input: &str) -> Option<Result<String, String>> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        self.pouch_last_used.insert(name.to_string(), now);
+
+        if !self.is_pouch_awake(name) && name != "language" {
+            return Some(Err(format!("{}正在休眠", name)));
+        }
+
+        if name != "language" {
+            if let Some(cached) = self.check_promoted(input).cloned() {
+                self.log_event(format!("CACHE {} → promoted", name));
+                if let Some(t) = self.trace.as_mut() {
+                    t.cache_hits.push(format!("promoted {}", name));
                 }
+                return Some(Ok(cached));
             }
-            RouteDecision::Reject(_) => {
-                let kinds = logic::decompose_intent(input);
-                if !kinds.is_empty() {
-                    let entries = self.recent_evolution_entries(100);
-                    let (baseline, low_threshold, _) = self.clamped_routing();
-                    let scorer = move |name: &str| Self::score_pouch_from_entries(name, &entries, baseline);
-                    if let Some(plan) = self.registry.plan_for_kinds(&kinds, Some(&scorer), Some(low_threshold)) {
-                        let summary = crate::atom::CapabilityRegistry::plan_summary(&plan);
-                        self.log_event(format!("PLAN {}", summary));
-                        let r = self.execute_plan(&plan, input).await;
-                        return match r {
-                            Ok(data) => {
-                                if !self.language.is_fallback_response(&data) {
-                                    self.language.absorb(input, &data, 1.1);
-                                    self.log_event("ABSORB plan→language".into());
-                                }
-                                Ok((data, "plan".into()))
-                            }
-                            Err(e) => Err((e, "plan".into())),
-                        };
+        }
+        None
+    }
+
+    fn after_pouch_call(&mut self, name: &str, input: &str, result: &Result<String, String>) {
+        match result {
+            Ok(data) => {
+                if name != "language" && !self.language.learning_paused() {
+                    self.language.learn_routing(input, name);
+                    self.record_evolution(input, name, data);
+                    if let Some(kind) = self.pouch_kind(name) {
+                        let tokens = self.intent_tokens(input);
+                        self.intent.observe_text(input, &tokens, &[kind]);
                     }
-                }
-                match analyze_cloud(input).await {
-                    Ok(cloud_plan) if !cloud_plan.steps.is_empty() => {
-                        self.log_event(format!(
-                            "CLOUD_ANALYZE {}p {}s",
-                            cloud_plan.pouches.len(),
-                            cloud_plan.steps.len()
-                        ));
-                        for spec in &cloud_plan.pouches {
-                            if !self.installed().contains(&spec.name.as_str()) {
-                                let rp = crate::remote_pouch::RemotePouch::new(
-                                    &spec.name, PouchRole::E1, &spec.endpoint,
-                                );
-                                let caps = rp.atom_capabilities();
-                                self.pouches.insert(spec.name.clone(), Box::new(rp));
-                                self.meta.insert(
-                                    spec.name.clone(),
-                                    PouchMeta { role: PouchRole::E1 },
-                                );
-                                for cap in caps {
-                                    self.registry.register(cap);
-                                }
-                                self.log_event(format!("CLOUD_INSTALL {}", spec.name));
-                            }
-                        }
-                        let mut last_output = String::new();
-                        for step in &cloud_plan.steps {
-                            match self.call_pouch(&step.pouch, &step.input).await {
-                                Ok(data) => {
-                                    self.log_event(format!("CLOUD_STEP {} ok", step.pouch));
-                                    last_output = data;
-                                }
-                                Err(e) => {
-                                    self.log_event(format!("CLOUD_STEP {} {}", step.pouch, e));
-                                    return Err((e, "cloud_plan".into()));
-                                }
-                            }
-                        }
-                        if !self.language.is_fallback_response(&last_output) && !last_output.is_empty() {
-                            self.language.absorb(input, &last_output, 1.1);
-                            self.log_event("ABSORB cloud→language".into());
-                        }
-                        return Ok((last_output, "cloud_plan".into()));
+                    let tokens = self.language.tokenize(input);
+                    let patterns = vec![(tokens, data.clone(), 1.0)];
+                    for (_, pouch) in self.pouches.iter_mut() {
+                        pouch.sync_patterns(&patterns);
                     }
-                    _ => {}
-                }
-                let lang_final = self.language.process(input).await;
-                self.log_event("LANG process".into());
-                if let Some(out) = self.execute_chain_spec(input, &lang_final).await {
-                    return self.unguard_then(Ok((out, "chain".into())));
-                }
-                if let Some(expanded) = self.expand_template(input, &lang_final).await {
-                    return self.unguard_then(Ok((expanded, "template".into())));
-                }
-                if !self.language.is_fallback_response(&lang_final) {
-                    return self.unguard_then(Ok((lang_final, "language".into())));
+                    self.language.receive_sync_patterns(&patterns);
                 }
-                if let Some((out, pouch)) = self.try_fallback_chain(input).await {
-                    if !self.language.is_fallback_response(&out) {
-                        self.language.absorb(input, &out, 1.0);
-                        self.log_event(format!("ABSORB {}→language", pouch));
-                    }
-                    return self.unguard_then(Ok((out, pouch)));
+            }
+            Err(e) => {
+                let now = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs();
+                self.evolution_chain.push(EvolutionEntry {
+                    timestamp: now,
+                    input_trunc: input.chars().take(80).collect(),
+                    pouch_name: name.to_string(),
+                    output_trunc: e.chars().take(80).collect(),
+                    success: false,
+                    step_index: 0,
+                });
+                if self.evolution_chain.len() > EVOLUTION_CHAIN_MAX {
+                    self.evolution_chain.remove(0);
                 }
-                return self.unguard_then(Ok((lang_final, "language".into())));
             }
-        };
-
-        self.unguard();
-        result
-    }
-
-    fn unguard_then<T>(&mut self, v: T) -> T {
-        self.unguard();
-        v
+        }
     }
 
-    fn role_priority(role: PouchRole) -> u8 {
-        match role {
-            PouchRole::E0 => 0,
-            PouchRole::E1 => 1,
-            PouchRole::E2 => 2,
+    fn step_input(
+        input_from: &crate::atom::StepInput,
+        user_input: &str,
+        outputs: &[Option<String>],
+    ) -> String {
+        let out = |i: &usize| outputs.get(*i).cloned().flatten().unwrap_or_default();
+        match input_from {
+            crate::atom::StepInput::UserInput => user_input.to_string(),
+            crate::atom::StepInput::PreviousStep(idx) => out(idx),
+            crate::atom::StepInput::Merge(idxs) => idxs
+                .iter()
+                .map(out)
+                .collect::<Vec<_>>()
+                .join(crate::atom::MERGE_SEPARATOR),
+            crate::atom::StepInput::Fragment(f) => f.clone(),
         }
     }
 
-    async fn sanitize_input(&mut self, input: &str) -> Option<(bool, String)> {
-        let name = self.pouches.keys().find(|n| n.contains("sanitize"))?.clone();
-        let pouch = self.pouches.get_mut(&name)?;
-        let proposal = create_proposal(input);
-        let validated = pouch.validator().validate(&proposal).ok()?;
-        let out = pouch.process_proposal(&validated).await.ok()?;
-        let rejected = pouch.is_fallback_output(&out.data);
-        Some((rejected, out.data))
+    fn route_targets(&self) -> Vec<logic::RouteTarget> {
+        self.installed()
+            .into_iter()
+            .map(|name| {
+                let mut target = logic::RouteTarget::bare(name);
+                if let Some(p) = self.pouches.get(name) {
+                    target.trigger = p.triggers();
+                }
+                target
+            })
+            .collect()
     }
 
-    async fn expand_template(&mut self, input: &str, response: &str) -> Option<String> {
-        if !response.contains("{{") || !response.contains("}}") {
-            return None;
-        }
-        let mut out = response.to_string();
-        for _ in 0..5 {
-            let start = match out.find("{{") {
-                Some(s) => s,
-                None => break,
-            };
-            let end = match out[start..].find("}}") {
-                Some(e) => start + e + 2,
-                None => break,
-            };
-            let inner = out[start..end].strip_prefix("{{").and_then(|s| s.strip_suffix("}}")).unwrap_or("").trim();
-            if let Some((pouch, query)) = inner.split_once(':') {
-                let pouch = pouch.trim();
-                let query = query.trim();
-                let q = if query == "input" { input } else { query };
-                if self.pouches.contains_key(pouch) {
-                    if let Ok(rep) = self.call_pouch(pouch, q).await {
-                        out.replace_range(start..end, &rep);
-                        continue;
-                    }
-                }
+    /*
+     * 多个尿袋同等优先级命中时按演化链路由分选择；同分取 route 给出的顺序（命中短语更长者在前）。
+     * 其余路由结果原样转成 Routed，之后的分支不再需要处理歧义。
+     */
+    fn resolve_ambiguous(&mut self, decision: RouteDecision) -> Routed {
+        let names = match decision {
+            RouteDecision::ToPouch(name) => return Routed::ToPouch(name),
+            RouteDecision::SystemCommand(cmd) => return Routed::SystemCommand(cmd),
+            RouteDecision::Usage(usage) => return Routed::Usage(usage),
+            RouteDecision::Reject(_) => return Routed::Reject,
+            RouteDecision::Ambiguous(names) => names,
+        };
+        let entries = self.recent_evolution_entries(100);
+        let (baseline, _, _) = self.clamped_routing();
+        let mut best: Option<(&String, f64)> = None;
+        for name in &names {
+            let score = Self::score_pouch_from_entries(name, &entries, baseline);
+            if best.is_none_or(|(_, s)| score > s) {
+                best = Some((name, score));
             }
-            out.replace_range(start..end, "");
         }
-        if out == response {
-            return None;
+        let chosen = best.map(|(n, _)| n.clone()).unwrap_or_default();
+        self.log_event(format!("ROUTE_AMBIGUOUS {} → {}", names.join(","), chosen));
+        Routed::ToPouch(chosen)
+    }
+
+    /*
+     * Reject 路径的意图分解：本地意图模型（关键词规则为先验）给出 AtomKind 序列，
+     * 置信度低于 intent.min_confidence 时返回空，交由云端/language 处理。
+     */
+    fn predict_intent(&mut self, input: &str) -> Vec<crate::atom::AtomKind> {
+        let rule = logic::decompose_intent(input);
+        let tokens = self.intent_tokens(input);
+        let Some(guess) = self.intent.predict(&tokens, &rule) else {
+            return Vec::new();
+        };
+        let summary = guess.summary();
+        if let Some(t) = self.trace.as_mut() {
+            t.intent = Some(summary.clone());
         }
-        Some(out)
+        if guess.confidence < self.config.intent.min_confidence {
+            self.log_event(format!("INTENT_LOW {}", summary));
+            return Vec::new();
+        }
+        self.log_event(format!("INTENT {}", summary));
+        guess.kinds
     }
 
-    async fn execute_chain_spec(&mut self, input: &str, response: &str) -> Option<String> {
-        let spec = response.strip_prefix("chain:")?.trim();
-        if spec.is_empty() {
+    /*
+     * 尿袋的主原子类别（注册表中首个非合并原子），用于把单步成功记录转成意图样本。
+     */
+    fn pouch_kind(&self, name: &str) -> Option<crate::atom::AtomKind> {
+        if name == "language" {
             return None;
         }
-        let pouches: Vec<&str> = spec.split("->").map(str::trim).filter(|s| !s.is_empty()).collect();
-        let max_depth = self.config.chain.chain_spec_max_depth.clamp(2, 12);
-        if pouches.is_empty() || pouches.len() > max_depth {
-            return None;
+        self.registry
+            .all()
+            .iter()
+            .find(|a| a.pouch == name && a.name != crate::atom::MERGE_ATOM)
+            .map(|a| a.kind)
+    }
+
+    /*
+     * 意图模型的 token 固定用 base 命名空间的分词器切分，与当前换入哪个命名空间无关。
+     */
+    fn intent_tokens(&self, input: &str) -> Vec<String> {
+        self.namespaces.base().unwrap_or(&self.language).tokenize(input)
+    }
+
+    /*
+     * 没有可用样本时引导意图模型：成功的演化记录与 base 的已学路由（路由 token 已按 base
+     * 分词器迁移，没有原文，不参与以后的重新训练）。只在 base 为当前命名空间时调用。
+     */
+    fn bootstrap_intent_model(&mut self) {
+        let mut texts: Vec<(String, crate::atom::AtomKind)> = Vec::new();
+        for e in self.evolution_chain.iter().filter(|e| e.success) {
+            if let Some(kind) = self.pouch_kind(&e.pouch_name) {
+                texts.push((e.input_trunc.clone(), kind));
+            }
         }
-        let cache_key = format!("{}|{}", hash_str(input), spec);
-        if let Some(cached) = self.chain_result_cache.iter().find(|(k, _)| k == &cache_key).map(|(_, v)| v.clone()) {
-            self.log_event("CACHE chain hit".into());
-            return Some(cached);
+        for (text, kind) in texts {
+            let tokens = self.language.tokenize(&text);
+            self.intent.observe_text(&text, &tokens, &[kind]);
         }
-        let mut carry = input.to_string();
-        for name in pouches {
-            if !self.pouches.contains_key(name) {
-                return None;
-            }
-            match self.call_pouch(name, &carry).await {
-                Ok(out) => carry = out,
-                Err(_) => return None,
+        let mut routes: Vec<(Vec<String>, crate::atom::AtomKind)> = Vec::new();
+        for (tokens, pouch) in self.language.route_patterns() {
+            if let Some(kind) = self.pouch_kind(pouch) {
+                routes.push((tokens.clone(), kind));
             }
         }
-        if self.chain_result_cache.len() >= 100 {
-            self.chain_result_cache.pop_front();
+        for (tokens, kind) in routes {
+            self.intent.observe(&tokens, &[kind]);
         }
-        self.chain_result_cache.push_back((cache_key, carry.clone()));
-        Some(carry)
     }
 
-    async fn try_fallback_chain(&mut self, input: &str) -> Option<(String, String)> {
-        let proposal = create_proposal(input);
-        let mut candidates: Vec<String> = self.pouches.keys().cloned().collect();
-        candidates.sort_by_key(|name| {
-            self.meta.get(name).map_or(1, |m| Self::role_priority(m.role))
-        });
-        for name in candidates {
-            let role = self.meta.get(&name).map_or(PouchRole::E1, |m| m.role);
-            if role == PouchRole::E2 && !self.is_pouch_awake(&name) {
-                continue;
-            }
-            if let Some(pouch) = self.pouches.get_mut(&name) {
-                if let Ok(validated) = pouch.validator().validate(&proposal) {
-                    if let Ok(output) = pouch.process_proposal(&validated).await {
-                        if !output.data.is_empty() && !pouch.is_fallback_output(&output.data) {
-                            let conf_note = if output.confidence < 0.5 { " [低置信度]" } else { "" };
-                            return Some((format!("{}{}", output.data, conf_note), name));
-                        }
-                    }
-                }
-            }
+    fn output_rejection(&self, pouch: &str, data: &str) -> Option<String> {
+        if data.trim().is_empty() {
+            return Some("空输出".into());
+        }
+        if data.ends_with(LOW_CONFIDENCE_NOTE) {
+            return Some("低置信度".into());
+        }
+        let pouch_fallback = self.pouches.get(pouch).is_some_and(|p| p.is_fallback_output(data));
+        if pouch_fallback || self.language.is_fallback_response(data) {
+            return Some("兜底输出".into());
         }
         None
     }
 
-    pub async fn call_pouch(&mut self, name: &str, input: &str) -> Result<String, String> {
-        self.guard(Layer::Pouch)?;
+    fn record_chain_attempt(&mut self, input: &str, pouch: &str, output: &str, success: bool, step_index: usize) {
         let now = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap_or_default()
             .as_secs();
-        self.pouch_last_used.insert(name.to_string(), now);
-
-        if !self.is_pouch_awake(name) && name != "language" {
-            self.unguard();
-            return Err(format!("{}正在休眠", name));
+        self.evolution_chain.push(EvolutionEntry {
+            timestamp: now,
+            input_trunc: input.chars().take(80).collect(),
+            pouch_name: pouch.to_string(),
+            output_trunc: output.chars().take(80).collect(),
+            success,
+            step_index,
+        });
+        if self.evolution_chain.len() > EVOLUTION_CHAIN_MAX {
+            self.evolution_chain.remove(0);
         }
+    }
 
-        if name != "language" {
-            if let Some(cached) = self.check_promoted(input).cloned() {
-                self.log_event(format!("CACHE {} → promoted", name));
-                self.unguard();
-                return Ok(cached);
-            }
-        }
-
-        let result = if name == "language" {
-            Ok(self.language.process(input).await)
-        } else if let Some(pouch) = self.pouches.get_mut(name) {
-            let proposal = create_proposal(input);
-            match pouch.validator().validate(&proposal) {
-                Ok(validated) => match pouch.process_proposal(&validated).await {
-                    Ok(output) => {
-                        let conf_note = if output.confidence < 0.5 { " [低置信度]" } else { "" };
-                        Ok(format!("{}{}", output.data, conf_note))
-                    }
-                    Err(e) => Err(format!("处理失败: {}", e)),
-                },
-                Err(e) => Err(format!("验证失败: {}", e)),
-            }
-        } else {
-            Err(format!("尿袋{}未安装", name))
-        };
-
-        if let Ok(ref data) = result {
-            if name != "language" {
-                self.language.learn_routing(input, name);
-                self.record_evolution(input, name, data);
-                let tokens = self.language.tokenize(input);
-                let patterns = vec![(tokens, data.clone(), 1.0)];
-                for (_, pouch) in self.pouches.iter_mut() {
-                    pouch.sync_patterns(&patterns);
-                }
-                self.language.receive_sync_patterns(&patterns);
-            }
-        }
-        if let Err(ref e) = result {
-            self.evolution_chain.push(EvolutionEntry {
-                timestamp: now,
-                input_trunc: input.chars().take(80).collect(),
-                pouch_name: name.to_string(),
-                output_trunc: e.chars().take(80).collect(),
-                success: false,
-                step_index: 0,
-            });
-            if self.evolution_chain.len() > EVOLUTION_CHAIN_MAX {
-                self.evolution_chain.remove(0);
-            }
-        }
-
-        self.unguard();
-        result
-    }
-
-    async fn execute_plan(
-        &mut self,
-        plan: &crate::atom::ExecutionPlan,
-        user_input: &str,
-    ) -> Result<String, String> {
-        let mut outputs: Vec<String> = Vec::new();
-        let mut chain_updated = false;
-        for (step_index, step) in plan.steps.iter().enumerate() {
-            let input_data = match &step.input_from {
-                crate::atom::StepInput::UserInput => user_input.to_string(),
-                crate::atom::StepInput::PreviousStep(idx) => {
-                    outputs.get(*idx).cloned().unwrap_or_default()
-                }
+    fn trace_step(&mut self, step_index: usize, attempt: usize, cand: &StepCandidate, input: &str, result: &Result<String, String>, latency_ms: u64) {
+        if let Some(p) = self.trace.as_mut().and_then(|t| t.plan.as_mut()) {
+            let (ok, output) = match result {
+                Ok(d) => (true, trace::clip(d)),
+                Err(e) => (false, trace::clip(e)),
             };
-            let result = self.call_pouch(&step.pouch, &input_data).await;
-            match result {
-                Ok(data) => {
-                    self.log_event(format!("PLAN_STEP {} → ok", step.atom_name));
-                    let in_trunc: String = input_data.chars().take(80).collect();
-                    let out_trunc: String = data.chars().take(80).collect();
-                    self.log_event(format!("PouchSuccess: pouch={}, input={}, output={}", step.pouch, in_trunc, out_trunc));
-                    /*
-                     * 两条演化系统区别：
-                     *   - evolution.json (record_evolution)：聚合计数，用于 promotion 阈值判断
-                     *   - evolution_chain.json (EvolutionEntry)：时间顺序审计轨迹，用于路由偏好和未来分析
-                     *   - plan 成功步会同时更新两者，但语义独立
-                     */
-                    let now = std::time::SystemTime::now()
-                        .duration_since(std::time::UNIX_EPOCH)
-                        .unwrap_or_default()
-                        .as_secs();
-                    self.evolution_chain.push(EvolutionEntry {
-                        timestamp: now,
-                        input_trunc: in_trunc.clone(),
-                        pouch_name: step.pouch.clone(),
-                        output_trunc: out_trunc.clone(),
-                        success: true,
-                        step_index,
-                    });
-                    chain_updated = true;
-                    if self.evolution_chain.len() > EVOLUTION_CHAIN_MAX {
-                        self.evolution_chain.remove(0);
-                    }
-                    outputs.push(data);
-                }
-                Err(e) => {
-                    self.log_event(format!("PLAN_STEP {} → {}", step.atom_name, e));
-                    return Err(format!("执行计划步骤{}失败: {}", step.atom_name, e));
-                }
-            }
-        }
-        if chain_updated {
-            self.save_state();
-        }
-        Ok(outputs.last().cloned().unwrap_or_default())
-    }
-
-    async fn handle_cmd(&mut self, cmd: SystemCmd) -> Result<String, String> {
-        match cmd {
-            SystemCmd::InstallPouch(name) => self.install(&name),
-            SystemCmd::UninstallPouch(name) => self.uninstall(&name),
-            SystemCmd::SleepPouch(name) => self.sleep_pouch(&name),
-            SystemCmd::WakePouch(name) => self.wake_pouch(&name),
-            SystemCmd::ConfigShow => self.config_show(),
-            SystemCmd::ConfigSet(key, value) => self.config_set(&key, &value),
-            SystemCmd::ListPouches => Ok(self.list()),
-            SystemCmd::Status => Ok(self.status()),
-            SystemCmd::Help => Ok(Self::help()),
-            SystemCmd::Teach(trigger, response) => {
-                self.language.teach(&trigger, &response);
-                self.save_state();
-                Ok(format!("学会了:「{}」→「{}」", trigger, response))
-            }
-            SystemCmd::RunPipeline(stages, data) => {
-                let stages_ref: Vec<&str> = stages.iter().map(|s| s.as_str()).collect();
-                self.run_pipeline(&stages_ref, data).await
-            }
-            SystemCmd::SelfTest => self.selftest().await,
-            SystemCmd::ImportPatterns(path) => self.import_patterns_from_file(&path).await,
-            SystemCmd::ExportPatterns => Ok(self.export_patterns_display()),
-            SystemCmd::Rollback => self.rollback(),
-            SystemCmd::Train => self.trigger_train().await,
-            SystemCmd::Explain(name) => Ok(self.explain_pouch(&name)),
-            SystemCmd::EvolutionStatus => Ok(self.evolution_status()),
-            SystemCmd::Capabilities => Ok(self.registry.summary()),
-            SystemCmd::ClearContext => {
-                self.language.clear_context();
-                Ok("对话上下文已清空".into())
-            }
+            p.steps.push(StepTrace {
+                index: step_index,
+                atom: cand.atom_name.clone(),
+                pouch: cand.pouch.clone(),
+                input: trace::clip(input),
+                output,
+                ok,
+                latency_ms,
+                attempt,
+            });
         }
     }
 
@@ -912,63 +1208,15 @@ impl Orchestrator {
         !self.pouch_sleep_state.get(name).copied().unwrap_or(false)
     }
 
-    async fn run_pipeline(
-        &mut self,
-        stages: &[&str],
-        initial_data: String,
-    ) -> Result<String, String> {
-        if stages.is_empty() {
-            return Err("Pipeline为空".into());
-        }
-        if stages.len() > bedrock::MAX_PIPELINE_STAGES {
-            return Err("Pipeline阶段过多".into());
-        }
-        let mut current_data = initial_data;
-        let mut trace = Vec::new();
-
-        for (i, &stage) in stages.iter().enumerate() {
-            let stage_lower = stage.to_lowercase();
-            if stage_lower == "language" {
-                if i > 0 {
-                    return Err("language不能在Pipeline中间".into());
-                }
-            } else if let Some(pouch) = self.pouches.get_mut(&stage_lower) {
-                if i > 0 && !pouch.can_call_others() {
-                    return Err(format!("{}不能接收Pipeline数据", stage));
-                }
-                let proposal = create_proposal(&current_data);
-                let validated = pouch
-                    .validator()
-                    .validate(&proposal)
-                    .map_err(|e| format!("阶段{}验证失败: {}", stage, e))?;
-                let output = pouch
-                    .process_proposal(&validated)
-                    .await
-                    .map_err(|e| format!("阶段{}处理失败: {}", stage, e))?;
-                current_data = output.data;
-            } else {
-                return Err(format!("尿袋{}未安装", stage));
-            }
-            trace.push(format!("{}→", stage));
-        }
-
-        trace.push("完成".into());
-        let pipeline_desc = trace.join("");
-        self.log_event(format!("PIPE {}", pipeline_desc));
-        Ok(format!("Pipeline: {}\n结果: {}", pipeline_desc, current_data))
-    }
-
-    fn save_state(&self) {
+    fn save_state(&mut self) {
         let _ = std::fs::create_dir_all(&self.data_dir);
         let names: Vec<&String> = self.pouches.keys().collect();
         if let Ok(json) = serde_json::to_string(&names) {
             let _ = std::fs::write(format!("{}/pouches.json", self.data_dir), json);
         }
-        if let Ok(data) = self.language.save() {
-            let _ = std::fs::write(format!("{}/language.bin", self.data_dir), data);
-        }
-        if let Ok(data) = self.language.save_routes() {
-            let _ = std::fs::write(format!("{}/routes.bin", self.data_dir), data);
+        let _ = namespace::save_pouch(&namespace::dir(&self.data_dir, self.namespaces.active()), &self.language);
+        for (name, language) in self.namespaces.take_dirty() {
+            let _ = namespace::save_pouch(&namespace::dir(&self.data_dir, name), language);
         }
         if let Ok(json) = serde_json::to_string(&self.evolution) {
             let _ = std::fs::write(format!("{}/evolution.json", self.data_dir), json);
@@ -979,12 +1227,74 @@ impl Orchestrator {
         if let Ok(data) = logic::save_promoted_rules() {
             let _ = std::fs::write(format!("{}/promoted_rules.json", self.data_dir), data);
         }
-        if let Ok(data) = self.language.save_feedback() {
-            let _ = std::fs::write(format!("{}/feedback.json", self.data_dir), data);
-        }
         if let Ok(json) = serde_json::to_string(&self.learning) {
             let _ = std::fs::write(format!("{}/learning_state.json", self.data_dir), json);
         }
+        if let Ok(json) = serde_json::to_string(&self.intent) {
+            let _ = std::fs::write(format!("{}/intent_model.json", self.data_dir), json);
+        }
+    }
+
+    /*
+     * 各命名空间的已存模式按其目录下 tokenizer.json 记录的分词器解释（base 缺失时为原字+二元组，
+     * 其他命名空间缺失时沿用 base 的记录）；配置给该命名空间的分词器或词典与之不同时，
+     * 把它的模式重新分词并立即落盘。意图模型不是用当前 base 分词器训练的就按其原文样本重新训练。
+     */
+    fn init_tokenizer(&mut self, language_restored: bool) {
+        let base_stored = if language_restored {
+            namespace::stored_tokenizer(&self.data_dir).unwrap_or_default()
+        } else {
+            TokenizerState::of(&tokenizer::CharBigramTokenizer)
+        };
+        self.language.set_tokenizer(tokenizer::build(base_stored.kind, &self.data_dir));
+        let mut migrated_any = false;
+        for name in self.namespaces.names() {
+            let stored = match name.as_str() {
+                BASE_NAMESPACE => base_stored,
+                _ => self.stored_tokenizer(&name),
+            };
+            let kind = self.config.language.tokenizer_for(&name);
+            let want = tokenizer::build(kind, &self.data_dir);
+            if TokenizerState::of(want.as_ref()) == stored {
+                if name == BASE_NAMESPACE {
+                    self.language.set_tokenizer(want);
+                }
+                continue;
+            }
+            let language = match name.as_str() {
+                BASE_NAMESPACE => &mut self.language,
+                _ => match self.namespaces.parked_mut(&name) {
+                    Some(language) => language,
+                    None => continue,
+                },
+            };
+            let migrated = language.retokenize(want);
+            self.log_event(format!("TOKENIZER_MIGRATE {} {:?}->{:?} {}", name, stored.kind, kind, migrated));
+            migrated_any = true;
+        }
+        let state = self.language.tokenizer_state();
+        if !self.intent.matches_tokenizer(&state) {
+            let stale = self.intent.class_count() > 0;
+            let language = &self.language;
+            let retrained = self.intent.retokenize(state, |text| language.tokenize(text));
+            if retrained == 0 {
+                self.bootstrap_intent_model();
+            }
+            if stale {
+                self.log_event(format!("INTENT_RETOKENIZE {}", retrained));
+                migrated_any = true;
+            }
+        }
+        if migrated_any {
+            self.save_state();
+        }
+    }
+
+    /* 非 base 命名空间已存 token 所用的分词器；没有记录时沿用 base 的 */
+    fn stored_tokenizer(&self, name: &str) -> TokenizerState {
+        namespace::stored_tokenizer(&namespace::dir(&self.data_dir, name))
+            .or_else(|| namespace::stored_tokenizer(&self.data_dir))
+            .unwrap_or_default()
     }
 
     fn maybe_adjust_baseline(&mut self) {
@@ -1039,12 +1349,34 @@ impl Orchestrator {
         }
     }
 
+    /*
+     * 旧格式 language.bin（无来源信息等）加载成功后立即按新格式重写，原文件留作 .premigrate。
+     */
+    fn migrate_language_file(&mut self, path: &str, old: &[u8]) {
+        let data = match self.language.save() {
+            Ok(d) => d,
+            Err(e) => {
+                self.log_event(format!("LANGUAGE_MIGRATE 失败 {}", e));
+                return;
+            }
+        };
+        let backup = format!("{}.premigrate", path);
+        if std::fs::write(&backup, old).is_err() || std::fs::write(path, data).is_err() {
+            self.log_event("LANGUAGE_MIGRATE 写入失败".into());
+            return;
+        }
+        self.log_event(format!("LANGUAGE_MIGRATE {}", self.language.memory_count()));
+    }
+
     fn load_state(&mut self) {
         let chain_path = format!("{}/evolution_chain.json", self.data_dir);
         let loaded_chain: Vec<EvolutionEntry> = std::fs::read_to_string(&chain_path)
             .ok()
             .and_then(|json| serde_json::from_str(&json).ok())
             .unwrap_or_default();
+        let loaded_intent: Option<IntentModel> = std::fs::read_to_string(format!("{}/intent_model.json", self.data_dir))
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok());
         if let Ok(json) = std::fs::read_to_string(format!("{}/pouches.json", self.data_dir)) {
             if let Ok(names) = serde_json::from_str::<Vec<String>>(&json) {
                 for name in names {
@@ -1052,14 +1384,25 @@ impl Orchestrator {
                 }
             }
         }
-        if let Ok(data) = std::fs::read(format!("{}/language.bin", self.data_dir)) {
+        let language_path = format!("{}/language.bin", self.data_dir);
+        if let Ok(data) = std::fs::read(&language_path) {
             if self.language.load(&data).is_ok() {
                 log::info!("语言尿袋已恢复");
+                if !LanguagePouch::is_current_format(&data) {
+                    self.migrate_language_file(&language_path, &data);
+                }
             }
         }
         if let Ok(data) = std::fs::read(format!("{}/routes.bin", self.data_dir)) {
             self.language.load_routes(&data).ok();
         }
+        for name in namespace::stored_names(&self.data_dir) {
+            let stored = tokenizer::build(self.stored_tokenizer(&name).kind, &self.data_dir);
+            match namespace::load_pouch(&namespace::dir(&self.data_dir, &name), stored) {
+                Ok(language) => self.namespaces.park(&name, language),
+                Err(e) => log::warn!("命名空间 {} 加载失败: {}", name, e),
+            }
+        }
         if let Ok(json) = std::fs::read_to_string(format!("{}/evolution.json", self.data_dir)) {
             if let Ok(records) = serde_json::from_str::<Vec<EvolutionRecord>>(&json) {
                 self.evolution = records;
@@ -1072,11 +1415,28 @@ impl Orchestrator {
         if let Ok(data) = std::fs::read(format!("{}/feedback.json", self.data_dir)) {
             self.language.load_feedback(&data).ok();
         }
+        if let Ok(data) = std::fs::read(format!("{}/guard.json", self.data_dir)) {
+            self.language.load_guard(&data).ok();
+        }
+        if let Some(model) = loaded_intent {
+            self.intent = model;
+        }
         if let Ok(json) = std::fs::read_to_string(format!("{}/learning_state.json", self.data_dir)) {
             if let Ok(ls) = serde_json::from_str::<LearningState>(&json) {
                 self.learning = ls;
             }
         }
+        if self.config.session.persist {
+            if let Ok(data) = std::fs::read(format!("{}/sessions.json", self.data_dir)) {
+                if self.sessions.load(&data).is_ok() {
+                    let now = std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .unwrap_or_default()
+                        .as_secs();
+                    self.sessions.expire_idle(now, self.config.session.idle_ttl_secs);
+                }
+            }
+        }
     }
 
     pub fn total_memory_count(&self) -> usize {
@@ -1134,7 +1494,21 @@ impl Orchestrator {
         out
     }
 
+    pub fn set_event_tap(&mut self, tap: Option<tokio::sync::mpsc::UnboundedSender<ProgressEvent>>) {
+        self.event_tap = tap;
+    }
+
+    fn emit_progress(&self, kind: &str, detail: String) {
+        if let Some(tap) = &self.event_tap {
+            let _ = tap.send(ProgressEvent { kind: kind.to_string(), detail });
+        }
+    }
+
     fn log_event(&mut self, msg: String) {
+        if self.event_tap.is_some() {
+            let (kind, detail) = msg.split_once(' ').unwrap_or((msg.as_str(), ""));
+            self.emit_progress(kind, detail.trim().to_string());
+        }
         if self.events.len() >= 50 {
             self.events.remove(0);
         }
@@ -1159,10 +1533,12 @@ impl Orchestrator {
     }
 
     fn score_pouch_from_entries(pouch_name: &str, entries: &[EvolutionEntry], baseline: f64) -> f64 {
-        let matching: Vec<_> = entries.iter().filter(|e| e.pouch_name == pouch_name).collect();
+        let matching: Vec<_> = entries.iter().filter(|e| e.pouch_name == pouch_name && e.success).collect();
         let matching_count = matching.len();
         let total_output_len: usize = matching.iter().map(|e| e.output_trunc.len()).sum();
-        manager_math::score_from_evolution_stats(matching_count, total_output_len, baseline)
+        let failures = entries.iter().filter(|e| e.pouch_name == pouch_name && !e.success).count();
+        let score = manager_math::score_from_evolution_stats(matching_count, total_output_len, baseline);
+        manager_math::penalize_failures(score, matching_count, failures)
     }
 
     /*
@@ -1300,7 +1676,7 @@ impl Orchestrator {
         let mut records: Vec<_> = self.evolution.iter()
             .map(|r| (r.pouch_name.clone(), r.verify_count, r.promoted, r.last_seen))
             .collect();
-        records.sort_by(|a, b| b.1.cmp(&a.1));
+        records.sort_by_key(|r| std::cmp::Reverse(r.1));
         records
     }
 
@@ -1389,38 +1765,24 @@ impl Orchestrator {
         self.language.pending_misses(limit)
     }
 
-    pub async fn language_debug(&mut self, input: &str) -> (String, bool, f64) {
-        let result = self.language.process(input).await;
+    /* 当前命名空间的语言尿袋处理 input，未命中时只读地查 base */
+    async fn process_language(&mut self, input: &str) -> String {
+        let lookup = self.prepared_for(input).and_then(|p| p.lookup.take());
+        self.language.process_with_lookup(input, self.namespaces.base(), lookup).await
+    }
+
+    pub async fn language_debug(&mut self, input: &str) -> (String, bool, f64, Option<u32>) {
+        let result = self.process_language(input).await;
         let is_fallback = self.language.is_fallback_response(&result);
         let weight = self.language.last_match_weight();
-        (result, is_fallback, weight)
+        (result, is_fallback, weight, self.language.last_pattern_id())
     }
 
-    pub async fn import_patterns_from_file(&mut self, path: &str) -> Result<String, String> {
-        if path.is_empty() {
-            return Err("路径为空".into());
-        }
-        if let Ok(data) = self.language.save() {
-            let backup_path = format!("{}/language.bin.backup", self.data_dir);
-            std::fs::write(&backup_path, data).map_err(|e| format!("备份失败: {}", e))?;
-        }
-        let content = if path.starts_with("http://") || path.starts_with("https://") {
-            let client = reqwest::Client::builder()
-                .timeout(std::time::Duration::from_secs(30))
-                .build()
-                .map_err(|e| format!("http client: {}", e))?;
-            let resp = client.get(path).send().await.map_err(|e| format!("fetch: {}", e))?;
-            if !resp.status().is_success() {
-                return Err(format!("http {} {}", resp.status(), path));
-            }
-            resp.text().await.map_err(|e| format!("body: {}", e))?
-        } else {
-            std::fs::read_to_string(path).map_err(|e| format!("读取失败: {}", e))?
-        };
-        let is_jsonl = path.ends_with(".jsonl") || path.contains(".jsonl");
-        let count = self.language.import_from_content(&content, is_jsonl)?;
-        self.save_state();
-        Ok(format!("导入 {} 条模式", count))
+    /*
+     * 只读地推演语言尿袋对 input 的匹配过程（候选得分明细、过滤原因、兜底阶段）。
+     */
+    pub fn explain_match(&self, input: &str, top_k: usize) -> MatchExplanation {
+        self.language.explain(input, top_k, self.namespaces.base())
     }
 
     pub fn seed_route(&mut self, input: &str, pouch_name: &str) {
@@ -1479,133 +1841,251 @@ impl Orchestrator {
         }
     }
 
-    async fn discover_gaps_from_misses(&mut self) {
-        if self.pouches.len() >= crate::frozen::bedrock::MAX_POUCHES { return; }
-        let clusters = self.language.miss_token_clusters(3);
-        let maturities: Vec<(String, f64)> = self.pouches.keys()
-            .map(|n| (n.clone(), self.pouch_maturity(n)))
-            .collect();
-        let teachers: Vec<String> = {
-            let mut t: Vec<_> = maturities.iter()
-                .filter(|(n, m)| *m >= MATURITY_HUNGRY && n != "language")
-                .cloned()
-                .collect();
-            t.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
-            t.into_iter().map(|(n, _)| n).take(5).collect()
-        };
+    pub fn batch_teach_content(&mut self, content: &str) -> usize {
+        let before = self.language.memory_count();
+        let taught = self.language.batch_teach_from_content(content, "batch_teach");
+        let after = self.language.memory_count();
+        if taught > 0 {
+            self.save_state();
+        }
+        log::info!("batch_teach: taught={} before={} after={}", taught, before, after);
+        taught
+    }
 
-        for (token, count, sample_inputs) in clusters.iter().take(2) {
-            let target_domain: Option<String> =
-                if let Some(domain) = Self::classify_domain(&sample_inputs.join(" ")) {
-                    if !self.pouches.contains_key(domain) {
-                        Some(domain.to_string())
-                    } else { None }
-                } else if token.chars().count() >= 2 && *count >= 4 {
-                    if !self.pouches.contains_key(token.as_str()) {
-                        Some(token.clone())
-                    } else { None }
-                } else { None };
+    pub async fn eval_language(&mut self, path: &str, opts: &EvalOptions) -> Result<String, String> {
+        if path.is_empty() {
+            return Err("路径为空".into());
+        }
+        self.language.eval_from_path(path, &self.data_dir, opts).await
+    }
 
-            let Some(ref domain) = target_domain else { continue };
-            if !self.auto_ensure_pouch(domain) { continue; }
+    fn list_namespaces(&self) -> String {
+        let current = self.session_namespace.as_deref().unwrap_or(BASE_NAMESPACE);
+        let mut out = String::from("命名空间:\n");
+        for name in self.namespaces.names() {
+            let count = match self.namespaces.parked(&name) {
+                Some(language) => language.memory_count(),
+                None => self.language.memory_count(),
+            };
+            let mark = if name == current { " *" } else { "" };
+            out.push_str(&format!("  {} ({} 条模式){}\n", name, count, mark));
+        }
+        out.push_str("* 为当前会话所用；未命中时回退到 base");
+        out
+    }
 
-            for input in sample_inputs {
-                self.language.learn_routing(input, domain);
+    /*
+     * 新建命名空间（from 为 None 时只有种子模式），或复制 from 的模式、路由和反馈状态。
+     */
+    fn create_namespace(&mut self, name: &str, from: Option<&str>) -> Result<String, String> {
+        namespace::validate_name(name)?;
+        if self.namespaces.contains(name) {
+            return Err(format!("命名空间已存在: {}", name));
+        }
+        let want = tokenizer::build(self.config.language.tokenizer_for(name), &self.data_dir);
+        let language = match from {
+            Some(src) => {
+                let source = match self.namespaces.parked(src) {
+                    Some(language) => language,
+                    None if self.namespaces.active() == src => &self.language,
+                    None => return Err(format!("命名空间不存在: {}", src)),
+                };
+                let mut copy =
+                    namespace::duplicate(source, tokenizer::build(source.tokenizer_state().kind, &self.data_dir))?;
+                if copy.tokenizer_state() == TokenizerState::of(want.as_ref()) {
+                    copy.set_tokenizer(want);
+                } else {
+                    copy.retokenize(want);
+                }
+                copy
+            }
+            None => LanguagePouch::with_tokenizer(want),
+        };
+        namespace::save_pouch(&namespace::dir(&self.data_dir, name), &language)?;
+        let count = language.memory_count();
+        self.namespaces.park(name, language);
+        match from {
+            Some(src) => {
+                self.log_event(format!("NAMESPACE_COPY {}→{} {}", src, name, count));
+                Ok(format!("已复制命名空间 {} → {}（{} 条模式）", src, name, count))
+            }
+            None => {
+                self.log_event(format!("NAMESPACE_CREATE {}", name));
+                Ok(format!("已新建命名空间 {}", name))
             }
+        }
+    }
 
-            let mut train_pairs: Vec<(Vec<String>, String, f64)> = Vec::new();
-            let mut trained = 0u32;
-            self.call_stack.clear();
-            let _ = self.guard(Layer::Orchestrator);
+    fn drop_namespace(&mut self, name: &str) -> Result<String, String> {
+        if name == BASE_NAMESPACE {
+            return Err("base 命名空间不能删除".into());
+        }
+        if self.namespaces.active() == name {
+            return Err(format!("命名空间 {} 正在使用，请先切换到其他命名空间", name));
+        }
+        self.namespaces.remove(name).ok_or_else(|| format!("命名空间不存在: {}", name))?;
+        let _ = std::fs::remove_dir_all(namespace::dir(&self.data_dir, name));
+        self.sessions.clear_namespace(name);
+        if self.session_namespace.as_deref() == Some(name) {
+            self.session_namespace = None;
+        }
+        self.log_event(format!("NAMESPACE_DROP {}", name));
+        Ok(format!("已删除命名空间 {}", name))
+    }
 
-            for input in sample_inputs {
-                let tokens = self.language.tokenize(input);
-                let mut responses: Vec<String> = Vec::new();
-                for teacher_name in &teachers {
-                    let result = self.call_pouch(teacher_name, input).await;
-                    if let Ok(ref output) = result {
-                        if output.len() > 15 && !self.language.is_fallback_response(output) {
-                            responses.push(output.clone());
-                        }
-                    }
-                    if responses.len() >= 3 { break; }
-                }
-                if responses.is_empty() {
-                    let lang_result = self.call_pouch("language", input).await;
-                    if let Ok(ref lang_out) = lang_result {
-                        if lang_out.len() > 20 && !self.language.is_fallback_response(lang_out) {
-                            train_pairs.push((tokens.clone(), lang_out.clone(), 0.6));
-                            trained += 1;
-                        }
-                    }
-                } else {
-                    let best = responses.iter()
-                        .max_by_key(|r| r.len())
-                        .cloned()
-                        .unwrap_or_default();
-                    let weight = if responses.len() >= 2 { 1.2 } else { 0.6 };
-                    train_pairs.push((tokens.clone(), best.clone(), weight));
-                    self.language.absorb(input, &best, weight * 0.8);
-                    trained += 1;
-                }
-            }
+    /*
+     * 整体替换或批量删除模式前备份 language.bin，回滚命令从这里恢复。
+     */
+    fn backup_language(&self) -> Result<(), String> {
+        self.backup_namespace(None)
+    }
 
-            self.unguard();
+    fn backup_namespace(&self, ns: Option<&str>) -> Result<(), String> {
+        if let Ok(data) = self.pouch_in(ns)?.save() {
+            let name = ns.unwrap_or(self.namespaces.active());
+            let backup_path = format!("{}/language.bin.backup", namespace::dir(&self.data_dir, name));
+            std::fs::write(&backup_path, data).map_err(|e| format!("备份失败: {}", e))?;
+        }
+        Ok(())
+    }
 
-            if let Some(pouch) = self.pouches.get_mut(domain.as_str()) {
-                pouch.sync_patterns(&train_pairs);
-            }
-            self.log_event(format!(
-                "GAP_FILL miss频:{} 词:{} → {} 训练:{}/{}",
-                count, token, domain, trained, sample_inputs.len()
-            ));
+    /*
+     * 管理接口按命名空间操作：None 为当前命名空间（请求之外总是 base），否则为指定的命名空间。
+     */
+    fn pouch_in(&self, ns: Option<&str>) -> Result<&LanguagePouch, String> {
+        match ns.filter(|n| *n != self.namespaces.active()) {
+            None => Ok(&self.language),
+            Some(n) => self.namespaces.parked(n).ok_or_else(|| format!("命名空间不存在: {}", n)),
         }
     }
 
-    pub fn batch_teach_content(&mut self, content: &str) -> usize {
-        let before = self.language.memory_count();
-        let taught = self.language.batch_teach_from_content(content);
-        let after = self.language.memory_count();
-        if taught > 0 {
-            self.save_state();
+    fn pouch_in_mut(&mut self, ns: Option<&str>) -> Result<&mut LanguagePouch, String> {
+        match ns.filter(|n| *n != self.namespaces.active()) {
+            None => Ok(&mut self.language),
+            Some(n) => self.namespaces.parked_mut(n).ok_or_else(|| format!("命名空间不存在: {}", n)),
         }
-        log::info!("batch_teach: taught={} before={} after={}", taught, before, after);
-        taught
     }
 
-    pub async fn eval_language(&mut self, path: &str) -> Result<String, String> {
-        if path.is_empty() {
-            return Err("路径为空".into());
+    pub fn list_patterns(&self, ns: Option<&str>, query: &PatternQuery) -> Result<PatternPage, String> {
+        Ok(self.pouch_in(ns)?.list_patterns(query))
+    }
+
+    pub fn edit_pattern(&mut self, ns: Option<&str>, id: u32, edit: &PatternEdit) -> Result<(), String> {
+        self.pouch_in_mut(ns)?.edit_pattern(id, edit)?;
+        self.log_event(format!("PATTERN_EDIT {}#{}", ns.unwrap_or(self.namespaces.active()), id));
+        self.save_state();
+        Ok(())
+    }
+
+    pub fn delete_pattern(&mut self, ns: Option<&str>, id: u32) -> Result<bool, String> {
+        if !self.pouch_in_mut(ns)?.delete_pattern(id) {
+            return Ok(false);
+        }
+        self.log_event(format!("PATTERN_DELETE {}#{}", ns.unwrap_or(self.namespaces.active()), id));
+        self.save_state();
+        Ok(true)
+    }
+
+    /*
+     * 回复拦截规则与隔离区（作用于当前命名空间，见 response_guard）。
+     */
+    pub fn add_block_rule(&mut self, spec: &str) -> Result<u32, String> {
+        let id = self.language.add_block_rule(spec)?;
+        self.log_event(format!("GUARD_ADD #{} block {}", id, spec.trim()));
+        self.save_state();
+        Ok(id)
+    }
+
+    pub fn add_negative_pattern(&mut self, trigger: &str, response: &str) -> Result<u32, String> {
+        let id = self.language.add_negative_pattern(trigger, response)?;
+        self.log_event(format!("GUARD_ADD #{} negative {} -> {}", id, trigger.trim(), response.trim()));
+        self.save_state();
+        Ok(id)
+    }
+
+    pub fn remove_guard_rule(&mut self, id: u32) -> bool {
+        if !self.language.remove_guard_rule(id) {
+            return false;
+        }
+        self.log_event(format!("GUARD_REMOVE #{}", id));
+        self.save_state();
+        true
+    }
+
+    pub fn release_quarantined(&mut self, id: u32) -> Result<u32, String> {
+        let pattern = self.language.release_quarantined(id)?;
+        self.log_event(format!("QUARANTINE_RELEASE #{} -> pattern #{}", id, pattern));
+        self.save_state();
+        Ok(pattern)
+    }
+
+    pub fn discard_quarantined(&mut self, id: u32) -> bool {
+        if !self.language.discard_quarantined(id) {
+            return false;
+        }
+        self.log_event(format!("QUARANTINE_DISCARD #{}", id));
+        self.save_state();
+        true
+    }
+
+    pub fn guard_json(&self) -> serde_json::Value {
+        let guard = self.language.guard();
+        serde_json::json!({ "rules": guard.rules(), "quarantine": guard.quarantined() })
+    }
+
+    pub fn delete_patterns(&mut self, ns: Option<&str>, query: &PatternQuery) -> Result<usize, String> {
+        if !query.has_filter() {
+            return Err("批量删除需要至少一个筛选条件".into());
+        }
+        self.backup_namespace(ns)?;
+        let n = self.pouch_in_mut(ns)?.delete_matching(query)?;
+        self.log_event(format!("PATTERN_DELETE_BULK {} {}", ns.unwrap_or(self.namespaces.active()), n));
+        if n > 0 {
+            self.save_state();
         }
-        self.language.eval_from_path(path, &self.data_dir).await
+        Ok(n)
     }
 
     fn export_patterns_display(&self) -> String {
-        self.language.export_summary()
+        format!("{}（用「导出数据集 <jsonl|sharegpt|csv>」导出全文）", self.language.export_summary())
+    }
+
+    /*
+     * 当前命名空间的模式按格式渲染，返回 (内容, 行数)。
+     */
+    pub fn export_dataset(&self, opts: &ExportOptions) -> Result<(String, usize), String> {
+        let rows = self.language.export_rows(opts)?;
+        Ok((pattern_export::render(&rows, opts.format), rows.len()))
+    }
+
+    /*
+     * 写入文件；未给路径时写到 <数据目录>/export/patterns_<时间>.<扩展名>。
+     */
+    fn export_dataset_to_file(&mut self, opts: &ExportOptions, path: Option<String>) -> Result<String, String> {
+        let (content, count) = self.export_dataset(opts)?;
+        let dir = format!("{}/export", self.data_dir);
+        let path = match path {
+            Some(p) => pattern_export::output_path(&dir, &p)?,
+            None => {
+                let stamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
+                std::path::PathBuf::from(format!("{}/patterns_{}.{}", dir, stamp, opts.format.extension()))
+            }
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| format!("创建目录失败: {}", e))?;
+        }
+        std::fs::write(&path, content).map_err(|e| format!("写入失败: {}", e))?;
+        self.log_event(format!("EXPORT {:?} rows={} path={}", opts.format, count, path.display()));
+        Ok(format!("已导出 {} 条问答到 {}", count, path.display()))
     }
 
     fn rollback(&mut self) -> Result<String, String> {
-        let backup_path = format!("{}/language.bin.backup", self.data_dir);
+        let backup_path = format!("{}/language.bin.backup", namespace::dir(&self.data_dir, self.namespaces.active()));
         let count = self.language.rollback_from(&backup_path)?;
         self.save_state();
         Ok(format!("回滚完成，当前 {} 条模式", count))
     }
 
-    async fn trigger_train(&mut self) -> Result<String, String> {
-        let trainer_name = self.pouches.keys()
-            .find(|k| k.contains("cloud_trainer") || k.contains("训练"))
-            .cloned();
-        let trainer_name = trainer_name.ok_or("未安装云训练尿袋")?;
-        let pouch = self.pouches.get_mut(&trainer_name).ok_or("云训练尿袋不可用")?;
-        let proposal = create_proposal("train");
-        let validated = pouch.validator().validate(&proposal)
-            .map_err(|e| format!("验证失败: {}", e))?;
-        let output = pouch.process_proposal(&validated)
-            .await
-            .map_err(|e| format!("训练失败: {}", e))?;
-        Ok(output.data)
-    }
-
     fn explain_pouch(&self, name: &str) -> String {
         let name = name.trim().to_lowercase();
         if name == "language" || name.is_empty() {
@@ -1618,23 +2098,32 @@ impl Orchestrator {
         }
     }
 
-    fn status(&self) -> String {
-        format!(
-            "LOGOS v{}\n语言模式:{}条\n尿袋数:{}\n总记忆:{}\n上下文:{}/{}轮\n原子能力:{}\nL2规则:{}",
-            VERSION,
-            self.language.memory_count(),
-            self.pouches.len() + 1,
-            self.total_memory_count(),
-            self.language.context_len(),
-            crate::language_pouch::MAX_CONTEXT_TURNS,
-            self.registry.count(),
-            logic::promoted_rules_count(),
-        )
+    fn status(&self, lang: Lang) -> String {
+        let lp = &self.language;
+        let (patterns, tombstones, context) = (lp.memory_count(), lp.index_tombstones(), lp.context_len());
+        let (pouches, memory, max_turns) =
+            (self.pouches.len() + 1, self.total_memory_count(), crate::language_pouch::MAX_CONTEXT_TURNS);
+        let (sessions, capabilities, intents) = (self.sessions.len(), self.registry.count(), self.intent.class_count());
+        let (tokenizer, rules) = (lp.tokenizer_state().kind, logic::promoted_rules_count());
+        match lang {
+            Lang::Zh => format!(
+                "LOGOS v{}\n语言模式:{}条(索引墓碑{})\n尿袋数:{}\n总记忆:{}\n上下文:{}/{}轮\n会话:{}\n原子能力:{}\n意图类别:{}\n分词器:{:?}\nL2规则:{}",
+                VERSION, patterns, tombstones, pouches, memory, context, max_turns, sessions, capabilities, intents, tokenizer, rules,
+            ),
+            Lang::En => format!(
+                "LOGOS v{}\nPatterns: {} (index tombstones {})\nPouches: {}\nTotal memory: {}\nContext: {}/{} turns\nSessions: {}\nCapabilities: {}\nIntent classes: {}\nTokenizer: {:?}\nL2 rules: {}",
+                VERSION, patterns, tombstones, pouches, memory, context, max_turns, sessions, capabilities, intents, tokenizer, rules,
+            ),
+        }
     }
 
-    fn list(&self) -> String {
-        let mut s = String::from("尿袋列表:\n");
-        s.push_str(&format!("language (E0) - LanguagePouch: {} 条模式\n", self.language.memory_count()));
+    fn list(&self, lang: Lang) -> String {
+        let (title, patterns, capacity) = match lang {
+            Lang::Zh => ("尿袋列表:", "条模式", "容量"),
+            Lang::En => ("Pouches:", "patterns", "Capacity"),
+        };
+        let mut s = format!("{}\n", title);
+        s.push_str(&format!("language (E0) - LanguagePouch: {} {}\n", self.language.memory_count(), patterns));
         for (name, meta) in &self.meta {
             if name != "language" {
                 if let Some(pouch) = self.pouches.get(name) {
@@ -1642,31 +2131,15 @@ impl Orchestrator {
                 }
             }
         }
-        s.push_str(&format!("容量: {}/{}", self.pouches.len() + 1, bedrock::MAX_POUCHES));
+        s.push_str(&format!("{}: {}/{}", capacity, self.pouches.len() + 1, bedrock::MAX_POUCHES));
         s
     }
 
-    fn help() -> String {
-        "LOGOS命令:\n\
-        直接对话 - 语言尿袋交流\n\
-        状态 - 系统状态\n\
-        帮助 - 命令列表\n\
-        尿袋列表 - 已安装尿袋\n\
-        安装尿袋 <名> - 安装\n\
-        卸载尿袋 <名> - 卸载\n\
-        休眠 <名> - 休眠尿袋\n\
-        唤醒 <名> - 唤醒尿袋\n\
-        教你 X -> Y - 教我新模式\n\
-        流水线 A,B,C: 数据 - Pipeline执行\n\
-        配置 - 查看配置\n\
-        自检 - 系统自检\n\
-        解释 <名> - 查看尿袋说明\n\
-        导出模式 - 导出语言模式\n\
-        导入模式 <路径> - 导入语言模式\n\
-        回滚 - 回滚语言模式\n\
-        训练 - 触发云训练\n\
-        演化 - 被动演化状态"
-            .into()
+    fn help(lang: Lang) -> String {
+        match lang {
+            Lang::Zh => crate::frozen::commands::help_text(),
+            Lang::En => crate::frozen::commands::help_text_en(),
+        }
     }
 
     fn config_show(&self) -> Result<String, String> {
@@ -1693,10 +2166,9 @@ impl Orchestrator {
         let mut results = Vec::new();
 
         let (route_ok, pouch_count) = {
-            let installed = self.installed();
-            let decision = logic::route("你好", &installed);
+            let decision = logic::route("你好", &self.route_targets());
             let ok = matches!(decision, RouteDecision::ToPouch(_));
-            let count = installed.len();
+            let count = self.installed().len();
             (ok, count)
         };
         results.push(format!("路由: {}", if route_ok { "ok" } else { "fail" }));
@@ -1757,132 +2229,6 @@ impl Orchestrator {
         }).to_string()
     }
 
-    async fn self_optimize(&mut self) -> Result<String, String> {
-        let mut report = Vec::new();
-        report.push("=== 自我优化启动 ===".to_string());
-
-        let core_pouches = ["benchmark", "defect_scanner", "capability_comparer", "code_analyzer", "knowledge_retriever"];
-        for name in &core_pouches {
-            if !self.pouches.contains_key(*name) {
-                match self.install(name) {
-                    Ok(msg) => {
-                        report.push(format!("自动安装: {}", msg));
-                        self.log_event(format!("SELF_OPT auto_install {}", name));
-                    }
-                    Err(e) => report.push(format!("安装{}失败: {}", name, e)),
-                }
-            }
-        }
-
-        let metrics_json = self.collect_system_metrics_json();
-
-        if let Some(bp) = self.pouches.get_mut("benchmark") {
-            let proposal = create_proposal(&metrics_json);
-            if let Ok(validated) = bp.validator().validate(&proposal) {
-                if let Ok(output) = bp.process_proposal(&validated).await {
-                    report.push(output.data);
-                }
-            }
-        }
-
-        let defect_json = self.collect_system_metrics_json();
-        if let Some(ds) = self.pouches.get_mut("defect_scanner") {
-            let proposal = create_proposal(&defect_json);
-            if let Ok(validated) = ds.validator().validate(&proposal) {
-                if let Ok(output) = ds.process_proposal(&validated).await {
-                    report.push(output.data.clone());
-                    for pname in ds.recommended_follow_ups(&output.data) {
-                        if !self.pouches.contains_key(&pname) {
-                            if let Ok(msg) = self.install(&pname) {
-                                report.push(format!("自动补齐: {}", msg));
-                                self.log_event(format!("SELF_OPT remediate {}", pname));
-                            }
-                        }
-                    }
-                }
-            }
-        }
-
-        let pouch_count = self.pouches.len() + 1;
-        let atom_count = self.registry.count();
-        let total_mem = self.total_memory_count();
-        report.push(format!(
-            "优化后: 尿袋:{} 原子:{} 记忆:{}",
-            pouch_count, atom_count, total_mem
-        ));
-
-        self.log_event("SELF_OPT complete".into());
-        self.save_state();
-        Ok(report.join("\n"))
-    }
-
-    async fn evolve(&mut self) -> Result<String, String> {
-        let mut report = Vec::new();
-        report.push("=== 进化对标启动 ===".to_string());
-
-        let core_pouches = ["benchmark", "defect_scanner", "capability_comparer"];
-        for name in &core_pouches {
-            if !self.pouches.contains_key(*name) {
-                let _ = self.install(name);
-            }
-        }
-
-        let caps_json = self.collect_system_metrics_json();
-        let mut gaps: Vec<(String, f64)> = Vec::new();
-
-        if let Some(cc) = self.pouches.get_mut("capability_comparer") {
-            let proposal = create_proposal(&caps_json);
-            if let Ok(validated) = cc.validator().validate(&proposal) {
-                if let Ok(output) = cc.process_proposal(&validated).await {
-                    report.push(output.data.clone());
-                    gaps = cc.evolution_gaps_from_output(&output.data);
-                }
-            }
-        }
-
-        if gaps.is_empty() {
-            report.push("未发现显著能力差距（LOGOS >= 竞品80%）".to_string());
-        } else {
-            report.push(format!("发现 {} 个能力差距:", gaps.len()));
-            let remediation_map: Vec<(&str, &[&str])> = vec![
-                ("语言理解", &["context", "memory"]),
-                ("逻辑推理", &["reasoning"]),
-                ("知识检索", &["knowledge_retriever"]),
-                ("创意生成", &["creative"]),
-                ("代码分析", &["code_analyzer", "programming"]),
-            ];
-            for (category, deficit) in &gaps {
-                report.push(format!("  {} 差距: {:.0}%", category, deficit));
-                for (cat_key, pouches_needed) in &remediation_map {
-                    if category.contains(cat_key) {
-                        for pname in *pouches_needed {
-                            if !self.pouches.contains_key(*pname) {
-                                match self.install(pname) {
-                                    Ok(msg) => {
-                                        report.push(format!("  → 补齐: {}", msg));
-                                        self.log_event(format!("EVOLVE auto_install {}", pname));
-                                    }
-                                    Err(e) => report.push(format!("  → 安装{}失败: {}", pname, e)),
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
-
-        let pouch_count = self.pouches.len() + 1;
-        let atom_count = self.registry.count();
-        report.push(format!(
-            "进化后: 尿袋:{} 原子:{}",
-            pouch_count, atom_count
-        ));
-
-        self.log_event("EVOLVE complete".into());
-        self.save_state();
-        Ok(report.join("\n"))
-    }
-
     fn pouch_maturity(&self, name: &str) -> f64 {
         let mem = if name == "language" {
             self.language.memory_count()
@@ -2137,6 +2483,21 @@ impl Orchestrator {
         &self.learning
     }
 
+    /*
+     * 单条模式的完整记录（含来源与反馈计数），附还原后的触发词，供 /api/pattern/:id 使用。
+     */
+    pub fn pattern_json(&self, ns: Option<&str>, id: u32) -> Result<Option<serde_json::Value>, String> {
+        let language = self.pouch_in(ns)?;
+        let Some(p) = language.pattern(id) else {
+            return Ok(None);
+        };
+        Ok(Some(serde_json::json!({
+            "trigger": language.trigger_text(p),
+            "origin_label": p.provenance.origin.label(),
+            "pattern": p,
+        })))
+    }
+
     pub fn learning_metrics_extra(&self) -> (usize, usize, f64) {
         let info = self.pouches_info();
         let pattern_count = info.iter().find(|(n, _, _, _)| n == "language").map(|(_, _, m, _)| *m).unwrap_or(0);
@@ -2148,50 +2509,47 @@ impl Orchestrator {
 
     const CLOUD_WORKER_BASE: &'static str = "https://logos-gateway.amrta.workers.dev";
 
-    pub async fn sync_with_cloud(&mut self) {
+    pub fn cloud_sync_request(&self) -> CloudSyncRequest {
+        CloudSyncRequest {
+            cursor: self.learning.cloud_sync_cursor,
+            push_pairs: self.language.top_quality_pairs(50),
+        }
+    }
+
+    pub async fn fetch_cloud_sync(req: CloudSyncRequest) -> CloudSyncResult {
+        let mut res = CloudSyncResult::default();
         let client = match reqwest::Client::builder()
             .timeout(std::time::Duration::from_secs(15))
             .build()
         {
             Ok(c) => c,
-            Err(_) => return,
+            Err(_) => return res,
         };
 
         let pull_url = format!(
             "{}/sync?since={}&limit=200",
             Self::CLOUD_WORKER_BASE,
-            self.learning.cloud_sync_cursor
+            req.cursor
         );
         if let Ok(resp) = client.get(&pull_url).send().await {
             if resp.status().is_success() {
                 if let Ok(body) = resp.json::<serde_json::Value>().await {
-                    let mut pulled = 0u64;
                     if let Some(pairs) = body["pairs"].as_array() {
                         for pair in pairs {
                             let human = pair["human"].as_str().unwrap_or("");
                             let gpt = pair["gpt"].as_str().unwrap_or("");
                             if human.len() >= 2 && gpt.len() > 5 {
-                                self.language.teach(human, gpt);
-                                pulled += 1;
+                                res.pairs.push((human.to_string(), gpt.to_string()));
                             }
                         }
                     }
-                    if let Some(max_id) = body["max_id"].as_i64() {
-                        if max_id > self.learning.cloud_sync_cursor {
-                            self.learning.cloud_sync_cursor = max_id;
-                        }
-                    }
-                    if pulled > 0 {
-                        self.learning.cloud_pulled += pulled;
-                        self.log_event(format!("CLOUD_PULL {} pairs, cursor={}", pulled, self.learning.cloud_sync_cursor));
-                    }
+                    res.max_id = body["max_id"].as_i64();
                 }
             }
         }
 
-        let top_pairs = self.language.top_quality_pairs(50);
-        if !top_pairs.is_empty() {
-            let push_pairs: Vec<serde_json::Value> = top_pairs.iter()
+        if !req.push_pairs.is_empty() {
+            let push_pairs: Vec<serde_json::Value> = req.push_pairs.iter()
                 .map(|(h, g)| serde_json::json!({"human": h, "gpt": g}))
                 .collect();
             let push_url = format!("{}/sync", Self::CLOUD_WORKER_BASE);
@@ -2203,18 +2561,51 @@ impl Orchestrator {
             {
                 if resp.status().is_success() {
                     if let Ok(body) = resp.json::<serde_json::Value>().await {
-                        let inserted = body["inserted"].as_u64().unwrap_or(0);
-                        if inserted > 0 {
-                            self.learning.cloud_pushed += inserted;
-                            self.log_event(format!("CLOUD_PUSH {} new pairs", inserted));
-                        }
+                        res.inserted = body["inserted"].as_u64().unwrap_or(0);
                     }
                 }
             }
         }
+        res
     }
 
-    pub async fn autonomous_learning_cycle(&mut self) {
+    pub fn apply_cloud_sync(&mut self, res: CloudSyncResult) {
+        let pulled = res.pairs.len() as u64;
+        let quarantined_before = self.language.guard().quarantined_total();
+        let mut rejected = 0;
+        for (human, gpt) in &res.pairs {
+            if self.language.teach_as(human, gpt, Origin::CloudPull).is_err() {
+                rejected += 1;
+            }
+        }
+        if rejected > 0 {
+            self.log_event(format!("CLOUD_PULL_REJECTED {} pairs", rejected));
+        }
+        let quarantined = self.language.guard().quarantined_total() - quarantined_before;
+        if quarantined > 0 {
+            self.log_event(format!("CLOUD_PULL_BLOCKED {} pairs quarantined", quarantined));
+        }
+        let mut moved = false;
+        if let Some(max_id) = res.max_id {
+            if max_id > self.learning.cloud_sync_cursor {
+                self.learning.cloud_sync_cursor = max_id;
+                moved = true;
+            }
+        }
+        if pulled > 0 {
+            self.learning.cloud_pulled += pulled;
+            self.log_event(format!("CLOUD_PULL {} pairs, cursor={}", pulled, self.learning.cloud_sync_cursor));
+        }
+        if res.inserted > 0 {
+            self.learning.cloud_pushed += res.inserted;
+            self.log_event(format!("CLOUD_PUSH {} new pairs", res.inserted));
+        }
+        if pulled > 0 || moved || res.inserted > 0 {
+            self.save_state();
+        }
+    }
+
+    pub fn begin_learning_cycle(&mut self) -> LearningCycle {
         let now = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap_or_default()
@@ -2232,9 +2623,9 @@ impl Orchestrator {
         }
         maturities.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
 
-        let hungry: Vec<(String, f64)> = maturities.iter()
+        let hungry: Vec<String> = maturities.iter()
             .filter(|(_, m)| *m < MATURITY_HUNGRY)
-            .cloned()
+            .map(|(n, _)| n.clone())
             .collect();
 
         let misses = self.pending_misses(12);
@@ -2245,101 +2636,51 @@ impl Orchestrator {
             && self.learning.saturation < SATURATION_THRESHOLD
             && !force_synapse;
 
+        let mut c = LearningCycle {
+            cycle,
+            miss_count: misses.len(),
+            hungry: Vec::new(),
+            teachers: Vec::new(),
+            students: Vec::new(),
+            before_mem: self.total_memory_count(),
+            before_evo: self.evolution.len(),
+            fed_count: 0,
+            lang_outputs: Vec::new(),
+            hop: 0,
+            a2_hops: self.config.chain.a2_chain_hops.clamp(2, 12),
+            hop_outputs: Vec::new(),
+            teacher_outputs: Vec::new(),
+            queue: std::collections::VecDeque::new(),
+        };
+
         if do_feed {
             self.learning.phase = if misses.is_empty() { "external".into() } else { "miss_driven".into() };
-            let before_mem = self.total_memory_count();
-            let before_evo = self.evolution.len();
-
-            self.call_stack.clear();
-            let _ = self.guard(Layer::Orchestrator);
-
-            let mut fed_count: u64 = 0;
             if !misses.is_empty() {
+                c.hungry = hungry.iter().take(4).cloned().collect();
                 for miss_input in misses.iter().take(8) {
-                    let lang_result = self.call_pouch("language", miss_input).await;
-                    if let Ok(ref lang_out) = lang_result {
-                        if lang_out.len() > 20 && !self.language.is_fallback_response(lang_out) {
-                            let tokens = self.language.tokenize(miss_input);
-                            let broadcast = vec![(tokens, lang_out.clone(), 1.3)];
-                            for (_, pouch) in self.pouches.iter_mut() {
-                                pouch.sync_patterns(&broadcast);
-                            }
-                            self.language.absorb(miss_input, lang_out, 1.0);
-                        }
-                    }
-                    for (name, _) in hungry.iter().take(4) {
-                        if name != "language" {
-                            let pouch_result = self.call_pouch(name, miss_input).await;
-                            if let Ok(ref pouch_out) = pouch_result {
-                                if pouch_out.len() > 15 && !self.language.is_fallback_response(pouch_out) {
-                                    self.language.absorb(miss_input, pouch_out, 0.8);
-                                }
-                            }
-                        }
-                    }
-                    fed_count += 1;
+                    c.queue.push_back(LearningTask::FeedMiss(miss_input.clone()));
                 }
             } else {
                 let seeds = Self::load_external_seeds(&self.data_dir);
-                let use_seed = cycle % 5 == 2 && !seeds.is_empty();
-
-                if use_seed {
+                if cycle % 5 == 2 && !seeds.is_empty() {
                     let seed_idx = (cycle as usize / 5) % seeds.len();
-                    let (ref s_intent, ref s_response) = seeds[seed_idx];
-                    let tokens = self.language.tokenize(s_intent);
-                    let broadcast = vec![(tokens, s_response.clone(), 1.5)];
-                    for (_, pouch) in self.pouches.iter_mut() {
-                        pouch.sync_patterns(&broadcast);
-                    }
-                    self.language.teach(s_intent, s_response);
-                    fed_count += 1;
-                    self.log_event(format!("SEED_FED {}", &s_intent[..s_intent.len().min(30)]));
+                    let (s_intent, s_response) = seeds[seed_idx].clone();
+                    c.queue.push_back(LearningTask::FeedSeed(s_intent, s_response));
                 }
-
-                for (idx, (name, _)) in hungry.iter().take(6).enumerate() {
-                    let combo = Self::learning_intent_combo(cycle + idx as u64);
+                for (idx, name) in hungry.iter().take(6).enumerate() {
                     let use_combo = cycle.is_multiple_of(3) && idx == 0;
-                    let fixed = Self::learning_intents_for(name, cycle + idx as u64);
-                    let intent_str: &str = if use_combo { &combo } else { fixed };
-                    let lang_result = self.call_pouch("language", intent_str).await;
-                    if let Ok(ref lang_out) = lang_result {
-                        if lang_out.len() > 20 && !self.language.is_fallback_response(lang_out) {
-                            let tokens = self.language.tokenize(intent_str);
-                            let broadcast = vec![(tokens, lang_out.clone(), 1.3)];
-                            for (_, pouch) in self.pouches.iter_mut() {
-                                pouch.sync_patterns(&broadcast);
-                            }
-                        }
-                    }
-                    let _ = self.call_pouch(name, intent_str).await;
-                    fed_count += 1;
+                    let intent = if use_combo {
+                        Self::learning_intent_combo(cycle + idx as u64)
+                    } else {
+                        Self::learning_intents_for(name, cycle + idx as u64).to_string()
+                    };
+                    c.queue.push_back(LearningTask::FeedHungry(name.clone(), intent));
                 }
             }
-
-            self.unguard();
-
-            self.discover_gaps_from_misses().await;
-
-            let gained_mem = self.total_memory_count().saturating_sub(before_mem);
-            let gained_evo = self.evolution.len().saturating_sub(before_evo);
-            self.learning.external_fed += fed_count;
-            self.learning.external_absorbed += (gained_mem + gained_evo) as u64;
-
-            if self.learning.external_fed > 8 {
-                let fed = self.learning.external_fed.max(1) as f64;
-                let gap = self.learning.external_fed.saturating_sub(self.learning.external_absorbed) as f64;
-                self.learning.saturation = (1.0 - gap / fed).clamp(0.0, 1.0);
-            }
-
-            self.log_event(format!(
-                "LEARN_{} 投喂:{} 缺口:{} 记忆+{} 演化+{} 饱和:{:.0}%",
-                if misses.is_empty() { "EXT" } else { "MISS" },
-                fed_count, misses.len(), gained_mem, gained_evo, self.learning.saturation * 100.0
-            ));
+            c.queue.push_back(LearningTask::DiscoverGaps);
+            c.queue.push_back(LearningTask::FeedSummary);
         } else {
             self.learning.phase = "synapse".into();
-            let before_mem = self.total_memory_count();
-            let before_evo = self.evolution.len();
 
             let mut by_maturity = maturities.clone();
             by_maturity.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
@@ -2362,11 +2703,6 @@ impl Orchestrator {
                 .take(CROSS_LEARN_BATCH)
                 .collect();
 
-            let mut cross_count: u64 = 0;
-
-            self.call_stack.clear();
-            let _ = self.guard(Layer::Orchestrator);
-
             let intent_count = CROSS_LEARN_BATCH.max(misses.len().min(12));
             let mut intents: Vec<String> = Vec::with_capacity(intent_count);
             for i in 0..intent_count {
@@ -2384,129 +2720,1470 @@ impl Orchestrator {
                 }
             }
 
-            let mut lang_outputs: Vec<(Vec<String>, String)> = Vec::new();
-            for intent in &intents {
-                let lang_result = self.call_pouch("language", intent).await;
+            for intent in &intents {
+                c.queue.push_back(LearningTask::SynapseLang(intent.clone()));
+            }
+            c.queue.push_back(LearningTask::SynapseBroadcast);
+            for intent in &intents {
+                c.queue.push_back(LearningTask::SynapseHop(intent.clone()));
+            }
+            c.queue.push_back(LearningTask::SynapseHopEnd);
+            c.queue.push_back(LearningTask::SynapseTeach);
+            c.teachers = teachers;
+            c.students = students;
+        }
+
+        if cycle > 0 && cycle.is_multiple_of(20) {
+            c.queue.push_back(LearningTask::SelfOptimize);
+        }
+        if cycle > 0 && cycle.is_multiple_of(31) {
+            c.queue.push_back(LearningTask::Evolve);
+        }
+        c.queue.push_back(LearningTask::Persist);
+        c
+    }
+
+    fn lookup_remote_spec(&self, name: &str) -> Option<crate::remote_pouch::RemotePouchSpec> {
+        let spec_path = std::path::Path::new(&self.data_dir).join("remote_pouches.json");
+        if !spec_path.exists() {
+            return None;
+        }
+        let content = std::fs::read_to_string(&spec_path).ok()?;
+        let specs: Vec<crate::remote_pouch::RemotePouchSpec> =
+            serde_json::from_str(&content).ok()?;
+        specs.into_iter().find(|s| s.name == name)
+    }
+}
+
+impl Turn<'_> {
+    pub async fn execute_in_session(
+        &mut self,
+        session_id: &str,
+        input: &str,
+    ) -> Result<(String, String), (String, String)> {
+        let gate = self.session_gate(session_id);
+        let held = match gate.clone().try_lock_owned() {
+            Ok(held) => held,
+            Err(_) => {
+                self.log_event(format!("SESSION_WAIT {}", session_id));
+                self.unlocked(gate.clone().lock_owned()).await
+            }
+        };
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let result = match self.enter_session(session_id, now) {
+            Ok(outer) => {
+                let result = self.execute_with_pouch(input).await;
+                self.leave_session(session_id, outer, now);
+                result
+            }
+            Err(e) => Err((e, "system".to_string())),
+        };
+        drop(held);
+        self.release_session_gate(session_id, gate);
+        result
+    }
+
+    pub async fn execute_with_pouch(
+        &mut self,
+        input: &str,
+    ) -> Result<(String, String), (String, String)> {
+        let result = self.execute_routed(input).await;
+        self.prepared = None;
+        self.language.set_learning_paused(false);
+        if let Some(t) = self.trace.as_mut() {
+            match &result {
+                Ok((_, pouch)) => t.finish(pouch, true),
+                Err((_, pouch)) => t.finish(pouch, false),
+            }
+        }
+        result
+    }
+
+    async fn execute_routed(
+        &mut self,
+        input: &str,
+    ) -> Result<(String, String), (String, String)> {
+        if !self.call_stack.is_empty() {
+            self.call_stack.clear();
+        }
+        let input = if input.len() > bedrock::MAX_INPUT_LEN {
+            let mut end = bedrock::MAX_INPUT_LEN;
+            while !input.is_char_boundary(end) && end > 0 {
+                end -= 1;
+            }
+            &input[..end]
+        } else {
+            input
+        };
+
+        if let Some(domain) = Orchestrator::classify_domain(input) {
+            self.auto_ensure_pouch(domain);
+        }
+
+        if let Err(e) = self.guard(Layer::Orchestrator) {
+            return Err((e, "system".into()));
+        }
+
+        let trimmed = input.trim();
+        let sanitized_store;
+        let sanitized = self.sanitize_input(trimmed).await;
+        if let Some(t) = self.trace.as_mut() {
+            t.sanitize = sanitized.as_ref().map(|(rejected, out)| {
+                if *rejected {
+                    "rejected".to_string()
+                } else if out == trimmed {
+                    "passed".to_string()
+                } else {
+                    format!("rewritten → {}", trace::clip(out))
+                }
+            });
+        }
+        let input = if let Some((rejected, out)) = sanitized {
+            if rejected {
+                self.unguard();
+                return Ok((out, "sanitize".into()));
+            }
+            sanitized_store = out;
+            &sanitized_store
+        } else {
+            trimmed
+        };
+        if input.starts_with("语言评估") || input.to_lowercase().starts_with("eval language") {
+            let path = input
+                .replace("语言评估", "")
+                .replace("eval language", "")
+                .replace("Eval Language", "")
+                .trim()
+                .to_string();
+            if !path.is_empty() {
+                let (path, opts) = EvalOptions::split_command(&path);
+                match self.eval_language(&path, &opts).await {
+                    Ok(msg) => return Ok((msg, "system".into())),
+                    Err(e) => return Err((e, "system".into())),
+                }
+            }
+        }
+
+        let lower = trimmed.to_lowercase();
+        if let Some(fb_result) = self.detect_feedback(&lower) {
+            if let Some(t) = self.trace.as_mut() {
+                t.route = "feedback".into();
+            }
+            self.unguard();
+            return Ok((fb_result, "feedback".into()));
+        }
+        if lower == "反哺状态" || lower == "feedback status" {
+            let result = self.feedback_status();
+            self.unguard();
+            return Ok((result, "system".into()));
+        }
+        if lower == "反哺导出" || lower == "feedback export" {
+            let result = self.language.export_feedback_jsonl();
+            self.unguard();
+            if result.is_empty() {
+                return Ok(("无反馈数据".into(), "system".into()));
+            }
+            return Ok((result, "system".into()));
+        }
+        if lower == "自我优化" || lower == "自我进化" || lower == "优化自己"
+            || lower == "self optimize" || lower == "self-optimize"
+        {
+            match self.self_optimize().await {
+                Ok(msg) => return Ok((msg, "system".into())),
+                Err(e) => return Err((e, "system".into())),
+            }
+        }
+        if lower.starts_with("对标") || lower.starts_with("进化能力") || lower.starts_with("升级能力")
+            || lower == "evolve"
+            || (lower.contains("对标") && (lower.contains("进化") || lower.contains("升级") || lower.contains("补齐")))
+        {
+            match self.evolve().await {
+                Ok(msg) => return Ok((msg, "system".into())),
+                Err(e) => return Err((e, "system".into())),
+            }
+        }
+        if lower == "自主学习" || lower == "autonomous learn" || lower == "auto learn" {
+            self.autonomous_learning_cycle().await;
+            let ls = &self.learning;
+            let summary = format!(
+                "周期:{} 阶段:{} 外部:{}/{} 突触:{}/{} 饱和:{:.0}%",
+                ls.cycle_count, ls.phase,
+                ls.external_absorbed, ls.external_fed,
+                ls.cross_absorbed, ls.cross_fed,
+                ls.saturation * 100.0
+            );
+            return Ok((summary, "system".into()));
+        }
+
+        let resolved_store;
+        let input = match self.track_dialogue(input) {
+            Some(rewritten) => {
+                resolved_store = rewritten;
+                &resolved_store
+            }
+            None => input,
+        };
+
+        let route = match self.prepared_for(input).and_then(|p| p.route.take()) {
+            Some(route) => route,
+            None => logic::route(input, &self.route_targets()),
+        };
+        let mut decision = self.resolve_ambiguous(route);
+
+        if matches!(decision, Routed::Reject) {
+            if let Some(intent) = self.language.identify_requirement(input) {
+                if intent.confidence > 0.6 {
+                    let cap = &intent.capability_needed;
+                    if !self.installed().contains(&cap.as_str()) {
+                        self.log_event(format!("AUTO_INSTALL {}", cap));
+                        let _ = self.install(cap);
+                    }
+                    if self.installed().contains(&cap.as_str()) {
+                        decision = Routed::ToPouch(cap.clone());
+                    }
+                }
+            }
+        }
+        if matches!(decision, Routed::Reject) {
+            let lang_check = self.process_language(input).await;
+            if self.language.last_was_pattern_hit()
+                && self.language.last_match_weight() >= ABSORB_WEIGHT
+                && !self.language.is_fallback_response(&lang_check)
+            {
+                self.log_event("LANG_PRIORITY hit".into());
+                if let Some(t) = self.trace.as_mut() {
+                    t.route = format!("{:?} → language_priority", decision);
+                }
+                if let Some(out) = self.execute_chain_spec(input, &lang_check).await {
+                    self.unguard();
+                    return Ok((out, "chain".into()));
+                }
+                if let Some(expanded) = self.expand_template(input, &lang_check).await {
+                    self.unguard();
+                    return Ok((expanded, "template".into()));
+                }
+                self.trace_fallback("language_priority", true, "");
+                self.unguard();
+                return Ok((lang_check, "language".into()));
+            }
+        }
+
+        let route_event = match &decision {
+            Routed::ToPouch(n) => format!("ROUTE → {}", n),
+            Routed::SystemCommand(_) => "ROUTE → system".into(),
+            Routed::Usage(_) => "ROUTE → usage".into(),
+            Routed::Reject => "ROUTE → language".into(),
+        };
+        self.log_event(route_event);
+        if let Some(t) = self.trace.as_mut() {
+            t.route = format!("{:?}", decision);
+        }
+
+        let result = match decision {
+            Routed::ToPouch(name) => {
+                let r = self.call_pouch(&name, input).await;
+                let ev = match &r {
+                    Ok(_) => format!("EXEC {} → ok", name),
+                    Err(e) => format!("EXEC {} → {}", name, e),
+                };
+                self.log_event(ev);
+                match r {
+                    Ok(r) => Ok((r, name)),
+                    Err(e) => Err((e, name)),
+                }
+            }
+            Routed::SystemCommand(cmd) => {
+                let cmd_name = format!("{:?}", cmd).chars().take(40).collect::<String>();
+                let r = self.handle_cmd(cmd, Lang::detect(input)).await;
+                self.log_event(format!("CMD {} → {}", cmd_name, if r.is_ok() { "ok" } else { "err" }));
+                match r {
+                    Ok(r) => Ok((r, "system".into())),
+                    Err(e) => Err((e, "system".into())),
+                }
+            }
+            Routed::Usage(usage) => Ok((usage, "system".into())),
+            Routed::Reject => {
+                let kinds = self.predict_intent(input);
+                if !kinds.is_empty() {
+                    let fragments = crate::atom::split_parallel_subjects(input);
+                    if fragments.len() >= 2 {
+                        self.auto_ensure_pouch("compose");
+                    }
+                    let entries = self.recent_evolution_entries(100);
+                    let (baseline, low_threshold, _) = self.clamped_routing();
+                    let scorer = move |name: &str| Orchestrator::score_pouch_from_entries(name, &entries, baseline);
+                    let planned = if fragments.len() >= 2 {
+                        let stages: Vec<Vec<crate::atom::AtomKind>> = kinds.iter().map(|k| vec![*k]).collect();
+                        self.registry.plan_for_stages(&stages, &fragments, Some(&scorer), Some(low_threshold))
+                    } else {
+                        self.registry.plan_for_kinds(&kinds, Some(&scorer), Some(low_threshold))
+                    };
+                    if let Some(plan) = planned {
+                        let summary = crate::atom::CapabilityRegistry::plan_summary(&plan);
+                        self.log_event(format!("PLAN {}", summary));
+                        if let Some(t) = self.trace.as_mut() {
+                            t.plan = Some(PlanTrace { summary, plan: plan.clone(), steps: Vec::new() });
+                        }
+                        let r = self.execute_plan(&plan, input).await;
+                        return match r {
+                            Ok(data) => {
+                                let tokens = self.intent_tokens(input);
+                                self.intent.observe_text(input, &tokens, &kinds);
+                                if !self.language.learning_paused() && !self.language.is_fallback_response(&data) {
+                                    self.language.absorb(input, &data, 1.1, "plan");
+                                    self.log_event("ABSORB plan→language".into());
+                                    self.trace_absorb("plan→language");
+                                }
+                                Ok((data, "plan".into()))
+                            }
+                            Err(e) => Err((e, "plan".into())),
+                        };
+                    }
+                }
+                let cloud = self.analyze_cloud_bounded(input).await;
+                match &cloud {
+                    Ok(p) if !p.steps.is_empty() => self.trace_fallback("cloud_plan", true, ""),
+                    Ok(_) => self.trace_fallback("cloud_plan", false, "云端计划无步骤"),
+                    Err(e) => self.trace_fallback("cloud_plan", false, e),
+                }
+                match cloud {
+                    Ok(cloud_plan) if !cloud_plan.steps.is_empty() => {
+                        self.log_event(format!(
+                            "CLOUD_ANALYZE {}p {}s",
+                            cloud_plan.pouches.len(),
+                            cloud_plan.steps.len()
+                        ));
+                        for spec in &cloud_plan.pouches {
+                            if !self.installed().contains(&spec.name.as_str()) {
+                                let rp = crate::remote_pouch::RemotePouch::new(
+                                    &spec.name, PouchRole::E1, &spec.endpoint,
+                                );
+                                let caps = rp.atom_capabilities();
+                                self.pouches.insert(spec.name.clone(), Box::new(rp));
+                                self.meta.insert(
+                                    spec.name.clone(),
+                                    PouchMeta { role: PouchRole::E1 },
+                                );
+                                for cap in caps {
+                                    self.registry.register(cap);
+                                }
+                                self.log_event(format!("CLOUD_INSTALL {}", spec.name));
+                            }
+                        }
+                        let mut last_output = String::new();
+                        for step in &cloud_plan.steps {
+                            match self.call_pouch(&step.pouch, &step.input).await {
+                                Ok(data) => {
+                                    self.log_event(format!("CLOUD_STEP {} ok", step.pouch));
+                                    self.emit_progress("STEP_OUTPUT", format!("{}: {}", step.pouch, data.chars().take(200).collect::<String>()));
+                                    last_output = data;
+                                }
+                                Err(e) => {
+                                    self.log_event(format!("CLOUD_STEP {} {}", step.pouch, e));
+                                    return Err((e, "cloud_plan".into()));
+                                }
+                            }
+                        }
+                        if !self.language.learning_paused()
+                            && !self.language.is_fallback_response(&last_output)
+                            && !last_output.is_empty()
+                        {
+                            self.language.absorb(input, &last_output, 1.1, "cloud");
+                            self.log_event("ABSORB cloud→language".into());
+                            self.trace_absorb("cloud→language");
+                        }
+                        return Ok((last_output, "cloud_plan".into()));
+                    }
+                    _ => {}
+                }
+                let lang_final = self.process_language(input).await;
+                self.log_event("LANG process".into());
+                if let Some(out) = self.execute_chain_spec(input, &lang_final).await {
+                    return self.unguard_then(Ok((out, "chain".into())));
+                }
+                if let Some(expanded) = self.expand_template(input, &lang_final).await {
+                    return self.unguard_then(Ok((expanded, "template".into())));
+                }
+                if !self.language.is_fallback_response(&lang_final) {
+                    self.trace_fallback("language", true, "");
+                    return self.unguard_then(Ok((lang_final, "language".into())));
+                }
+                self.trace_fallback("language", false, "language 返回兜底回复");
+                if let Some((out, pouch)) = self.try_fallback_chain(input).await {
+                    if !self.language.learning_paused() && !self.language.is_fallback_response(&out) {
+                        self.language.absorb(input, &out, 1.0, &pouch);
+                        self.log_event(format!("ABSORB {}→language", pouch));
+                        self.trace_absorb(&format!("{}→language", pouch));
+                    }
+                    return self.unguard_then(Ok((out, pouch)));
+                }
+                self.trace_fallback("language_final", true, "无尿袋给出有效输出，返回 language 兜底");
+                return self.unguard_then(Ok((lang_final, "language".into())));
+            }
+        };
+
+        self.unguard();
+        result
+    }
+
+    async fn sanitize_input(&mut self, input: &str) -> Option<(bool, String)> {
+        let name = self.pouches.keys().find(|n| n.contains("sanitize"))?.clone();
+        let validated = self.validated_for(&name, &create_proposal(input))?;
+        let out = self.run_pouch(&name, &validated).await.ok()?;
+        let rejected = self.pouches.get(&name).is_some_and(|p| p.is_fallback_output(&out.data));
+        Some((rejected, out.data))
+    }
+
+    async fn expand_template(&mut self, input: &str, response: &str) -> Option<String> {
+        if !response.contains("{{") || !response.contains("}}") {
+            return None;
+        }
+        let mut out = response.to_string();
+        for _ in 0..5 {
+            let start = match out.find("{{") {
+                Some(s) => s,
+                None => break,
+            };
+            let end = match out[start..].find("}}") {
+                Some(e) => start + e + 2,
+                None => break,
+            };
+            let inner = out[start..end].strip_prefix("{{").and_then(|s| s.strip_suffix("}}")).unwrap_or("").trim();
+            if let Some((pouch, query)) = inner.split_once(':') {
+                let pouch = pouch.trim();
+                let query = query.trim();
+                let q = if query == "input" { input } else { query };
+                if self.pouches.contains_key(pouch) {
+                    if let Ok(rep) = self.call_pouch(pouch, q).await {
+                        out.replace_range(start..end, &rep);
+                        continue;
+                    }
+                }
+            }
+            out.replace_range(start..end, "");
+        }
+        if out == response {
+            self.trace_fallback("template", false, "模板未展开");
+            return None;
+        }
+        self.trace_fallback("template", true, "");
+        Some(out)
+    }
+
+    async fn execute_chain_spec(&mut self, input: &str, response: &str) -> Option<String> {
+        let spec = response.strip_prefix("chain:")?.trim();
+        if spec.is_empty() {
+            return None;
+        }
+        let pouches: Vec<&str> = spec.split("->").map(str::trim).filter(|s| !s.is_empty()).collect();
+        let max_depth = self.config.chain.chain_spec_max_depth.clamp(2, 12);
+        if pouches.is_empty() || pouches.len() > max_depth {
+            self.trace_fallback("chain", false, &format!("链长 {} 超出 1..={}", pouches.len(), max_depth));
+            return None;
+        }
+        let cache_key = format!("{}|{}", hash_str(input), spec);
+        if let Some(cached) = self.chain_result_cache.iter().find(|(k, _)| k == &cache_key).map(|(_, v)| v.clone()) {
+            self.log_event("CACHE chain hit".into());
+            if let Some(t) = self.trace.as_mut() {
+                t.cache_hits.push(format!("chain {}", spec));
+            }
+            return Some(cached);
+        }
+        let mut carry = input.to_string();
+        for name in pouches {
+            if !self.pouches.contains_key(name) {
+                self.trace_fallback("chain", false, &format!("{}未安装", name));
+                return None;
+            }
+            match self.call_pouch(name, &carry).await {
+                Ok(out) => carry = out,
+                Err(e) => {
+                    self.trace_fallback("chain", false, &format!("{}: {}", name, e));
+                    return None;
+                }
+            }
+        }
+        self.trace_fallback("chain", true, spec);
+        if self.chain_result_cache.len() >= 100 {
+            self.chain_result_cache.pop_front();
+        }
+        self.chain_result_cache.push_back((cache_key, carry.clone()));
+        Some(carry)
+    }
+
+    async fn try_fallback_chain(&mut self, input: &str) -> Option<(String, String)> {
+        let proposal = create_proposal(input).with_dialogue(self.dialogue.snapshot());
+        let mut candidates: Vec<String> = self.pouches.keys().cloned().collect();
+        candidates.sort_by_key(|name| {
+            self.meta.get(name).map_or(1, |m| Orchestrator::role_priority(m.role))
+        });
+        for name in candidates {
+            let role = self.meta.get(&name).map_or(PouchRole::E1, |m| m.role);
+            if role == PouchRole::E2 && !self.is_pouch_awake(&name) {
+                self.trace_fallback(&name, false, "E2 休眠");
+                continue;
+            }
+            if let Some(why) = self.take_call_budget(&name) {
+                self.log_event(format!("BUDGET {} {}", name, why));
+                self.trace_fallback(&name, false, &why);
+                break;
+            }
+            let (limit, cancel) = self.call_bounds(&name);
+            let mut reason = String::new();
+            let mut cut = None;
+            match self.pouches.get(&name).map(|p| p.validator().validate(&proposal)) {
+                Some(Ok(validated)) => match self.run_pouch_bounded(&name, &validated, limit, cancel).await {
+                    Err(c) => cut = Some(c),
+                    Ok(Ok(output)) => {
+                        let fallback = self.pouches.get(&name).is_some_and(|p| p.is_fallback_output(&output.data));
+                        if !output.data.is_empty() && !fallback {
+                            let conf_note = if output.confidence < 0.5 { LOW_CONFIDENCE_NOTE } else { "" };
+                            self.trace_fallback(&name, true, "");
+                            return Some((format!("{}{}", output.data, conf_note), name));
+                        }
+                        reason = "空输出或兜底输出".into();
+                    }
+                    Ok(Err(e)) => reason = format!("处理失败: {}", e),
+                },
+                Some(Err(e)) => reason = format!("验证失败: {}", e),
+                None => {}
+            }
+            let cancelled = cut == Some(CallCut::Cancelled);
+            if let Some(Err(e)) = cut.map(|c| self.cut_call(&name, input, c)) {
+                reason = e;
+            }
+            self.trace_fallback(&name, false, &reason);
+            if cancelled {
+                break;
+            }
+        }
+        None
+    }
+
+    /*
+     * 云端分析与尿袋调用走同一套请求预算：计一次调用，在剩余时限内等待，请求取消时中止。
+     */
+    async fn analyze_cloud_bounded(&mut self, input: &str) -> Result<CloudPlan, String> {
+        if let Some(why) = self.take_call_budget(CLOUD_ANALYZE) {
+            self.log_event(format!("BUDGET {} {}", CLOUD_ANALYZE, why));
+            return Err(why);
+        }
+        let (limit, cancel) = self.call_bounds(CLOUD_ANALYZE);
+        match self.unlocked(budget::bounded(analyze_cloud(input.to_string()), limit, cancel)).await {
+            Ok(r) => r,
+            Err(CallCut::Timeout(ms)) => {
+                self.log_event(format!("TIMEOUT {} {}ms", CLOUD_ANALYZE, ms));
+                Err(format!("云端分析超时({}ms)", ms))
+            }
+            Err(CallCut::Cancelled) => {
+                self.log_event(format!("CANCEL {}", CLOUD_ANALYZE));
+                Err("请求已取消".into())
+            }
+        }
+    }
+
+    pub async fn call_pouch(&mut self, name: &str, input: &str) -> Result<String, String> {
+        self.guard(Layer::Pouch)?;
+        if let Some(early) = self.pouch_precheck(name, input) {
+            self.unguard();
+            return early;
+        }
+        if let Some(why) = self.take_call_budget(name) {
+            self.log_event(format!("BUDGET {} {}", name, why));
+            self.unguard();
+            return Err(why);
+        }
+
+        let (limit, cancel) = self.call_bounds(name);
+        let outcome = if name == "language" {
+            budget::bounded(self.process_language(input), limit, cancel).await.map(Ok)
+        } else {
+            let proposal = create_proposal(input).with_dialogue(self.dialogue.snapshot());
+            self.run_proposal(name, &proposal, limit, cancel).await
+        };
+
+        let result = self.finish_call(name, input, outcome);
+        self.unguard();
+        result
+    }
+
+    /*
+     * 执行一次尿袋提案，不限时。需要网络的尿袋（detach 给出远程调用）放开写锁后等待，
+     * 重新持锁后交回 finish_remote；其余尿袋持锁执行。
+     */
+    async fn run_pouch(&mut self, name: &str, validated: &ValidatedProposal) -> Result<PouchOutput, String> {
+        let Some(pouch) = self.pouches.get_mut(name) else {
+            return Err(format!("尿袋{}未安装", name));
+        };
+        match pouch.detach(validated) {
+            Some(call) => {
+                let result = self.unlocked(call).await;
+                self.finish_remote_call(name, result)
+            }
+            None => pouch.process_proposal(validated).await,
+        }
+    }
+
+    /* 同 run_pouch，在 limit 内等待，请求取消时中止 */
+    async fn run_pouch_bounded(
+        &mut self,
+        name: &str,
+        validated: &ValidatedProposal,
+        limit: std::time::Duration,
+        cancel: Option<CancelToken>,
+    ) -> Result<Result<PouchOutput, String>, CallCut> {
+        let Some(pouch) = self.pouches.get_mut(name) else {
+            return Ok(Err(format!("尿袋{}未安装", name)));
+        };
+        match pouch.detach(validated) {
+            Some(call) => {
+                let result = self.unlocked(budget::bounded(call, limit, cancel)).await?;
+                Ok(self.finish_remote_call(name, result))
+            }
+            None => budget::bounded(pouch.process_proposal(validated), limit, cancel).await,
+        }
+    }
+
+    async fn run_proposal(
+        &mut self,
+        name: &str,
+        proposal: &ProposalMessage,
+        limit: std::time::Duration,
+        cancel: Option<CancelToken>,
+    ) -> Result<Result<String, String>, CallCut> {
+        let validated = match self.pouches.get(name).map(|p| p.validator().validate(proposal)) {
+            Some(Ok(v)) => v,
+            Some(Err(e)) => return Ok(Err(format!("验证失败: {}", e))),
+            None => return Ok(Err(format!("尿袋{}未安装", name))),
+        };
+        let output = self.run_pouch_bounded(name, &validated, limit, cancel).await?;
+        Ok(Orchestrator::proposal_result(output))
+    }
+
+    /*
+     * 一个波次内的步骤互不依赖：本地尿袋持锁依次执行，需要网络的步骤（detach 给出远程调用）
+     * 收集起来，放开写锁后并发等待，重新持锁后统一收尾。
+     * language、休眠/缓存命中、未安装等走 call_pouch 原路径。
+     * 返回顺序与 jobs 一致：(结果, 耗时ms)。
+     */
+    async fn run_wave(&mut self, jobs: &[(usize, String, String)]) -> Vec<(Result<String, String>, u64)> {
+        let mut results: Vec<Option<(Result<String, String>, u64)>> = vec![None; jobs.len()];
+        if jobs.len() <= 1 {
+            for (slot, (_, pouch, input)) in jobs.iter().enumerate() {
+                let started = std::time::Instant::now();
+                let r = self.call_pouch(pouch, input).await;
+                results[slot] = Some((r, started.elapsed().as_millis() as u64));
+            }
+            return results.into_iter().flatten().collect();
+        }
+
+        let dialogue = self.dialogue.snapshot();
+        let mut remote = Vec::new();
+        for (slot, (_, pouch, input)) in jobs.iter().enumerate() {
+            if pouch == "language" || !self.pouches.contains_key(pouch.as_str()) {
+                let started = std::time::Instant::now();
+                let r = self.call_pouch(pouch, input).await;
+                results[slot] = Some((r, started.elapsed().as_millis() as u64));
+                continue;
+            }
+            if let Some(early) = self.pouch_precheck(pouch, input) {
+                results[slot] = Some((early, 0));
+                continue;
+            }
+            if let Some(why) = self.take_call_budget(pouch) {
+                self.log_event(format!("BUDGET {} {}", pouch, why));
+                results[slot] = Some((Err(why), 0));
+                continue;
+            }
+            let (limit, cancel) = self.call_bounds(pouch);
+            let started = std::time::Instant::now();
+            let proposal = create_proposal(input).with_dialogue(dialogue.clone());
+            let call = self
+                .pouches
+                .get(pouch.as_str())
+                .and_then(|p| p.validator().validate(&proposal).ok().and_then(|v| p.detach(&v)));
+            let outcome = match call {
+                Some(call) => {
+                    remote.push(async move {
+                        let r = budget::bounded(call, limit, cancel).await;
+                        (slot, r, started.elapsed().as_millis() as u64)
+                    });
+                    continue;
+                }
+                None => self.run_proposal(pouch, &proposal, limit, cancel).await,
+            };
+            let r = self.finish_call(pouch, input, outcome);
+            results[slot] = Some((r, started.elapsed().as_millis() as u64));
+        }
+
+        if !remote.is_empty() {
+            let finished = self.unlocked(futures_util::future::join_all(remote)).await;
+            for (slot, outcome, ms) in finished {
+                let (_, pouch, input) = &jobs[slot];
+                let outcome = outcome.map(|r| Orchestrator::proposal_result(self.finish_remote_call(pouch, r)));
+                let r = self.finish_call(pouch, input, outcome);
+                results[slot] = Some((r, ms));
+            }
+        }
+        results
+            .into_iter()
+            .map(|r| r.unwrap_or_else(|| (Err("并行分支未返回".into()), 0)))
+            .collect()
+    }
+
+    /*
+     * 步骤级兜底：首选原子失败、低置信度或输出像兜底话术时，按 alternatives 顺序重试。
+     *   - 每次被拒绝的尝试都以 success=false 写入 evolution_chain（call_pouch 报错时已由 after_pouch_call 写入），
+     *     score_pouch_from_entries 据此降低该尿袋的路由分
+     *   - 全部候选都被拒绝时：若有 Ok 输出则采用首个 Ok（与无备选时行为一致），否则返回首选的错误
+     */
+    async fn retry_step_alternatives(
+        &mut self,
+        step_index: usize,
+        step: &crate::atom::ExecutionStep,
+        input_data: &str,
+        first: Result<String, String>,
+    ) -> (String, Result<String, String>) {
+        let first_reason = match &first {
+            Ok(d) => self.output_rejection(&step.pouch, d),
+            Err(e) => Some(e.clone()),
+        };
+        let Some(first_reason) = first_reason else {
+            return (step.pouch.clone(), first);
+        };
+        if step.alternatives.is_empty() {
+            return (step.pouch.clone(), first);
+        }
+        if let Ok(ref d) = first {
+            self.record_chain_attempt(input_data, &step.pouch, &format!("{}: {}", first_reason, d), false, step_index);
+        }
+        let mut kept: Option<(String, String)> = first.as_ref().ok().map(|d| (step.pouch.clone(), d.clone()));
+        let mut from = step.pouch.clone();
+        let mut reason = first_reason;
+        for (n, alt) in step.alternatives.iter().enumerate() {
+            if self.budget_exhausted().is_some() {
+                break;
+            }
+            self.log_event(format!("PLAN_FALLBACK {} {} → {} ({})", step.atom_name, from, alt.pouch, reason));
+            let started = std::time::Instant::now();
+            let r = self.call_pouch(&alt.pouch, input_data).await;
+            let ms = started.elapsed().as_millis() as u64;
+            self.trace_step(step_index, n + 1, alt, input_data, &r, ms);
+            match r {
+                Ok(d) => match self.output_rejection(&alt.pouch, &d) {
+                    None => return (alt.pouch.clone(), Ok(d)),
+                    Some(why) => {
+                        self.record_chain_attempt(input_data, &alt.pouch, &format!("{}: {}", why, d), false, step_index);
+                        if kept.is_none() {
+                            kept = Some((alt.pouch.clone(), d));
+                        }
+                        reason = why;
+                    }
+                },
+                Err(e) => reason = e,
+            }
+            from = alt.pouch.clone();
+        }
+        match kept {
+            Some((pouch, d)) => (pouch, Ok(d)),
+            None => (step.pouch.clone(), first),
+        }
+    }
+
+    async fn execute_plan(
+        &mut self,
+        plan: &crate::atom::ExecutionPlan,
+        user_input: &str,
+    ) -> Result<String, String> {
+        let mut outputs: Vec<Option<String>> = vec![None; plan.steps.len()];
+        let mut chain_updated = false;
+        for wave in plan.waves() {
+            if let Some(why) = self.budget_exhausted() {
+                self.log_event(format!("PLAN_ABORT {}", why));
+                if chain_updated {
+                    self.save_state();
+                }
+                return Err(format!("执行计划中止: {}", why));
+            }
+            let jobs: Vec<(usize, String, String)> = wave
+                .iter()
+                .filter_map(|i| plan.steps.get(*i).map(|step| (*i, step)))
+                .map(|(i, step)| (i, step.pouch.clone(), Orchestrator::step_input(&step.input_from, user_input, &outputs)))
+                .collect();
+            if jobs.len() > 1 {
+                self.log_event(format!("PLAN_PARALLEL {} 步", jobs.len()));
+            }
+            let results = self.run_wave(&jobs).await;
+            for ((step_index, _, input_data), (result, latency_ms)) in jobs.into_iter().zip(results) {
+                let step = &plan.steps[step_index];
+                let primary = StepCandidate { atom_name: step.atom_name.clone(), pouch: step.pouch.clone() };
+                self.trace_step(step_index, 0, &primary, &input_data, &result, latency_ms);
+                let (used_pouch, result) = self.retry_step_alternatives(step_index, step, &input_data, result).await;
+                if used_pouch != step.pouch {
+                    chain_updated = true;
+                }
+                match result {
+                    Ok(data) => {
+                        self.log_event(format!("PLAN_STEP {} → ok", step.atom_name));
+                        let in_trunc: String = input_data.chars().take(80).collect();
+                        let out_trunc: String = data.chars().take(80).collect();
+                        self.log_event(format!("PouchSuccess: pouch={}, input={}, output={}", used_pouch, in_trunc, out_trunc));
+                        self.emit_progress("STEP_OUTPUT", format!("{}: {}", used_pouch, data.chars().take(200).collect::<String>()));
+                        /*
+                         * 两条演化系统区别：
+                         *   - evolution.json (record_evolution)：聚合计数，用于 promotion 阈值判断
+                         *   - evolution_chain.json (EvolutionEntry)：时间顺序审计轨迹，用于路由偏好和未来分析
+                         *   - plan 成功步会同时更新两者，但语义独立
+                         */
+                        self.record_chain_attempt(&in_trunc, &used_pouch, &out_trunc, true, step_index);
+                        chain_updated = true;
+                        outputs[step_index] = Some(data);
+                    }
+                    Err(e) => {
+                        self.log_event(format!("PLAN_STEP {} → {}", step.atom_name, e));
+                        if chain_updated {
+                            self.save_state();
+                        }
+                        return Err(format!("执行计划步骤{}失败: {}", step.atom_name, e));
+                    }
+                }
+            }
+        }
+        if chain_updated {
+            self.save_state();
+        }
+        let sinks: Vec<String> = plan
+            .sinks()
+            .iter()
+            .filter_map(|i| outputs.get(*i).cloned().flatten())
+            .collect();
+        Ok(sinks.join("\n"))
+    }
+
+    /*
+     * lang 为整条命令输入的语言：帮助、状态、尿袋列表等系统回复按它选中文或英文。
+     */
+    async fn handle_cmd(&mut self, cmd: SystemCmd, lang: Lang) -> Result<String, String> {
+        match cmd {
+            SystemCmd::InstallPouch(name) => self.install(&name),
+            SystemCmd::UninstallPouch(name) => self.uninstall(&name),
+            SystemCmd::SleepPouch(name) => self.sleep_pouch(&name),
+            SystemCmd::WakePouch(name) => self.wake_pouch(&name),
+            SystemCmd::ConfigShow => self.config_show(),
+            SystemCmd::ConfigSet(key, value) => self.config_set(&key, &value),
+            SystemCmd::ListPouches => Ok(self.list(lang)),
+            SystemCmd::Status => Ok(self.status(lang)),
+            SystemCmd::Help => Ok(Orchestrator::help(lang)),
+            SystemCmd::Teach(trigger, response) => {
+                self.language.teach(&trigger, &response)?;
+                self.save_state();
+                match Lang::detect(&trigger) {
+                    Lang::Zh => Ok(format!("学会了:「{}」→「{}」", trigger, response)),
+                    Lang::En => Ok(format!("Learned: \"{}\" -> \"{}\"", trigger, response)),
+                }
+            }
+            SystemCmd::TeachVariant(trigger, response) => {
+                let id = self.language.teach_variant(&trigger, &response)?;
+                self.save_state();
+                match lang {
+                    Lang::Zh => Ok(format!("已为模式 #{} 补充回复:「{}」", id, response)),
+                    Lang::En => Ok(format!("Added a response to pattern #{}: \"{}\"", id, response)),
+                }
+            }
+            SystemCmd::RunPipeline(stages, data) => {
+                let stages_ref: Vec<&str> = stages.iter().map(|s| s.as_str()).collect();
+                self.run_pipeline(&stages_ref, data).await
+            }
+            SystemCmd::SelfTest => self.selftest().await,
+            SystemCmd::ImportPatterns(arg) => {
+                let (path, opts) = ImportOptions::split_command(&arg)?;
+                self.import_patterns_from_file(&path, opts).await
+            }
+            SystemCmd::ExportPatterns => Ok(self.export_patterns_display()),
+            SystemCmd::ExportDataset(arg) => {
+                let (opts, path) = ExportOptions::parse(&arg)?;
+                self.export_dataset_to_file(&opts, path)
+            }
+            SystemCmd::Rollback => self.rollback(),
+            SystemCmd::Train => self.trigger_train().await,
+            SystemCmd::Explain(name) => Ok(self.explain_pouch(&name)),
+            SystemCmd::EvolutionStatus => Ok(self.evolution_status()),
+            SystemCmd::Capabilities => Ok(self.registry.summary()),
+            SystemCmd::PatternInfo(id) => {
+                let id = parse_pattern_id(&id)?;
+                self.language.describe_pattern(id).ok_or_else(|| format!("没有编号为 {} 的模式", id))
+            }
+            SystemCmd::ListNamespaces => Ok(self.list_namespaces()),
+            SystemCmd::CreateNamespace(name) => self.create_namespace(&name, None),
+            SystemCmd::CopyNamespace(from, to) => self.create_namespace(&to, Some(&from)),
+            SystemCmd::DropNamespace(name) => self.drop_namespace(&name),
+            SystemCmd::UseNamespace(name) => {
+                if !self.namespaces.contains(&name) {
+                    return Err(format!("命名空间不存在: {}", name));
+                }
+                self.session_namespace = Some(name.clone()).filter(|n| n != BASE_NAMESPACE);
+                Ok(format!("本会话下一句起使用命名空间 {}", name))
+            }
+            SystemCmd::BlockResponse(spec) => {
+                let id = self.add_block_rule(&spec)?;
+                Ok(format!("已添加屏蔽规则 #{}", id))
+            }
+            SystemCmd::ForbidAnswer(trigger, response) => {
+                let id = self.add_negative_pattern(&trigger, &response)?;
+                Ok(format!("已添加否定模式 #{}", id))
+            }
+            SystemCmd::ListGuardRules => Ok(self.language.guard().render_rules()),
+            SystemCmd::RemoveGuardRule(id) => {
+                let id = parse_guard_id(&id)?;
+                if self.remove_guard_rule(id) {
+                    Ok(format!("已删除屏蔽规则 #{}", id))
+                } else {
+                    Err(format!("没有编号为 {} 的屏蔽规则", id))
+                }
+            }
+            SystemCmd::ListQuarantine => Ok(self.language.guard().render_quarantine()),
+            SystemCmd::ReleaseQuarantine(id) => {
+                let pattern = self.release_quarantined(parse_guard_id(&id)?)?;
+                Ok(format!("已放行并学习为模式 #{}", pattern))
+            }
+            SystemCmd::DiscardQuarantine(id) => {
+                let id = parse_guard_id(&id)?;
+                if self.discard_quarantined(id) {
+                    Ok(format!("已丢弃隔离条目 #{}", id))
+                } else {
+                    Err(format!("隔离区没有编号 {}", id))
+                }
+            }
+            SystemCmd::ListPatterns => Ok(self.list_patterns(None, &PatternQuery::default())?.render()),
+            SystemCmd::SearchPatterns(cond) => Ok(self.list_patterns(None, &PatternQuery::parse(&cond)?)?.render()),
+            SystemCmd::EditPattern(id, spec) => {
+                let id = parse_pattern_id(&id)?;
+                self.edit_pattern(None, id, &PatternEdit::parse(&spec)?)?;
+                self.language.describe_pattern(id).ok_or_else(|| format!("没有编号为 {} 的模式", id))
+            }
+            SystemCmd::DeletePattern(id) => {
+                let id = parse_pattern_id(&id)?;
+                if self.delete_pattern(None, id)? {
+                    Ok(format!("已删除模式 #{}", id))
+                } else {
+                    Err(format!("没有编号为 {} 的模式", id))
+                }
+            }
+            SystemCmd::DeletePatterns(cond) => {
+                let n = self.delete_patterns(None, &PatternQuery::parse(&cond)?)?;
+                Ok(format!("已删除 {} 条模式（可用 回滚 恢复）", n))
+            }
+            SystemCmd::ClearContext => {
+                self.language.clear_context();
+                self.dialogue.clear();
+                Ok("对话上下文已清空".into())
+            }
+        }
+    }
+
+    async fn run_pipeline(
+        &mut self,
+        stages: &[&str],
+        initial_data: String,
+    ) -> Result<String, String> {
+        if stages.is_empty() {
+            return Err("Pipeline为空".into());
+        }
+        if stages.len() > bedrock::MAX_PIPELINE_STAGES {
+            return Err("Pipeline阶段过多".into());
+        }
+        let mut current_data = initial_data;
+        let mut trace = Vec::new();
+
+        for (i, &stage) in stages.iter().enumerate() {
+            let stage_lower = stage.to_lowercase();
+            if stage_lower == "language" {
+                if i > 0 {
+                    return Err("language不能在Pipeline中间".into());
+                }
+            } else if let Some(pouch) = self.pouches.get(&stage_lower) {
+                if i > 0 && !pouch.can_call_others() {
+                    return Err(format!("{}不能接收Pipeline数据", stage));
+                }
+                let proposal = create_proposal(&current_data);
+                let validated = pouch
+                    .validator()
+                    .validate(&proposal)
+                    .map_err(|e| format!("阶段{}验证失败: {}", stage, e))?;
+                let output = self
+                    .run_pouch(&stage_lower, &validated)
+                    .await
+                    .map_err(|e| format!("阶段{}处理失败: {}", stage, e))?;
+                current_data = output.data;
+            } else {
+                return Err(format!("尿袋{}未安装", stage));
+            }
+            trace.push(format!("{}→", stage));
+        }
+
+        trace.push("完成".into());
+        let pipeline_desc = trace.join("");
+        self.log_event(format!("PIPE {}", pipeline_desc));
+        Ok(format!("Pipeline: {}\n结果: {}", pipeline_desc, current_data))
+    }
+
+    /*
+     * 导入模式文件或 URL；dry-run 只报告统计，不备份也不落盘。
+     */
+    pub async fn import_patterns_from_file(&mut self, path: &str, opts: ImportOptions) -> Result<String, String> {
+        if path.is_empty() {
+            return Err("路径为空".into());
+        }
+        if !opts.dry_run {
+            self.backup_language()?;
+        }
+        let content = if path.starts_with("http://") || path.starts_with("https://") {
+            self.unlocked(fetch_import(path.to_string())).await?
+        } else {
+            std::fs::read_to_string(path).map_err(|e| format!("读取失败: {}", e))?
+        };
+        let is_jsonl = path.ends_with(".jsonl") || path.contains(".jsonl");
+        let summary = self.language.import_from_content(&content, is_jsonl, path, &opts)?;
+        self.log_event(format!(
+            "IMPORT {:?}/{:?} dry_run={} added={} updated={} skipped={} invalid={}",
+            opts.mode, opts.conflict, opts.dry_run, summary.added, summary.updated, summary.skipped, summary.invalid
+        ));
+        if !opts.dry_run {
+            self.save_state();
+        }
+        Ok(summary.render())
+    }
+
+    async fn discover_gaps_from_misses(&mut self) {
+        if self.pouches.len() >= crate::frozen::bedrock::MAX_POUCHES { return; }
+        let clusters = self.language.miss_token_clusters(3);
+        let maturities: Vec<(String, f64)> = self.pouches.keys()
+            .map(|n| (n.clone(), self.pouch_maturity(n)))
+            .collect();
+        let teachers: Vec<String> = {
+            let mut t: Vec<_> = maturities.iter()
+                .filter(|(n, m)| *m >= MATURITY_HUNGRY && n != "language")
+                .cloned()
+                .collect();
+            t.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+            t.into_iter().map(|(n, _)| n).take(5).collect()
+        };
+
+        for (token, count, sample_inputs) in clusters.iter().take(2) {
+            let target_domain: Option<String> =
+                if let Some(domain) = Orchestrator::classify_domain(&sample_inputs.join(" ")) {
+                    if !self.pouches.contains_key(domain) {
+                        Some(domain.to_string())
+                    } else { None }
+                } else if token.chars().count() >= 2 && *count >= 4 {
+                    if !self.pouches.contains_key(token.as_str()) {
+                        Some(token.clone())
+                    } else { None }
+                } else { None };
+
+            let Some(ref domain) = target_domain else { continue };
+            if !self.auto_ensure_pouch(domain) { continue; }
+
+            for input in sample_inputs {
+                self.language.learn_routing(input, domain);
+            }
+
+            let mut train_pairs: Vec<(Vec<String>, String, f64)> = Vec::new();
+            let mut trained = 0u32;
+            self.call_stack.clear();
+            let _ = self.guard(Layer::Orchestrator);
+
+            for input in sample_inputs {
+                let tokens = self.language.tokenize(input);
+                let mut responses: Vec<String> = Vec::new();
+                for teacher_name in &teachers {
+                    let result = self.call_pouch(teacher_name, input).await;
+                    if let Ok(ref output) = result {
+                        if output.len() > 15 && !self.language.is_fallback_response(output) {
+                            responses.push(output.clone());
+                        }
+                    }
+                    if responses.len() >= 3 { break; }
+                }
+                if responses.is_empty() {
+                    let lang_result = self.call_pouch("language", input).await;
+                    if let Ok(ref lang_out) = lang_result {
+                        if lang_out.len() > 20 && !self.language.is_fallback_response(lang_out) {
+                            train_pairs.push((tokens.clone(), lang_out.clone(), 0.6));
+                            trained += 1;
+                        }
+                    }
+                } else {
+                    let best = responses.iter()
+                        .max_by_key(|r| r.len())
+                        .cloned()
+                        .unwrap_or_default();
+                    let weight = if responses.len() >= 2 { 1.2 } else { 0.6 };
+                    train_pairs.push((tokens.clone(), best.clone(), weight));
+                    self.language.absorb(input, &best, weight * 0.8, "train");
+                    trained += 1;
+                }
+            }
+
+            self.unguard();
+
+            if let Some(pouch) = self.pouches.get_mut(domain.as_str()) {
+                pouch.sync_patterns(&train_pairs);
+            }
+            self.log_event(format!(
+                "GAP_FILL miss频:{} 词:{} → {} 训练:{}/{}",
+                count, token, domain, trained, sample_inputs.len()
+            ));
+        }
+    }
+
+    async fn trigger_train(&mut self) -> Result<String, String> {
+        let trainer_name = self.pouches.keys()
+            .find(|k| k.contains("cloud_trainer") || k.contains("训练"))
+            .cloned();
+        let trainer_name = trainer_name.ok_or("未安装云训练尿袋")?;
+        let pouch = self.pouches.get(&trainer_name).ok_or("云训练尿袋不可用")?;
+        let proposal = create_proposal("train");
+        let validated = pouch.validator().validate(&proposal)
+            .map_err(|e| format!("验证失败: {}", e))?;
+        let output = self.run_pouch(&trainer_name, &validated)
+            .await
+            .map_err(|e| format!("训练失败: {}", e))?;
+        Ok(output.data)
+    }
+
+    async fn self_optimize(&mut self) -> Result<String, String> {
+        let mut report = Vec::new();
+        report.push("=== 自我优化启动 ===".to_string());
+
+        let core_pouches = ["benchmark", "defect_scanner", "capability_comparer", "code_analyzer", "knowledge_retriever"];
+        for name in &core_pouches {
+            if !self.pouches.contains_key(*name) {
+                match self.install(name) {
+                    Ok(msg) => {
+                        report.push(format!("自动安装: {}", msg));
+                        self.log_event(format!("SELF_OPT auto_install {}", name));
+                    }
+                    Err(e) => report.push(format!("安装{}失败: {}", name, e)),
+                }
+            }
+        }
+
+        let metrics_json = self.collect_system_metrics_json();
+
+        if let Some(validated) = self.validated_for("benchmark", &create_proposal(&metrics_json)) {
+            if let Ok(output) = self.run_pouch("benchmark", &validated).await {
+                report.push(output.data);
+            }
+        }
+
+        let defect_json = self.collect_system_metrics_json();
+        if let Some(validated) = self.validated_for("defect_scanner", &create_proposal(&defect_json)) {
+            if let Ok(output) = self.run_pouch("defect_scanner", &validated).await {
+                report.push(output.data.clone());
+                let follow_ups = self
+                    .pouches
+                    .get("defect_scanner")
+                    .map(|ds| ds.recommended_follow_ups(&output.data))
+                    .unwrap_or_default();
+                for pname in follow_ups {
+                    if !self.pouches.contains_key(&pname) {
+                        if let Ok(msg) = self.install(&pname) {
+                            report.push(format!("自动补齐: {}", msg));
+                            self.log_event(format!("SELF_OPT remediate {}", pname));
+                        }
+                    }
+                }
+            }
+        }
+
+        let pouch_count = self.pouches.len() + 1;
+        let atom_count = self.registry.count();
+        let total_mem = self.total_memory_count();
+        report.push(format!(
+            "优化后: 尿袋:{} 原子:{} 记忆:{}",
+            pouch_count, atom_count, total_mem
+        ));
+
+        self.log_event("SELF_OPT complete".into());
+        self.save_state();
+        Ok(report.join("\n"))
+    }
+
+    async fn evolve(&mut self) -> Result<String, String> {
+        let mut report = Vec::new();
+        report.push("=== 进化对标启动 ===".to_string());
+
+        let core_pouches = ["benchmark", "defect_scanner", "capability_comparer"];
+        for name in &core_pouches {
+            if !self.pouches.contains_key(*name) {
+                let _ = self.install(name);
+            }
+        }
+
+        let caps_json = self.collect_system_metrics_json();
+        let mut gaps: Vec<(String, f64)> = Vec::new();
+
+        if let Some(validated) = self.validated_for("capability_comparer", &create_proposal(&caps_json)) {
+            if let Ok(output) = self.run_pouch("capability_comparer", &validated).await {
+                report.push(output.data.clone());
+                gaps = self
+                    .pouches
+                    .get("capability_comparer")
+                    .map(|cc| cc.evolution_gaps_from_output(&output.data))
+                    .unwrap_or_default();
+            }
+        }
+
+        if gaps.is_empty() {
+            report.push("未发现显著能力差距（LOGOS >= 竞品80%）".to_string());
+        } else {
+            report.push(format!("发现 {} 个能力差距:", gaps.len()));
+            let remediation_map: Vec<(&str, &[&str])> = vec![
+                ("语言理解", &["context", "memory"]),
+                ("逻辑推理", &["reasoning"]),
+                ("知识检索", &["knowledge_retriever"]),
+                ("创意生成", &["creative"]),
+                ("代码分析", &["code_analyzer", "programming"]),
+            ];
+            for (category, deficit) in &gaps {
+                report.push(format!("  {} 差距: {:.0}%", category, deficit));
+                for (cat_key, pouches_needed) in &remediation_map {
+                    if category.contains(cat_key) {
+                        for pname in *pouches_needed {
+                            if !self.pouches.contains_key(*pname) {
+                                match self.install(pname) {
+                                    Ok(msg) => {
+                                        report.push(format!("  → 补齐: {}", msg));
+                                        self.log_event(format!("EVOLVE auto_install {}", pname));
+                                    }
+                                    Err(e) => report.push(format!("  → 安装{}失败: {}", pname, e)),
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        let pouch_count = self.pouches.len() + 1;
+        let atom_count = self.registry.count();
+        report.push(format!(
+            "进化后: 尿袋:{} 原子:{}",
+            pouch_count, atom_count
+        ));
+
+        self.log_event("EVOLVE complete".into());
+        self.save_state();
+        Ok(report.join("\n"))
+    }
+
+    pub async fn sync_with_cloud(&mut self) {
+        let req = self.cloud_sync_request();
+        let res = self.unlocked(Orchestrator::fetch_cloud_sync(req)).await;
+        self.apply_cloud_sync(res);
+    }
+
+    pub async fn autonomous_learning_cycle(&mut self) {
+        let mut cycle = self.begin_learning_cycle();
+        while self.learning_step(&mut cycle).await {}
+        if cycle.cloud_sync_due() {
+            self.sync_with_cloud().await;
+        }
+    }
+
+    pub async fn learning_step(&mut self, c: &mut LearningCycle) -> bool {
+        let Some(task) = c.queue.pop_front() else {
+            return false;
+        };
+        self.call_stack.clear();
+        match task {
+            LearningTask::FeedMiss(miss_input) => {
+                let _ = self.guard(Layer::Orchestrator);
+                let lang_result = self.call_pouch("language", &miss_input).await;
                 if let Ok(ref lang_out) = lang_result {
                     if lang_out.len() > 20 && !self.language.is_fallback_response(lang_out) {
-                        let tokens = self.language.tokenize(intent);
-                        lang_outputs.push((tokens, lang_out.clone()));
+                        let tokens = self.language.tokenize(&miss_input);
+                        let broadcast = vec![(tokens, lang_out.clone(), 1.3)];
+                        for (_, pouch) in self.pouches.iter_mut() {
+                            pouch.sync_patterns(&broadcast);
+                        }
+                        self.language.absorb(&miss_input, lang_out, 1.0, "language");
+                    }
+                }
+                for name in c.hungry.clone() {
+                    if name != "language" {
+                        let pouch_result = self.call_pouch(&name, &miss_input).await;
+                        if let Ok(ref pouch_out) = pouch_result {
+                            if pouch_out.len() > 15 && !self.language.is_fallback_response(pouch_out) {
+                                self.language.absorb(&miss_input, pouch_out, 0.8, &name);
+                            }
+                        }
                     }
                 }
+                c.fed_count += 1;
+                self.unguard();
             }
-
-            if !lang_outputs.is_empty() {
-                let batch: Vec<(Vec<String>, String, f64)> = lang_outputs.iter()
-                    .map(|(t, o)| (t.clone(), o.clone(), 1.3))
-                    .collect();
+            LearningTask::FeedSeed(s_intent, s_response) => {
+                let tokens = self.language.tokenize(&s_intent);
+                let broadcast = vec![(tokens, s_response.clone(), 1.5)];
                 for (_, pouch) in self.pouches.iter_mut() {
-                    pouch.sync_patterns(&batch);
+                    pouch.sync_patterns(&broadcast);
+                }
+                if let Err(e) = self.language.teach_as(&s_intent, &s_response, Origin::Seed) {
+                    self.log_event(format!("SEED_REJECTED {}", e));
                 }
+                c.fed_count += 1;
+                self.log_event(format!("SEED_FED {}", s_intent.chars().take(10).collect::<String>()));
             }
-
-            let mut teacher_outputs: Vec<(String, String, String)> = Vec::new();
-            let a2_hops = self.config.chain.a2_chain_hops.clamp(2, 12);
-            let mut chain_intents: Vec<String> = intents.clone();
-            for hop in 0..a2_hops {
-                let mut hop_outputs: Vec<(String, String, String)> = Vec::new();
-                for teacher in &teachers {
-                    for intent in &chain_intents {
-                        let result = self.call_pouch(teacher, intent).await;
-                        if let Ok(ref output) = result {
-                            if output.len() > 10 && !self.language.is_fallback_response(output) {
-                                hop_outputs.push((teacher.clone(), intent.clone(), output.clone()));
-                            }
+            LearningTask::FeedHungry(name, intent) => {
+                let _ = self.guard(Layer::Orchestrator);
+                let lang_result = self.call_pouch("language", &intent).await;
+                if let Ok(ref lang_out) = lang_result {
+                    if lang_out.len() > 20 && !self.language.is_fallback_response(lang_out) {
+                        let tokens = self.language.tokenize(&intent);
+                        let broadcast = vec![(tokens, lang_out.clone(), 1.3)];
+                        for (_, pouch) in self.pouches.iter_mut() {
+                            pouch.sync_patterns(&broadcast);
                         }
                     }
                 }
-                for o in &hop_outputs {
-                    teacher_outputs.push(o.clone());
+                let _ = self.call_pouch(&name, &intent).await;
+                c.fed_count += 1;
+                self.unguard();
+            }
+            LearningTask::DiscoverGaps => {
+                self.discover_gaps_from_misses().await;
+            }
+            LearningTask::FeedSummary => {
+                let gained_mem = self.total_memory_count().saturating_sub(c.before_mem);
+                let gained_evo = self.evolution.len().saturating_sub(c.before_evo);
+                self.learning.external_fed += c.fed_count;
+                self.learning.external_absorbed += (gained_mem + gained_evo) as u64;
+
+                if self.learning.external_fed > 8 {
+                    let fed = self.learning.external_fed.max(1) as f64;
+                    let gap = self.learning.external_fed.saturating_sub(self.learning.external_absorbed) as f64;
+                    self.learning.saturation = (1.0 - gap / fed).clamp(0.0, 1.0);
                 }
-                if hop + 1 >= a2_hops {
-                    break;
+
+                let saturation = self.learning.saturation * 100.0;
+                self.log_event(format!(
+                    "LEARN_{} 投喂:{} 缺口:{} 记忆+{} 演化+{} 饱和:{:.0}%",
+                    if c.miss_count == 0 { "EXT" } else { "MISS" },
+                    c.fed_count, c.miss_count, gained_mem, gained_evo, saturation
+                ));
+            }
+            LearningTask::SynapseLang(intent) => {
+                let _ = self.guard(Layer::Orchestrator);
+                let lang_result = self.call_pouch("language", &intent).await;
+                if let Ok(ref lang_out) = lang_result {
+                    if lang_out.len() > 20 && !self.language.is_fallback_response(lang_out) {
+                        let tokens = self.language.tokenize(&intent);
+                        c.lang_outputs.push((tokens, lang_out.clone()));
+                    }
                 }
-                chain_intents = hop_outputs
-                    .iter()
-                    .map(|(_, _, o)| {
-                        let s = o.trim();
-                        if s.len() > A2_NEXT_INTENT_MAX {
-                            s.chars().take(A2_NEXT_INTENT_MAX).collect::<String>()
-                        } else {
-                            s.to_string()
-                        }
-                    })
-                    .filter(|s| s.len() > 10 && !self.language.is_fallback_response(s))
-                    .collect::<Vec<_>>();
-                if chain_intents.is_empty() {
-                    break;
+                self.unguard();
+            }
+            LearningTask::SynapseBroadcast => {
+                if !c.lang_outputs.is_empty() {
+                    let batch: Vec<(Vec<String>, String, f64)> = c.lang_outputs.iter()
+                        .map(|(t, o)| (t.clone(), o.clone(), 1.3))
+                        .collect();
+                    for (_, pouch) in self.pouches.iter_mut() {
+                        pouch.sync_patterns(&batch);
+                    }
                 }
             }
-
-            if !teacher_outputs.is_empty() {
-                let teach_batch: Vec<(Vec<String>, String, f64)> = teacher_outputs.iter()
-                    .map(|(_, intent, output)| {
-                        let tokens = self.language.tokenize(intent);
-                        (tokens, output.clone(), 1.2)
-                    })
-                    .collect();
-                for student in &students {
-                    if let Some(pouch) = self.pouches.get_mut(student.as_str()) {
-                        pouch.sync_patterns(&teach_batch);
+            LearningTask::SynapseHop(intent) => {
+                let _ = self.guard(Layer::Orchestrator);
+                for teacher in c.teachers.clone() {
+                    let result = self.call_pouch(&teacher, &intent).await;
+                    if let Ok(ref output) = result {
+                        if output.len() > 10 && !self.language.is_fallback_response(output) {
+                            c.hop_outputs.push((teacher.clone(), intent.clone(), output.clone()));
+                        }
                     }
                 }
-                for (_, intent, output) in &teacher_outputs {
-                    self.language.absorb(intent, output, 0.8);
+                self.unguard();
+            }
+            LearningTask::SynapseHopEnd => {
+                let hop_outputs = std::mem::take(&mut c.hop_outputs);
+                c.teacher_outputs.extend(hop_outputs.iter().cloned());
+                c.hop += 1;
+                if c.hop < c.a2_hops {
+                    let chain_intents = hop_outputs
+                        .iter()
+                        .map(|(_, _, o)| {
+                            let s = o.trim();
+                            if s.len() > A2_NEXT_INTENT_MAX {
+                                s.chars().take(A2_NEXT_INTENT_MAX).collect::<String>()
+                            } else {
+                                s.to_string()
+                            }
+                        })
+                        .filter(|s| s.len() > 10 && !self.language.is_fallback_response(s))
+                        .collect::<Vec<_>>();
+                    if !chain_intents.is_empty() {
+                        c.queue.push_front(LearningTask::SynapseHopEnd);
+                        for intent in chain_intents.into_iter().rev() {
+                            c.queue.push_front(LearningTask::SynapseHop(intent));
+                        }
+                    }
                 }
-                cross_count = teacher_outputs.len() as u64;
             }
+            LearningTask::SynapseTeach => {
+                let mut cross_count: u64 = 0;
+                if !c.teacher_outputs.is_empty() {
+                    let teach_batch: Vec<(Vec<String>, String, f64)> = c.teacher_outputs.iter()
+                        .map(|(_, intent, output)| {
+                            let tokens = self.language.tokenize(intent);
+                            (tokens, output.clone(), 1.2)
+                        })
+                        .collect();
+                    for student in &c.students {
+                        if let Some(pouch) = self.pouches.get_mut(student.as_str()) {
+                            pouch.sync_patterns(&teach_batch);
+                        }
+                    }
+                    for (teacher, intent, output) in &c.teacher_outputs {
+                        self.language.absorb(intent, output, 0.8, teacher);
+                    }
+                    cross_count = c.teacher_outputs.len() as u64;
+                }
 
-            self.unguard();
+                let gained_mem = self.total_memory_count().saturating_sub(c.before_mem);
+                let gained_evo = self.evolution.len().saturating_sub(c.before_evo);
+                self.learning.cross_fed += cross_count;
+                self.learning.cross_absorbed += (gained_mem + gained_evo) as u64;
 
-            let gained_mem = self.total_memory_count().saturating_sub(before_mem);
-            let gained_evo = self.evolution.len().saturating_sub(before_evo);
-            self.learning.cross_fed += cross_count;
-            self.learning.cross_absorbed += (gained_mem + gained_evo) as u64;
+                if cross_count > 0 {
+                    self.learning.saturation = (self.learning.saturation - 0.08).max(0.0);
+                }
 
-            if cross_count > 0 {
-                self.learning.saturation = (self.learning.saturation - 0.08).max(0.0);
+                let saturation = self.learning.saturation * 100.0;
+                self.log_event(format!(
+                    "LEARN_SYN 突触:{} 记忆+{} 演化+{} 饱和:{:.0}%",
+                    cross_count, gained_mem, gained_evo, saturation
+                ));
             }
-
-            self.log_event(format!(
-                "LEARN_SYN 突触:{} 记忆+{} 演化+{} 饱和:{:.0}%",
-                cross_count, gained_mem, gained_evo, self.learning.saturation * 100.0
-            ));
-        }
-
-        if cycle > 0 && cycle.is_multiple_of(20) {
-            match self.self_optimize().await {
+            LearningTask::SelfOptimize => match self.self_optimize().await {
                 Ok(msg) => self.log_event(format!("SELF_OPT_AUTO {}", msg.lines().count())),
                 Err(e) => self.log_event(format!("SELF_OPT_AUTO_ERR {}", e)),
-            }
-        }
-        if cycle > 0 && cycle.is_multiple_of(31) {
-            match self.evolve().await {
+            },
+            LearningTask::Evolve => match self.evolve().await {
                 Ok(msg) => self.log_event(format!("EVOLVE_AUTO {}", msg.lines().count())),
                 Err(e) => self.log_event(format!("EVOLVE_AUTO_ERR {}", e)),
+            },
+            LearningTask::Persist => {
+                self.maybe_adjust_baseline();
+                self.save_state();
             }
         }
-
-        if cycle > 0 && cycle.is_multiple_of(7) {
-            self.sync_with_cloud().await;
-        }
-
-        self.maybe_adjust_baseline();
-        self.save_state();
-    }
-
-    fn lookup_remote_spec(&self, name: &str) -> Option<crate::remote_pouch::RemotePouchSpec> {
-        let spec_path = std::path::Path::new(&self.data_dir).join("remote_pouches.json");
-        if !spec_path.exists() {
-            return None;
-        }
-        let content = std::fs::read_to_string(&spec_path).ok()?;
-        let specs: Vec<crate::remote_pouch::RemotePouchSpec> =
-            serde_json::from_str(&content).ok()?;
-        specs.into_iter().find(|s| s.name == name)
+        !c.queue.is_empty()
     }
 }
 
@@ -2539,6 +4216,231 @@ mod tests {
         assert!(chain_after > chain_before, "evolution_chain must grow after successful plan run");
     }
 
+    #[tokio::test]
+    async fn test_sessions_keep_separate_context() {
+        let dir = "/tmp/logos_test_sessions";
+        let _ = std::fs::remove_dir_all(dir);
+        let mut orch = Orchestrator::new(dir);
+        assert!(orch.execute_in_session("a", "你好").await.is_ok());
+        let (out_b, _) = match orch.execute_in_session("b", "好").await {
+            Ok(x) => x,
+            Err((msg, _)) => panic!("session b should not error: {}", msg),
+        };
+        assert!(out_b.contains("无上次对话记录"), "session b must not see a's context: {}", out_b);
+        let (out_a, _) = match orch.execute_in_session("a", "好").await {
+            Ok(x) => x,
+            Err((msg, _)) => panic!("session a should not error: {}", msg),
+        };
+        assert!(out_a.contains("正向反馈"), "session a must keep its own context: {}", out_a);
+        assert_eq!(orch.session_count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_session_saves_are_throttled_until_flush() {
+        let dir = "/tmp/logos_test_session_flush";
+        let _ = std::fs::remove_dir_all(dir);
+        let mut orch = Orchestrator::new(dir);
+        let path = format!("{}/sessions.json", dir);
+        assert!(orch.execute_in_session("first", "你好").await.is_ok());
+        assert!(std::fs::read_to_string(&path).unwrap_or_default().contains("first"));
+        assert!(orch.execute_in_session("second", "你好").await.is_ok());
+        assert!(!std::fs::read_to_string(&path).unwrap_or_default().contains("second"));
+        orch.flush_sessions();
+        assert!(std::fs::read_to_string(&path).unwrap_or_default().contains("second"));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn test_followup_pronoun_rewritten_per_session() {
+        let dir = "/tmp/logos_test_dialogue";
+        let _ = std::fs::remove_dir_all(dir);
+        let mut orch = Orchestrator::new(dir);
+        assert!(orch.language.teach("铁的熔点是多少", "约1538℃").is_ok());
+        assert!(orch.execute_in_session("a", "铁是什么金属").await.is_ok());
+        orch.begin_trace("那它的熔点是多少？");
+        let (out, _) = match orch.execute_in_session("a", "那它的熔点是多少？").await {
+            Ok(x) => x,
+            Err((msg, _)) => panic!("follow-up should not error: {}", msg),
+        };
+        let rewrite = orch.take_trace().and_then(|t| t.dialogue).unwrap_or_default();
+        assert!(rewrite.contains("那铁的熔点是多少"), "{}", rewrite);
+        assert!(out.contains("1538"), "{}", out);
+
+        assert!(orch.execute_in_session("a", "那它的硬度是多少？").await.is_ok());
+        assert!(!orch.language.learning_paused());
+        assert!(
+            orch.language.pending_misses(100).iter().all(|m| !m.contains("铁的硬度")),
+            "rewritten input must not be learned"
+        );
+
+        orch.begin_trace("那它的熔点是多少？");
+        assert!(orch.execute_in_session("b", "那它的熔点是多少？").await.is_ok());
+        assert!(orch.take_trace().is_some_and(|t| t.dialogue.is_none()), "session b has no topic");
+    }
+
+    #[tokio::test]
+    async fn test_system_replies_follow_command_language() {
+        let dir = "/tmp/logos_test_reply_language";
+        let _ = std::fs::remove_dir_all(dir);
+        let mut orch = Orchestrator::new(dir);
+        let ask = |r: Result<(String, String), (String, String)>| match r {
+            Ok((out, _)) => out,
+            Err((msg, _)) => panic!("{}", msg),
+        };
+        assert!(ask(orch.execute_in_session("a", "help").await).starts_with("LOGOS commands:"));
+        assert!(ask(orch.execute_in_session("a", "帮助").await).starts_with("LOGOS命令:"));
+        assert!(ask(orch.execute_in_session("a", "status").await).contains("Patterns: "));
+        assert!(ask(orch.execute_in_session("a", "list").await).starts_with("Pouches:"));
+        let usage = ask(orch.execute_in_session("a", "install").await);
+        assert!(usage.starts_with("Invalid arguments: missing argument"), "{}", usage);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn test_namespaces_isolate_and_fall_back_to_base() {
+        let dir = "/tmp/logos_test_namespaces";
+        let _ = std::fs::remove_dir_all(dir);
+        let mut orch = Orchestrator::new(dir);
+        assert!(orch.language.teach("公司的年假有几天", "基础库：十天").is_ok());
+        assert!(orch.execute_in_session("a", "新建命名空间 team-a").await.is_ok());
+        assert!(orch.execute_in_session("a", "使用命名空间 team-a").await.is_ok());
+        assert!(orch.execute_in_session("a", "教你 部署流程是什么 -> 甲组：先灰度").await.is_ok());
+        let ask = |r: Result<(String, String), (String, String)>| match r {
+            Ok((out, _)) => out,
+            Err((msg, _)) => panic!("{}", msg),
+        };
+        assert_eq!(ask(orch.execute_in_session("a", "部署流程是什么").await), "甲组：先灰度");
+        assert_eq!(ask(orch.execute_in_session("a", "公司的年假有几天").await), "基础库：十天");
+        assert_ne!(ask(orch.execute_in_session("b", "部署流程是什么").await), "甲组：先灰度");
+        assert_eq!(orch.namespaces.active(), BASE_NAMESPACE);
+
+        let team_query = match PatternQuery::parse("部署流程") {
+            Ok(q) => q,
+            Err(e) => panic!("{}", e),
+        };
+        assert_eq!(orch.list_patterns(None, &team_query).map(|p| p.total), Ok(0));
+        let team_page = match orch.list_patterns(Some("team-a"), &team_query) {
+            Ok(page) => page,
+            Err(e) => panic!("{}", e),
+        };
+        let team_id = team_page.items.first().map(|s| s.id).unwrap_or_default();
+        assert!(orch.pattern_json(Some("team-a"), team_id).is_ok_and(|v| v.is_some()));
+        assert!(orch.list_patterns(Some("missing"), &team_query).is_err());
+        let edit = PatternEdit { response: Some("甲组：先灰度".into()), weight: Some(2.0) };
+        assert!(orch.edit_pattern(Some("team-a"), team_id, &edit).is_ok());
+
+        orch.set_request_namespace(Some("team-a".into()));
+        assert_eq!(ask(orch.execute_in_session("b", "部署流程是什么").await), "甲组：先灰度");
+        orch.set_request_namespace(Some("missing".into()));
+        assert!(orch.execute_in_session("b", "你好").await.is_err());
+        orch.set_request_namespace(None);
+
+        let mut reloaded = Orchestrator::new(dir);
+        assert!(reloaded.namespaces.contains("team-a"));
+        assert!(reloaded.execute_in_session("c", "复制命名空间 team-a team-b").await.is_ok());
+        reloaded.set_request_namespace(Some("team-b".into()));
+        assert_eq!(ask(reloaded.execute_in_session("c", "部署流程是什么").await), "甲组：先灰度");
+        reloaded.set_request_namespace(None);
+        assert!(reloaded.execute_in_session("c", "删除命名空间 base").await.is_err());
+        let usage = ask(reloaded.execute_in_session("c", "新建命名空间").await);
+        assert!(usage.contains("用法: 新建命名空间 <名>"), "usage errors are a normal reply: {}", usage);
+        assert!(reloaded.execute_in_session("c", "删除命名空间 team-b").await.is_ok());
+        assert!(!reloaded.namespaces.contains("team-b"));
+        assert!(!std::path::Path::new(&namespace::dir(dir, "team-b")).exists());
+    }
+
+    #[tokio::test]
+    async fn test_learning_cycle_runs_in_slices() {
+        let dir = "/tmp/logos_test_learning_slices";
+        let _ = std::fs::remove_dir_all(dir);
+        let mut orch = Orchestrator::new(dir);
+        let before = orch.learning.cycle_count;
+        let mut cycle = orch.begin_learning_cycle();
+        assert_eq!(orch.learning.cycle_count, before + 1);
+        let mut steps = 0;
+        while Turn::direct(&mut orch).learning_step(&mut cycle).await {
+            steps += 1;
+            assert!(steps < 1000, "learning cycle must be bounded");
+            assert!(orch.execute_with_pouch("你好").await.is_ok(), "chat must run between learning slices");
+        }
+        assert!(steps > 0, "cycle should be split into more than one slice");
+        assert!(!Turn::direct(&mut orch).learning_step(&mut cycle).await);
+    }
+
+    #[tokio::test]
+    async fn test_event_tap_streams_route_progress() {
+        let dir = "/tmp/logos_test_event_tap";
+        let _ = std::fs::remove_dir_all(dir);
+        let mut orch = Orchestrator::new(dir);
+        assert!(orch.install("capability_comparer").is_ok());
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        orch.set_event_tap(Some(tx));
+        assert!(orch.execute_with_pouch("对比能力").await.is_ok());
+        orch.set_event_tap(None);
+        let mut kinds = Vec::new();
+        while let Ok(ev) = rx.try_recv() {
+            kinds.push(ev.kind);
+        }
+        assert!(kinds.iter().any(|k| k == "ROUTE"), "route decision must be streamed: {:?}", kinds);
+        assert!(kinds.iter().any(|k| k == "PLAN_STEP"), "plan steps must be streamed: {:?}", kinds);
+        assert!(kinds.iter().any(|k| k == "STEP_OUTPUT"), "step outputs must be streamed: {:?}", kinds);
+        assert!(orch.execute_with_pouch("你好").await.is_ok());
+        assert!(rx.try_recv().is_err(), "no events after tap is cleared");
+    }
+
+    #[tokio::test]
+    async fn test_trace_records_plan_steps() {
+        let dir = "/tmp/logos_test_trace";
+        let _ = std::fs::remove_dir_all(dir);
+        let mut orch = Orchestrator::new(dir);
+        assert!(orch.install("capability_comparer").is_ok());
+        orch.begin_trace("对比能力");
+        assert!(orch.execute_with_pouch("对比能力").await.is_ok());
+        let t = match orch.take_trace() {
+            Some(t) => t,
+            None => panic!("trace must be collected after begin_trace"),
+        };
+        assert_eq!(t.pouch, "plan");
+        assert!(t.ok);
+        assert!(t.route.starts_with("Reject"), "route: {}", t.route);
+        let plan = match t.plan {
+            Some(p) => p,
+            None => panic!("plan path must record plan"),
+        };
+        assert_eq!(plan.steps.len(), plan.plan.steps.len());
+        assert!(plan.steps.iter().all(|s| s.ok && !s.input.is_empty()));
+        assert!(t.absorbed.iter().any(|a| a == "plan→language"));
+        assert!(orch.execute_with_pouch("你好").await.is_ok());
+        assert!(orch.take_trace().is_none(), "trace is opt-in");
+    }
+
+    #[tokio::test]
+    async fn test_parallel_plan_fans_out_and_merges() {
+        let dir = "/tmp/logos_test_dag_plan";
+        let _ = std::fs::remove_dir_all(dir);
+        let mut orch = Orchestrator::new(dir);
+        assert!(orch.install("capability_comparer").is_ok());
+        orch.begin_trace("对比能力A和B然后打分");
+        let (out, pouch) = match orch.execute_with_pouch("对比能力A和B然后打分").await {
+            Ok(x) => x,
+            Err((msg, _)) => panic!("dag plan should not error: {}", msg),
+        };
+        assert_eq!(pouch, "plan");
+        assert!(!out.is_empty());
+        let plan = match orch.take_trace().and_then(|t| t.plan) {
+            Some(p) => p,
+            None => panic!("plan must be traced"),
+        };
+        assert!(plan.plan.is_parallel(), "plan: {}", plan.summary);
+        assert!(plan.plan.steps.iter().any(|s| s.pouch == "compose"), "fan-in via compose: {}", plan.summary);
+        let merge = plan.steps.iter().find(|s| s.pouch == "compose");
+        match merge {
+            Some(m) => assert!(m.input.contains(crate::atom::MERGE_SEPARATOR.trim()), "merge input: {}", m.input),
+            None => panic!("compose step must run"),
+        }
+        assert_eq!(plan.steps.len(), plan.plan.steps.len());
+    }
+
     #[test]
     fn test_guard_adjacent() {
         let mut orch = Orchestrator::new("/tmp/logos_test_guard");
@@ -2687,15 +4589,263 @@ mod tests {
                 pouch: "nonexistent_pouch_xyz".into(),
                 kind: crate::atom::AtomKind::Match,
                 input_from: crate::atom::StepInput::UserInput,
+                alternatives: Vec::new(),
             }],
         };
-        let r = orch.execute_plan(&plan, "input").await;
+        let r = Turn::direct(&mut orch).execute_plan(&plan, "input").await;
         assert!(r.is_err());
         let events = orch.recent_events();
         let pouch_success_count = events.iter().filter(|e| e.starts_with("PouchSuccess:")).count();
         assert_eq!(pouch_success_count, 0, "failed step must not record PouchSuccess: {:?}", events);
     }
 
+    #[tokio::test]
+    async fn test_plan_step_falls_back_to_alternative() {
+        let dir = "/tmp/logos_test_step_fallback";
+        let _ = std::fs::remove_dir_all(dir);
+        let mut orch = Orchestrator::new(dir);
+        assert!(orch.install("capability_comparer").is_ok());
+        let plan = crate::atom::ExecutionPlan {
+            steps: vec![crate::atom::ExecutionStep {
+                atom_name: "fake".into(),
+                pouch: "nonexistent_pouch_xyz".into(),
+                kind: crate::atom::AtomKind::Match,
+                input_from: crate::atom::StepInput::UserInput,
+                alternatives: vec![StepCandidate {
+                    atom_name: "compare".into(),
+                    pouch: "capability_comparer".into(),
+                }],
+            }],
+        };
+        let out = match Turn::direct(&mut orch).execute_plan(&plan, "对比能力").await {
+            Ok(o) => o,
+            Err(e) => panic!("alternative should rescue the step: {}", e),
+        };
+        assert!(!out.is_empty());
+        let entries = orch.recent_evolution_entries(10);
+        assert!(entries.iter().any(|e| e.pouch_name == "nonexistent_pouch_xyz" && !e.success));
+        assert!(entries.iter().any(|e| e.pouch_name == "capability_comparer" && e.success));
+        assert!(orch.recent_events().iter().any(|e| e.starts_with("PLAN_FALLBACK fake nonexistent_pouch_xyz → capability_comparer")));
+        assert!(orch.score_pouch("nonexistent_pouch_xyz") < orch.routing_baseline());
+    }
+
+    async fn hanging_remote(orch: &mut Orchestrator) -> tokio::net::TcpListener {
+        let listener = match tokio::net::TcpListener::bind("127.0.0.1:0").await {
+            Ok(l) => l,
+            Err(e) => panic!("bind: {}", e),
+        };
+        let addr = match listener.local_addr() {
+            Ok(a) => a,
+            Err(e) => panic!("addr: {}", e),
+        };
+        let rp = crate::remote_pouch::RemotePouch::new("slow_remote", PouchRole::E1, &format!("http://{}/", addr));
+        orch.pouches.insert("slow_remote".into(), Box::new(rp));
+        orch.meta.insert("slow_remote".into(), PouchMeta { role: PouchRole::E1 });
+        listener
+    }
+
+    #[tokio::test]
+    async fn test_pouch_timeout_logs_failed_entry() {
+        let dir = "/tmp/logos_test_pouch_timeout";
+        let _ = std::fs::remove_dir_all(dir);
+        let mut orch = Orchestrator::new(dir);
+        let _listener = hanging_remote(&mut orch).await;
+        orch.config.budget.pouch_timeout_ms = 100;
+        let started = std::time::Instant::now();
+        let r = orch.call_pouch("slow_remote", "远程慢调用").await;
+        assert!(started.elapsed() < std::time::Duration::from_secs(5));
+        match r {
+            Err(e) => assert!(e.contains("超时"), "{}", e),
+            Ok(o) => panic!("expected timeout, got {}", o),
+        }
+        let entries = orch.recent_evolution_entries(5);
+        assert!(entries.iter().any(|e| e.pouch_name == "slow_remote" && !e.success));
+        assert!(orch.recent_events().iter().any(|e| e.starts_with("TIMEOUT slow_remote")));
+    }
+
+    #[tokio::test]
+    async fn test_request_cancel_aborts_call_without_penalty() {
+        let dir = "/tmp/logos_test_request_cancel";
+        let _ = std::fs::remove_dir_all(dir);
+        let mut orch = Orchestrator::new(dir);
+        let _listener = hanging_remote(&mut orch).await;
+        let (handle, token) = crate::budget::cancel_pair();
+        orch.begin_request_budget(Some(token), std::time::Instant::now());
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            drop(handle);
+        });
+        let r = orch.call_pouch("slow_remote", "远程慢调用").await;
+        assert_eq!(r, Err("请求已取消".to_string()));
+        assert!(!orch.recent_evolution_entries(5).iter().any(|e| e.pouch_name == "slow_remote"));
+        assert_eq!(orch.call_pouch("slow_remote", "再次调用").await, Err("请求已取消".to_string()));
+        assert_eq!(Turn::direct(&mut orch).analyze_cloud_bounded("云端分析").await.err(), Some("请求已取消".to_string()));
+        assert!(orch.recent_events().iter().any(|e| e.starts_with("BUDGET cloud_analyze")));
+        orch.end_request_budget();
+    }
+
+    #[tokio::test]
+    async fn test_prepared_route_and_lookup_are_applied_or_recomputed() {
+        let dir = "/tmp/logos_test_prepared";
+        let _ = std::fs::remove_dir_all(dir);
+        let mut orch = Orchestrator::new(dir);
+        assert!(orch.language.teach("预先路由年假规定", "十五天").is_ok());
+        let prepared = orch.prepare("p1", "预先路由年假规定", None);
+        assert!(matches!(prepared.route, Some(RouteDecision::Reject(_))));
+        assert!(prepared.lookup.is_some());
+        orch.set_prepared(Some(prepared));
+        let r = orch.execute_in_session("p1", "预先路由年假规定").await;
+        assert_eq!(r, Ok(("十五天".to_string(), "language".to_string())));
+        assert!(orch.prepared.is_none());
+
+        let prepared = orch.prepare("p1", "@capability_comparer 差距矩阵评估", None);
+        assert!(matches!(prepared.route, Some(RouteDecision::Reject(_))));
+        assert!(orch.install("capability_comparer").is_ok());
+        orch.set_prepared(Some(prepared));
+        let r = orch.execute_in_session("p1", "@capability_comparer 差距矩阵评估").await;
+        assert!(matches!(r, Ok((_, ref pouch)) if pouch == "capability_comparer"), "{:?}", r);
+    }
+
+    #[tokio::test]
+    async fn test_same_session_turns_are_serialized() {
+        let dir = "/tmp/logos_test_session_gate";
+        let _ = std::fs::remove_dir_all(dir);
+        let mut orch = Orchestrator::new(dir);
+        let _listener = hanging_remote(&mut orch).await;
+        orch.config.budget.pouch_timeout_ms = 300;
+        let lock = Arc::new(RwLock::new(orch));
+        let (first_lock, second_lock) = (lock.clone(), lock.clone());
+        let first = tokio::spawn(async move {
+            Turn::shared(&first_lock).await.execute_in_session("s1", "@slow_remote 远程慢调用").await
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        let second = tokio::spawn(async move {
+            Turn::shared(&second_lock).await.execute_in_session("s1", "会话门测试第二轮").await
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(!second.is_finished(), "second turn must wait for the first on the same session");
+        {
+            let orch = lock.read().await;
+            assert!(orch.recent_events().iter().any(|e| e == "SESSION_WAIT s1"));
+        }
+        for handle in [first, second] {
+            if let Err(e) = handle.await {
+                panic!("join: {}", e);
+            }
+        }
+        let mut orch = lock.write().await;
+        assert!(orch.session_gates.is_empty());
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let ctx = orch.sessions.take_context("s1", now, 10);
+        assert!(ctx.iter().any(|(i, _)| i == "会话门测试第二轮"), "{:?}", ctx);
+    }
+
+    #[tokio::test]
+    async fn test_remote_call_releases_lock_and_parks_turn_state() {
+        let dir = "/tmp/logos_test_turn_unlock";
+        let _ = std::fs::remove_dir_all(dir);
+        let mut orch = Orchestrator::new(dir);
+        let _listener = hanging_remote(&mut orch).await;
+        orch.config.budget.pouch_timeout_ms = 1000;
+        let lock = std::sync::Arc::new(RwLock::new(orch));
+        let shared = lock.clone();
+        let slow = tokio::spawn(async move {
+            let mut turn = Turn::shared(&shared).await;
+            turn.begin_trace("远程慢调用");
+            let r = turn.call_pouch("slow_remote", "远程慢调用").await;
+            (r, turn.take_trace().is_some())
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        {
+            let orch = match tokio::time::timeout(std::time::Duration::from_millis(500), lock.write()).await {
+                Ok(g) => g,
+                Err(_) => panic!("remote call must not hold the write lock"),
+            };
+            assert!(orch.trace.is_none());
+            assert!(orch.call_stack.is_empty());
+        }
+        let (r, traced) = match slow.await {
+            Ok(x) => x,
+            Err(e) => panic!("join: {}", e),
+        };
+        assert!(matches!(r, Err(ref e) if e.contains("超时")), "{:?}", r);
+        assert!(traced);
+    }
+
+    #[tokio::test]
+    async fn test_intent_model_learns_from_successful_calls() {
+        let dir = "/tmp/logos_test_intent_model";
+        let _ = std::fs::remove_dir_all(dir);
+        let mut orch = Orchestrator::new(dir);
+        assert!(orch.install("capability_comparer").is_ok());
+        let kind = match orch.pouch_kind("capability_comparer") {
+            Some(k) => k,
+            None => panic!("capability_comparer should declare atoms"),
+        };
+        assert!(orch.predict_intent("差距矩阵评估").is_empty());
+        for _ in 0..3 {
+            assert!(orch.call_pouch("capability_comparer", "差距矩阵评估").await.is_ok());
+        }
+        assert_eq!(orch.predict_intent("评估差距矩阵"), vec![kind]);
+        assert!(orch.recent_events().iter().any(|e| e.starts_with("INTENT ")));
+        orch.save_state();
+        let mut reloaded = Orchestrator::new(dir);
+        assert!(reloaded.intent.class_count() >= 1);
+        assert!(!reloaded.recent_events().iter().any(|e| e.starts_with("INTENT_RETOKENIZE")));
+        reloaded.save_state();
+        drop(reloaded);
+        let mut config = crate::config::SystemConfig::default();
+        config.language.tokenizer = crate::tokenizer::TokenizerKind::Dictionary;
+        let _ = config.save(&format!("{}/pouch_config.json", dir));
+        let migrated = Orchestrator::new(dir);
+        assert!(migrated.recent_events().iter().any(|e| e.starts_with("INTENT_RETOKENIZE")));
+        assert!(migrated.intent.matches_tokenizer(&migrated.language.tokenizer_state()));
+        assert!(migrated.intent.class_count() >= 1);
+        drop(migrated);
+        let again = Orchestrator::new(dir);
+        assert!(!again.recent_events().iter().any(|e| e.starts_with("INTENT_RETOKENIZE")));
+    }
+
+    #[tokio::test]
+    async fn test_ambiguous_route_picks_by_score() {
+        let dir = "/tmp/logos_test_ambiguous_route";
+        let _ = std::fs::remove_dir_all(dir);
+        let mut orch = Orchestrator::new(dir);
+        assert!(orch.install("pilot").is_ok());
+        assert!(orch.install("benchmark").is_ok());
+        for _ in 0..3 {
+            orch.record_chain_attempt("基准评估", "benchmark", "失败", false, 0);
+        }
+        let pouch = match orch.execute_with_pouch("试点 基准评估 流程").await {
+            Ok((_, p)) => p,
+            Err((e, _)) => panic!("route failed: {}", e),
+        };
+        assert_eq!(pouch, "pilot");
+        assert!(orch.recent_events().iter().any(|e| e == "ROUTE_AMBIGUOUS benchmark,pilot → pilot"));
+    }
+
+    #[tokio::test]
+    async fn test_media_pouch_triggers_take_part_in_routing() {
+        let dir = "/tmp/logos_test_media_triggers";
+        let _ = std::fs::remove_dir_all(dir);
+        let mut orch = Orchestrator::new(dir);
+        assert!(orch.install("image").is_ok());
+        assert!(orch.install("realtime").is_ok());
+        let pouch = match orch.execute_with_pouch("帮我找图片 日落").await {
+            Ok((_, p)) => p,
+            Err((e, _)) => panic!("route failed: {}", e),
+        };
+        assert_eq!(pouch, "image");
+        let pouch = match orch.execute_with_pouch("看看实时行情").await {
+            Ok((_, p)) => p,
+            Err((e, _)) => panic!("route failed: {}", e),
+        };
+        assert_eq!(pouch, "realtime");
+    }
+
     #[tokio::test]
     async fn test_evolution_chain_has_entry_after_success() {
         let _ = std::fs::remove_dir_all("/tmp/logos_test_chain_ok");
@@ -2723,10 +4873,11 @@ mod tests {
                 pouch: "capability_comparer".into(),
                 kind: crate::atom::AtomKind::Match,
                 input_from: crate::atom::StepInput::UserInput,
+                alternatives: Vec::new(),
             }],
         };
-        let _ = orch.execute_plan(&plan, "对比能力").await;
-        let _ = orch.execute_plan(&plan, "对比能力").await;
+        let _ = Turn::direct(&mut orch).execute_plan(&plan, "对比能力").await;
+        let _ = Turn::direct(&mut orch).execute_plan(&plan, "对比能力").await;
         let entries = orch.recent_evolution_entries(100);
         assert!(entries.len() >= 2, "chain should have at least 2 entries after two execute_plan calls: {}", entries.len());
         let mut prev_ts = 0u64;
@@ -2747,10 +4898,11 @@ mod tests {
                 pouch: "capability_comparer".into(),
                 kind: crate::atom::AtomKind::Match,
                 input_from: crate::atom::StepInput::UserInput,
+                alternatives: Vec::new(),
             }],
         };
-        let _ = orch1.execute_plan(&plan, "对比能力").await;
-        let _ = orch1.execute_plan(&plan, "对比能力").await;
+        let _ = Turn::direct(&mut orch1).execute_plan(&plan, "对比能力").await;
+        let _ = Turn::direct(&mut orch1).execute_plan(&plan, "对比能力").await;
         let entries1 = orch1.recent_evolution_entries(100);
         assert!(entries1.len() >= 2, "orch1 should have at least 2 entries");
         drop(orch1);
@@ -2767,6 +4919,48 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_tokenizer_switch_migrates_once() {
+        let dir = "/tmp/logos_test_tokenizer_migrate";
+        let _ = std::fs::remove_dir_all(dir);
+        drop(Orchestrator::new(dir));
+        let mut config = crate::config::SystemConfig::default();
+        config.language.tokenizer = crate::tokenizer::TokenizerKind::Dictionary;
+        let _ = config.save(&format!("{}/pouch_config.json", dir));
+        let orch = Orchestrator::new(dir);
+        assert!(orch.recent_events().iter().any(|e| e.starts_with("TOKENIZER_MIGRATE")));
+        let stored = std::fs::read_to_string(format!("{}/tokenizer.json", dir)).unwrap_or_default();
+        assert!(stored.contains("dictionary"), "tokenizer.json: {}", stored);
+        drop(orch);
+        let again = Orchestrator::new(dir);
+        assert!(!again.recent_events().iter().any(|e| e.starts_with("TOKENIZER_MIGRATE")));
+        assert_eq!(again.language.tokenizer_state().kind, crate::tokenizer::TokenizerKind::Dictionary);
+    }
+
+    #[tokio::test]
+    async fn test_namespace_tokenizer_override() {
+        use crate::tokenizer::TokenizerKind;
+        let dir = "/tmp/logos_test_namespace_tokenizer";
+        let _ = std::fs::remove_dir_all(dir);
+        let mut config = crate::config::SystemConfig::default();
+        config.language.namespace_tokenizers.insert("team-a".into(), TokenizerKind::Dictionary);
+        let _ = std::fs::create_dir_all(dir);
+        let _ = config.save(&format!("{}/pouch_config.json", dir));
+        let mut orch = Orchestrator::new(dir);
+        assert!(orch.execute_in_session("a", "新建命名空间 team-a").await.is_ok());
+        assert!(orch.execute_in_session("a", "复制命名空间 base team-b").await.is_ok());
+        let kind = |o: &Orchestrator, ns: &str| o.namespaces.parked(ns).map(|l| l.tokenizer_state().kind);
+        assert_eq!(kind(&orch, "team-a"), Some(TokenizerKind::Dictionary));
+        assert_eq!(kind(&orch, "team-b"), Some(TokenizerKind::CharBigram));
+        assert_eq!(orch.language.tokenizer_state().kind, TokenizerKind::CharBigram);
+        drop(orch);
+        let again = Orchestrator::new(dir);
+        assert!(!again.recent_events().iter().any(|e| e.starts_with("TOKENIZER_MIGRATE")));
+        assert_eq!(kind(&again, "team-a"), Some(TokenizerKind::Dictionary));
+        assert_eq!(kind(&again, "team-b"), Some(TokenizerKind::CharBigram));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
     #[tokio::test]
     async fn test_reject_branch_fallback_when_no_plan() {
         let _ = std::fs::remove_dir_all("/tmp/logos_test_plan_fallback");
@@ -0,0 +1,235 @@
+use crate::language_pouch::Pattern;
+use crate::pattern_index::PatternId;
+use serde::{Deserialize, Serialize};
+
+/*
+ * 模式管理：按文字/token/来源/权重筛选，按权重或最近使用分页，供终端命令和 /api/patterns 使用。
+ *   - text：触发词或任一回复包含该文字（不区分大小写）
+ *   - token：分词结果中有完全相同的 token
+ *   - origin：来源类别（seed/teach/absorb/cloud_pull/import/correction/legacy）或来源标签中的文字
+ * 终端条件写法：普通文字作为 text，另支持 token:词 origin:absorb weight<0.5 weight>2
+ * sort:recent page:2 size:50，多个条件同时满足。
+ */
+pub const DEFAULT_PAGE_SIZE: usize = 20;
+pub const MAX_PAGE_SIZE: usize = 200;
+const RESPONSE_PREVIEW_CHARS: usize = 40;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PatternSort {
+    #[default]
+    Weight,
+    Recent,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct PatternQuery {
+    pub text: Option<String>,
+    pub token: Option<String>,
+    pub origin: Option<String>,
+    pub min_weight: Option<f64>,
+    pub max_weight: Option<f64>,
+    pub sort: PatternSort,
+    pub page: usize,
+    pub page_size: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PatternSummary {
+    pub id: PatternId,
+    pub trigger: String,
+    pub response: String,
+    pub variants: usize,
+    pub weight: f64,
+    pub frequency: u32,
+    pub last_used: u64,
+    pub origin: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PatternPage {
+    pub total: usize,
+    pub page: usize,
+    pub page_size: usize,
+    pub items: Vec<PatternSummary>,
+}
+
+/*
+ * 修改内容：回复替换主回复（模板模式需只引用已定义的槽位），权重为模式级权重。
+ */
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct PatternEdit {
+    pub response: Option<String>,
+    pub weight: Option<f64>,
+}
+
+fn non_empty(s: Option<&String>) -> Option<&str> {
+    s.map(|s| s.trim()).filter(|s| !s.is_empty())
+}
+
+impl PatternQuery {
+    pub fn parse(cond: &str) -> Result<Self, String> {
+        let mut q = PatternQuery::default();
+        let mut words: Vec<&str> = Vec::new();
+        for part in cond.split_whitespace() {
+            let number = |v: &str| v.parse::<f64>().map_err(|_| format!("权重应为数字: {}", part));
+            let index = |v: &str| v.parse::<usize>().map_err(|_| format!("应为正整数: {}", part));
+            if let Some(v) = part.strip_prefix("weight<").or_else(|| part.strip_prefix("权重<")) {
+                q.max_weight = Some(number(v)?);
+            } else if let Some(v) = part.strip_prefix("weight>").or_else(|| part.strip_prefix("权重>")) {
+                q.min_weight = Some(number(v)?);
+            } else if let Some((key, v)) = part.split_once(':') {
+                match key {
+                    "token" => q.token = Some(v.to_string()),
+                    "origin" | "来源" => q.origin = Some(v.to_string()),
+                    "page" | "页" => q.page = index(v)?,
+                    "size" => q.page_size = index(v)?,
+                    "sort" | "排序" => {
+                        q.sort = match v {
+                            "weight" | "权重" => PatternSort::Weight,
+                            "recent" | "最近" => PatternSort::Recent,
+                            _ => return Err(format!("未知排序: {}（weight/recent）", v)),
+                        }
+                    }
+                    _ => words.push(part),
+                }
+            } else {
+                words.push(part);
+            }
+        }
+        if !words.is_empty() {
+            q.text = Some(words.join(" "));
+        }
+        Ok(q)
+    }
+
+    /*
+     * 是否带有筛选条件（排序和分页不算）；批量删除要求至少一个条件。
+     */
+    pub fn has_filter(&self) -> bool {
+        non_empty(self.text.as_ref()).is_some()
+            || non_empty(self.token.as_ref()).is_some()
+            || non_empty(self.origin.as_ref()).is_some()
+            || self.min_weight.is_some()
+            || self.max_weight.is_some()
+    }
+
+    pub fn matches(&self, p: &Pattern, trigger: &str) -> bool {
+        if let Some(text) = non_empty(self.text.as_ref()) {
+            let text = text.to_lowercase();
+            let hit = trigger.to_lowercase().contains(&text)
+                || p.variants.iter().any(|v| v.text.to_lowercase().contains(&text));
+            if !hit {
+                return false;
+            }
+        }
+        if let Some(token) = non_empty(self.token.as_ref()) {
+            if !p.tokens.iter().any(|t| t.eq_ignore_ascii_case(token)) {
+                return false;
+            }
+        }
+        if let Some(origin) = non_empty(self.origin.as_ref()) {
+            let o = &p.provenance.origin;
+            if o.kind() != origin && !o.label().contains(origin) {
+                return false;
+            }
+        }
+        self.min_weight.is_none_or(|w| p.weight >= w) && self.max_weight.is_none_or(|w| p.weight <= w)
+    }
+
+    /* (页码, 每页条数)：页码从 1 开始，上限保证 页码 × 条数 不溢出 */
+    pub fn paging(&self) -> (usize, usize) {
+        let size = if self.page_size == 0 { DEFAULT_PAGE_SIZE } else { self.page_size.min(MAX_PAGE_SIZE) };
+        (self.page.clamp(1, usize::MAX / size), size)
+    }
+}
+
+impl PatternSummary {
+    pub fn of(p: &Pattern, trigger: String) -> Self {
+        PatternSummary {
+            id: p.id,
+            trigger,
+            response: p.primary().to_string(),
+            variants: p.variants.len(),
+            weight: p.weight,
+            frequency: p.frequency,
+            last_used: p.last_used,
+            origin: p.provenance.origin.label(),
+        }
+    }
+}
+
+impl PatternPage {
+    pub fn render(&self) -> String {
+        if self.total == 0 {
+            return "没有符合条件的模式".into();
+        }
+        let pages = self.total.div_ceil(self.page_size);
+        let mut out = format!("共 {} 条，第 {}/{} 页\n", self.total, self.page, pages);
+        for s in &self.items {
+            let mut response: String = s.response.chars().take(RESPONSE_PREVIEW_CHARS).collect();
+            if s.response.chars().count() > RESPONSE_PREVIEW_CHARS {
+                response.push('…');
+            }
+            let more = if s.variants > 1 { format!(" (+{}变体)", s.variants - 1) } else { String::new() };
+            out.push_str(&format!(
+                "#{} [权重 {:.2} 命中 {}] {} → {}{} ({})\n",
+                s.id, s.weight, s.frequency, s.trigger, response, more, s.origin
+            ));
+        }
+        out.pop();
+        out
+    }
+}
+
+impl PatternEdit {
+    /*
+     * 终端写法：回复=新回复 或 权重=2.5（英文 response= / weight= 亦可）。
+     */
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let spec = spec.trim();
+        let (key, value) = spec.split_once('=').ok_or("修改内容应为 回复=… 或 权重=…")?;
+        let value = value.trim();
+        match key.trim() {
+            "回复" | "response" => Ok(PatternEdit { response: Some(value.to_string()), weight: None }),
+            "权重" | "weight" => {
+                let w = value.parse::<f64>().map_err(|_| format!("权重应为数字: {}", value))?;
+                Ok(PatternEdit { response: None, weight: Some(w) })
+            }
+            other => Err(format!("未知修改项: {}", other)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_query_and_edit() {
+        let q = match PatternQuery::parse("天气 token:北京 weight<0.5 sort:recent page:2") {
+            Ok(q) => q,
+            Err(e) => panic!("{}", e),
+        };
+        assert_eq!(q.text.as_deref(), Some("天气"));
+        assert_eq!(q.token.as_deref(), Some("北京"));
+        assert_eq!(q.max_weight, Some(0.5));
+        assert_eq!(q.sort, PatternSort::Recent);
+        assert_eq!(q.paging(), (2, DEFAULT_PAGE_SIZE));
+        let huge = PatternQuery { page: usize::MAX, page_size: MAX_PAGE_SIZE, ..Default::default() };
+        assert!(huge.paging().0.checked_mul(MAX_PAGE_SIZE).is_some());
+        assert!(q.has_filter());
+        assert!(!PatternQuery::default().has_filter());
+        assert!(PatternQuery::parse("sort:random").is_err());
+        assert!(PatternQuery::parse("weight<abc").is_err());
+
+        assert_eq!(PatternEdit::parse("权重=2.5").map(|e| e.weight), Ok(Some(2.5)));
+        assert_eq!(
+            PatternEdit::parse("response = 新回复").map(|e| e.response),
+            Ok(Some("新回复".to_string()))
+        );
+        assert!(PatternEdit::parse("颜色=红").is_err());
+    }
+}
@@ -0,0 +1,238 @@
+use crate::language_pouch::Pattern;
+use serde::Deserialize;
+
+/*
+ * 模式导出为常见数据集格式，触发词用 trigger_text 还原为可读原文（不是 token 拼接）：
+ *   - jsonl：每行 {"human","gpt"}，与 import_from_content 读取的格式一致，可直接再导入
+ *   - sharegpt：JSON 数组，每项 {"conversations":[{"from":"human"},{"from":"gpt"}]}
+ *   - csv：trigger,response,weight,origin,created_at（RFC 4180 转义）
+ * 每个回复变体导出一行（条件块不导出）；槽位模板不是具体问答，不导出。
+ * 筛选：最低权重、来源类别或标签文字、创建日期范围（含首尾两天，本地时区）；
+ * 日期未知（旧数据迁移，created_at 为 0）的模式在指定日期范围时不导出。
+ */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Jsonl,
+    ShareGpt,
+    Csv,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct ExportOptions {
+    pub format: ExportFormat,
+    pub min_weight: Option<f64>,
+    pub origin: Option<String>,
+    pub since: Option<String>,
+    pub until: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportRow {
+    pub human: String,
+    pub gpt: String,
+    pub weight: f64,
+    pub origin: String,
+    pub created_at: u64,
+}
+
+impl ExportFormat {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "jsonl" => Some(ExportFormat::Jsonl),
+            "sharegpt" => Some(ExportFormat::ShareGpt),
+            "csv" => Some(ExportFormat::Csv),
+            _ => None,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Jsonl => "jsonl",
+            ExportFormat::ShareGpt => "json",
+            ExportFormat::Csv => "csv",
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Jsonl => "application/x-ndjson; charset=utf-8",
+            ExportFormat::ShareGpt => "application/json; charset=utf-8",
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+        }
+    }
+}
+
+/* 本地日期 YYYY-MM-DD 当天 00:00:00 的 Unix 秒 */
+fn day_start(date: &str) -> Result<u64, String> {
+    let day = chrono::NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+        .map_err(|_| format!("日期应为 YYYY-MM-DD: {}", date))?;
+    day.and_hms_opt(0, 0, 0)
+        .and_then(|t| t.and_local_timezone(chrono::Local).earliest())
+        .map(|t| t.timestamp().max(0) as u64)
+        .ok_or_else(|| format!("无效日期: {}", date))
+}
+
+impl ExportOptions {
+    /*
+     * 终端写法：<jsonl|sharegpt|csv> [路径] [weight>0.5] [origin:teach] [since:2026-01-01] [until:2026-12-31]
+     * 返回 (选项, 路径)；路径省略时由调用方决定默认位置。
+     */
+    pub fn parse(arg: &str) -> Result<(Self, Option<String>), String> {
+        let mut words = arg.split_whitespace();
+        let format = words.next().unwrap_or_default();
+        let mut opts = ExportOptions {
+            format: ExportFormat::parse(format).ok_or_else(|| format!("未知导出格式: {}（jsonl/sharegpt/csv）", format))?,
+            ..Default::default()
+        };
+        let mut path = None;
+        for part in words {
+            if let Some(v) = part.strip_prefix("weight>").or_else(|| part.strip_prefix("权重>")) {
+                opts.min_weight = Some(v.parse::<f64>().map_err(|_| format!("权重应为数字: {}", part))?);
+            } else if let Some(v) = part.strip_prefix("origin:").or_else(|| part.strip_prefix("来源:")) {
+                opts.origin = Some(v.to_string());
+            } else if let Some(v) = part.strip_prefix("since:").or_else(|| part.strip_prefix("从:")) {
+                opts.since = Some(v.to_string());
+            } else if let Some(v) = part.strip_prefix("until:").or_else(|| part.strip_prefix("到:")) {
+                opts.until = Some(v.to_string());
+            } else if path.is_none() {
+                path = Some(part.to_string());
+            } else {
+                return Err(format!("多余参数: {}", part));
+            }
+        }
+        opts.date_range()?;
+        Ok((opts, path))
+    }
+
+    /* [起, 止) 的 Unix 秒；未指定的一端为 None */
+    pub fn date_range(&self) -> Result<(Option<u64>, Option<u64>), String> {
+        let since = self.since.as_deref().filter(|s| !s.trim().is_empty()).map(day_start).transpose()?;
+        let until = self
+            .until
+            .as_deref()
+            .filter(|s| !s.trim().is_empty())
+            .map(|d| day_start(d).map(|t| t + 86_400))
+            .transpose()?;
+        Ok((since, until))
+    }
+
+    pub fn matches(&self, p: &Pattern, range: (Option<u64>, Option<u64>)) -> bool {
+        if p.template.is_some() || self.min_weight.is_some_and(|w| p.weight < w) {
+            return false;
+        }
+        if let Some(origin) = self.origin.as_deref().map(str::trim).filter(|o| !o.is_empty()) {
+            let o = &p.provenance.origin;
+            if o.kind() != origin && !o.label().contains(origin) {
+                return false;
+            }
+        }
+        let created = p.provenance.created_at;
+        match range {
+            (None, None) => true,
+            _ if created == 0 => false,
+            (since, until) => since.is_none_or(|s| created >= s) && until.is_none_or(|u| created < u),
+        }
+    }
+}
+
+/*
+ * 终端指定的导出路径只能落在 <data_dir>/export/ 下：拒绝绝对路径和 ..，
+ * 中间目录按需创建由调用方处理。
+ */
+pub fn output_path(export_dir: &str, name: &str) -> Result<std::path::PathBuf, String> {
+    use std::path::{Component, Path};
+    let rel = Path::new(name.trim());
+    if rel.as_os_str().is_empty() {
+        return Err("导出路径为空".into());
+    }
+    if !rel.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir)) {
+        return Err(format!("导出路径只能是 export 目录下的相对路径: {}", name));
+    }
+    Ok(Path::new(export_dir).join(rel))
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+pub fn render(rows: &[ExportRow], format: ExportFormat) -> String {
+    match format {
+        ExportFormat::Jsonl => rows
+            .iter()
+            .map(|r| serde_json::json!({ "human": r.human, "gpt": r.gpt }).to_string() + "\n")
+            .collect(),
+        ExportFormat::ShareGpt => {
+            let items: Vec<serde_json::Value> = rows
+                .iter()
+                .map(|r| {
+                    serde_json::json!({ "conversations": [
+                        { "from": "human", "value": r.human },
+                        { "from": "gpt", "value": r.gpt },
+                    ] })
+                })
+                .collect();
+            serde_json::to_string_pretty(&items).unwrap_or_else(|_| "[]".into())
+        }
+        ExportFormat::Csv => {
+            let mut out = String::from("trigger,response,weight,origin,created_at\n");
+            for r in rows {
+                let created = chrono::DateTime::from_timestamp(r.created_at as i64, 0)
+                    .filter(|_| r.created_at > 0)
+                    .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S").to_string())
+                    .unwrap_or_default();
+                out.push_str(&format!(
+                    "{},{},{:.3},{},{}\n",
+                    csv_field(&r.human),
+                    csv_field(&r.gpt),
+                    r.weight,
+                    csv_field(&r.origin),
+                    created
+                ));
+            }
+            out
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_options_and_render_formats() {
+        let (opts, path) = match ExportOptions::parse("csv out.csv weight>0.5 origin:teach since:2026-01-01") {
+            Ok(x) => x,
+            Err(e) => panic!("{}", e),
+        };
+        assert_eq!(opts.format, ExportFormat::Csv);
+        assert_eq!(path.as_deref(), Some("out.csv"));
+        assert_eq!(opts.min_weight, Some(0.5));
+        assert_eq!(opts.origin.as_deref(), Some("teach"));
+        assert!(ExportOptions::parse("xml").is_err());
+        assert!(ExportOptions::parse("jsonl since:yesterday").is_err());
+        assert_eq!(output_path("d/export", "a/out.csv").ok(), Some(std::path::PathBuf::from("d/export/a/out.csv")));
+        for bad in ["/etc/passwd", "../out.csv", "a/../../out.csv", " "] {
+            assert!(output_path("d/export", bad).is_err(), "{}", bad);
+        }
+
+        let rows = vec![ExportRow {
+            human: "你好, \"世界\"".into(),
+            gpt: "你好".into(),
+            weight: 1.0,
+            origin: "teach".into(),
+            created_at: 0,
+        }];
+        let line: serde_json::Value = serde_json::from_str(render(&rows, ExportFormat::Jsonl).trim()).unwrap_or_default();
+        assert_eq!(line["human"], "你好, \"世界\"");
+        assert!(render(&rows, ExportFormat::Csv).ends_with("\"你好, \"\"世界\"\"\",你好,1.000,teach,\n"));
+        let share: serde_json::Value = serde_json::from_str(&render(&rows, ExportFormat::ShareGpt)).unwrap_or_default();
+        assert_eq!(share[0]["conversations"][1]["value"], "你好");
+    }
+}
@@ -0,0 +1,135 @@
+/*
+ * 模式导入方式：
+ *   - replace：清空后整体导入（旧行为）
+ *   - append：全部追加，不去重
+ *   - merge（默认）：按 token 集合去重；回复完全相同的跳过，同触发词不同回复按冲突策略处理
+ * 冲突策略：keep-old（默认，保留已有回复）、keep-new（新回复替换主回复）、
+ * keep-both（新回复作为变体加入）。dry-run 只统计，不修改模式库也不落盘。
+ */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ImportMode {
+    Replace,
+    Append,
+    #[default]
+    Merge,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /* keep-old */
+    #[default]
+    Existing,
+    /* keep-new */
+    Incoming,
+    /* keep-both */
+    Both,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ImportOptions {
+    pub mode: ImportMode,
+    pub conflict: ConflictPolicy,
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportSummary {
+    pub dry_run: bool,
+    pub total: usize,
+    pub added: usize,
+    pub updated: usize,
+    pub skipped: usize,
+    pub invalid: usize,
+    pub removed: usize,
+}
+
+impl ImportOptions {
+    /*
+     * 识别一个选项词；不是选项时返回 false。
+     */
+    fn apply_word(&mut self, word: &str) -> bool {
+        match word.to_ascii_lowercase().as_str() {
+            "replace" | "替换" => self.mode = ImportMode::Replace,
+            "append" | "追加" => self.mode = ImportMode::Append,
+            "merge" | "合并" => self.mode = ImportMode::Merge,
+            "keep-old" | "保留旧" => self.conflict = ConflictPolicy::Existing,
+            "keep-new" | "保留新" => self.conflict = ConflictPolicy::Incoming,
+            "keep-both" | "都保留" => self.conflict = ConflictPolicy::Both,
+            "dry-run" | "预演" => self.dry_run = true,
+            _ => return false,
+        }
+        true
+    }
+
+    pub fn parse(words: &[&str]) -> Result<Self, String> {
+        let mut opts = ImportOptions::default();
+        for w in words {
+            if !opts.apply_word(w) {
+                return Err(format!("未知导入选项: {}", w));
+            }
+        }
+        Ok(opts)
+    }
+
+    /*
+     * 导入命令参数：路径在前，末尾的选项词依次识别（路径本身可含空格）。
+     */
+    pub fn split_command(arg: &str) -> Result<(String, Self), String> {
+        let mut opts = ImportOptions::default();
+        let mut words: Vec<&str> = arg.split_whitespace().collect();
+        while words.len() > 1 {
+            let last = words[words.len() - 1];
+            if !opts.apply_word(last) {
+                break;
+            }
+            words.pop();
+        }
+        let path = words.join(" ");
+        if path.is_empty() {
+            return Err("路径为空".into());
+        }
+        Ok((path, opts))
+    }
+}
+
+impl ImportSummary {
+    pub fn new(opts: &ImportOptions) -> Self {
+        ImportSummary { dry_run: opts.dry_run, ..Default::default() }
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = format!(
+            "{}导入 {} 条：新增 {}，更新 {}，跳过 {}",
+            if self.dry_run { "[预演] " } else { "" },
+            self.total,
+            self.added,
+            self.updated,
+            self.skipped
+        );
+        if self.invalid > 0 {
+            out.push_str(&format!("，无效 {}", self.invalid));
+        }
+        if self.removed > 0 {
+            out.push_str(&format!("；替换前清除 {} 条", self.removed));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_command_options() {
+        let (path, opts) = match ImportOptions::split_command("data/my file.jsonl keep-both dry-run") {
+            Ok(x) => x,
+            Err(e) => panic!("{}", e),
+        };
+        assert_eq!(path, "data/my file.jsonl");
+        assert_eq!(opts, ImportOptions { mode: ImportMode::Merge, conflict: ConflictPolicy::Both, dry_run: true });
+        assert_eq!(ImportOptions::split_command("a.json").map(|(_, o)| o), Ok(ImportOptions::default()));
+        assert_eq!(ImportOptions::split_command("replace").map(|(p, _)| p), Ok("replace".to_string()));
+        assert!(ImportOptions::parse(&["merge", "sideways"]).is_err());
+    }
+}
@@ -0,0 +1,241 @@
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+/*
+ * 语言模式的倒排索引：token → (模式 id, 词频)。id 是模式的稳定 id，不是在 Vec 中的位置。
+ *   - search：BM25 给候选打分，只返回前 limit 个，交给 LanguagePouch 的权重/频次/新鲜度排序
+ *   - exact：按完整 token 序列的哈希直查，供 teach 等「同一触发词」判断使用
+ *   - remove：从 doc_len 删除并立即扣减各 token 的存活文档数（idf 只按存活模式算），
+ *     倒排表里的旧条目作为墓碑在查询时跳过；墓碑超过存活模式的 1/4 时 maybe_compact 清理倒排表
+ *   - doc_len 按 id 存放在 map 里，内存只随存活模式数增长，与历史上分配过多少 id 无关
+ * 查询按文档频率从低到高处理 token；已经有更稀有的 token 命中后，跳过覆盖过多模式的高频 token
+ * （如「的」），它们的 idf 很低，对排序几乎没有贡献，却会拖慢百万级模式库的查询。
+ */
+const BM25_K1: f64 = 1.2;
+const BM25_B: f64 = 0.75;
+const COMMON_DF_RATIO: f64 = 0.2;
+const COMMON_DF_MIN: usize = 1000;
+const COMPACT_MIN_DEAD: usize = 64;
+
+pub type PatternId = u32;
+
+/* 一个 token 的倒排表：entries 含未清理的墓碑，live 为存活文档数（即 df） */
+#[derive(Debug, Clone, Default)]
+struct Posting {
+    entries: Vec<(PatternId, u32)>,
+    live: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct InvertedIndex {
+    postings: HashMap<String, Posting>,
+    doc_len: HashMap<PatternId, u32>,
+    total_len: u64,
+    docs: usize,
+    dead: usize,
+    exact: HashMap<u64, Vec<PatternId>>,
+}
+
+fn seq_hash(tokens: &[String]) -> u64 {
+    let mut h = std::collections::hash_map::DefaultHasher::new();
+    tokens.hash(&mut h);
+    h.finish()
+}
+
+impl InvertedIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn build<'a>(docs: impl Iterator<Item = (PatternId, &'a [String])>) -> Self {
+        let mut idx = Self::new();
+        for (id, tokens) in docs {
+            idx.insert(id, tokens);
+        }
+        idx
+    }
+
+    pub fn insert(&mut self, id: PatternId, tokens: &[String]) {
+        if self.doc_len.contains_key(&id) || tokens.is_empty() {
+            return;
+        }
+        let mut tf: HashMap<&String, u32> = HashMap::new();
+        for t in tokens {
+            *tf.entry(t).or_insert(0) += 1;
+        }
+        for (t, n) in tf {
+            let posting = self.postings.entry(t.clone()).or_default();
+            posting.entries.push((id, n));
+            posting.live += 1;
+        }
+        self.doc_len.insert(id, tokens.len() as u32);
+        self.total_len += tokens.len() as u64;
+        self.docs += 1;
+        self.exact.entry(seq_hash(tokens)).or_default().push(id);
+    }
+
+    /*
+     * tokens 必须是插入时的 token，用于清理 exact 表。
+     */
+    pub fn remove(&mut self, id: PatternId, tokens: &[String]) {
+        let len = match self.doc_len.remove(&id) {
+            Some(len) => len,
+            None => return,
+        };
+        let mut distinct: Vec<&String> = tokens.iter().collect();
+        distinct.sort();
+        distinct.dedup();
+        for t in distinct {
+            if let Some(posting) = self.postings.get_mut(t) {
+                posting.live = posting.live.saturating_sub(1);
+            }
+        }
+        self.total_len -= len as u64;
+        self.docs -= 1;
+        self.dead += 1;
+        let h = seq_hash(tokens);
+        if let Some(ids) = self.exact.get_mut(&h) {
+            ids.retain(|&i| i != id);
+            if ids.is_empty() {
+                self.exact.remove(&h);
+            }
+        }
+    }
+
+    pub fn maybe_compact(&mut self) -> bool {
+        if self.dead < COMPACT_MIN_DEAD || self.dead * 4 < self.docs {
+            return false;
+        }
+        let doc_len = &self.doc_len;
+        self.postings.retain(|_, posting| {
+            posting.entries.retain(|(id, _)| doc_len.contains_key(id));
+            !posting.entries.is_empty()
+        });
+        self.dead = 0;
+        true
+    }
+
+    pub fn len(&self) -> usize {
+        self.docs
+    }
+
+    pub fn tombstones(&self) -> usize {
+        self.dead
+    }
+
+    /*
+     * 与 tokens 完全相同的模式 id 候选（哈希相同，调用方需再比对 token）。
+     */
+    pub fn exact(&self, tokens: &[String]) -> &[PatternId] {
+        self.exact.get(&seq_hash(tokens)).map(|v| v.as_slice()).unwrap_or(&[])
+    }
+
+    fn idf(&self, df: usize) -> f64 {
+        let n = self.docs as f64;
+        let df = df as f64;
+        (1.0 + (n - df + 0.5) / (df + 0.5)).ln()
+    }
+
+    /*
+     * 返回 (模式 id, 相似度)，按相似度降序。相似度 = BM25 / 理想分（每个查询 token 都以平均长度命中一次），
+     * 截断到 [0, 1]，与原分形搜索的相似度同量纲，可直接套用 SIMILARITY_THRESHOLD。
+     */
+    pub fn search(&self, tokens: &[String], limit: usize) -> Vec<(PatternId, f64)> {
+        if self.docs == 0 || tokens.is_empty() || limit == 0 {
+            return Vec::new();
+        }
+        let mut query: Vec<&String> = tokens.iter().collect();
+        query.sort();
+        query.dedup();
+        let ideal: f64 = query
+            .iter()
+            .map(|t| self.idf(self.postings.get(*t).map(|p| p.live).unwrap_or(0)))
+            .sum();
+        if ideal <= 0.0 {
+            return Vec::new();
+        }
+        let mut lists: Vec<&Posting> = query.iter().filter_map(|t| self.postings.get(*t)).collect();
+        lists.sort_by_key(|p| p.live);
+        let common_df = ((self.docs as f64 * COMMON_DF_RATIO) as usize).max(COMMON_DF_MIN);
+        let avgdl = self.total_len as f64 / self.docs as f64;
+        let mut scores: HashMap<PatternId, f64> = HashMap::new();
+        for (i, list) in lists.iter().enumerate() {
+            if i > 0 && list.live > common_df {
+                break;
+            }
+            let idf = self.idf(list.live);
+            for &(id, tf) in list.entries.iter() {
+                let dl = match self.doc_len.get(&id) {
+                    Some(&len) => len as f64,
+                    None => continue,
+                };
+                let tf = tf as f64;
+                let s = idf * tf * (BM25_K1 + 1.0) / (tf + BM25_K1 * (1.0 - BM25_B + BM25_B * dl / avgdl));
+                *scores.entry(id).or_insert(0.0) += s;
+            }
+        }
+        let norm = ideal * (BM25_K1 + 1.0) / (1.0 + BM25_K1);
+        let mut ranked: Vec<(PatternId, f64)> = scores
+            .into_iter()
+            .map(|(id, s)| (id, (s / norm).min(1.0)))
+            .collect();
+        let by_score = |a: &(PatternId, f64), b: &(PatternId, f64)| {
+            b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal).then(a.0.cmp(&b.0))
+        };
+        if ranked.len() > limit {
+            ranked.select_nth_unstable_by(limit - 1, by_score);
+            ranked.truncate(limit);
+        }
+        ranked.sort_by(by_score);
+        ranked
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn toks(s: &[&str]) -> Vec<String> {
+        s.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn test_bm25_prefers_rare_token_match() {
+        let docs = [toks(&["的", "天气"]), toks(&["的", "代码"]), toks(&["的", "分析"]), toks(&["代码", "分析"])];
+        let idx = InvertedIndex::build(docs.iter().enumerate().map(|(i, d)| (i as PatternId, d.as_slice())));
+        let hits = idx.search(&toks(&["代码", "分析"]), 10);
+        assert_eq!(hits.first().map(|h| h.0), Some(3));
+        assert!(hits[0].1 > 0.9);
+        assert!(hits.iter().all(|h| h.0 != 0));
+        assert!(idx.search(&toks(&["没有"]), 10).is_empty());
+    }
+
+    #[test]
+    fn test_limit_and_exact_lookup() {
+        let docs: Vec<Vec<String>> = (0..50).map(|i| toks(&["共同", &format!("词{}", i)])).collect();
+        let mut idx = InvertedIndex::build(docs.iter().enumerate().map(|(i, d)| (i as PatternId, d.as_slice())));
+        assert_eq!(idx.search(&toks(&["共同"]), 5).len(), 5);
+        assert_eq!(idx.exact(&docs[7]), &[7]);
+        idx.insert(7, &toks(&["重复"]));
+        assert_eq!(idx.len(), 50);
+    }
+
+    #[test]
+    fn test_remove_tombstones_then_compacts() {
+        let docs: Vec<Vec<String>> = (0..200).map(|i| toks(&["共同", &format!("词{}", i)])).collect();
+        let mut idx = InvertedIndex::build(docs.iter().enumerate().map(|(i, d)| (i as PatternId, d.as_slice())));
+        for i in 0..100 {
+            idx.remove(i, &docs[i as usize]);
+        }
+        assert_eq!(idx.len(), 100);
+        assert!(idx.search(&toks(&["词3"]), 10).is_empty());
+        assert!(idx.exact(&docs[3]).is_empty());
+        let before = idx.search(&toks(&["共同", "词150"]), 300);
+        assert!(before.iter().all(|(id, _)| *id >= 100));
+        assert_eq!(idx.doc_len.len(), 100);
+        assert!(idx.maybe_compact());
+        assert_eq!(idx.tombstones(), 0);
+        assert_eq!(idx.search(&toks(&["共同"]), 300).len(), 100);
+        assert_eq!(idx.search(&toks(&["共同", "词150"]), 300), before, "tombstones must not skew idf");
+    }
+}
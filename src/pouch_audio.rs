@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 use crate::atom::{AtomDeclaration, AtomKind};
+use crate::frozen::logic::PouchTrigger;
 use crate::pouch_trait::{Pouch, PouchRole, PouchOutput, ProposalValidator, ValidatedProposal};
 use async_trait::async_trait;
 
@@ -57,6 +58,9 @@ impl Pouch for AudioPouch {
     }
     fn memory_count(&self) -> usize { self.index.len() }
     fn explain(&self) -> String { format!("AudioPouch: 音频描述检索，{}条", self.index.len()) }
+    fn triggers(&self) -> PouchTrigger {
+        PouchTrigger { phrases: vec!["音频检索".into(), "找音频".into()], negative: vec![], priority: 0 }
+    }
     fn atom_capabilities(&self) -> Vec<AtomDeclaration> {
         vec![AtomDeclaration {
             name: "audio_retrieve".into(),
@@ -1,4 +1,5 @@
 use crate::atom::{AtomDeclaration, AtomKind};
+use crate::frozen::logic::PouchTrigger;
 use crate::pouch_trait::{Pouch, PouchRole, PouchOutput, ProposalValidator, ValidatedProposal};
 use async_trait::async_trait;
 
@@ -126,6 +127,9 @@ impl Pouch for BenchmarkPouch {
     fn explain(&self) -> String {
         format!("BenchmarkPouch: 基准评估，学习{}条", self.learned.len())
     }
+    fn triggers(&self) -> PouchTrigger {
+        PouchTrigger { phrases: vec!["基准评估".into(), "基准测试".into(), "benchmark".into()], negative: vec![], priority: 0 }
+    }
     fn atom_capabilities(&self) -> Vec<AtomDeclaration> {
         vec![AtomDeclaration {
             name: "system_benchmark".into(),
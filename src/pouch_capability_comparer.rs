@@ -1,4 +1,5 @@
 use crate::atom::{AtomDeclaration, AtomKind};
+use crate::frozen::logic::PouchTrigger;
 use crate::pouch_trait::{Pouch, PouchRole, PouchOutput, ProposalValidator, ValidatedProposal};
 use async_trait::async_trait;
 
@@ -175,6 +176,9 @@ impl Pouch for CapabilityComparerPouch {
     fn evolution_gaps_from_output(&self, my_output: &str) -> Vec<(String, f64)> {
         self.parse_evolution_gaps(my_output)
     }
+    fn triggers(&self) -> PouchTrigger {
+        PouchTrigger { phrases: vec!["能力对标".into(), "对标能力".into()], negative: vec![], priority: 1 }
+    }
     fn atom_capabilities(&self) -> Vec<AtomDeclaration> {
         vec![
             AtomDeclaration { name: "compare".into(), kind: AtomKind::Match, pouch: self.name.clone(), confidence_range: (0.7, 0.95) },
@@ -21,6 +21,10 @@ pub fn instantiate(name: &str, data_dir: &str) -> Option<(Box<dyn Pouch>, PouchR
         return Some((Box::new(rp), PouchRole::E1));
     }
 
+    if let Some(exact) = exact_local(&name) {
+        return Some(exact);
+    }
+
     let (pouch, role): (Box<dyn Pouch>, PouchRole) =
         if name.contains("benchmark") || name.contains("基准") {
             (Box::new(crate::pouch_benchmark::BenchmarkPouch::new()), PouchRole::E1)
@@ -57,6 +61,8 @@ pub fn instantiate(name: &str, data_dir: &str) -> Option<(Box<dyn Pouch>, PouchR
             (Box::new(ChemistryPouch::new(&name)), PouchRole::E1)
         } else if name.contains("pilot") || name.contains("试点") {
             (Box::new(crate::pouch_pilot::PilotPouch::new()), PouchRole::E1)
+        } else if name == crate::atom::MERGE_ATOM {
+            (Box::new(crate::pouch_compose::ComposePouch::new(&name)), PouchRole::E1)
         } else {
             let endpoint = format!("{}/pouch/{}", GATEWAY_BASE, name);
             (Box::new(crate::remote_pouch::RemotePouch::new(&name, PouchRole::E1, &endpoint)), PouchRole::E1)
@@ -64,3 +70,24 @@ pub fn instantiate(name: &str, data_dir: &str) -> Option<(Box<dyn Pouch>, PouchR
 
     Some((pouch, role))
 }
+
+/*
+ * 只按完整名字安装的本地尿袋：不参与上面的关键词匹配，名字里含这些词的其他尿袋照旧解析。
+ */
+fn exact_local(name: &str) -> Option<(Box<dyn Pouch>, PouchRole)> {
+    let pouch: Box<dyn Pouch> = match name {
+        "sanitize" => return Some((Box::new(crate::pouch_sanitize::SanitizePouch::new(name)), PouchRole::E0)),
+        "code_template" => Box::new(crate::pouch_code_template::CodeTemplatePouch::new(name)),
+        "analogy" => Box::new(crate::pouch_analogy::AnalogyPouch::new(name)),
+        "induction" => Box::new(crate::pouch_induction::InductionPouch::new(name)),
+        "deduction" => Box::new(crate::pouch_deduction::DeductionPouch::new(name)),
+        "fragment" => Box::new(crate::pouch_fragment::FragmentPouch::new(name)),
+        "generator" => Box::new(crate::pouch_generator::GeneratorPouch::new(name)),
+        "explorer" => Box::new(crate::pouch_explorer::ExplorerPouch::new(name)),
+        "image" => Box::new(crate::pouch_image::ImagePouch::new(name)),
+        "audio" => Box::new(crate::pouch_audio::AudioPouch::new(name)),
+        "realtime" => Box::new(crate::pouch_realtime::RealtimePouch::new(name)),
+        _ => return None,
+    };
+    Some((pouch, PouchRole::E1))
+}
@@ -1,4 +1,5 @@
 use crate::atom::{AtomDeclaration, AtomKind};
+use crate::frozen::logic::PouchTrigger;
 use crate::pouch_trait::{Pouch, PouchRole, PouchOutput, ProposalValidator, ValidatedProposal};
 use async_trait::async_trait;
 
@@ -74,6 +75,9 @@ impl Pouch for CodeAnalyzerPouch {
     }
     fn memory_count(&self) -> usize { self.learned.len() }
     fn explain(&self) -> String { format!("CodeAnalyzerPouch: 代码分析，已学{}条", self.learned.len()) }
+    fn triggers(&self) -> PouchTrigger {
+        PouchTrigger { phrases: vec!["代码分析".into(), "分析代码".into()], negative: vec!["代码模板".into()], priority: 0 }
+    }
     fn atom_capabilities(&self) -> Vec<AtomDeclaration> {
         vec![AtomDeclaration {
             name: "code_analyze".into(),
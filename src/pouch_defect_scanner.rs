@@ -1,4 +1,5 @@
 use crate::atom::{AtomDeclaration, AtomKind};
+use crate::frozen::logic::PouchTrigger;
 use crate::pouch_trait::{Pouch, PouchRole, PouchOutput, ProposalValidator, ValidatedProposal};
 use async_trait::async_trait;
 
@@ -80,7 +81,7 @@ impl DefectScannerPouch {
             return "=== 缺陷扫描 ===\n未发现缺陷。系统状态良好。".to_string();
         }
 
-        defects.sort_by(|a, b| b.1.cmp(&a.1));
+        defects.sort_by_key(|d| std::cmp::Reverse(d.1));
 
         let mut result = format!("=== 缺陷扫描 ===\n发现 {} 个问题:\n", defects.len());
         for (id, sev, desc) in &defects {
@@ -148,6 +149,9 @@ impl Pouch for DefectScannerPouch {
     fn recommended_follow_ups(&self, my_output: &str) -> Vec<String> {
         DefectScannerPouch::recommended_follow_ups(self, my_output)
     }
+    fn triggers(&self) -> PouchTrigger {
+        PouchTrigger { phrases: vec!["缺陷扫描".into(), "扫描缺陷".into(), "defect scan".into()], negative: vec![], priority: 1 }
+    }
     fn atom_capabilities(&self) -> Vec<AtomDeclaration> {
         vec![AtomDeclaration {
             name: "defect_scan".into(),
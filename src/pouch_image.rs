@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 use crate::atom::{AtomDeclaration, AtomKind};
+use crate::frozen::logic::PouchTrigger;
 use crate::pouch_trait::{Pouch, PouchRole, PouchOutput, ProposalValidator, ValidatedProposal};
 use async_trait::async_trait;
 
@@ -57,6 +58,9 @@ impl Pouch for ImagePouch {
     }
     fn memory_count(&self) -> usize { self.index.len() }
     fn explain(&self) -> String { format!("ImagePouch: 图像描述检索，{}条", self.index.len()) }
+    fn triggers(&self) -> PouchTrigger {
+        PouchTrigger { phrases: vec!["图像检索".into(), "找图片".into()], negative: vec!["生成图片".into(), "画一张".into()], priority: 0 }
+    }
     fn atom_capabilities(&self) -> Vec<AtomDeclaration> {
         vec![AtomDeclaration {
             name: "image_retrieve".into(),
@@ -30,7 +30,7 @@ impl Pouch for InductionPouch {
     async fn process_proposal(&mut self, proposal: &ValidatedProposal) -> Result<PouchOutput, String> {
         let content = proposal.inner().content.to_lowercase();
         for (triggers, conclusion) in &self.rules {
-            if triggers.iter().filter(|t| content.contains(t.as_str())).count() >= triggers.len() / 2 + 1 {
+            if triggers.iter().filter(|t| content.contains(t.as_str())).count() > triggers.len() / 2 {
                 return Ok(PouchOutput {
                     data: conclusion.clone(),
                     confidence: 0.82,
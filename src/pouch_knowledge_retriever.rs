@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 use crate::atom::{AtomDeclaration, AtomKind};
+use crate::frozen::logic::PouchTrigger;
 use crate::pouch_trait::{Pouch, PouchRole, PouchOutput, ProposalValidator, ValidatedProposal};
 use async_trait::async_trait;
 
@@ -27,13 +28,22 @@ impl KnowledgeRetrieverPouch {
         }
     }
 
-    fn search(&self, query: &str) -> String {
-        for (key, value) in &self.knowledge_base {
-            if query.contains(key) {
-                return format!("{}: {}", key, value);
-            }
+    /*
+     * 多个词条同时出现时，优先对话中最近提到的实体，其次取最长词条（按字典序定序，结果确定）。
+     */
+    fn search(&self, query: &str, recent: &[String]) -> String {
+        let best = self
+            .knowledge_base
+            .iter()
+            .filter(|(key, _)| query.contains(key.as_str()))
+            .min_by_key(|(key, _)| {
+                let rank = recent.iter().position(|e| e.contains(key.as_str())).unwrap_or(usize::MAX);
+                (rank, std::cmp::Reverse(key.chars().count()), key.as_str())
+            });
+        match best {
+            Some((key, value)) => format!("{}: {}", key, value),
+            None => "未找到相关知识".to_string(),
         }
-        "未找到相关知识".to_string()
     }
 }
 
@@ -43,7 +53,8 @@ impl Pouch for KnowledgeRetrieverPouch {
     fn role(&self) -> PouchRole { PouchRole::E1 }
     fn validator(&self) -> &ProposalValidator { &self.validator }
     async fn process_proposal(&mut self, proposal: &ValidatedProposal) -> Result<PouchOutput, String> {
-        let result = self.search(&proposal.inner().content);
+        let inner = proposal.inner();
+        let result = self.search(&inner.content, &inner.dialogue.entities);
         Ok(PouchOutput { data: result, confidence: 0.85 })
     }
     fn sync_patterns(&mut self, patterns: &[(Vec<String>, String, f64)]) {
@@ -59,6 +70,9 @@ impl Pouch for KnowledgeRetrieverPouch {
     }
     fn memory_count(&self) -> usize { self.knowledge_base.len() }
     fn explain(&self) -> String { format!("KnowledgeRetrieverPouch: 知识检索，{}条", self.knowledge_base.len()) }
+    fn triggers(&self) -> PouchTrigger {
+        PouchTrigger { phrases: vec!["知识检索".into(), "查知识".into()], negative: vec![], priority: 0 }
+    }
     fn atom_capabilities(&self) -> Vec<AtomDeclaration> {
         vec![AtomDeclaration {
             name: "knowledge_retrieve".into(),
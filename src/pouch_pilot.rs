@@ -3,6 +3,7 @@
  * 当前适配：最小可执行形态，单 Transform 原子，供 e2e/路由偏好测试用。
  */
 use crate::atom::{AtomDeclaration, AtomKind};
+use crate::frozen::logic::PouchTrigger;
 use crate::pouch_trait::{Pouch, PouchRole, PouchOutput, ProposalValidator, ValidatedProposal};
 use async_trait::async_trait;
 
@@ -74,6 +75,9 @@ impl Pouch for PilotPouch {
     fn explain(&self) -> String {
         format!("PilotPouch: 试点验证，学习{}条", self.learned.len())
     }
+    fn triggers(&self) -> PouchTrigger {
+        PouchTrigger { phrases: vec!["试点".into(), "试点验证".into()], negative: vec![], priority: 0 }
+    }
     fn atom_capabilities(&self) -> Vec<AtomDeclaration> {
         vec![AtomDeclaration {
             name: "pilot_transform".into(),
@@ -1,4 +1,5 @@
 use crate::atom::{AtomDeclaration, AtomKind};
+use crate::frozen::logic::PouchTrigger;
 use crate::pouch_trait::{Pouch, PouchRole, PouchOutput, ProposalValidator, ValidatedProposal};
 use async_trait::async_trait;
 
@@ -69,6 +70,9 @@ impl Pouch for ProgrammingPouch {
     }
     fn memory_count(&self) -> usize { self.learned.len() }
     fn explain(&self) -> String { format!("ProgrammingPouch: 编程尿袋，已学{}条", self.learned.len()) }
+    fn triggers(&self) -> PouchTrigger {
+        PouchTrigger { phrases: vec!["编程".into(), "写代码".into()], negative: vec!["代码分析".into()], priority: 0 }
+    }
     fn atom_capabilities(&self) -> Vec<AtomDeclaration> {
         vec![AtomDeclaration {
             name: "code_template".into(),
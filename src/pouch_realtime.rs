@@ -1,4 +1,5 @@
 use crate::atom::{AtomDeclaration, AtomKind};
+use crate::frozen::logic::PouchTrigger;
 use crate::pouch_trait::{Pouch, PouchRole, PouchOutput, ProposalValidator, ValidatedProposal};
 use async_trait::async_trait;
 
@@ -51,6 +52,9 @@ impl Pouch for RealtimePouch {
     }
     fn memory_count(&self) -> usize { self.cache.len() }
     fn explain(&self) -> String { format!("RealtimePouch: 实时数据，{}条", self.cache.len()) }
+    fn triggers(&self) -> PouchTrigger {
+        PouchTrigger { phrases: vec!["实时数据".into(), "实时行情".into()], negative: vec![], priority: 0 }
+    }
     fn atom_capabilities(&self) -> Vec<AtomDeclaration> {
         vec![AtomDeclaration {
             name: "realtime_query".into(),
@@ -4,6 +4,8 @@ use std::collections::HashMap;
 use serde::{Serialize, Deserialize};
 use async_trait::async_trait;
 use crate::atom::{AtomKind, AtomDeclaration};
+use crate::frozen::logic::PouchTrigger;
+use crate::dialogue_state::DialogueSnapshot;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum PouchRole { E0, E1, E2 }
@@ -27,6 +29,14 @@ pub struct ProposalMessage {
     pub content: String,
     pub confidence: f32,
     pub evidence: Vec<String>,
+    pub dialogue: DialogueSnapshot,
+}
+
+impl ProposalMessage {
+    pub fn with_dialogue(mut self, dialogue: DialogueSnapshot) -> Self {
+        self.dialogue = dialogue;
+        self
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -64,12 +74,25 @@ impl ProposalValidator {
     }
 }
 
+/* 不借用尿袋本身的远程调用，见 Pouch::detach */
+pub type RemoteCall = futures_util::future::BoxFuture<'static, Result<PouchOutput, String>>;
+
 #[async_trait]
 pub trait Pouch: Send + Sync {
     fn name(&self) -> &str;
     fn role(&self) -> PouchRole;
     fn validator(&self) -> &ProposalValidator;
     async fn process_proposal(&mut self, proposal: &ValidatedProposal) -> Result<PouchOutput, String>;
+    /*
+     * 需要网络的提案拆成远程调用返回，编排器放开写锁后等待，结果再交给 finish_remote；
+     * 本地即可回答时返回 None，走 process_proposal。
+     */
+    fn detach(&self, _proposal: &ValidatedProposal) -> Option<RemoteCall> {
+        None
+    }
+    fn finish_remote(&mut self, result: Result<PouchOutput, String>) -> Result<PouchOutput, String> {
+        result
+    }
     fn can_call_others(&self) -> bool {
         matches!(self.role(), PouchRole::E1 | PouchRole::E2)
     }
@@ -89,6 +112,9 @@ pub trait Pouch: Send + Sync {
     fn evolution_gaps_from_output(&self, _my_output: &str) -> Vec<(String, f64)> {
         vec![]
     }
+    fn triggers(&self) -> PouchTrigger {
+        PouchTrigger::default()
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -256,6 +282,7 @@ pub fn create_proposal(content: &str) -> ProposalMessage {
         content: content.to_string(),
         confidence: 0.8,
         evidence: vec![],
+        dialogue: DialogueSnapshot::default(),
     }
 }
 
@@ -330,7 +357,7 @@ impl ReasoningPouch {
                     '+' => left + right,
                     '-' => left - right,
                     '*' => left * right,
-                    '/' => if right != 0.0 { left / right } else { return None; },
+                    '/' if right != 0.0 => left / right,
                     _ => return None,
                 });
             }
@@ -519,6 +546,13 @@ impl Pouch for MemoryPouch {
     fn explain(&self) -> String {
         format!("MemoryPouch: 记忆尿袋，存储{}条高质量记忆", self.memories.len())
     }
+    fn triggers(&self) -> PouchTrigger {
+        PouchTrigger {
+            phrases: vec!["记住".into(), "回忆一下".into(), "你还记得".into()],
+            negative: vec!["内存".into(), "memory leak".into()],
+            priority: 0,
+        }
+    }
     fn atom_capabilities(&self) -> Vec<AtomDeclaration> {
         vec![AtomDeclaration {
             name: "memory_query".into(),
@@ -627,37 +661,36 @@ impl CloudTrainerPouch {
         }
     }
 
-    async fn train_async(&mut self) -> Result<String, String> {
-        if self.pending_data.is_empty() {
-            return Err("没有训练数据".to_string());
-        }
-        let client = reqwest::Client::builder()
-            .timeout(std::time::Duration::from_secs(30))
-            .build()
-            .map_err(|e| format!("创建客户端失败: {}", e))?;
-        let pairs: Vec<serde_json::Value> = self.pending_data.iter()
+    fn training_pairs(&self) -> Vec<serde_json::Value> {
+        self.pending_data.iter()
             .map(|(input, output)| serde_json::json!({ "input": input, "output": output }))
-            .collect();
-        let endpoint = "https://logos-gateway.amrta.workers.dev/train";
-        let response = client
-            .post(endpoint)
-            .header("Content-Type", "application/json")
-            .json(&serde_json::json!({ "pairs": pairs }))
-            .send()
-            .await
-            .map_err(|e| format!("云端训练请求失败: {}", e))?;
-        if !response.status().is_success() {
-            let error_text = response.text().await.unwrap_or_else(|_| "无法读取错误".to_string());
-            return Err(format!("云端训练失败: {}", error_text));
-        }
-        let json: serde_json::Value = response.json().await
-            .map_err(|e| format!("解析失败: {}", e))?;
-        let count = json["patterns"].as_array().map(|a| a.len()).unwrap_or(0);
-        self.trained = true;
-        Ok(format!("云端训练完成，生成 {} 条模式", count))
+            .collect()
     }
 }
 
+async fn train_remote(pairs: Vec<serde_json::Value>) -> Result<PouchOutput, String> {
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(30))
+        .build()
+        .map_err(|e| format!("创建客户端失败: {}", e))?;
+    let endpoint = "https://logos-gateway.amrta.workers.dev/train";
+    let response = client
+        .post(endpoint)
+        .header("Content-Type", "application/json")
+        .json(&serde_json::json!({ "pairs": pairs }))
+        .send()
+        .await
+        .map_err(|e| format!("云端训练请求失败: {}", e))?;
+    if !response.status().is_success() {
+        let error_text = response.text().await.unwrap_or_else(|_| "无法读取错误".to_string());
+        return Err(format!("云端训练失败: {}", error_text));
+    }
+    let json: serde_json::Value = response.json().await
+        .map_err(|e| format!("解析失败: {}", e))?;
+    let count = json["patterns"].as_array().map(|a| a.len()).unwrap_or(0);
+    Ok(PouchOutput { data: format!("云端训练完成，生成 {} 条模式", count), confidence: 0.95 })
+}
+
 #[async_trait]
 impl Pouch for CloudTrainerPouch {
     fn name(&self) -> &str { &self.name }
@@ -674,17 +707,33 @@ impl Pouch for CloudTrainerPouch {
                 });
             }
         }
+        if let Some(call) = self.detach(proposal) {
+            let result = call.await;
+            return self.finish_remote(result);
+        }
         if inner.content == "train" || inner.proposal_type == "training_request" {
-            return match self.train_async().await {
-                Ok(msg) => Ok(PouchOutput { data: msg, confidence: 0.95 }),
-                Err(e) => Err(e),
-            };
+            return Err("没有训练数据".to_string());
         }
         Ok(PouchOutput {
             data: format!("CloudTrainer 待训练: {} 条", self.pending_data.len()),
             confidence: 0.9,
         })
     }
+    fn detach(&self, proposal: &ValidatedProposal) -> Option<RemoteCall> {
+        let inner = proposal.inner();
+        let collect = inner.proposal_type == "collect_data" && inner.content.contains("|||");
+        let train = inner.content == "train" || inner.proposal_type == "training_request";
+        if collect || !train || self.pending_data.is_empty() {
+            return None;
+        }
+        Some(Box::pin(train_remote(self.training_pairs())))
+    }
+    fn finish_remote(&mut self, result: Result<PouchOutput, String>) -> Result<PouchOutput, String> {
+        if result.is_ok() {
+            self.trained = true;
+        }
+        result
+    }
     fn memory_count(&self) -> usize { self.pending_data.len() }
     fn explain(&self) -> String {
         format!("CloudTrainerPouch: 云端训练尿袋，待训练 {} 条，状态: {}",
@@ -732,36 +781,46 @@ impl DiscoveryPouch {
         }
     }
 
-    async fn discover_services(&self) -> String {
-        let client = match reqwest::Client::builder()
-            .timeout(std::time::Duration::from_secs(30))
-            .build()
-        {
-            Ok(c) => c,
-            Err(_) => return r#"{"error":"无法连接","services":[]}"#.to_string(),
-        };
-        let endpoint = "https://logos-gateway.amrta.workers.dev/discover";
-        match client.get(endpoint).send().await {
-            Ok(resp) => resp.text().await.unwrap_or_else(|_| r#"{"error":"无法读取响应"}"#.into()),
-            Err(_) => r#"{"error":"发现服务不可用","services":[]}"#.to_string(),
-        }
+    fn learned_reply(&self, input: &str) -> Option<PouchOutput> {
+        let lower = input.to_lowercase();
+        self.learned.iter()
+            .find(|(tokens, _)| tokens.iter().filter(|t| lower.contains(t.as_str())).count() >= 2)
+            .map(|(_, response)| PouchOutput { data: response.clone(), confidence: 0.82 })
     }
 }
 
+async fn discover_services() -> Result<PouchOutput, String> {
+    let client = match reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(30))
+        .build()
+    {
+        Ok(c) => c,
+        Err(_) => return Ok(PouchOutput { data: r#"{"error":"无法连接","services":[]}"#.to_string(), confidence: 0.9 }),
+    };
+    let endpoint = "https://logos-gateway.amrta.workers.dev/discover";
+    let data = match client.get(endpoint).send().await {
+        Ok(resp) => resp.text().await.unwrap_or_else(|_| r#"{"error":"无法读取响应"}"#.into()),
+        Err(_) => r#"{"error":"发现服务不可用","services":[]}"#.to_string(),
+    };
+    Ok(PouchOutput { data, confidence: 0.9 })
+}
+
 #[async_trait]
 impl Pouch for DiscoveryPouch {
     fn name(&self) -> &str { &self.name }
     fn role(&self) -> PouchRole { PouchRole::E1 }
     fn validator(&self) -> &ProposalValidator { &self.validator }
     async fn process_proposal(&mut self, proposal: &ValidatedProposal) -> Result<PouchOutput, String> {
-        let input = &proposal.inner().content;
-        let lower = input.to_lowercase();
-        for (tokens, response) in &self.learned {
-            let hits = tokens.iter().filter(|t| lower.contains(t.as_str())).count();
-            if hits >= 2 { return Ok(PouchOutput { data: response.clone(), confidence: 0.82 }); }
+        match self.learned_reply(&proposal.inner().content) {
+            Some(out) => Ok(out),
+            None => discover_services().await,
+        }
+    }
+    fn detach(&self, proposal: &ValidatedProposal) -> Option<RemoteCall> {
+        match self.learned_reply(&proposal.inner().content) {
+            Some(_) => None,
+            None => Some(Box::pin(discover_services())),
         }
-        let result = self.discover_services().await;
-        Ok(PouchOutput { data: result, confidence: 0.9 })
     }
     fn sync_patterns(&mut self, patterns: &[(Vec<String>, String, f64)]) {
         for (tokens, content, weight) in patterns {
@@ -1161,6 +1220,7 @@ mod tests {
             content: "hello".into(),
             confidence: 0.6,
             evidence: vec!["e1".into()],
+            dialogue: DialogueSnapshot::default(),
         };
         assert!(validator.validate(&proposal).is_ok());
         let bad = ProposalMessage {
@@ -1168,6 +1228,7 @@ mod tests {
             content: "hello".into(),
             confidence: 0.6,
             evidence: vec!["e1".into()],
+            dialogue: DialogueSnapshot::default(),
         };
         assert!(validator.validate(&bad).is_err());
     }
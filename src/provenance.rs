@@ -0,0 +1,106 @@
+use serde::{Deserialize, Serialize};
+
+/*
+ * 模式来源与审计信息：每条模式记录从哪来、何时创建/修改、收到多少正负反馈。
+ *   - tick 是 LanguagePouch 的对话计数，时间戳是 Unix 秒；迁移来的旧模式时间戳为 0（未知）
+ *   - 修改：重新教学、吸收合并/替换回复、正负反馈；单纯命中只更新 last_used，不算修改
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Origin {
+    Seed,
+    Teach,
+    Absorb(String),
+    CloudPull,
+    Import(String),
+    Correction,
+    Legacy,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Provenance {
+    pub origin: Origin,
+    pub created_tick: u64,
+    pub created_at: u64,
+    pub modified_tick: u64,
+    pub modified_at: u64,
+    pub positive: u32,
+    pub negative: u32,
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+impl Origin {
+    /* 来源类别（不含具体尿袋/文件），用于筛选 */
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Origin::Seed => "seed",
+            Origin::Teach => "teach",
+            Origin::Absorb(_) => "absorb",
+            Origin::CloudPull => "cloud_pull",
+            Origin::Import(_) => "import",
+            Origin::Correction => "correction",
+            Origin::Legacy => "legacy",
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            Origin::Seed => "种子".into(),
+            Origin::Teach => "教学".into(),
+            Origin::Absorb(pouch) => format!("吸收({})", pouch),
+            Origin::CloudPull => "云端拉取".into(),
+            Origin::Import(source) => format!("导入({})", source),
+            Origin::Correction => "反馈纠正".into(),
+            Origin::Legacy => "旧数据迁移".into(),
+        }
+    }
+}
+
+impl Provenance {
+    pub fn new(origin: Origin, tick: u64) -> Self {
+        let now = now_secs();
+        Self {
+            origin,
+            created_tick: tick,
+            created_at: now,
+            modified_tick: tick,
+            modified_at: now,
+            positive: 0,
+            negative: 0,
+        }
+    }
+
+    /*
+     * 旧格式没有来源信息：只知道最后使用的 tick。
+     */
+    pub fn legacy(last_used: u64) -> Self {
+        Self {
+            origin: Origin::Legacy,
+            created_tick: 0,
+            created_at: 0,
+            modified_tick: last_used,
+            modified_at: 0,
+            positive: 0,
+            negative: 0,
+        }
+    }
+
+    pub fn touch(&mut self, tick: u64) {
+        self.modified_tick = tick;
+        self.modified_at = now_secs();
+    }
+
+    pub fn record_signal(&mut self, positive: bool, tick: u64) {
+        if positive {
+            self.positive = self.positive.saturating_add(1);
+        } else {
+            self.negative = self.negative.saturating_add(1);
+        }
+        self.touch(tick);
+    }
+}
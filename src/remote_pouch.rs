@@ -1,5 +1,5 @@
 use crate::atom::{AtomDeclaration, AtomKind};
-use crate::pouch_trait::{Pouch, PouchOutput, PouchRole, ProposalValidator, ValidatedProposal};
+use crate::pouch_trait::{Pouch, PouchOutput, PouchRole, ProposalValidator, RemoteCall, ValidatedProposal};
 use async_trait::async_trait;
 use std::time::Duration;
 
@@ -28,56 +28,63 @@ impl RemotePouch {
         }
     }
 
-    async fn call_with_failover(&self, input: &str) -> String {
-        let result = self.call_endpoint(&self.endpoint, input).await;
-        if !result.contains("暂时不可用") {
-            return result;
-        }
-
-        for ep in &self.failover_endpoints {
-            let result = self.call_endpoint(ep, input).await;
-            if !result.contains("暂时不可用") {
-                return result;
-            }
-        }
+    fn endpoints(&self) -> Vec<String> {
+        std::iter::once(self.endpoint.clone()).chain(self.failover_endpoints.iter().cloned()).collect()
+    }
 
-        result
+    fn learned_reply(&self, input: &str) -> Option<PouchOutput> {
+        let lower = input.to_lowercase();
+        self.learned.iter()
+            .find(|(tokens, _)| tokens.iter().filter(|t| lower.contains(t.as_str())).count() >= 2)
+            .map(|(_, response)| PouchOutput { data: response.clone(), confidence: 0.82 })
     }
+}
 
-    async fn call_endpoint(&self, endpoint: &str, input: &str) -> String {
-        let client = match reqwest::Client::builder()
-            .timeout(Duration::from_secs(10))
-            .build()
-        {
-            Ok(c) => c,
-            Err(_) => return "远程尿袋服务暂时不可用".into(),
-        };
-        let payload = serde_json::json!({ "input": input });
-        let response = match client
-            .post(endpoint)
-            .header("Content-Type", "application/json")
-            .json(&payload)
-            .send()
-            .await
-        {
-            Ok(r) => r,
-            Err(_) => return "远程尿袋服务暂时不可用".into(),
-        };
-        if !response.status().is_success() {
-            return "远程尿袋服务暂时不可用".into();
+/* 依次尝试主端点和容灾端点，不借用尿袋，可在编排器放开锁后等待 */
+async fn call_with_failover(endpoints: Vec<String>, input: String) -> Result<PouchOutput, String> {
+    let mut result = String::new();
+    for ep in &endpoints {
+        result = call_endpoint(ep, &input).await;
+        if !result.contains("暂时不可用") {
+            break;
         }
-        match response.json::<serde_json::Value>().await {
-            Ok(json) => {
-                if let Some(result) = json.get("result").and_then(|v| v.as_str()) {
-                    result.to_string()
-                } else if let Some(output) = json.get("output").and_then(|v| v.as_str()) {
-                    output.to_string()
-                } else {
-                    serde_json::to_string(&json).unwrap_or_else(|_| "远程尿袋服务暂时不可用".into())
-                }
+    }
+    Ok(PouchOutput { data: result, confidence: 0.8 })
+}
+
+async fn call_endpoint(endpoint: &str, input: &str) -> String {
+    let client = match reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+    {
+        Ok(c) => c,
+        Err(_) => return "远程尿袋服务暂时不可用".into(),
+    };
+    let payload = serde_json::json!({ "input": input });
+    let response = match client
+        .post(endpoint)
+        .header("Content-Type", "application/json")
+        .json(&payload)
+        .send()
+        .await
+    {
+        Ok(r) => r,
+        Err(_) => return "远程尿袋服务暂时不可用".into(),
+    };
+    if !response.status().is_success() {
+        return "远程尿袋服务暂时不可用".into();
+    }
+    match response.json::<serde_json::Value>().await {
+        Ok(json) => {
+            if let Some(result) = json.get("result").and_then(|v| v.as_str()) {
+                result.to_string()
+            } else if let Some(output) = json.get("output").and_then(|v| v.as_str()) {
+                output.to_string()
+            } else {
+                serde_json::to_string(&json).unwrap_or_else(|_| "远程尿袋服务暂时不可用".into())
             }
-            Err(_) => "远程尿袋服务暂时不可用".into(),
         }
+        Err(_) => "远程尿袋服务暂时不可用".into(),
     }
 }
 
@@ -88,13 +95,17 @@ impl Pouch for RemotePouch {
     fn validator(&self) -> &ProposalValidator { &self.validator }
     async fn process_proposal(&mut self, proposal: &ValidatedProposal) -> Result<PouchOutput, String> {
         let input = &proposal.inner().content;
-        let lower = input.to_lowercase();
-        for (tokens, response) in &self.learned {
-            let hits = tokens.iter().filter(|t| lower.contains(t.as_str())).count();
-            if hits >= 2 { return Ok(PouchOutput { data: response.clone(), confidence: 0.82 }); }
+        match self.learned_reply(input) {
+            Some(out) => Ok(out),
+            None => call_with_failover(self.endpoints(), input.clone()).await,
+        }
+    }
+    fn detach(&self, proposal: &ValidatedProposal) -> Option<RemoteCall> {
+        let input = &proposal.inner().content;
+        match self.learned_reply(input) {
+            Some(_) => None,
+            None => Some(Box::pin(call_with_failover(self.endpoints(), input.clone()))),
         }
-        let result = self.call_with_failover(input).await;
-        Ok(PouchOutput { data: result, confidence: 0.8 })
     }
     fn sync_patterns(&mut self, patterns: &[(Vec<String>, String, f64)]) {
         for (tokens, content, weight) in patterns {
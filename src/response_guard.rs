@@ -0,0 +1,301 @@
+use crate::provenance::Origin;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/*
+ * 回复拦截：屏蔽规则 + 否定模式 + 隔离区，每个命名空间一份（guard.json）。
+ *   - 屏蔽：回复包含某段文字，或匹配正则（写成 /…/ 或 re:…）时拦截，不论触发词
+ *   - 否定模式「不要用 Y 回答 X」：输入与 X 的 token 重合率 ≥ NEGATIVE_TRIGGER_OVERLAP
+ *     且回复包含 Y 时拦截；只存 X 的原文，token 在加载或换分词器时由 compile 重新切分
+ *   - 学习入口（吸收、教学、同步缓冲、云端拉取）命中时不学，候选放入隔离区待审：
+ *     放行后按原来源学习（不再检查），丢弃则删除；隔离区满时丢最旧的
+ *   - 匹配时同样生效：已学到的被拦截变体不再被选中
+ */
+const NEGATIVE_TRIGGER_OVERLAP: f64 = 0.7;
+const QUARANTINE_MAX: usize = 500;
+const PREVIEW_CHARS: usize = 40;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum GuardRule {
+    Substring { text: String },
+    Regex { pattern: String },
+    Negative {
+        trigger: String,
+        #[serde(default, skip_serializing)]
+        tokens: Vec<String>,
+        response: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GuardEntry {
+    pub id: u32,
+    pub rule: GuardRule,
+    pub created_at: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuarantineEntry {
+    pub id: u32,
+    pub input: String,
+    pub response: String,
+    pub origin: Origin,
+    pub reason: String,
+    pub at: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ResponseGuard {
+    next_id: u32,
+    rules: Vec<GuardEntry>,
+    quarantine: Vec<QuarantineEntry>,
+    #[serde(default)]
+    quarantined_total: u64,
+    #[serde(skip)]
+    compiled: HashMap<u32, Regex>,
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+fn preview(s: &str) -> String {
+    let mut out: String = s.chars().take(PREVIEW_CHARS).collect();
+    if s.chars().count() > PREVIEW_CHARS {
+        out.push('…');
+    }
+    out
+}
+
+impl GuardRule {
+    /*
+     * 屏蔽规则写法：/正则/ 或 re:正则 为正则，其余为包含的文字。
+     * 空正则或连空回复都能匹配的正则（如 .*、^）会拦下一切，拒绝。
+     */
+    pub fn parse_block(spec: &str) -> Result<Self, String> {
+        let spec = spec.trim();
+        let pattern = spec
+            .strip_prefix("re:")
+            .or_else(|| spec.strip_prefix('/').and_then(|s| s.strip_suffix('/')));
+        match pattern {
+            Some(p) => {
+                let re = Regex::new(p).map_err(|e| format!("正则无效: {}", e))?;
+                if p.trim().is_empty() || re.is_match("") {
+                    return Err(format!("正则会匹配任意回复: {}", p));
+                }
+                Ok(GuardRule::Regex { pattern: p.to_string() })
+            }
+            None if spec.is_empty() => Err("屏蔽内容为空".into()),
+            None => Ok(GuardRule::Substring { text: spec.to_string() }),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            GuardRule::Substring { text } => format!("屏蔽文字「{}」", text),
+            GuardRule::Regex { pattern } => format!("屏蔽正则 /{}/", pattern),
+            GuardRule::Negative { trigger, response, .. } => format!("不要用「{}」回答「{}」", response, trigger),
+        }
+    }
+}
+
+impl ResponseGuard {
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    pub fn rules(&self) -> &[GuardEntry] {
+        &self.rules
+    }
+
+    pub fn quarantined(&self) -> &[QuarantineEntry] {
+        &self.quarantine
+    }
+
+    /* 累计放入隔离区的条数（含已被挤出的） */
+    pub fn quarantined_total(&self) -> u64 {
+        self.quarantined_total
+    }
+
+    fn next(&mut self) -> u32 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    /* 反序列化或换分词器后重建正则缓存，并按当前分词器重新切分否定模式的触发词 */
+    pub fn compile(&mut self, tokenize: impl Fn(&str) -> Vec<String>) {
+        for e in self.rules.iter_mut() {
+            if let GuardRule::Negative { trigger, tokens, .. } = &mut e.rule {
+                *tokens = tokenize(trigger);
+            }
+        }
+        self.compiled = self
+            .rules
+            .iter()
+            .filter_map(|e| match &e.rule {
+                GuardRule::Regex { pattern } => Regex::new(pattern).ok().map(|re| (e.id, re)),
+                _ => None,
+            })
+            .collect();
+    }
+
+    /*
+     * 添加规则；已有相同规则时返回原编号。
+     */
+    pub fn add(&mut self, rule: GuardRule) -> Result<u32, String> {
+        if let Some(e) = self.rules.iter().find(|e| e.rule == rule) {
+            return Ok(e.id);
+        }
+        let id = self.next();
+        if let GuardRule::Regex { pattern } = &rule {
+            let re = Regex::new(pattern).map_err(|e| format!("正则无效: {}", e))?;
+            self.compiled.insert(id, re);
+        }
+        self.rules.push(GuardEntry { id, rule, created_at: now_secs() });
+        Ok(id)
+    }
+
+    pub fn remove(&mut self, id: u32) -> bool {
+        let before = self.rules.len();
+        self.rules.retain(|e| e.id != id);
+        self.compiled.remove(&id);
+        self.rules.len() != before
+    }
+
+    /*
+     * 返回拦截原因；tokens 为输入的分词结果（否定模式用）。
+     */
+    pub fn check(&self, tokens: &[String], response: &str) -> Option<String> {
+        let hit = self.rules.iter().find(|e| match &e.rule {
+            GuardRule::Substring { text } => response.contains(text.as_str()),
+            GuardRule::Regex { .. } => self.compiled.get(&e.id).is_some_and(|re| re.is_match(response)),
+            GuardRule::Negative { tokens: trigger, response: banned, .. } => {
+                let common = trigger.iter().filter(|t| tokens.contains(t)).count();
+                let denom = trigger.len().max(tokens.len());
+                denom > 0
+                    && common as f64 / denom as f64 >= NEGATIVE_TRIGGER_OVERLAP
+                    && response.contains(banned.as_str())
+            }
+        })?;
+        Some(format!("#{} {}", hit.id, hit.rule.describe()))
+    }
+
+    /*
+     * 放入隔离区；同一输入和回复只保留一条。
+     */
+    pub fn quarantine(&mut self, input: &str, response: &str, origin: Origin, reason: String) {
+        if self.quarantine.iter().any(|q| q.input == input && q.response == response) {
+            return;
+        }
+        if self.quarantine.len() >= QUARANTINE_MAX {
+            self.quarantine.remove(0);
+        }
+        let id = self.next();
+        self.quarantined_total += 1;
+        self.quarantine.push(QuarantineEntry {
+            id,
+            input: input.to_string(),
+            response: response.to_string(),
+            origin,
+            reason,
+            at: now_secs(),
+        });
+    }
+
+    pub fn quarantined_entry(&self, id: u32) -> Option<&QuarantineEntry> {
+        self.quarantine.iter().find(|q| q.id == id)
+    }
+
+    pub fn take_quarantined(&mut self, id: u32) -> Option<QuarantineEntry> {
+        let pos = self.quarantine.iter().position(|q| q.id == id)?;
+        Some(self.quarantine.remove(pos))
+    }
+
+    pub fn render_rules(&self) -> String {
+        if self.rules.is_empty() {
+            return "没有屏蔽规则".into();
+        }
+        let mut out = format!("屏蔽规则 {} 条:", self.rules.len());
+        for e in &self.rules {
+            out.push_str(&format!("\n#{} {}", e.id, e.rule.describe()));
+        }
+        out
+    }
+
+    pub fn render_quarantine(&self) -> String {
+        if self.quarantine.is_empty() {
+            return "隔离区为空".into();
+        }
+        let mut out = format!("隔离区 {} 条（放行 <编号> / 丢弃隔离 <编号>）:", self.quarantine.len());
+        for q in &self.quarantine {
+            out.push_str(&format!(
+                "\n#{} {} → {} ({}，{})",
+                q.id,
+                preview(&q.input),
+                preview(&q.response),
+                q.origin.label(),
+                q.reason
+            ));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rules_check_and_quarantine() {
+        let mut guard = ResponseGuard::default();
+        assert!(GuardRule::parse_block("/([/").is_err());
+        for everything in ["re:", "re:.*", "//", "/^/", "re:a*"] {
+            assert!(GuardRule::parse_block(everything).is_err(), "{}", everything);
+        }
+        let substring = match GuardRule::parse_block("文心一言") {
+            Ok(r) => r,
+            Err(e) => panic!("{}", e),
+        };
+        let regex = match GuardRule::parse_block("re:TODO|FIXME") {
+            Ok(r) => r,
+            Err(e) => panic!("{}", e),
+        };
+        assert!(matches!(regex, GuardRule::Regex { .. }));
+        let id = guard.add(substring.clone()).unwrap_or_default();
+        assert_eq!(guard.add(substring), Ok(id));
+        assert!(guard.add(regex).is_ok());
+        let tokens = vec!["你".to_string(), "是".to_string(), "谁".to_string()];
+        let negative = GuardRule::Negative { trigger: "你是谁".into(), tokens: tokens.clone(), response: "机器人".into() };
+        assert!(guard.add(negative).is_ok());
+
+        assert!(guard.check(&[], "我是文心一言").is_some());
+        assert!(guard.check(&[], "稍后补充 TODO").is_some());
+        assert!(guard.check(&tokens, "我是机器人").is_some());
+        assert!(guard.check(&["天气".to_string()], "我是机器人").is_none());
+        assert!(guard.check(&tokens, "我是逻各斯").is_none());
+
+        guard.quarantine("你是谁", "我是文心一言", Origin::CloudPull, "r".into());
+        guard.quarantine("你是谁", "我是文心一言", Origin::CloudPull, "r".into());
+        assert_eq!(guard.quarantined().len(), 1);
+        let qid = guard.quarantined()[0].id;
+        assert_eq!(guard.take_quarantined(qid).map(|q| q.origin), Some(Origin::CloudPull));
+        assert!(guard.remove(id));
+        assert!(guard.check(&[], "我是文心一言").is_none());
+
+        let saved = serde_json::to_string(&guard).unwrap_or_default();
+        assert!(!saved.contains("\"tokens\""), "{}", saved);
+        let mut loaded: ResponseGuard = match serde_json::from_str(&saved) {
+            Ok(g) => g,
+            Err(e) => panic!("{}", e),
+        };
+        loaded.compile(|t| vec![t.to_string()]);
+        assert!(loaded.check(&["你是谁".to_string()], "我是机器人").is_some());
+        assert!(loaded.check(&tokens, "我是机器人").is_none());
+        assert!(loaded.check(&[], "稍后补充 TODO").is_some());
+    }
+}
@@ -0,0 +1,193 @@
+use serde::{Deserialize, Serialize};
+use std::hash::{Hash, Hasher};
+
+/*
+ * 模式的回复变体：一个模式可以有多个回复，各自带权重和生效条件。
+ *   - 条件：after（上一轮用户输入包含该文本）、session_tag（当前会话带该标签）、
+ *     hours（本地时间小时窗口 [start, end)，start > end 表示跨午夜）
+ *   - 选择：先按条件过滤；有带条件的变体满足时只在这些更具体的变体里选；
+ *     再去掉权重不足最高者一半的变体，剩下的按权重做确定性加权抽取，种子来自输入哈希，
+ *     保证「相同输入 + 相同上下文 → 相同输出」
+ * 教学语法：回复末尾可带 [[when after=你好; tag=vip; hours=9-18]]（只在交互教学「教你」「补充回复」时解析）
+ */
+pub const MAX_VARIANTS: usize = 8;
+const COMPETITIVE_RATIO: f64 = 0.5;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VariantCondition {
+    pub after: Option<String>,
+    pub session_tag: Option<String>,
+    pub hours: Option<(u8, u8)>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResponseVariant {
+    pub text: String,
+    pub weight: f64,
+    pub condition: VariantCondition,
+}
+
+/*
+ * 选择变体时可见的上下文。
+ */
+pub struct VariantContext<'a> {
+    pub prev_input: Option<&'a str>,
+    pub session_tags: &'a [String],
+    pub hour: u8,
+}
+
+impl VariantCondition {
+    pub fn is_unconditional(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn matches(&self, ctx: &VariantContext) -> bool {
+        if let Some(after) = &self.after {
+            let hit = ctx
+                .prev_input
+                .is_some_and(|prev| prev.to_lowercase().contains(&after.to_lowercase()));
+            if !hit {
+                return false;
+            }
+        }
+        if let Some(tag) = &self.session_tag {
+            if !ctx.session_tags.iter().any(|t| t == tag) {
+                return false;
+            }
+        }
+        if let Some((start, end)) = self.hours {
+            let in_window = if start <= end {
+                ctx.hour >= start && ctx.hour < end
+            } else {
+                ctx.hour >= start || ctx.hour < end
+            };
+            if !in_window {
+                return false;
+            }
+        }
+        true
+    }
+
+    /*
+     * 从回复文本末尾拆出 [[when ...]] 条件块；没有条件块时原样返回。
+     */
+    pub fn split_suffix(response: &str) -> Result<(String, VariantCondition), String> {
+        let trimmed = response.trim_end();
+        let start = match (trimmed.ends_with("]]"), trimmed.rfind("[[when")) {
+            (true, Some(i)) => i,
+            _ => return Ok((response.to_string(), VariantCondition::default())),
+        };
+        let body = &trimmed[start + "[[when".len()..trimmed.len() - 2];
+        let mut cond = VariantCondition::default();
+        for part in body.split(';').map(str::trim).filter(|p| !p.is_empty()) {
+            let (key, value) = match part.split_once('=') {
+                Some((k, v)) => (k.trim(), v.trim()),
+                None => return Err(format!("条件格式错误: {}", part)),
+            };
+            if value.is_empty() {
+                return Err(format!("条件缺少取值: {}", key));
+            }
+            match key {
+                "after" => cond.after = Some(value.to_string()),
+                "tag" => cond.session_tag = Some(value.to_string()),
+                "hours" => {
+                    let parsed = value
+                        .split_once('-')
+                        .and_then(|(a, b)| Some((a.trim().parse::<u8>().ok()?, b.trim().parse::<u8>().ok()?)));
+                    match parsed {
+                        Some((a, b)) if a < 24 && b <= 24 && a != b => cond.hours = Some((a, b)),
+                        _ => return Err(format!("时间窗口应为 起-止 小时(0-24): {}", value)),
+                    }
+                }
+                _ => return Err(format!("未知条件: {}", key)),
+            }
+        }
+        let text = trimmed[..start].trim_end().to_string();
+        if text.is_empty() {
+            return Err("回复内容为空".into());
+        }
+        Ok((text, cond))
+    }
+}
+
+fn unit_seed(seed_text: &str, salt: u64) -> f64 {
+    let mut h = std::collections::hash_map::DefaultHasher::new();
+    seed_text.hash(&mut h);
+    salt.hash(&mut h);
+    (h.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/*
+ * 返回被选中变体的下标；没有满足条件的变体时返回 None（该模式本轮不参与匹配）。
+ */
+pub fn select(variants: &[ResponseVariant], ctx: &VariantContext, seed_text: &str, salt: u64) -> Option<usize> {
+    let eligible: Vec<usize> = (0..variants.len()).filter(|&i| variants[i].condition.matches(ctx)).collect();
+    let specific: Vec<usize> = eligible
+        .iter()
+        .copied()
+        .filter(|&i| !variants[i].condition.is_unconditional())
+        .collect();
+    let pool = if specific.is_empty() { eligible } else { specific };
+    let max = pool.iter().map(|&i| variants[i].weight).fold(0.0, f64::max);
+    let pool: Vec<usize> = pool
+        .into_iter()
+        .filter(|&i| variants[i].weight >= max * COMPETITIVE_RATIO)
+        .collect();
+    let total: f64 = pool.iter().map(|&i| variants[i].weight.max(0.0)).sum();
+    if total <= 0.0 {
+        return pool.first().copied();
+    }
+    let mut point = unit_seed(seed_text, salt) * total;
+    for &i in &pool {
+        point -= variants[i].weight.max(0.0);
+        if point < 0.0 {
+            return Some(i);
+        }
+    }
+    pool.last().copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(text: &str, weight: f64, condition: VariantCondition) -> ResponseVariant {
+        ResponseVariant { text: text.into(), weight, condition }
+    }
+
+    #[test]
+    fn test_split_suffix_parses_conditions() {
+        let (text, cond) = match VariantCondition::split_suffix("早上好呀 [[when after=你好; tag=vip; hours=22-6]]") {
+            Ok(r) => r,
+            Err(e) => panic!("{}", e),
+        };
+        assert_eq!(text, "早上好呀");
+        assert_eq!(cond.after.as_deref(), Some("你好"));
+        assert_eq!(cond.session_tag.as_deref(), Some("vip"));
+        assert_eq!(cond.hours, Some((22, 6)));
+        assert!(VariantCondition::split_suffix("x [[when color=red]]").is_err());
+        let plain = VariantCondition::split_suffix("普通回复");
+        assert!(plain.is_ok_and(|(t, c)| t == "普通回复" && c.is_unconditional()));
+    }
+
+    #[test]
+    fn test_select_is_deterministic_and_prefers_specific() {
+        let tags = vec!["vip".to_string()];
+        let variants = vec![
+            v("甲", 1.0, VariantCondition::default()),
+            v("乙", 1.0, VariantCondition::default()),
+            v("贵宾", 1.0, VariantCondition { session_tag: Some("vip".into()), ..Default::default() }),
+            v("深夜", 1.0, VariantCondition { hours: Some((23, 5)), ..Default::default() }),
+        ];
+        let day = VariantContext { prev_input: None, session_tags: &[], hour: 12 };
+        let first = select(&variants, &day, "输入", 7);
+        assert!(matches!(first, Some(0) | Some(1)));
+        assert!((0..10).all(|_| select(&variants, &day, "输入", 7) == first));
+        let vip = VariantContext { prev_input: None, session_tags: &tags, hour: 12 };
+        assert_eq!(select(&variants, &vip, "输入", 7), Some(2));
+        let night = VariantContext { prev_input: None, session_tags: &[], hour: 2 };
+        assert_eq!(select(&variants, &night, "输入", 7), Some(3));
+        let only_conditional = vec![variants[2].clone()];
+        assert_eq!(select(&only_conditional, &day, "输入", 7), None);
+    }
+}
@@ -0,0 +1,215 @@
+use crate::dialogue_state::DialogueState;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+pub const DEFAULT_SESSION: &str = "default";
+const SESSION_ID_MAX_LEN: usize = 64;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub context: Vec<(String, String)>,
+    pub created_at: u64,
+    pub last_active: u64,
+    pub turns: u64,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub dialogue: DialogueState,
+    #[serde(default)]
+    pub namespace: Option<String>,
+}
+
+impl Session {
+    fn new(now: u64) -> Self {
+        Self {
+            context: Vec::new(),
+            created_at: now,
+            last_active: now,
+            turns: 0,
+            tags: Vec::new(),
+            dialogue: DialogueState::default(),
+            namespace: None,
+        }
+    }
+}
+
+/*
+ * 会话存储：每个 session_id 独立保存对话上下文（context），
+ * 使反馈检测（好/不对）与 context_fallback 只作用于本会话的上一轮。
+ *   - 空闲超过 idle_ttl_secs 的会话在 expire_idle 时清除
+ *   - 超过 max_sessions 时淘汰最久未活动的会话
+ */
+#[derive(Debug, Default)]
+pub struct SessionStore {
+    sessions: HashMap<String, Session>,
+}
+
+impl SessionStore {
+    pub fn new() -> Self {
+        Self { sessions: HashMap::new() }
+    }
+
+    pub fn normalize_id(raw: Option<&str>) -> String {
+        let id: String = raw
+            .unwrap_or("")
+            .trim()
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+            .take(SESSION_ID_MAX_LEN)
+            .collect();
+        if id.is_empty() {
+            DEFAULT_SESSION.to_string()
+        } else {
+            id
+        }
+    }
+
+    pub fn take_context(&mut self, id: &str, now: u64, max_sessions: usize) -> Vec<(String, String)> {
+        if !self.sessions.contains_key(id) {
+            self.evict_oldest(max_sessions.max(1) - 1);
+        }
+        let session = self.sessions.entry(id.to_string()).or_insert_with(|| Session::new(now));
+        session.last_active = now;
+        std::mem::take(&mut session.context)
+    }
+
+    /* 会话上一轮的输入（只读，供读锁下的预先匹配使用） */
+    pub fn last_input(&self, id: &str) -> Option<&str> {
+        self.sessions.get(id)?.context.last().map(|(input, _)| input.as_str())
+    }
+
+    pub fn put_context(&mut self, id: &str, context: Vec<(String, String)>, now: u64) {
+        let session = self.sessions.entry(id.to_string()).or_insert_with(|| Session::new(now));
+        session.context = context;
+        session.last_active = now;
+        session.turns += 1;
+    }
+
+    /*
+     * 会话标签供条件回复变体使用（[[when tag=...]]），随会话持久化。
+     */
+    pub fn set_tags(&mut self, id: &str, tags: Vec<String>, now: u64) {
+        let session = self.sessions.entry(id.to_string()).or_insert_with(|| Session::new(now));
+        session.tags = tags;
+    }
+
+    pub fn tags(&self, id: &str) -> Vec<String> {
+        self.sessions.get(id).map(|s| s.tags.clone()).unwrap_or_default()
+    }
+
+    pub fn take_dialogue(&mut self, id: &str) -> DialogueState {
+        self.sessions.get_mut(id).map(|s| std::mem::take(&mut s.dialogue)).unwrap_or_default()
+    }
+
+    pub fn put_dialogue(&mut self, id: &str, dialogue: DialogueState) {
+        if let Some(s) = self.sessions.get_mut(id) {
+            s.dialogue = dialogue;
+        }
+    }
+
+    /*
+     * 会话选用的语言模式命名空间（None 为 base），随会话持久化。
+     */
+    pub fn namespace(&self, id: &str) -> Option<String> {
+        self.sessions.get(id).and_then(|s| s.namespace.clone())
+    }
+
+    pub fn set_namespace(&mut self, id: &str, namespace: Option<String>) {
+        if let Some(s) = self.sessions.get_mut(id) {
+            s.namespace = namespace;
+        }
+    }
+
+    /*
+     * 命名空间被删除后，选用它的会话回到 base。
+     */
+    pub fn clear_namespace(&mut self, namespace: &str) {
+        for s in self.sessions.values_mut() {
+            if s.namespace.as_deref() == Some(namespace) {
+                s.namespace = None;
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.sessions.len()
+    }
+
+    pub fn expire_idle(&mut self, now: u64, idle_ttl_secs: u64) -> usize {
+        let before = self.sessions.len();
+        self.sessions
+            .retain(|id, s| id == DEFAULT_SESSION || now.saturating_sub(s.last_active) <= idle_ttl_secs);
+        before - self.sessions.len()
+    }
+
+    fn evict_oldest(&mut self, keep: usize) {
+        while self.sessions.len() > keep {
+            let oldest = self
+                .sessions
+                .iter()
+                .filter(|(id, _)| id.as_str() != DEFAULT_SESSION)
+                .min_by_key(|(_, s)| s.last_active)
+                .map(|(id, _)| id.clone());
+            match oldest {
+                Some(id) => {
+                    self.sessions.remove(&id);
+                }
+                None => break,
+            }
+        }
+    }
+
+    pub fn save(&self) -> Result<Vec<u8>, String> {
+        serde_json::to_vec(&self.sessions).map_err(|e| format!("序列化失败: {}", e))
+    }
+
+    pub fn load(&mut self, data: &[u8]) -> Result<(), String> {
+        self.sessions = serde_json::from_slice(data).map_err(|e| format!("反序列化失败: {}", e))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_id() {
+        assert_eq!(SessionStore::normalize_id(None), DEFAULT_SESSION);
+        assert_eq!(SessionStore::normalize_id(Some("  ")), DEFAULT_SESSION);
+        assert_eq!(SessionStore::normalize_id(Some("tab-1")), "tab-1");
+        assert_eq!(SessionStore::normalize_id(Some("a/b c")), "abc");
+    }
+
+    #[test]
+    fn test_context_isolated_per_session() {
+        let mut store = SessionStore::new();
+        let mut ctx = store.take_context("a", 10, 8);
+        ctx.push(("你好".into(), "你好。有什么需要？".into()));
+        store.put_context("a", ctx, 10);
+        assert!(store.take_context("b", 11, 8).is_empty());
+        assert_eq!(store.take_context("a", 12, 8).len(), 1);
+    }
+
+    #[test]
+    fn test_expire_idle_keeps_default() {
+        let mut store = SessionStore::new();
+        store.put_context(DEFAULT_SESSION, Vec::new(), 0);
+        store.put_context("old", Vec::new(), 0);
+        store.put_context("fresh", Vec::new(), 100);
+        assert_eq!(store.expire_idle(100, 50), 1);
+        assert!(store.sessions.contains_key(DEFAULT_SESSION));
+        assert!(!store.sessions.contains_key("old"));
+        assert!(store.sessions.contains_key("fresh"));
+    }
+
+    #[test]
+    fn test_max_sessions_evicts_oldest() {
+        let mut store = SessionStore::new();
+        store.put_context("s1", Vec::new(), 1);
+        store.put_context("s2", Vec::new(), 2);
+        let _ = store.take_context("s3", 3, 2);
+        assert_eq!(store.len(), 2);
+        assert!(!store.sessions.contains_key("s1"));
+    }
+}
@@ -0,0 +1,327 @@
+use serde::{Deserialize, Serialize};
+
+/*
+ * 槽位模板：触发词里用 {名字} 或 {名字:类型} 标出可变部分，匹配时从输入中抓取，代入回复。
+ *   - 类型：text/文本（默认，任意非空文本）、number/数字（阿拉伯或中文数字）、
+ *     entity/实体（不含空白和标点的一段，如人名、城市）
+ *   - 教学：教你 我叫{name} -> 你好，{name}
+ *   - 与 {{尿袋:查询}} 组合：教你 {city:entity}天气 -> {{weather:{city}}}，
+ *     槽位先代入，再由 Orchestrator::expand_template 调用尿袋
+ * 匹配要求整句对上（忽略首尾空白和句末标点），槽位取最短可行值；抓取值里的花括号会被去掉，
+ * 避免用户输入拼出新的 {{尿袋:查询}}。
+ */
+const SLOT_VALUE_MAX_CHARS: usize = 64;
+const SHORT_VALUE_MAX_CHARS: usize = 20;
+const TRAILING_PUNCT: &[char] = &['。', '！', '？', '!', '?', '.', '，', ',', '~', '～'];
+
+/* (槽位名, 抓取值)，按模板中出现的顺序 */
+pub type Captures = Vec<(String, String)>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SlotKind {
+    Text,
+    Number,
+    Entity,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+enum Segment {
+    Literal(String),
+    Slot { name: String, kind: SlotKind },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SlotTemplate {
+    source: String,
+    segments: Vec<Segment>,
+}
+
+impl SlotKind {
+    fn parse(s: &str) -> Result<Self, String> {
+        match s.trim() {
+            "" | "text" | "文本" => Ok(SlotKind::Text),
+            "number" | "数字" => Ok(SlotKind::Number),
+            "entity" | "实体" => Ok(SlotKind::Entity),
+            other => Err(format!("未知槽位类型: {}", other)),
+        }
+    }
+
+    fn accepts(self, value: &str) -> bool {
+        let len = value.chars().count();
+        if len == 0 {
+            return false;
+        }
+        match self {
+            SlotKind::Text => len <= SLOT_VALUE_MAX_CHARS,
+            SlotKind::Number => {
+                len <= SHORT_VALUE_MAX_CHARS
+                    && value.chars().any(|c| c.is_ascii_digit() || "零一二三四五六七八九十两".contains(c))
+                    && value
+                        .chars()
+                        .all(|c| c.is_ascii_digit() || ".-+".contains(c) || "零一二三四五六七八九十百千万亿两点".contains(c))
+            }
+            SlotKind::Entity => {
+                len <= SHORT_VALUE_MAX_CHARS && value.chars().all(|c| c.is_alphanumeric() || "-_·".contains(c))
+            }
+        }
+    }
+}
+
+/*
+ * 触发词里至少有一个写法正确的 {名字} 或 {名字:类型} 才按模板处理；代码、JSON 等
+ * 其他花括号（含 {{…}}、带空白或引号的内容）当作普通文字学习。
+ */
+pub fn is_template(trigger: &str) -> bool {
+    let mut from = 0;
+    while let Some(open) = trigger[from..].find('{').map(|i| from + i) {
+        let after = &trigger[open + 1..];
+        if let Some(close) = after.find(['{', '}']).filter(|&c| after[c..].starts_with('}')) {
+            let doubled = trigger[..open].ends_with('{') || after[close + 1..].starts_with('}');
+            if !doubled && is_slot(&after[..close]) {
+                return true;
+            }
+        }
+        from = open + 1;
+    }
+    false
+}
+
+fn is_slot(inner: &str) -> bool {
+    let (name, kind) = inner.split_once(':').unwrap_or((inner, ""));
+    !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_') && kind.chars().all(char::is_alphabetic)
+}
+
+impl SlotTemplate {
+    pub fn parse(trigger: &str) -> Result<Self, String> {
+        let source = trigger.trim().trim_end_matches(TRAILING_PUNCT).to_string();
+        let mut segments: Vec<Segment> = Vec::new();
+        let mut literal = String::new();
+        let mut rest = source.as_str();
+        while let Some(open) = rest.find(['{', '}']) {
+            if rest[open..].starts_with('}') {
+                return Err("槽位缺少左括号".into());
+            }
+            literal.push_str(&rest[..open]);
+            let after = &rest[open + 1..];
+            let close = match after.find('}') {
+                Some(c) => c,
+                None => return Err("槽位缺少右括号".into()),
+            };
+            let inner = &after[..close];
+            if inner.contains('{') {
+                return Err("槽位不能嵌套".into());
+            }
+            let (name, kind) = match inner.split_once(':') {
+                Some((n, k)) => (n.trim(), SlotKind::parse(k)?),
+                None => (inner.trim(), SlotKind::Text),
+            };
+            if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+                return Err(format!("槽位名无效: {{{}}}", inner));
+            }
+            if segments.iter().any(|s| matches!(s, Segment::Slot { name: n, .. } if n == name)) {
+                return Err(format!("槽位重名: {}", name));
+            }
+            if literal.is_empty() && matches!(segments.last(), Some(Segment::Slot { .. })) {
+                return Err("相邻槽位之间需要文字分隔".into());
+            }
+            if !literal.is_empty() {
+                segments.push(Segment::Literal(std::mem::take(&mut literal).to_ascii_lowercase()));
+            }
+            segments.push(Segment::Slot { name: name.to_string(), kind });
+            rest = &after[close + 1..];
+        }
+        literal.push_str(rest);
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal.to_ascii_lowercase()));
+        }
+        if !segments.iter().any(|s| matches!(s, Segment::Slot { .. })) {
+            return Err("模板中没有槽位".into());
+        }
+        let template = SlotTemplate { source, segments };
+        if template.literal_text().trim().is_empty() {
+            return Err("模板至少需要一段固定文字".into());
+        }
+        Ok(template)
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /*
+     * 去掉槽位后的固定文字，用于分词、语言判断和多个模板同时命中时的排序（越长越具体）。
+     */
+    pub fn literal_text(&self) -> String {
+        self.segments
+            .iter()
+            .filter_map(|s| match s {
+                Segment::Literal(l) => Some(l.as_str()),
+                Segment::Slot { .. } => None,
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    pub fn slot_names(&self) -> Vec<&str> {
+        self.segments
+            .iter()
+            .filter_map(|s| match s {
+                Segment::Slot { name, .. } => Some(name.as_str()),
+                Segment::Literal(_) => None,
+            })
+            .collect()
+    }
+
+    /*
+     * 回复里引用的槽位必须都在触发词中定义过。
+     */
+    pub fn check_response(&self, response: &str) -> Result<(), String> {
+        let names = self.slot_names();
+        match placeholders(response).into_iter().find(|p| !names.contains(&p.as_str())) {
+            Some(unknown) => Err(format!("回复引用了未定义的槽位: {}", unknown)),
+            None => Ok(()),
+        }
+    }
+
+    /*
+     * 整句匹配，返回 (槽位名, 抓取值)；对不上返回 None。
+     */
+    pub fn capture(&self, input: &str) -> Option<Captures> {
+        let input = input.trim().trim_end_matches(TRAILING_PUNCT).trim_end();
+        let lower = input.to_ascii_lowercase();
+        let mut out = Vec::new();
+        if capture_from(&self.segments, input, &lower, 0, &mut out) {
+            Some(out)
+        } else {
+            None
+        }
+    }
+}
+
+fn clean_value(raw: &str) -> String {
+    raw.trim().chars().filter(|c| *c != '{' && *c != '}').collect()
+}
+
+fn capture_from(segs: &[Segment], input: &str, lower: &str, pos: usize, out: &mut Captures) -> bool {
+    let (first, rest) = match segs.split_first() {
+        Some(s) => s,
+        None => return pos == input.len(),
+    };
+    match first {
+        Segment::Literal(l) => lower[pos..].starts_with(l.as_str()) && capture_from(rest, input, lower, pos + l.len(), out),
+        Segment::Slot { name, kind } => {
+            let next = match rest.first() {
+                Some(Segment::Literal(l)) => l.as_str(),
+                _ => {
+                    let value = clean_value(&input[pos..]);
+                    if !kind.accepts(&value) {
+                        return false;
+                    }
+                    out.push((name.clone(), value));
+                    return true;
+                }
+            };
+            for (i, _) in lower[pos..].match_indices(next) {
+                let end = pos + i;
+                if end == pos {
+                    continue;
+                }
+                let value = clean_value(&input[pos..end]);
+                if !kind.accepts(&value) {
+                    continue;
+                }
+                out.push((name.clone(), value));
+                if capture_from(rest, input, lower, end, out) {
+                    return true;
+                }
+                out.pop();
+            }
+            false
+        }
+    }
+}
+
+/*
+ * 回复中的单层 {名字} 占位符；{{尿袋:查询}} 的外层双括号不算，但其中嵌套的 {名字} 算。
+ */
+pub fn placeholders(response: &str) -> Vec<String> {
+    let mut found = Vec::new();
+    let mut rest = response;
+    while let Some(open) = rest.find('{') {
+        let after = &rest[open + 1..];
+        if let Some(inner) = after.strip_prefix('{') {
+            rest = inner;
+            continue;
+        }
+        match after.find(['{', '}']) {
+            Some(close) if after[close..].starts_with('}') => {
+                let name = after[..close].trim();
+                if !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+                    found.push(name.to_string());
+                }
+                rest = &after[close + 1..];
+            }
+            Some(close) => rest = &after[close..],
+            None => break,
+        }
+    }
+    found
+}
+
+pub fn fill(response: &str, captures: &[(String, String)]) -> String {
+    captures
+        .iter()
+        .fold(response.to_string(), |out, (name, value)| out.replace(&format!("{{{}}}", name), value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> SlotTemplate {
+        match SlotTemplate::parse(s) {
+            Ok(t) => t,
+            Err(e) => panic!("{}: {}", s, e),
+        }
+    }
+
+    #[test]
+    fn test_capture_typed_slots() {
+        let name = parse("我叫{name}");
+        assert_eq!(name.capture("我叫张三。"), Some(vec![("name".into(), "张三".into())]));
+        assert_eq!(name.capture("你叫张三"), None);
+
+        let order = parse("买{n:number}个{item:entity}");
+        assert_eq!(
+            order.capture("买 3 个苹果"),
+            Some(vec![("n".into(), "3".into()), ("item".into(), "苹果".into())])
+        );
+        assert_eq!(order.capture("买几个苹果"), None);
+
+        let en = parse("My name is {name:entity}");
+        assert_eq!(en.capture("my name is Bob!"), Some(vec![("name".into(), "Bob".into())]));
+        assert_eq!(en.capture("my name is Bob Smith"), None);
+
+        assert_eq!(parse("{x}说{y}").capture("他说{{evil:input}}"), Some(vec![
+            ("x".into(), "他".into()),
+            ("y".into(), "evil:input".into()),
+        ]));
+    }
+
+    #[test]
+    fn test_parse_errors_and_placeholders() {
+        assert!(SlotTemplate::parse("{a}{b}").is_err());
+        assert!(SlotTemplate::parse("我叫{name").is_err());
+        assert!(SlotTemplate::parse("我叫{name:color}").is_err());
+        assert!(SlotTemplate::parse("{a}和{a}").is_err());
+        assert!(SlotTemplate::parse("{name}").is_err());
+        assert!(is_template("我叫{name}") && is_template("买{n:数字}个") && is_template("我叫{name:color}"));
+        for plain in ["{\"a\": 1}", "fn main() { run(); }", "{{weather:北京}}", "{ x }", "{a:1}", "a}b{", "我叫{name"] {
+            assert!(!is_template(plain), "{}", plain);
+        }
+        assert_eq!(placeholders("{{weather:{city}}}，{name}好"), vec!["city".to_string(), "name".to_string()]);
+        assert!(parse("{city:entity}天气").check_response("{{weather:{city}}}").is_ok());
+        assert!(parse("我叫{name}").check_response("你好，{nick}").is_err());
+        assert_eq!(fill("{{weather:{city}}}", &[("city".into(), "北京".into())]), "{{weather:北京}}");
+    }
+}
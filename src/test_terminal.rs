@@ -23,8 +23,9 @@ pub async fn run() {
     orch.install("capability_comparer").ok();
     orch.install("pilot").ok();
     let mut stdout = io::stdout();
+    let mut tracing = false;
     writeln!(stdout, "LOGOS 本地终端 (data_dir={})", DIR).ok();
-    writeln!(stdout, "命令: run <意图> | feed <json_path> | status | chain | score <pouch> | promote | reset | quit | help").ok();
+    writeln!(stdout, "命令: run <意图> | feed <json_path> | trace [on|off] | status | chain | score <pouch> | promote | reset | quit | help").ok();
 
     loop {
         write!(stdout, "> ").ok();
@@ -49,6 +50,9 @@ pub async fn run() {
                 }
                 let chain_before = orch.recent_evolution_entries(100).len();
                 let prom_before = orch.promoted_cache_len();
+                if tracing {
+                    orch.begin_trace(rest);
+                }
                 let result = orch.execute_with_pouch(rest).await;
                 let chain_after = orch.recent_evolution_entries(100).len();
                 let prom_after = orch.promoted_cache_len();
@@ -72,6 +76,21 @@ pub async fn run() {
                         writeln!(stdout, "错误: {} pouch={}", e, pouch).ok();
                     }
                 }
+                if let Some(t) = orch.take_trace() {
+                    writeln!(stdout, "{}", t.render()).ok();
+                }
+            }
+            "trace" => {
+                tracing = match rest {
+                    "on" => true,
+                    "off" => false,
+                    "" => !tracing,
+                    _ => {
+                        writeln!(stdout, "用法: trace [on|off]").ok();
+                        continue;
+                    }
+                };
+                writeln!(stdout, "执行轨迹: {}", if tracing { "开启" } else { "关闭" }).ok();
             }
             "status" => {
                 let n = orch.promoted_cache_len();
@@ -164,6 +183,9 @@ pub async fn run() {
                             writeln!(stdout, "[{}] \"{}\"", i + 1, trunc(intent, 50)).ok();
                             let chain_before = orch.recent_evolution_entries(100).len();
                             let prom_before = orch.promoted_cache_len();
+                            if tracing {
+                                orch.begin_trace(intent);
+                            }
                             let result = orch.execute_with_pouch(intent).await;
                             let chain_after = orch.recent_evolution_entries(100).len();
                             let prom_after = orch.promoted_cache_len();
@@ -182,6 +204,9 @@ pub async fn run() {
                                     writeln!(stdout, "  错误: {}  pouch={}", e, pouch).ok();
                                 }
                             }
+                            if let Some(t) = orch.take_trace() {
+                                writeln!(stdout, "{}", t.render()).ok();
+                            }
                             writeln!(
                                 stdout,
                                 "  chain {} -> {}  promoted {} -> {}",
@@ -213,6 +238,7 @@ pub async fn run() {
                 writeln!(stdout, "score <名>  - score_pouch_from_entries 值").ok();
                 writeln!(stdout, "promote     - 手动跑一次晋升检查（仅检查与说明，实际晋升在 ToPouch record_evolution）").ok();
                 writeln!(stdout, "feed <path> - 从 JSON 文件批量 run 意图列表（Vec<String>）").ok();
+                writeln!(stdout, "trace [on|off] - 切换执行轨迹（路由/清洗/缓存/计划步骤/兜底/吸收）").ok();
                 writeln!(stdout, "reset       - 清空目录并重启").ok();
                 writeln!(stdout, "quit        - 退出").ok();
                 writeln!(stdout, "help        - 本列表").ok();
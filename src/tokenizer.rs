@@ -0,0 +1,429 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+
+/*
+ * 可插拔分词器：LanguagePouch 每个实例持有一个。
+ *   - CharBigram：逐字 + 相邻字二元组（原始实现，默认）
+ *   - Dictionary：基于词典的双向最大匹配，ASCII 字母数字连续段整体为一个词，标点丢弃
+ * reconstruct 用于迁移：从已存的 token 还原出可重新分词的文本（空白与标点不保留），
+ * 无法还原时返回 None，迁移保留原 token。
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenizerKind {
+    #[default]
+    CharBigram,
+    Dictionary,
+}
+
+pub trait Tokenizer: Send + Sync {
+    fn kind(&self) -> TokenizerKind;
+    /* 影响分词结果的外部数据（词典）指纹；无外部数据时为 0 */
+    fn fingerprint(&self) -> u64 {
+        0
+    }
+    fn tokenize(&self, input: &str) -> Vec<String>;
+    fn reconstruct(&self, tokens: &[String]) -> Option<String>;
+}
+
+pub struct CharBigramTokenizer;
+
+impl Tokenizer for CharBigramTokenizer {
+    fn kind(&self) -> TokenizerKind {
+        TokenizerKind::CharBigram
+    }
+
+    fn tokenize(&self, input: &str) -> Vec<String> {
+        let chars: Vec<char> = input.chars().filter(|c| !c.is_whitespace()).collect();
+        if chars.len() < 4 {
+            return chars.iter().map(|c| c.to_string()).collect();
+        }
+        let mut tokens: Vec<String> = Vec::with_capacity(chars.len() * 2);
+        for c in &chars {
+            tokens.push(c.to_string());
+        }
+        for pair in chars.windows(2) {
+            tokens.push(pair.iter().collect());
+        }
+        tokens
+    }
+
+    fn reconstruct(&self, tokens: &[String]) -> Option<String> {
+        let singles = tokens.iter().take_while(|t| t.chars().count() == 1).count();
+        if singles == 0 {
+            return None;
+        }
+        let bigrams = &tokens[singles..];
+        if !bigrams.is_empty() {
+            if bigrams.len() + 1 != singles {
+                return None;
+            }
+            let consistent = bigrams
+                .iter()
+                .enumerate()
+                .all(|(i, b)| *b == format!("{}{}", tokens[i], tokens[i + 1]));
+            if !consistent {
+                return None;
+            }
+        }
+        Some(tokens[..singles].concat())
+    }
+}
+
+pub const DEFAULT_LEXICON: &str = include_str!("../data/lexicon.txt");
+pub const LEXICON_FILE: &str = "lexicon.txt";
+
+#[derive(Debug, Clone, Default)]
+pub struct Lexicon {
+    words: HashSet<String>,
+    max_len: usize,
+}
+
+impl Lexicon {
+    pub fn parse(text: &str) -> Self {
+        let mut lex = Self::default();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(word) = line.split_whitespace().next() {
+                let word = word.to_lowercase();
+                lex.max_len = lex.max_len.max(word.chars().count());
+                lex.words.insert(word);
+            }
+        }
+        lex
+    }
+
+    /*
+     * 读取数据目录下的词典；不存在时写出内置默认词典，便于用户在其上追加。
+     */
+    pub fn load_or_init(data_dir: &str) -> Self {
+        let path = format!("{}/{}", data_dir, LEXICON_FILE);
+        match std::fs::read_to_string(&path) {
+            Ok(text) => Self::parse(&text),
+            Err(_) => {
+                let _ = std::fs::create_dir_all(data_dir);
+                let _ = std::fs::write(&path, DEFAULT_LEXICON);
+                Self::parse(DEFAULT_LEXICON)
+            }
+        }
+    }
+
+    /*
+     * 与词序无关的内容指纹，词典变化时触发重新分词。
+     */
+    pub fn fingerprint(&self) -> u64 {
+        let mut words: Vec<&String> = self.words.iter().collect();
+        words.sort();
+        let mut h = std::collections::hash_map::DefaultHasher::new();
+        words.hash(&mut h);
+        h.finish()
+    }
+
+    fn contains(&self, chars: &[char]) -> bool {
+        self.words.contains(&chars.iter().collect::<String>())
+    }
+}
+
+pub struct DictSegmenter {
+    lexicon: Lexicon,
+}
+
+impl DictSegmenter {
+    pub fn new(lexicon: Lexicon) -> Self {
+        Self { lexicon }
+    }
+
+    fn forward(&self, chars: &[char]) -> Vec<String> {
+        let mut out = Vec::new();
+        let mut i = 0;
+        while i < chars.len() {
+            let max = self.lexicon.max_len.min(chars.len() - i).max(1);
+            let len = (1..=max)
+                .rev()
+                .find(|&l| l == 1 || self.lexicon.contains(&chars[i..i + l]))
+                .unwrap_or(1);
+            out.push(chars[i..i + len].iter().collect());
+            i += len;
+        }
+        out
+    }
+
+    fn backward(&self, chars: &[char]) -> Vec<String> {
+        let mut out = Vec::new();
+        let mut j = chars.len();
+        while j > 0 {
+            let max = self.lexicon.max_len.min(j).max(1);
+            let len = (1..=max)
+                .rev()
+                .find(|&l| l == 1 || self.lexicon.contains(&chars[j - l..j]))
+                .unwrap_or(1);
+            out.push(chars[j - len..j].iter().collect());
+            j -= len;
+        }
+        out.reverse();
+        out
+    }
+
+    /*
+     * 双向最大匹配：取词数更少的一侧；词数相同取单字更少的一侧；仍相同取逆向结果。
+     */
+    fn segment(&self, chars: &[char]) -> Vec<String> {
+        let f = self.forward(chars);
+        let b = self.backward(chars);
+        let singles = |v: &[String]| v.iter().filter(|w| w.chars().count() == 1).count();
+        if f.len() < b.len() || (f.len() == b.len() && singles(&f) < singles(&b)) {
+            f
+        } else {
+            b
+        }
+    }
+}
+
+impl Tokenizer for DictSegmenter {
+    fn kind(&self) -> TokenizerKind {
+        TokenizerKind::Dictionary
+    }
+
+    fn fingerprint(&self) -> u64 {
+        self.lexicon.fingerprint()
+    }
+
+    fn tokenize(&self, input: &str) -> Vec<String> {
+        let lower = input.to_lowercase();
+        let mut tokens = Vec::new();
+        let mut ascii = String::new();
+        let mut run: Vec<char> = Vec::new();
+        for c in lower.chars() {
+            if c.is_ascii_alphanumeric() {
+                if !run.is_empty() {
+                    tokens.extend(self.segment(&run));
+                    run.clear();
+                }
+                ascii.push(c);
+                continue;
+            }
+            if !ascii.is_empty() {
+                tokens.push(std::mem::take(&mut ascii));
+            }
+            if c.is_alphanumeric() {
+                run.push(c);
+            } else if !run.is_empty() {
+                tokens.extend(self.segment(&run));
+                run.clear();
+            }
+        }
+        if !ascii.is_empty() {
+            tokens.push(ascii);
+        }
+        if !run.is_empty() {
+            tokens.extend(self.segment(&run));
+        }
+        tokens
+    }
+
+    fn reconstruct(&self, tokens: &[String]) -> Option<String> {
+        if tokens.is_empty() {
+            return None;
+        }
+        let mut text = String::new();
+        let mut prev_ascii = false;
+        for t in tokens {
+            let is_ascii = t.chars().all(|c| c.is_ascii_alphanumeric());
+            if prev_ascii && is_ascii {
+                text.push(' ');
+            }
+            text.push_str(t);
+            prev_ascii = is_ascii;
+        }
+        Some(text)
+    }
+}
+
+/*
+ * 输入语言。含汉字即为中文（中英混合按中文处理，其中的英文单词整体成词）；
+ * 只有拉丁字母时为英文，走空白/标点分词 + 小写 + 简单词干。
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Lang {
+    #[default]
+    Zh,
+    En,
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32, 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xF900..=0xFAFF)
+}
+
+impl Lang {
+    pub fn detect(input: &str) -> Lang {
+        if input.chars().any(is_cjk) {
+            Lang::Zh
+        } else if input.chars().any(|c| c.is_ascii_alphabetic()) {
+            Lang::En
+        } else {
+            Lang::Zh
+        }
+    }
+}
+
+/*
+ * 极简英文词干：只去掉常见屈折后缀，保证同一词的不同形态落到同一 token，且对结果幂等。
+ */
+pub fn stem(word: &str) -> String {
+    let w = word.strip_suffix("'s").unwrap_or(word).replace('\'', "");
+    if !w.is_ascii() || w.len() <= 3 {
+        return w;
+    }
+    if let Some(base) = w.strip_suffix("sses") {
+        return format!("{}ss", base);
+    }
+    if let Some(base) = w.strip_suffix("ies") {
+        return format!("{}y", base);
+    }
+    for (suffix, min_base) in [("ing", 3), ("ed", 3), ("ly", 4)] {
+        if let Some(base) = w.strip_suffix(suffix) {
+            if base.len() >= min_base && base.chars().any(|c| "aeiouy".contains(c)) {
+                return undouble(base);
+            }
+        }
+    }
+    if w.ends_with('s') && !w.ends_with("ss") && !w.ends_with("us") && !w.ends_with("is") {
+        return w[..w.len() - 1].to_string();
+    }
+    w
+}
+
+fn undouble(base: &str) -> String {
+    let b = base.as_bytes();
+    let n = b.len();
+    if n >= 2 && b[n - 1] == b[n - 2] && !b"aeiouylsz".contains(&b[n - 1]) {
+        return base[..n - 1].to_string();
+    }
+    base.to_string()
+}
+
+pub fn english_words(input: &str) -> Vec<String> {
+    input
+        .to_lowercase()
+        .split(|c: char| !(c.is_alphanumeric() || c == '\''))
+        .map(|w| w.trim_matches('\''))
+        .filter(|w| !w.is_empty())
+        .map(stem)
+        .filter(|w| !w.is_empty())
+        .collect()
+}
+
+/*
+ * LanguagePouch 的统一分词入口：英文输入按词；中文/混合输入中的英文单词（含字母的 ASCII 连续段）
+ * 整体取词干成词，其余片段交给配置的中文分词器。纯中文输入与直接调用中文分词器结果一致。
+ */
+pub fn tokenize_mixed(t: &dyn Tokenizer, input: &str) -> Vec<String> {
+    if Lang::detect(input) == Lang::En {
+        return english_words(input);
+    }
+    let mut tokens = Vec::new();
+    let mut rest = String::new();
+    let mut word = String::new();
+    let flush_word = |word: &mut String, rest: &mut String, tokens: &mut Vec<String>| {
+        if word.chars().any(|c| c.is_ascii_alphabetic()) {
+            if !rest.trim().is_empty() {
+                tokens.extend(t.tokenize(rest));
+            }
+            rest.clear();
+            tokens.push(stem(&word.to_lowercase()));
+        } else {
+            rest.push_str(word);
+        }
+        word.clear();
+    };
+    for c in input.chars() {
+        if c.is_ascii_alphanumeric() {
+            word.push(c);
+        } else {
+            flush_word(&mut word, &mut rest, &mut tokens);
+            rest.push(c);
+        }
+    }
+    flush_word(&mut word, &mut rest, &mut tokens);
+    if !rest.trim().is_empty() {
+        tokens.extend(t.tokenize(&rest));
+    }
+    tokens
+}
+
+/*
+ * 已存模式所用分词器，持久化为 tokenizer.json；与配置不一致时触发迁移。
+ * revision 对应 tokenize_mixed 的规则版本：0 为混合输入逐字切分的旧规则。
+ */
+pub const TOKENIZER_REVISION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct TokenizerState {
+    pub kind: TokenizerKind,
+    #[serde(default)]
+    pub lexicon_hash: u64,
+    #[serde(default)]
+    pub revision: u32,
+}
+
+impl TokenizerState {
+    pub fn of(t: &dyn Tokenizer) -> Self {
+        Self { kind: t.kind(), lexicon_hash: t.fingerprint(), revision: TOKENIZER_REVISION }
+    }
+}
+
+pub fn build(kind: TokenizerKind, data_dir: &str) -> Box<dyn Tokenizer> {
+    match kind {
+        TokenizerKind::CharBigram => Box::new(CharBigramTokenizer),
+        TokenizerKind::Dictionary => Box::new(DictSegmenter::new(Lexicon::load_or_init(data_dir))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seg() -> DictSegmenter {
+        DictSegmenter::new(Lexicon::parse(DEFAULT_LEXICON))
+    }
+
+    #[test]
+    fn test_dictionary_keeps_word_order_distinct() {
+        let s = seg();
+        assert_eq!(s.tokenize("机器学习"), vec!["机器学习"]);
+        assert_eq!(s.tokenize("学习机器"), vec!["学习", "机器"]);
+        assert_eq!(s.tokenize("用 Rust 写代码分析！"), vec!["用", "rust", "写", "代码分析"]);
+    }
+
+    #[test]
+    fn test_bidirectional_prefers_fewer_words() {
+        let s = DictSegmenter::new(Lexicon::parse("研究\n研究生\n生命\n起源"));
+        assert_eq!(s.tokenize("研究生命起源"), vec!["研究", "生命", "起源"]);
+    }
+
+    #[test]
+    fn test_char_bigram_reconstruct_roundtrip() {
+        let t = CharBigramTokenizer;
+        let tokens = t.tokenize("自我介绍一下");
+        assert_eq!(t.reconstruct(&tokens).as_deref(), Some("自我介绍一下"));
+        assert_eq!(t.reconstruct(&["ab".to_string(), "c".to_string()]), None);
+        let d = seg();
+        assert_eq!(d.reconstruct(&d.tokenize("hello world 你好")).as_deref(), Some("hello world你好"));
+    }
+
+    #[test]
+    fn test_english_and_mixed_tokenize() {
+        assert_eq!(english_words("Running tests, learned flies!"), vec!["run", "test", "learn", "fly"]);
+        assert_eq!(stem("run"), "run");
+        assert_eq!(Lang::detect("hello"), Lang::En);
+        assert_eq!(Lang::detect("用rust写"), Lang::Zh);
+        let t = CharBigramTokenizer;
+        assert_eq!(tokenize_mixed(&t, "你和ChatGPT有什么区别"), vec!["你", "和", "chatgpt", "有", "什", "么", "区", "别", "有什", "什么", "么区", "区别"]);
+        assert_eq!(tokenize_mixed(&t, "自我介绍一下"), t.tokenize("自我介绍一下"));
+    }
+}
@@ -0,0 +1,164 @@
+use crate::atom::ExecutionPlan;
+use serde::Serialize;
+
+/*
+ * 执行轨迹：execute_with_pouch 的结构化记录，仅在 begin_trace 之后收集。
+ *   - route：frozen::logic::route 的决策（含 LANG_PRIORITY 直回时的 language 标记）
+ *   - sanitize：None=未安装清洗尿袋；passed / rewritten / rejected
+ *   - dialogue：指代/省略改写（原输入 → 改写后），没有改写时为 None
+ *   - intent：Reject 路径意图模型的预测（序列 置信度 来源）
+ *   - cache_hits：promoted 缓存与 chain 结果缓存命中
+ *   - plan：Reject 路径选中的 ExecutionPlan 及逐步输入/输出/耗时
+ *   - fallbacks：依次尝试的兜底阶段，accepted=false 时 reason 说明拒绝原因
+ *   - absorbed：本轮写回 language 的吸收记录
+ * 输入/输出均截断到 TRACE_TEXT_MAX 字符，轨迹不落盘。
+ */
+pub const TRACE_TEXT_MAX: usize = 120;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ExecutionTrace {
+    pub input: String,
+    pub route: String,
+    pub sanitize: Option<String>,
+    pub dialogue: Option<String>,
+    pub intent: Option<String>,
+    pub cache_hits: Vec<String>,
+    pub plan: Option<PlanTrace>,
+    pub fallbacks: Vec<FallbackTrace>,
+    pub absorbed: Vec<String>,
+    pub pouch: String,
+    pub ok: bool,
+    pub elapsed_ms: u64,
+    #[serde(skip)]
+    started: Option<std::time::Instant>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlanTrace {
+    pub summary: String,
+    pub plan: ExecutionPlan,
+    pub steps: Vec<StepTrace>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StepTrace {
+    pub index: usize,
+    pub atom: String,
+    pub pouch: String,
+    pub input: String,
+    pub output: String,
+    pub ok: bool,
+    pub latency_ms: u64,
+    pub attempt: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FallbackTrace {
+    pub stage: String,
+    pub accepted: bool,
+    pub reason: String,
+}
+
+pub fn clip(s: &str) -> String {
+    s.chars().take(TRACE_TEXT_MAX).collect()
+}
+
+impl ExecutionTrace {
+    pub fn new(input: &str) -> Self {
+        Self {
+            input: clip(input),
+            started: Some(std::time::Instant::now()),
+            ..Default::default()
+        }
+    }
+
+    pub fn finish(&mut self, pouch: &str, ok: bool) {
+        self.pouch = pouch.to_string();
+        self.ok = ok;
+        self.elapsed_ms = self.started.map_or(0, |t| t.elapsed().as_millis() as u64);
+    }
+
+    pub fn fallback(&mut self, stage: &str, accepted: bool, reason: &str) {
+        self.fallbacks.push(FallbackTrace {
+            stage: stage.to_string(),
+            accepted,
+            reason: clip(reason),
+        });
+    }
+
+    pub fn render(&self) -> String {
+        let mut lines = vec![format!(
+            "trace: route={} pouch={} ok={} {}ms",
+            if self.route.is_empty() { "-" } else { &self.route },
+            self.pouch,
+            self.ok,
+            self.elapsed_ms
+        )];
+        if let Some(ref s) = self.sanitize {
+            lines.push(format!("  sanitize: {}", s));
+        }
+        if let Some(ref d) = self.dialogue {
+            lines.push(format!("  dialogue: {}", d));
+        }
+        if let Some(ref i) = self.intent {
+            lines.push(format!("  intent: {}", i));
+        }
+        for c in &self.cache_hits {
+            lines.push(format!("  cache: {}", c));
+        }
+        if let Some(ref p) = self.plan {
+            lines.push(format!("  plan: {}", p.summary));
+            for s in &p.steps {
+                lines.push(format!(
+                    "    [{}{}] {}@{} {} {}ms in={} out={}",
+                    s.index,
+                    if s.attempt > 0 { format!(" 重试{}", s.attempt) } else { String::new() },
+                    s.atom,
+                    s.pouch,
+                    if s.ok { "ok" } else { "err" },
+                    s.latency_ms,
+                    s.input,
+                    s.output
+                ));
+            }
+        }
+        for f in &self.fallbacks {
+            lines.push(format!(
+                "  fallback {}: {}{}",
+                f.stage,
+                if f.accepted { "采用" } else { "拒绝" },
+                if f.reason.is_empty() { String::new() } else { format!(" ({})", f.reason) }
+            ));
+        }
+        for a in &self.absorbed {
+            lines.push(format!("  absorb: {}", a));
+        }
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_lists_fallbacks_and_absorb() {
+        let mut t = ExecutionTrace::new("你好");
+        t.route = "Reject".into();
+        t.fallback("cloud_analyze", false, "云端分析不可用");
+        t.fallback("language", true, "");
+        t.absorbed.push("plan→language".into());
+        t.finish("language", true);
+        let out = t.render();
+        assert!(out.contains("route=Reject"));
+        assert!(out.contains("fallback cloud_analyze: 拒绝 (云端分析不可用)"));
+        assert!(out.contains("fallback language: 采用"));
+        assert!(out.contains("absorb: plan→language"));
+    }
+
+    #[test]
+    fn test_clip_counts_chars() {
+        let long = "字".repeat(TRACE_TEXT_MAX + 10);
+        assert_eq!(clip(&long).chars().count(), TRACE_TEXT_MAX);
+    }
+}